@@ -0,0 +1,53 @@
+/* C ABI of my-solana-program-ffi (MSP_ABI_VERSION 1).
+ *
+ * Pubkeys are 32-byte buffers. Functions writing variable-length output take the buffer,
+ * its capacity and an out-length; the full length is always written to the out-length,
+ * so a call with a NULL buffer and zero capacity returns MSP_BUFFER_TOO_SMALL with the
+ * size to allocate. */
+#ifndef MY_SOLANA_PROGRAM_H
+#define MY_SOLANA_PROGRAM_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define MSP_OK 0
+#define MSP_NULL_POINTER 1
+#define MSP_INVALID_ARGUMENT 2
+#define MSP_BUFFER_TOO_SMALL 3
+#define MSP_DERIVATION_FAILED 4
+#define MSP_ENCODING_FAILED 5
+
+uint32_t msp_abi_version(void);
+
+int32_t msp_program_state_pda(const uint8_t *program_id, uint8_t *out_address, uint8_t *out_bump);
+int32_t msp_program_config_pda(const uint8_t *program_id, uint8_t *out_address, uint8_t *out_bump);
+int32_t msp_metrics_pda(const uint8_t *program_id, uint8_t *out_address, uint8_t *out_bump);
+int32_t msp_user_pda(const uint8_t *owner, const uint8_t *program_id, uint8_t *out_address, uint8_t *out_bump);
+int32_t msp_session_key_pda(const uint8_t *owner, const uint8_t *delegate, const uint8_t *program_id,
+                            uint8_t *out_address, uint8_t *out_bump);
+int32_t msp_lock_pda(const uint8_t *owner, uint16_t lock_id, const uint8_t *program_id,
+                     uint8_t *out_address, uint8_t *out_bump);
+int32_t msp_daily_stats_pda(int64_t timestamp, const uint8_t *program_id, uint8_t *out_address, uint8_t *out_bump);
+
+int32_t msp_encode_create_user_account(uint64_t initial_balance, uint64_t pow_nonce,
+                                       uint8_t *out, size_t capacity, size_t *out_len);
+/* idempotency_key is NULL or 16 bytes */
+int32_t msp_encode_transfer_with_fee(uint64_t amount, uint16_t fee_basis_points, const uint8_t *idempotency_key,
+                                     uint8_t *out, size_t capacity, size_t *out_len);
+int32_t msp_encode_transfer_up_to(uint64_t max_amount, uint64_t min_amount,
+                                  uint8_t *out, size_t capacity, size_t *out_len);
+int32_t msp_encode_pay_invoice(uint64_t amount, uint8_t *out, size_t capacity, size_t *out_len);
+
+/* Writes a NUL-terminated string; out_len excludes the terminator, so allocate out_len + 1 */
+int32_t msp_format_amount(uint64_t amount, char *out, size_t capacity, size_t *out_len);
+int32_t msp_parse_amount(const char *value, uint64_t *out_amount);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* MY_SOLANA_PROGRAM_H */
@@ -0,0 +1,300 @@
+use solana_program::pubkey::Pubkey;
+use common::CommonResult;
+use account_utils::account_creation;
+use my_solana_program::{analytics, ProgramInstruction};
+use my_solana_program_sdk::formatting;
+use std::ffi::{c_char, CStr};
+
+/// Version of the C ABI; bumped on any incompatible change to a signature or status code
+pub const MSP_ABI_VERSION: u32 = 1;
+
+/// Status codes returned by every fallible function
+pub mod status {
+    pub const OK: i32 = 0;
+    /// A required pointer argument was null
+    pub const NULL_POINTER: i32 = 1;
+    /// An argument was out of range or malformed
+    pub const INVALID_ARGUMENT: i32 = 2;
+    /// The output buffer is smaller than the length written to `out_len`
+    pub const BUFFER_TOO_SMALL: i32 = 3;
+    /// No valid program address exists for the seeds
+    pub const DERIVATION_FAILED: i32 = 4;
+    /// The instruction could not be serialized
+    pub const ENCODING_FAILED: i32 = 5;
+}
+
+unsafe fn read_pubkey(ptr: *const u8) -> Result<Pubkey, i32> {
+    if ptr.is_null() {
+        return Err(status::NULL_POINTER);
+    }
+    let mut bytes = [0u8; 32];
+    bytes.copy_from_slice(std::slice::from_raw_parts(ptr, 32));
+    Ok(Pubkey::new_from_array(bytes))
+}
+
+/// Write `data` to `out` if it fits in `capacity`, always reporting the full length in `out_len`
+unsafe fn write_bytes(data: &[u8], out: *mut u8, capacity: usize, out_len: *mut usize) -> i32 {
+    if out_len.is_null() {
+        return status::NULL_POINTER;
+    }
+    *out_len = data.len();
+    if capacity < data.len() {
+        return status::BUFFER_TOO_SMALL;
+    }
+    if out.is_null() {
+        return status::NULL_POINTER;
+    }
+    std::ptr::copy_nonoverlapping(data.as_ptr(), out, data.len());
+    status::OK
+}
+
+/// Version of the C ABI this library implements
+#[no_mangle]
+pub extern "C" fn msp_abi_version() -> u32 {
+    MSP_ABI_VERSION
+}
+
+/// Program-derived addresses; every pubkey argument points to 32 bytes
+pub mod pda {
+    use super::*;
+
+    unsafe fn write_pda(result: Result<CommonResult<(Pubkey, u8)>, i32>, out_address: *mut u8, out_bump: *mut u8) -> i32 {
+        if out_address.is_null() || out_bump.is_null() {
+            return status::NULL_POINTER;
+        }
+        match result {
+            Ok(Ok((address, bump))) => {
+                std::ptr::copy_nonoverlapping(address.as_ref().as_ptr(), out_address, 32);
+                *out_bump = bump;
+                status::OK
+            }
+            Ok(Err(_)) => status::DERIVATION_FAILED,
+            Err(code) => code,
+        }
+    }
+
+    /// Program state singleton
+    ///
+    /// # Safety
+    /// `program_id` must point to 32 readable bytes, `out_address` to 32 writable bytes and
+    /// `out_bump` to one writable byte.
+    #[no_mangle]
+    pub unsafe extern "C" fn msp_program_state_pda(program_id: *const u8, out_address: *mut u8, out_bump: *mut u8) -> i32 {
+        let result = read_pubkey(program_id).map(|program_id| account_creation::create_program_state_pda(&program_id));
+        write_pda(result, out_address, out_bump)
+    }
+
+    /// Program config singleton
+    ///
+    /// # Safety
+    /// As for `msp_program_state_pda`.
+    #[no_mangle]
+    pub unsafe extern "C" fn msp_program_config_pda(program_id: *const u8, out_address: *mut u8, out_bump: *mut u8) -> i32 {
+        let result = read_pubkey(program_id).map(|program_id| account_creation::create_program_config_pda(&program_id));
+        write_pda(result, out_address, out_bump)
+    }
+
+    /// Metrics singleton
+    ///
+    /// # Safety
+    /// As for `msp_program_state_pda`.
+    #[no_mangle]
+    pub unsafe extern "C" fn msp_metrics_pda(program_id: *const u8, out_address: *mut u8, out_bump: *mut u8) -> i32 {
+        let result = read_pubkey(program_id).map(|program_id| account_creation::create_metrics_pda(&program_id));
+        write_pda(result, out_address, out_bump)
+    }
+
+    /// User account of `owner`
+    ///
+    /// # Safety
+    /// `owner` and `program_id` must point to 32 readable bytes each; outputs as for
+    /// `msp_program_state_pda`.
+    #[no_mangle]
+    pub unsafe extern "C" fn msp_user_pda(
+        owner: *const u8,
+        program_id: *const u8,
+        out_address: *mut u8,
+        out_bump: *mut u8,
+    ) -> i32 {
+        let result = read_pubkey(owner).and_then(|owner| {
+            read_pubkey(program_id).map(|program_id| account_creation::create_user_pda(&owner, &program_id))
+        });
+        write_pda(result, out_address, out_bump)
+    }
+
+    /// Session key letting `delegate` act for `owner`
+    ///
+    /// # Safety
+    /// `owner`, `delegate` and `program_id` must point to 32 readable bytes each; outputs as for
+    /// `msp_program_state_pda`.
+    #[no_mangle]
+    pub unsafe extern "C" fn msp_session_key_pda(
+        owner: *const u8,
+        delegate: *const u8,
+        program_id: *const u8,
+        out_address: *mut u8,
+        out_bump: *mut u8,
+    ) -> i32 {
+        let result = (|| {
+            let (owner, delegate, program_id) = (read_pubkey(owner)?, read_pubkey(delegate)?, read_pubkey(program_id)?);
+            Ok(account_creation::create_session_key_pda(&owner, &delegate, &program_id))
+        })();
+        write_pda(result, out_address, out_bump)
+    }
+
+    /// Token lock `lock_id` of `owner`
+    ///
+    /// # Safety
+    /// As for `msp_user_pda`.
+    #[no_mangle]
+    pub unsafe extern "C" fn msp_lock_pda(
+        owner: *const u8,
+        lock_id: u16,
+        program_id: *const u8,
+        out_address: *mut u8,
+        out_bump: *mut u8,
+    ) -> i32 {
+        let result = read_pubkey(owner).and_then(|owner| {
+            read_pubkey(program_id).map(|program_id| account_creation::create_lock_pda(&owner, lock_id, &program_id))
+        });
+        write_pda(result, out_address, out_bump)
+    }
+
+    /// Daily analytics rollup covering unix time `timestamp`
+    ///
+    /// # Safety
+    /// As for `msp_program_state_pda`.
+    #[no_mangle]
+    pub unsafe extern "C" fn msp_daily_stats_pda(
+        timestamp: i64,
+        program_id: *const u8,
+        out_address: *mut u8,
+        out_bump: *mut u8,
+    ) -> i32 {
+        let result = read_pubkey(program_id).map(|program_id| {
+            account_creation::create_daily_stats_pda(analytics::day_start(timestamp), &program_id)
+        });
+        write_pda(result, out_address, out_bump)
+    }
+}
+
+/// Instruction data encoding; call with a null `out` and zero `capacity` to query the length
+pub mod encoding {
+    use super::*;
+
+    unsafe fn write_instruction(instruction: ProgramInstruction, out: *mut u8, capacity: usize, out_len: *mut usize) -> i32 {
+        match instruction.pack() {
+            Ok(data) => write_bytes(&data, out, capacity, out_len),
+            Err(_) => status::ENCODING_FAILED,
+        }
+    }
+
+    /// `CreateUserAccount` instruction data
+    ///
+    /// # Safety
+    /// `out` must point to `capacity` writable bytes (or be null when `capacity` is zero) and
+    /// `out_len` to a writable `size_t`.
+    #[no_mangle]
+    pub unsafe extern "C" fn msp_encode_create_user_account(
+        initial_balance: u64,
+        pow_nonce: u64,
+        out: *mut u8,
+        capacity: usize,
+        out_len: *mut usize,
+    ) -> i32 {
+        write_instruction(ProgramInstruction::CreateUserAccount { initial_balance, pow_nonce }, out, capacity, out_len)
+    }
+
+    /// `TransferWithFee` instruction data; `idempotency_key` is null or points to 16 bytes
+    ///
+    /// # Safety
+    /// As for `msp_encode_create_user_account`, and a non-null `idempotency_key` must point to 16
+    /// readable bytes.
+    #[no_mangle]
+    pub unsafe extern "C" fn msp_encode_transfer_with_fee(
+        amount: u64,
+        fee_basis_points: u16,
+        idempotency_key: *const u8,
+        out: *mut u8,
+        capacity: usize,
+        out_len: *mut usize,
+    ) -> i32 {
+        let idempotency_key = (!idempotency_key.is_null()).then(|| {
+            let mut key = [0u8; 16];
+            key.copy_from_slice(std::slice::from_raw_parts(idempotency_key, 16));
+            key
+        });
+        write_instruction(
+            ProgramInstruction::TransferWithFee { amount, fee_basis_points, idempotency_key },
+            out,
+            capacity,
+            out_len,
+        )
+    }
+
+    /// `TransferUpTo` instruction data
+    ///
+    /// # Safety
+    /// As for `msp_encode_create_user_account`.
+    #[no_mangle]
+    pub unsafe extern "C" fn msp_encode_transfer_up_to(
+        max_amount: u64,
+        min_amount: u64,
+        out: *mut u8,
+        capacity: usize,
+        out_len: *mut usize,
+    ) -> i32 {
+        write_instruction(ProgramInstruction::TransferUpTo { max_amount, min_amount }, out, capacity, out_len)
+    }
+
+    /// `PayInvoice` instruction data
+    ///
+    /// # Safety
+    /// As for `msp_encode_create_user_account`.
+    #[no_mangle]
+    pub unsafe extern "C" fn msp_encode_pay_invoice(amount: u64, out: *mut u8, capacity: usize, out_len: *mut usize) -> i32 {
+        write_instruction(ProgramInstruction::PayInvoice { amount }, out, capacity, out_len)
+    }
+}
+
+/// Decimal amount strings in `DEFAULT_DECIMALS` places
+pub mod amounts {
+    use super::*;
+
+    /// Format base units as a NUL-terminated decimal string; `out_len` excludes the terminator
+    ///
+    /// # Safety
+    /// `out` must point to `capacity` writable bytes (or be null when `capacity` is zero) and
+    /// `out_len` to a writable `size_t`.
+    #[no_mangle]
+    pub unsafe extern "C" fn msp_format_amount(amount: u64, out: *mut c_char, capacity: usize, out_len: *mut usize) -> i32 {
+        let mut text = formatting::format_amount(amount).into_bytes();
+        text.push(0);
+        let code = write_bytes(&text, out.cast(), capacity, out_len);
+        if !out_len.is_null() {
+            *out_len -= 1;
+        }
+        code
+    }
+
+    /// Parse a decimal amount string into base units, rejecting excess precision
+    ///
+    /// # Safety
+    /// `value` must be a NUL-terminated string and `out_amount` must point to a writable `uint64_t`.
+    #[no_mangle]
+    pub unsafe extern "C" fn msp_parse_amount(value: *const c_char, out_amount: *mut u64) -> i32 {
+        if value.is_null() || out_amount.is_null() {
+            return status::NULL_POINTER;
+        }
+        let Ok(value) = CStr::from_ptr(value).to_str() else {
+            return status::INVALID_ARGUMENT;
+        };
+        match formatting::parse_amount(value) {
+            Ok(amount) => {
+                *out_amount = amount;
+                status::OK
+            }
+            Err(_) => status::INVALID_ARGUMENT,
+        }
+    }
+}
@@ -1,23 +1,45 @@
 use solana_program::{
     pubkey::Pubkey,
+    pubkey,
     account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    instruction::Instruction,
+    program::invoke_signed,
+    bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+    instruction::{get_stack_height, TRANSACTION_LEVEL_STACK_HEIGHT},
+    sysvar::instructions as instructions_sysvar,
+    ed25519_program,
 };
 use borsh::{BorshSerialize, BorshDeserialize};
 use common::{
     CommonError, CommonResult, 
     validation,
-    constants::{MAX_SEED_LENGTH, PROGRAM_STATE_SEED}
+    constants::{
+        MAX_SEED_LENGTH, PROGRAM_STATE_SEED, AUTHORITY_HISTORY_SEED, METRICS_SEED,
+        PROGRAM_CONFIG_SEED, BOUNTY_POOL_SEED, TREASURY_SEED, COMPLIANCE_CONFIG_SEED,
+        AUDIT_LOG_SEED, RATE_STATE_SEED, LENDING_CONFIG_SEED, INSURANCE_FUND_SEED,
+        FEE_SCHEDULE_SEED, DAILY_STATS_SEED, USER_SEED, ADVANCED_USER_SEED, SESSION_KEY_SEED,
+        IDEMPOTENCY_LOG_SEED, ACCOUNT_LIMITS_SEED, DELEGATIONS_SEED, POSITION_SEED, LOCK_SEED,
+        CRANKER_SEED, SCHEDULED_TRANSFER_SEED, SUBSCRIPTION_SEED, STREAM_SEED, INVOICE_SEED,
+        MULTISIG_SEED, PROPOSAL_SEED, PROGRAM_AUTHORITY_SEED,
+    }
 };
 // ONLY account-utils imports crypto-primitives - creates isolated depth-2 dependency
 use crypto_primitives::{
-    hashing, seed_generation, address_derivation, 
-    validation as crypto_validation, security
+    hashing, seed_generation, address_derivation,
+    validation as crypto_validation, security, offchain_message
 };
 
+pub use crypto_primitives::offchain_message::OffchainMessage;
+pub use crypto_primitives::merkle;
+
 /// Account creation and validation utilities
 pub mod account_creation {
     use super::*;
 
+    /// Hierarchical seed child type of token locks
+    pub const LOCK_CHILD_TYPE: u8 = 1;
+
     /// Create a PDA (Program Derived Address) with validation
     pub fn create_pda_with_validation(
         seeds: &[&[u8]], 
@@ -40,13 +62,151 @@ pub mod account_creation {
         create_pda_with_validation(&[PROGRAM_STATE_SEED], program_id)
     }
 
+    /// Create authority history PDA
+    pub fn create_authority_history_pda(program_id: &Pubkey) -> CommonResult<(Pubkey, u8)> {
+        create_pda_with_validation(&[AUTHORITY_HISTORY_SEED], program_id)
+    }
+
+    /// Create metrics PDA
+    pub fn create_metrics_pda(program_id: &Pubkey) -> CommonResult<(Pubkey, u8)> {
+        create_pda_with_validation(&[METRICS_SEED], program_id)
+    }
+
+    /// Create program config PDA
+    pub fn create_program_config_pda(program_id: &Pubkey) -> CommonResult<(Pubkey, u8)> {
+        create_pda_with_validation(&[PROGRAM_CONFIG_SEED], program_id)
+    }
+
+    /// Create bounty pool PDA funding crank rewards
+    pub fn create_bounty_pool_pda(program_id: &Pubkey) -> CommonResult<(Pubkey, u8)> {
+        create_pda_with_validation(&[BOUNTY_POOL_SEED], program_id)
+    }
+
+    /// Create treasury PDA
+    pub fn create_treasury_pda(program_id: &Pubkey) -> CommonResult<(Pubkey, u8)> {
+        create_pda_with_validation(&[TREASURY_SEED], program_id)
+    }
+
+    /// Create compliance configuration PDA
+    pub fn create_compliance_config_pda(program_id: &Pubkey) -> CommonResult<(Pubkey, u8)> {
+        create_pda_with_validation(&[COMPLIANCE_CONFIG_SEED], program_id)
+    }
+
+    /// Create audit log PDA
+    pub fn create_audit_log_pda(program_id: &Pubkey) -> CommonResult<(Pubkey, u8)> {
+        create_pda_with_validation(&[AUDIT_LOG_SEED], program_id)
+    }
+
+    /// Create exchange-rate state PDA
+    pub fn create_rate_state_pda(program_id: &Pubkey) -> CommonResult<(Pubkey, u8)> {
+        create_pda_with_validation(&[RATE_STATE_SEED], program_id)
+    }
+
+    /// Create lending configuration PDA
+    pub fn create_lending_config_pda(program_id: &Pubkey) -> CommonResult<(Pubkey, u8)> {
+        create_pda_with_validation(&[LENDING_CONFIG_SEED], program_id)
+    }
+
+    /// Create insurance fund PDA
+    pub fn create_insurance_fund_pda(program_id: &Pubkey) -> CommonResult<(Pubkey, u8)> {
+        create_pda_with_validation(&[INSURANCE_FUND_SEED], program_id)
+    }
+
+    /// Create fee distribution schedule PDA
+    pub fn create_fee_schedule_pda(program_id: &Pubkey) -> CommonResult<(Pubkey, u8)> {
+        create_pda_with_validation(&[FEE_SCHEDULE_SEED], program_id)
+    }
+
+    /// Create the program authority PDA owning every program-held token account
+    pub fn create_program_authority_pda(program_id: &Pubkey) -> CommonResult<(Pubkey, u8)> {
+        create_pda_with_validation(&[PROGRAM_AUTHORITY_SEED], program_id)
+    }
+
+    /// Create PDA holding a borrower's collateral and debt
+    pub fn create_position_pda(
+        owner: &Pubkey,
+        program_id: &Pubkey
+    ) -> CommonResult<(Pubkey, u8)> {
+        validation::validate_not_default(owner)?;
+        create_pda_with_validation(&[POSITION_SEED, owner.as_ref()], program_id)
+    }
+
+    /// Create PDA holding the transfer limits of a user account
+    pub fn create_account_limits_pda(
+        user_account: &Pubkey,
+        program_id: &Pubkey
+    ) -> CommonResult<(Pubkey, u8)> {
+        validation::validate_not_default(user_account)?;
+        create_pda_with_validation(&[ACCOUNT_LIMITS_SEED, user_account.as_ref()], program_id)
+    }
+
+    /// Create PDA of an attestor's attestation about a user (uses crypto-primitives)
+    pub fn create_attestation_pda(
+        user: &Pubkey,
+        attestor: &Pubkey,
+        program_id: &Pubkey
+    ) -> CommonResult<(Pubkey, u8)> {
+        validation::validate_not_default(user)?;
+        validation::validate_not_default(attestor)?;
+        address_derivation::derive_attestation_address(user, attestor, program_id)
+    }
+
+    /// Create PDA holding the delegate entries of an owner
+    pub fn create_delegations_pda(
+        owner: &Pubkey,
+        program_id: &Pubkey
+    ) -> CommonResult<(Pubkey, u8)> {
+        validation::validate_not_default(owner)?;
+        create_pda_with_validation(&[DELEGATIONS_SEED, owner.as_ref()], program_id)
+    }
+
+    /// Create PDA of a user's token lock, nested under the user via a hierarchical seed
+    pub fn create_lock_pda(
+        owner: &Pubkey,
+        lock_id: u16,
+        program_id: &Pubkey
+    ) -> CommonResult<(Pubkey, u8)> {
+        validation::validate_not_default(owner)?;
+        
+        let lock_seed = lock_seed(owner, lock_id)?;
+        create_pda_with_validation(&[LOCK_SEED, owner.as_ref(), &lock_seed], program_id)
+    }
+
+    /// Hierarchical seed following `LOCK_SEED` and the owner in a lock PDA's seeds
+    pub fn lock_seed(owner: &Pubkey, lock_id: u16) -> CommonResult<Vec<u8>> {
+        seed_generation::create_hierarchical_seed(owner, LOCK_CHILD_TYPE, lock_id)
+    }
+
+    /// Create the analytics rollup PDA of the day starting at `day_start` (unix seconds)
+    pub fn create_daily_stats_pda(
+        day_start: i64,
+        program_id: &Pubkey
+    ) -> CommonResult<(Pubkey, u8)> {
+        let day_seed = daily_stats_seed(day_start, program_id);
+        create_pda_with_validation(&[DAILY_STATS_SEED, &day_seed], program_id)
+    }
+
+    /// Temporal seed following `DAILY_STATS_SEED` in a daily stats PDA's seeds
+    pub fn daily_stats_seed(day_start: i64, program_id: &Pubkey) -> Vec<u8> {
+        seed_generation::generate_temporal_seed(program_id, day_start)
+    }
+
+    /// Create cranker registration PDA
+    pub fn create_cranker_pda(
+        authority: &Pubkey,
+        program_id: &Pubkey
+    ) -> CommonResult<(Pubkey, u8)> {
+        validation::validate_not_default(authority)?;
+        create_pda_with_validation(&[CRANKER_SEED, authority.as_ref()], program_id)
+    }
+
     /// Create user-specific PDA
     pub fn create_user_pda(
         user_pubkey: &Pubkey, 
         program_id: &Pubkey
     ) -> CommonResult<(Pubkey, u8)> {
         validation::validate_not_default(user_pubkey)?;
-        create_pda_with_validation(&[b"user", user_pubkey.as_ref()], program_id)
+        create_pda_with_validation(&[USER_SEED, user_pubkey.as_ref()], program_id)
     }
     
     /// Create advanced user PDA with crypto-generated seed (uses crypto-primitives)
@@ -65,7 +225,7 @@ pub mod account_creation {
             nonce
         )?;
         
-        create_pda_with_validation(&[b"advanced_user", &crypto_seed], program_id)
+        create_pda_with_validation(&[ADVANCED_USER_SEED, &crypto_seed], program_id)
     }
     
     /// Create vault PDA using crypto-primitives address derivation
@@ -79,6 +239,103 @@ pub mod account_creation {
         // Use crypto-primitives for vault address derivation
         address_derivation::derive_vault_address(owner, vault_id, program_id)
     }
+
+    /// Create session key PDA for a delegate acting on behalf of an owner
+    pub fn create_session_key_pda(
+        owner: &Pubkey,
+        delegate: &Pubkey,
+        program_id: &Pubkey
+    ) -> CommonResult<(Pubkey, u8)> {
+        validation::validate_not_default(owner)?;
+        validation::validate_not_default(delegate)?;
+        create_pda_with_validation(&[SESSION_KEY_SEED, owner.as_ref(), delegate.as_ref()], program_id)
+    }
+
+    /// Create idempotency log PDA recording recent payment keys of a sender
+    pub fn create_idempotency_log_pda(
+        owner: &Pubkey,
+        program_id: &Pubkey
+    ) -> CommonResult<(Pubkey, u8)> {
+        validation::validate_not_default(owner)?;
+        create_pda_with_validation(&[IDEMPOTENCY_LOG_SEED, owner.as_ref()], program_id)
+    }
+
+    /// Create scheduled transfer PDA for one of an owner's pending transfers
+    pub fn create_scheduled_transfer_pda(
+        owner: &Pubkey,
+        schedule_id: u64,
+        program_id: &Pubkey
+    ) -> CommonResult<(Pubkey, u8)> {
+        validation::validate_not_default(owner)?;
+        create_pda_with_validation(
+            &[SCHEDULED_TRANSFER_SEED, owner.as_ref(), &schedule_id.to_le_bytes()],
+            program_id
+        )
+    }
+
+    /// Create subscription PDA for a payer's recurring payment
+    pub fn create_subscription_pda(
+        payer: &Pubkey,
+        subscription_id: u64,
+        program_id: &Pubkey
+    ) -> CommonResult<(Pubkey, u8)> {
+        validation::validate_not_default(payer)?;
+        create_pda_with_validation(
+            &[SUBSCRIPTION_SEED, payer.as_ref(), &subscription_id.to_le_bytes()],
+            program_id
+        )
+    }
+
+    /// Create stream PDA for a sender's continuous payment
+    pub fn create_stream_pda(
+        sender: &Pubkey,
+        stream_id: u64,
+        program_id: &Pubkey
+    ) -> CommonResult<(Pubkey, u8)> {
+        validation::validate_not_default(sender)?;
+        create_pda_with_validation(
+            &[STREAM_SEED, sender.as_ref(), &stream_id.to_le_bytes()],
+            program_id
+        )
+    }
+
+    /// Create invoice PDA for a merchant's payment request
+    pub fn create_invoice_pda(
+        merchant: &Pubkey,
+        invoice_id: u64,
+        program_id: &Pubkey
+    ) -> CommonResult<(Pubkey, u8)> {
+        validation::validate_not_default(merchant)?;
+        create_pda_with_validation(
+            &[INVOICE_SEED, merchant.as_ref(), &invoice_id.to_le_bytes()],
+            program_id
+        )
+    }
+
+    /// Create multisig PDA, unique per creator and id
+    pub fn create_multisig_pda(
+        creator: &Pubkey,
+        multisig_id: u64,
+        program_id: &Pubkey
+    ) -> CommonResult<(Pubkey, u8)> {
+        validation::validate_not_default(creator)?;
+        create_pda_with_validation(
+            &[MULTISIG_SEED, creator.as_ref(), &multisig_id.to_le_bytes()],
+            program_id
+        )
+    }
+
+    /// Create PDA of a multisig's proposal with the given index
+    pub fn create_proposal_pda(
+        multisig: &Pubkey,
+        index: u64,
+        program_id: &Pubkey
+    ) -> CommonResult<(Pubkey, u8)> {
+        create_pda_with_validation(
+            &[PROPOSAL_SEED, multisig.as_ref(), &index.to_le_bytes()],
+            program_id
+        )
+    }
 }
 
 /// Account validation utilities
@@ -96,12 +353,13 @@ pub mod account_validation {
         Ok(())
     }
 
-    /// Validate signer account
+    /// Validate signer account. Wallets hold no data, so unlike other accounts a signer may
+    /// be empty
     pub fn validate_signer(account_info: &AccountInfo) -> CommonResult<()> {
         if !account_info.is_signer {
             return Err(CommonError::InsufficientPermissions);
         }
-        validate_account_info(account_info)
+        validation::validate_not_default(account_info.key)
     }
 
     /// Validate writable account
@@ -121,6 +379,126 @@ pub mod account_validation {
         validate_account_info(account_info)
     }
     
+    /// Validate that an account is the program-owned attestation of `attestor` about `user`
+    pub fn validate_attestation_account(
+        account_info: &AccountInfo,
+        user: &Pubkey,
+        attestor: &Pubkey,
+        program_id: &Pubkey
+    ) -> CommonResult<()> {
+        let (expected_address, _bump) = account_creation::create_attestation_pda(user, attestor, program_id)?;
+        
+        if account_info.key != &expected_address {
+            return Err(CommonError::AccountValidationFailed);
+        }
+        
+        validate_account_owner(account_info, program_id)
+    }
+
+    /// Validate that something expiring at `expires_at` is still valid at `now`
+    pub fn validate_not_expired(expires_at: i64, now: i64) -> CommonResult<()> {
+        if now >= expires_at {
+            return Err(CommonError::Custom("Expired".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Validate that a value last updated at `updated_at` is at most `max_age` seconds old
+    pub fn validate_fresh(updated_at: i64, now: i64, max_age: u64) -> CommonResult<()> {
+        if updated_at == 0 || now.saturating_sub(updated_at) > max_age as i64 {
+            return Err(CommonError::Custom("Stale".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Validate that `expected_authority` holds the upgrade authority recorded in a
+    /// program's `ProgramData` account (owned by the BPF upgradeable loader)
+    pub fn validate_program_upgrade_authority(
+        program_data_account: &AccountInfo,
+        expected_authority: &Pubkey
+    ) -> CommonResult<()> {
+        validation::validate_owner(program_data_account.owner, &bpf_loader_upgradeable::id())
+            .map_err(|_| CommonError::AccountValidationFailed)?;
+        
+        let state = program_data_account
+            .deserialize_data::<UpgradeableLoaderState>()
+            .map_err(|_| CommonError::AccountValidationFailed)?;
+        
+        match state {
+            UpgradeableLoaderState::ProgramData {
+                upgrade_authority_address: Some(authority),
+                ..
+            } if authority == *expected_authority => Ok(()),
+            UpgradeableLoaderState::ProgramData { .. } => Err(CommonError::InsufficientPermissions),
+            _ => Err(CommonError::AccountValidationFailed),
+        }
+    }
+    
+    /// Validate that the current instruction runs as a CPI from `expected_caller_program`
+    ///
+    /// Uses the stack height to reject direct invocation and the instructions sysvar to
+    /// identify the top-level program of the transaction instruction being executed.
+    pub fn validate_invoked_by(
+        instructions_sysvar_account: &AccountInfo,
+        expected_caller_program: &Pubkey
+    ) -> CommonResult<()> {
+        if get_stack_height() <= TRANSACTION_LEVEL_STACK_HEIGHT {
+            return Err(CommonError::InsufficientPermissions);
+        }
+        
+        let current_index = instructions_sysvar::load_current_index_checked(instructions_sysvar_account)
+            .map_err(|_| CommonError::AccountValidationFailed)?;
+        let top_level = instructions_sysvar::load_instruction_at_checked(
+            current_index as usize,
+            instructions_sysvar_account
+        )
+        .map_err(|_| CommonError::AccountValidationFailed)?;
+        
+        if top_level.program_id != *expected_caller_program {
+            return Err(CommonError::InsufficientPermissions);
+        }
+        
+        Ok(())
+    }
+
+    /// Require an ed25519 program instruction in this transaction verifying `signer`'s
+    /// signature over `message` in the off-chain message format
+    pub fn validate_offchain_signature(
+        instructions_sysvar_account: &AccountInfo,
+        signer: &Pubkey,
+        message: &OffchainMessage
+    ) -> CommonResult<()> {
+        let current_index = instructions_sysvar::load_current_index_checked(instructions_sysvar_account)
+            .map_err(|_| CommonError::AccountValidationFailed)?;
+        
+        for index in 0..current_index as usize {
+            let instruction = instructions_sysvar::load_instruction_at_checked(index, instructions_sysvar_account)
+                .map_err(|_| CommonError::AccountValidationFailed)?;
+            if instruction.program_id == ed25519_program::id()
+                && offchain_message::verify_ed25519_instruction_data(&instruction.data, signer, message).is_ok()
+            {
+                return Ok(());
+            }
+        }
+        
+        Err(CommonError::InsufficientPermissions)
+    }
+
+    /// Check whether every bit of `feature` is set in `feature_flags`
+    pub fn is_feature_enabled(feature_flags: u64, feature: u64) -> bool {
+        feature != 0 && feature_flags & feature == feature
+    }
+
+    /// Require a feature to be enabled before running a gated instruction
+    pub fn validate_feature_enabled(feature_flags: u64, feature: u64) -> CommonResult<()> {
+        if !is_feature_enabled(feature_flags, feature) {
+            return Err(CommonError::Custom(
+                format!("Feature {:#x} is not enabled", feature)
+            ));
+        }
+        Ok(())
+    }
+    
     /// Cryptographic validation using crypto-primitives (only account-utils has this)
     pub fn validate_account_with_crypto_proof(
         account_info: &AccountInfo,
@@ -170,6 +548,18 @@ pub mod account_validation {
         
         security::generate_security_token(account, operation, timestamp)
     }
+
+    /// Validate the anti-spam proof of work required to create an account
+    pub fn validate_account_creation_pow(
+        user: &Pubkey,
+        nonce: u64,
+        difficulty: u8
+    ) -> CommonResult<()> {
+        if difficulty == 0 {
+            return Ok(());
+        }
+        security::verify_pow(user, nonce, difficulty)
+    }
 }
 
 /// Account data management
@@ -205,4 +595,676 @@ pub mod account_data {
         }
         Ok(())
     }
-} 
\ No newline at end of file
+
+    /// Serialize data into the start of an account's data buffer
+    pub fn serialize_account_data<T: BorshSerialize>(
+        account_info: &AccountInfo,
+        data: &T
+    ) -> CommonResult<()> {
+        let bytes = data.try_to_vec()
+            .map_err(|_| CommonError::InvalidCalculation)?;
+        validate_account_space(account_info, bytes.len())?;
+
+        account_info.data.borrow_mut()[..bytes.len()].copy_from_slice(&bytes);
+        Ok(())
+    }
+
+    /// Copy `bytes` into an account's data at `offset`
+    pub fn write_account_bytes(
+        account_info: &AccountInfo,
+        offset: usize,
+        bytes: &[u8],
+    ) -> CommonResult<()> {
+        let end = offset.checked_add(bytes.len())
+            .ok_or(CommonError::InvalidCalculation)?;
+        
+        account_info.data.borrow_mut()
+            .get_mut(offset..end)
+            .ok_or(CommonError::AccountValidationFailed)?
+            .copy_from_slice(bytes);
+        Ok(())
+    }
+
+    /// Check an account's full data against a `hashing::hash_account_data` digest
+    pub fn verify_account_data_hash(
+        account_info: &AccountInfo,
+        expected_hash: &[u8; 32],
+    ) -> CommonResult<()> {
+        hashing::verify_account_integrity(&account_info.data.borrow(), expected_hash)
+    }
+
+    /// Size of a TLV record header: little-endian `u16` type and `u16` length
+    pub const TLV_HEADER_LEN: usize = 4;
+
+    /// Split a TLV region into `(type, value)` records, stopping at a zero type (free space)
+    pub fn read_tlv(data: &[u8]) -> CommonResult<Vec<(u16, &[u8])>> {
+        let mut records = Vec::new();
+        let mut offset = 0;
+        
+        while offset + TLV_HEADER_LEN <= data.len() {
+            let record_type = u16::from_le_bytes([data[offset], data[offset + 1]]);
+            if record_type == 0 {
+                break;
+            }
+            
+            let len = u16::from_le_bytes([data[offset + 2], data[offset + 3]]) as usize;
+            let start = offset + TLV_HEADER_LEN;
+            let value = data.get(start..start + len)
+                .ok_or(CommonError::AccountValidationFailed)?;
+            records.push((record_type, value));
+            offset = start + len;
+        }
+        
+        Ok(records)
+    }
+
+    /// Write TLV records to the start of `data`, zero-filling the remainder
+    pub fn write_tlv(data: &mut [u8], records: &[(u16, Vec<u8>)]) -> CommonResult<()> {
+        let mut offset = 0;
+        
+        for (record_type, value) in records {
+            if *record_type == 0 {
+                return Err(CommonError::Custom("TLV type 0 marks free space".to_string()));
+            }
+            
+            let len = u16::try_from(value.len())
+                .map_err(|_| CommonError::InvalidCalculation)?;
+            let end = offset + TLV_HEADER_LEN + value.len();
+            if end > data.len() {
+                return Err(CommonError::AccountValidationFailed);
+            }
+            
+            data[offset..offset + 2].copy_from_slice(&record_type.to_le_bytes());
+            data[offset + 2..offset + 4].copy_from_slice(&len.to_le_bytes());
+            data[offset + TLV_HEADER_LEN..end].copy_from_slice(value);
+            offset = end;
+        }
+        
+        data[offset..].fill(0);
+        Ok(())
+    }
+} 
+
+/// In-place upgrades of account layouts that only append fixed-size fields. A zero-filled
+/// tail decodes those fields as zero, so growing the account yields them at that default.
+pub mod migration {
+    use super::*;
+    use solana_program::{program::invoke, rent::Rent, system_instruction, sysvar::Sysvar};
+
+    /// Grow `account_info` to `new_len` bytes with a zeroed tail, topping its rent up from
+    /// `payer`; accounts already `new_len` bytes or longer are left as they are. Returns
+    /// whether the account grew.
+    pub fn grow_account<'a>(
+        account_info: &AccountInfo<'a>,
+        payer: &AccountInfo<'a>,
+        system_program: &AccountInfo<'a>,
+        new_len: usize,
+    ) -> CommonResult<bool> {
+        if account_info.data_len() >= new_len {
+            return Ok(false);
+        }
+        
+        let required = Rent::get()?.minimum_balance(new_len);
+        let top_up = required.saturating_sub(account_info.lamports());
+        if top_up > 0 {
+            invoke(
+                &system_instruction::transfer(payer.key, account_info.key, top_up),
+                &[payer.clone(), account_info.clone(), system_program.clone()],
+            )?;
+        }
+        
+        account_info.realloc(new_len, true)?;
+        Ok(true)
+    }
+}
+
+/// Custody of program-held SPL token accounts: one program authority PDA owns every token
+/// account the program controls (vaults, escrows, treasury holdings) and signs their CPIs
+pub mod custody {
+    use super::*;
+
+    /// SPL Token program
+    pub const TOKEN_PROGRAM_ID: Pubkey = pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+    /// Token-2022 program, whose accounts share the SPL Token base layout
+    pub const TOKEN_2022_PROGRAM_ID: Pubkey = pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+    /// Length of the base token account layout
+    pub const TOKEN_ACCOUNT_LEN: usize = 165;
+
+    // Base token account layout: mint, owner, amount, delegate, state, is_native,
+    // delegated_amount, close_authority
+    const OWNER_OFFSET: usize = 32;
+    const AMOUNT_OFFSET: usize = 64;
+    const DELEGATE_OFFSET: usize = 72;
+    const STATE_OFFSET: usize = 108;
+    const CLOSE_AUTHORITY_OFFSET: usize = 129;
+    const STATE_UNINITIALIZED: u8 = 0;
+
+    /// The program authority PDA and its bump
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct ProgramAuthority {
+        pub address: Pubkey,
+        pub bump: u8,
+    }
+
+    impl ProgramAuthority {
+        /// Derive the program authority, searching for its bump
+        pub fn find(program_id: &Pubkey) -> CommonResult<Self> {
+            let (address, bump) = account_creation::create_program_authority_pda(program_id)?;
+            Ok(Self { address, bump })
+        }
+
+        /// Rebuild the program authority from a known bump, skipping the bump search
+        pub fn from_bump(program_id: &Pubkey, bump: u8) -> CommonResult<Self> {
+            let address = Pubkey::create_program_address(&[PROGRAM_AUTHORITY_SEED, &[bump]], program_id)
+                .map_err(|_| CommonError::Custom("Invalid program authority bump".to_string()))?;
+            Ok(Self { address, bump })
+        }
+
+        /// Seeds signing for the program authority in `invoke_signed`
+        pub fn signer_seeds(&self) -> [&[u8]; 2] {
+            [PROGRAM_AUTHORITY_SEED, std::slice::from_ref(&self.bump)]
+        }
+
+        /// Invoke `instruction` with the program authority as a signer
+        pub fn invoke_signed(&self, instruction: &Instruction, account_infos: &[AccountInfo]) -> ProgramResult {
+            invoke_signed(instruction, account_infos, &[&self.signer_seeds()])
+        }
+
+        /// Check that `authority_info` is the program authority
+        pub fn validate(&self, authority_info: &AccountInfo) -> CommonResult<()> {
+            if *authority_info.key != self.address {
+                return Err(CommonError::AccountValidationFailed);
+            }
+            Ok(())
+        }
+
+        /// Check that `token_account_info` is an initialized token account in the program's
+        /// sole custody: owned by the authority, with no delegate and no foreign close authority
+        pub fn validate_token_account(&self, token_account_info: &AccountInfo) -> CommonResult<()> {
+            if *token_account_info.owner != TOKEN_PROGRAM_ID && *token_account_info.owner != TOKEN_2022_PROGRAM_ID {
+                return Err(CommonError::AccountValidationFailed);
+            }
+            
+            let data = token_account_info.data.borrow();
+            if data.len() < TOKEN_ACCOUNT_LEN || data[STATE_OFFSET] == STATE_UNINITIALIZED {
+                return Err(CommonError::AccountValidationFailed);
+            }
+            if data[OWNER_OFFSET..OWNER_OFFSET + 32] != self.address.to_bytes() {
+                return Err(CommonError::InsufficientPermissions);
+            }
+            if !coption_is_none_or(&data[DELEGATE_OFFSET..], &Pubkey::default())
+                || !coption_is_none_or(&data[CLOSE_AUTHORITY_OFFSET..], &self.address)
+            {
+                return Err(CommonError::InsufficientPermissions);
+            }
+            Ok(())
+        }
+
+        /// Balance of a token account in the program's custody
+        pub fn token_amount(&self, token_account_info: &AccountInfo) -> CommonResult<u64> {
+            self.validate_token_account(token_account_info)?;
+            let data = token_account_info.data.borrow();
+            let mut amount = [0u8; 8];
+            amount.copy_from_slice(&data[AMOUNT_OFFSET..AMOUNT_OFFSET + 8]);
+            Ok(u64::from_le_bytes(amount))
+        }
+    }
+
+    /// Whether a `COption<Pubkey>` (u32 tag, then the key) is `None` or holds `allowed`
+    fn coption_is_none_or(data: &[u8], allowed: &Pubkey) -> bool {
+        match data[..4] {
+            [0, 0, 0, 0] => true,
+            [1, 0, 0, 0] => data[4..36] == allowed.to_bytes(),
+            _ => false,
+        }
+    }
+}
+
+/// Known-answer vectors for the hashing, seed and PDA derivation schemes, so client ports can
+/// check byte-for-byte compatibility (exported as JSON by `cargo xtask export-test-vectors`)
+#[cfg(feature = "test-vectors")]
+pub mod test_vectors {
+    use super::*;
+    use account_creation::*;
+    use crypto_primitives::hashing;
+    use std::str::FromStr;
+
+    /// Program id the PDA vectors are derived under
+    pub const PROGRAM_ID: &str = "US517G5965aydkZ46HS38QLi7UQiSojurfbQfKCELFx";
+    /// Key used as the owner, user or primary account input
+    pub const OWNER: &str = "4vJ9JU1bJJE96FWSJKvHsmmFADCg4gpZQff4P3bkLKi";
+    /// Key used as the delegate, attestor or multisig input
+    pub const OTHER: &str = "8qbHbw2BbbTHBW1sbeqakYXVKRQM8Ne7pLK7m6CVfeR";
+
+    /// `function` applied to `inputs` returns `output`
+    ///
+    /// Inputs are named after the Rust parameters: pubkeys in base58, byte strings in hex,
+    /// integers in decimal and other strings verbatim. Outputs are hex bytes, or the base58
+    /// address of a PDA with its `bump`.
+    #[derive(Debug)]
+    pub struct KnownAnswer {
+        pub function: &'static str,
+        pub inputs: &'static [(&'static str, &'static str)],
+        pub output: &'static str,
+        pub bump: Option<u8>,
+    }
+
+    pub const VECTORS: &[KnownAnswer] = &[
+        KnownAnswer {
+            function: "hash_account_data",
+            inputs: &[("data", "")],
+            output: "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470",
+            bump: None,
+        },
+        KnownAnswer {
+            function: "hash_account_data",
+            inputs: &[("data", "00")],
+            output: "bc36789e7a1e281436464229828f817d6612f7b477d66591ff96a9e064bcc98a",
+            bump: None,
+        },
+        KnownAnswer {
+            function: "hash_account_data",
+            inputs: &[("data", "68656c6c6f")],
+            output: "1c8aff950685c2ed4bc3174f3472287b56d9517b9c948127319a09a7a36deac8",
+            bump: None,
+        },
+        KnownAnswer {
+            function: "create_account_identifier",
+            inputs: &[("owner", OWNER), ("seed", "")],
+            output: "cebc8882fecbec7fb80d2cf4b312bec018884c2d66667c67a90508214bd8bafc",
+            bump: None,
+        },
+        KnownAnswer {
+            function: "create_account_identifier",
+            inputs: &[("owner", OWNER), ("seed", "7573657220736565640001")],
+            output: "1eeb83969d7418bd9c59b3bade558bf21135db9f1b86ba6c956f6fdc1a4e4089",
+            bump: None,
+        },
+        KnownAnswer {
+            function: "generate_account_salt",
+            inputs: &[("base", OWNER), ("nonce", "42")],
+            output: "607a462f5a4640cf46e3dbd6e293e3b47bd78ede59b13c3ab94baa85ffe97623",
+            bump: None,
+        },
+        KnownAnswer {
+            function: "merkle_empty_root",
+            inputs: &[("depth", "3")],
+            output: "21ddb9a356815c3fac1026b6dec5df3124afbadb485c9ba5a3e3398a04b7ba85",
+            bump: None,
+        },
+        KnownAnswer {
+            function: "merkle_compute_root",
+            inputs: &[
+                ("leaf", "1111111111111111111111111111111111111111111111111111111111111111"),
+                ("index", "5"),
+                (
+                    "proof",
+                    "2222222222222222222222222222222222222222222222222222222222222222\
+                     3333333333333333333333333333333333333333333333333333333333333333\
+                     4444444444444444444444444444444444444444444444444444444444444444",
+                ),
+            ],
+            output: "4b223fcf644ca775a0492480beb7038df266e7141693be45888d922a9b1e7b77",
+            bump: None,
+        },
+        KnownAnswer {
+            function: "generate_deterministic_seed",
+            inputs: &[("base", OWNER), ("identifier", "premium"), ("nonce", "7")],
+            output: "010101010101010101010101010101017072656d69756d07000000",
+            bump: None,
+        },
+        KnownAnswer {
+            function: "generate_deterministic_seed",
+            inputs: &[("base", OWNER), ("identifier", "truncated_identifier"), ("nonce", "4294967295")],
+            output: "010101010101010101010101010101017472756e63617465ffffffff",
+            bump: None,
+        },
+        KnownAnswer {
+            function: "create_hierarchical_seed",
+            inputs: &[("parent", OWNER), ("child_type", "1"), ("child_index", "513")],
+            output: "0101010101010101010101010101010101010101010102",
+            bump: None,
+        },
+        KnownAnswer {
+            function: "generate_temporal_seed",
+            inputs: &[("base", OWNER), ("timestamp", "1700000000")],
+            output: "0101010101010101010101010101010100f1536500000000",
+            bump: None,
+        },
+        KnownAnswer {
+            function: "generate_temporal_seed",
+            inputs: &[("base", OWNER), ("timestamp", "-1")],
+            output: "01010101010101010101010101010101ffffffffffffffff",
+            bump: None,
+        },
+        KnownAnswer {
+            function: "derive_secondary_address",
+            inputs: &[("primary", OWNER), ("derivation_path", "0102"), ("program_id", PROGRAM_ID)],
+            output: "CGokx6n4nhnUUtLVmaoSVjH7WhrY5pmtFsMLoSRF4F5H",
+            bump: Some(255),
+        },
+        KnownAnswer {
+            function: "derive_metadata_address",
+            inputs: &[("account", OWNER), ("metadata_type", "profile"), ("program_id", PROGRAM_ID)],
+            output: "3k7UsJbNWMdppW6bzAnvfZoQYjtY5JjcQnEarJoB2QYN",
+            bump: Some(254),
+        },
+        KnownAnswer {
+            function: "create_program_state_pda",
+            inputs: &[("program_id", PROGRAM_ID)],
+            output: "Btjr689GfK8U825KGmtPC5XPzW7J2mtwaUndPDsTGDn9",
+            bump: Some(253),
+        },
+        KnownAnswer {
+            function: "create_authority_history_pda",
+            inputs: &[("program_id", PROGRAM_ID)],
+            output: "EFLd1C46R3SvMKeboEnzeCKd3sp3NVud21Rhg2A6CRzq",
+            bump: Some(255),
+        },
+        KnownAnswer {
+            function: "create_metrics_pda",
+            inputs: &[("program_id", PROGRAM_ID)],
+            output: "9fQa3PrBYpi6o5ckfNSLs8aB5V8W53YmHQS84VNhpQff",
+            bump: Some(255),
+        },
+        KnownAnswer {
+            function: "create_program_config_pda",
+            inputs: &[("program_id", PROGRAM_ID)],
+            output: "EW13q4nZUtSXV5aEfxfN6ncgSYTZumihRexUTGqh1fEN",
+            bump: Some(248),
+        },
+        KnownAnswer {
+            function: "create_bounty_pool_pda",
+            inputs: &[("program_id", PROGRAM_ID)],
+            output: "7eoVAGna2X627ECY4JJ86CvAb5phj8MLg9Qtqu54ABhG",
+            bump: Some(252),
+        },
+        KnownAnswer {
+            function: "create_treasury_pda",
+            inputs: &[("program_id", PROGRAM_ID)],
+            output: "4attr7g63ikMyckRQUCFmDQBH2kvmMBtbvRV9nbVF723",
+            bump: Some(255),
+        },
+        KnownAnswer {
+            function: "create_compliance_config_pda",
+            inputs: &[("program_id", PROGRAM_ID)],
+            output: "54Di9srjWb9rxGUc6Dht1yc78Jm1F7C475eQ55Lk5bNf",
+            bump: Some(255),
+        },
+        KnownAnswer {
+            function: "create_audit_log_pda",
+            inputs: &[("program_id", PROGRAM_ID)],
+            output: "675ep8XTuHgDoLFgTsEDxBDbs1JZBNZF8FikBmzxuqVH",
+            bump: Some(255),
+        },
+        KnownAnswer {
+            function: "create_rate_state_pda",
+            inputs: &[("program_id", PROGRAM_ID)],
+            output: "76nHCRySWcZ71TJLBtypvBtN6QBy3ZBiaXswcNnUCoGy",
+            bump: Some(255),
+        },
+        KnownAnswer {
+            function: "create_lending_config_pda",
+            inputs: &[("program_id", PROGRAM_ID)],
+            output: "DA4pH9jMtbje3TAzKNMpGVL3Cp7LhGD5NAzbYBdeaded",
+            bump: Some(254),
+        },
+        KnownAnswer {
+            function: "create_insurance_fund_pda",
+            inputs: &[("program_id", PROGRAM_ID)],
+            output: "74DDzwvAiagj2d7kAb8KZL71c6A2433BGx11FzVSS4J",
+            bump: Some(255),
+        },
+        KnownAnswer {
+            function: "create_fee_schedule_pda",
+            inputs: &[("program_id", PROGRAM_ID)],
+            output: "3CiwaKwgzdbT236iotJ9uuZPzQyhSaSevego2odZN8sB",
+            bump: Some(255),
+        },
+        KnownAnswer {
+            function: "create_program_authority_pda",
+            inputs: &[("program_id", PROGRAM_ID)],
+            output: "FYkZqDUNL1BZXVafKz5Jz73DGPE62o4GpsSA1Mdidrnt",
+            bump: Some(255),
+        },
+        KnownAnswer {
+            function: "create_position_pda",
+            inputs: &[("owner", OWNER), ("program_id", PROGRAM_ID)],
+            output: "H41P2Dm1wehhxQXDaXootQ8KyuiLDxkExkypKbgTfMqY",
+            bump: Some(254),
+        },
+        KnownAnswer {
+            function: "create_account_limits_pda",
+            inputs: &[("user_account", OWNER), ("program_id", PROGRAM_ID)],
+            output: "FJuhbRnMuUxRo7ikUrYgcCp1RNmtSccMvfEzpa14XdX",
+            bump: Some(253),
+        },
+        KnownAnswer {
+            function: "create_attestation_pda",
+            inputs: &[("user", OWNER), ("attestor", OTHER), ("program_id", PROGRAM_ID)],
+            output: "8dNB9HXHu97x4NxYcmSWZ6WJHj1CaPhxvHkW3EtKJ1cc",
+            bump: Some(253),
+        },
+        KnownAnswer {
+            function: "create_delegations_pda",
+            inputs: &[("owner", OWNER), ("program_id", PROGRAM_ID)],
+            output: "Ao9Zk4Wjm8e6ZsyBE8YFim9aC9MdvjPZ3uf647qWJwxS",
+            bump: Some(254),
+        },
+        KnownAnswer {
+            function: "create_lock_pda",
+            inputs: &[("owner", OWNER), ("lock_id", "3"), ("program_id", PROGRAM_ID)],
+            output: "izbi3wJh97p5BJhjgnj7Qkdq6J1JShcs283mHvcsyR3",
+            bump: Some(255),
+        },
+        KnownAnswer {
+            function: "create_daily_stats_pda",
+            inputs: &[("day_start", "1699920000"), ("program_id", PROGRAM_ID)],
+            output: "FAy83a6HAmPjFGWfE4yobK9k2DxbMhXLo6Hg9GuxPwZh",
+            bump: Some(252),
+        },
+        KnownAnswer {
+            function: "create_cranker_pda",
+            inputs: &[("authority", OWNER), ("program_id", PROGRAM_ID)],
+            output: "3Q9N18HZy5Pnd1iN8DwABbprC6XstDNeRJr73FCRVD4L",
+            bump: Some(255),
+        },
+        KnownAnswer {
+            function: "create_user_pda",
+            inputs: &[("user", OWNER), ("program_id", PROGRAM_ID)],
+            output: "5GbCLQ4WwFuw87FJmt9RGqNnKEZFUhhw7uoUW98bvoqZ",
+            bump: Some(253),
+        },
+        KnownAnswer {
+            function: "create_advanced_user_pda",
+            inputs: &[("user", OWNER), ("user_type", "premium"), ("nonce", "7"), ("program_id", PROGRAM_ID)],
+            output: "6Eeh5aJNLBXtMtStMMB5aZsvZm57iJYdT4RoUNvrCd1R",
+            bump: Some(255),
+        },
+        KnownAnswer {
+            function: "create_vault_pda",
+            inputs: &[("owner", OWNER), ("vault_id", "9"), ("program_id", PROGRAM_ID)],
+            output: "HRsR8bFSjGg4Z6MxMZByar4hY4qwE8pgWz9BruVu2oir",
+            bump: Some(255),
+        },
+        KnownAnswer {
+            function: "create_session_key_pda",
+            inputs: &[("owner", OWNER), ("delegate", OTHER), ("program_id", PROGRAM_ID)],
+            output: "ApMZ8iWqLK753mPXpHPg248qST1oc6S1YFRnRGLuS4Ha",
+            bump: Some(254),
+        },
+        KnownAnswer {
+            function: "create_idempotency_log_pda",
+            inputs: &[("owner", OWNER), ("program_id", PROGRAM_ID)],
+            output: "83JmYRcCmfKrJ5RiW5WLu7SznVsdDQFiSTRBDgFuoWPX",
+            bump: Some(255),
+        },
+        KnownAnswer {
+            function: "create_scheduled_transfer_pda",
+            inputs: &[("owner", OWNER), ("schedule_id", "5"), ("program_id", PROGRAM_ID)],
+            output: "4azdpygdJHpjoK1WBgWeecy8YENZATNaDLjmd4XNrspo",
+            bump: Some(255),
+        },
+        KnownAnswer {
+            function: "create_subscription_pda",
+            inputs: &[("payer", OWNER), ("subscription_id", "5"), ("program_id", PROGRAM_ID)],
+            output: "HunXZPb8uyQXSHwHRrAsVCPXHbeK6r9jJa3d9MnPSfRT",
+            bump: Some(255),
+        },
+        KnownAnswer {
+            function: "create_stream_pda",
+            inputs: &[("sender", OWNER), ("stream_id", "5"), ("program_id", PROGRAM_ID)],
+            output: "CYvergUrLajWowK8zhRmLj6GittxCKp4zkjZKBpSREvg",
+            bump: Some(254),
+        },
+        KnownAnswer {
+            function: "create_invoice_pda",
+            inputs: &[("merchant", OWNER), ("invoice_id", "5"), ("program_id", PROGRAM_ID)],
+            output: "E6yM2T7tJGgxEZpSyJLhG9VQxPYoKwcZfP2Lc3wTr5jJ",
+            bump: Some(254),
+        },
+        KnownAnswer {
+            function: "create_multisig_pda",
+            inputs: &[("creator", OWNER), ("multisig_id", "5"), ("program_id", PROGRAM_ID)],
+            output: "EFfNSQmB3wSUkHyrEM8osodXiuemtNzUsMZdAyM7D1CL",
+            bump: Some(255),
+        },
+        KnownAnswer {
+            function: "create_proposal_pda",
+            inputs: &[("multisig", OTHER), ("index", "5"), ("program_id", PROGRAM_ID)],
+            output: "5upeCRwPMbyEar2hoNDVR6taFTrc2Mqg9Fpug7s6zRJU",
+            bump: Some(252),
+        },
+    ];
+
+    pub fn to_hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    fn from_hex(value: &str) -> CommonResult<Vec<u8>> {
+        if !value.len().is_multiple_of(2) {
+            return Err(CommonError::Custom(format!("Odd-length hex {}", value)));
+        }
+        (0..value.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&value[i..i + 2], 16))
+            .collect::<Result<_, _>>()
+            .map_err(|_| CommonError::Custom(format!("Invalid hex {}", value)))
+    }
+
+    fn input(vector: &KnownAnswer, name: &str) -> CommonResult<&'static str> {
+        vector.inputs.iter().find(|(key, _)| *key == name).map(|(_, value)| *value).ok_or_else(|| {
+            CommonError::Custom(format!("{} is missing input {}", vector.function, name))
+        })
+    }
+
+    fn number<T: FromStr>(vector: &KnownAnswer, name: &str) -> CommonResult<T> {
+        let value = input(vector, name)?;
+        value.parse().map_err(|_| CommonError::Custom(format!("Invalid {} {}", name, value)))
+    }
+
+    /// Recompute `vector` with this implementation, as `(output, bump)`
+    pub fn compute(vector: &KnownAnswer) -> CommonResult<(String, Option<u8>)> {
+        let key = |name: &str| number::<Pubkey>(vector, name);
+        let bytes = |name: &str| input(vector, name).and_then(from_hex);
+        let input = |name: &str| input(vector, name);
+        
+        let hex = |data: &[u8]| Ok((to_hex(data), None));
+        let pda = |result: CommonResult<(Pubkey, u8)>| result.map(|(address, bump)| (address.to_string(), Some(bump)));
+        let program_id = || key("program_id");
+        
+        match vector.function {
+            "hash_account_data" => hex(&hashing::hash_account_data(&bytes("data")?)),
+            "create_account_identifier" => hex(&hashing::create_account_identifier(&key("owner")?, &bytes("seed")?)),
+            "generate_account_salt" => hex(&hashing::generate_account_salt(&key("base")?, number(vector, "nonce")?)),
+            "merkle_empty_root" => hex(&merkle::empty_root(number(vector, "depth")?)),
+            "merkle_compute_root" => {
+                let node = |bytes: &[u8]| -> CommonResult<[u8; 32]> {
+                    bytes.try_into().map_err(|_| CommonError::Custom("Merkle nodes are 32 bytes".to_string()))
+                };
+                let proof = bytes("proof")?.chunks(32).map(node).collect::<CommonResult<Vec<_>>>()?;
+                hex(&merkle::compute_root(&node(&bytes("leaf")?)?, number(vector, "index")?, &proof))
+            }
+            "generate_deterministic_seed" => hex(&seed_generation::generate_deterministic_seed(
+                &key("base")?,
+                input("identifier")?,
+                number(vector, "nonce")?,
+            )?),
+            "create_hierarchical_seed" => hex(&seed_generation::create_hierarchical_seed(
+                &key("parent")?,
+                number(vector, "child_type")?,
+                number(vector, "child_index")?,
+            )?),
+            "generate_temporal_seed" => hex(&seed_generation::generate_temporal_seed(&key("base")?, number(vector, "timestamp")?)),
+            "derive_secondary_address" => pda(address_derivation::derive_secondary_address(
+                &key("primary")?,
+                &bytes("derivation_path")?,
+                &program_id()?,
+            )),
+            "derive_metadata_address" => pda(address_derivation::derive_metadata_address(
+                &key("account")?,
+                input("metadata_type")?,
+                &program_id()?,
+            )),
+            "create_program_state_pda" => pda(create_program_state_pda(&program_id()?)),
+            "create_authority_history_pda" => pda(create_authority_history_pda(&program_id()?)),
+            "create_metrics_pda" => pda(create_metrics_pda(&program_id()?)),
+            "create_program_config_pda" => pda(create_program_config_pda(&program_id()?)),
+            "create_bounty_pool_pda" => pda(create_bounty_pool_pda(&program_id()?)),
+            "create_treasury_pda" => pda(create_treasury_pda(&program_id()?)),
+            "create_compliance_config_pda" => pda(create_compliance_config_pda(&program_id()?)),
+            "create_audit_log_pda" => pda(create_audit_log_pda(&program_id()?)),
+            "create_rate_state_pda" => pda(create_rate_state_pda(&program_id()?)),
+            "create_lending_config_pda" => pda(create_lending_config_pda(&program_id()?)),
+            "create_insurance_fund_pda" => pda(create_insurance_fund_pda(&program_id()?)),
+            "create_fee_schedule_pda" => pda(create_fee_schedule_pda(&program_id()?)),
+            "create_program_authority_pda" => pda(create_program_authority_pda(&program_id()?)),
+            "create_position_pda" => pda(create_position_pda(&key("owner")?, &program_id()?)),
+            "create_account_limits_pda" => pda(create_account_limits_pda(&key("user_account")?, &program_id()?)),
+            "create_attestation_pda" => pda(create_attestation_pda(&key("user")?, &key("attestor")?, &program_id()?)),
+            "create_delegations_pda" => pda(create_delegations_pda(&key("owner")?, &program_id()?)),
+            "create_lock_pda" => pda(create_lock_pda(&key("owner")?, number(vector, "lock_id")?, &program_id()?)),
+            "create_daily_stats_pda" => pda(create_daily_stats_pda(number(vector, "day_start")?, &program_id()?)),
+            "create_cranker_pda" => pda(create_cranker_pda(&key("authority")?, &program_id()?)),
+            "create_user_pda" => pda(create_user_pda(&key("user")?, &program_id()?)),
+            "create_advanced_user_pda" => pda(create_advanced_user_pda(
+                &key("user")?,
+                input("user_type")?,
+                number(vector, "nonce")?,
+                &program_id()?,
+            )),
+            "create_vault_pda" => pda(create_vault_pda(&key("owner")?, number(vector, "vault_id")?, &program_id()?)),
+            "create_session_key_pda" => pda(create_session_key_pda(&key("owner")?, &key("delegate")?, &program_id()?)),
+            "create_idempotency_log_pda" => pda(create_idempotency_log_pda(&key("owner")?, &program_id()?)),
+            "create_scheduled_transfer_pda" => pda(create_scheduled_transfer_pda(
+                &key("owner")?,
+                number(vector, "schedule_id")?,
+                &program_id()?,
+            )),
+            "create_subscription_pda" => pda(create_subscription_pda(
+                &key("payer")?,
+                number(vector, "subscription_id")?,
+                &program_id()?,
+            )),
+            "create_stream_pda" => pda(create_stream_pda(&key("sender")?, number(vector, "stream_id")?, &program_id()?)),
+            "create_invoice_pda" => pda(create_invoice_pda(&key("merchant")?, number(vector, "invoice_id")?, &program_id()?)),
+            "create_multisig_pda" => pda(create_multisig_pda(&key("creator")?, number(vector, "multisig_id")?, &program_id()?)),
+            "create_proposal_pda" => pda(create_proposal_pda(&key("multisig")?, number(vector, "index")?, &program_id()?)),
+            function => Err(CommonError::Custom(format!("Unknown test vector function {}", function))),
+        }
+    }
+
+    /// Check every vector against this implementation, naming the first mismatch
+    pub fn verify() -> CommonResult<()> {
+        for vector in VECTORS {
+            let computed = compute(vector)?;
+            if computed != (vector.output.to_string(), vector.bump) {
+                return Err(CommonError::Custom(format!(
+                    "{} {:?}: expected {} {:?}, computed {} {:?}",
+                    vector.function, vector.inputs, vector.output, vector.bump, computed.0, computed.1
+                )));
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,4 +1,4 @@
-use solana_program::pubkey::Pubkey;
+use solana_program::{program_error::ProgramError, pubkey::Pubkey};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -9,8 +9,26 @@ pub enum CommonError {
     AccountValidationFailed,
     #[error("Insufficient permissions")]
     InsufficientPermissions,
+    #[error("Price sources diverge beyond the allowed threshold")]
+    PriceDivergence,
     #[error("Custom error: {0}")]
     Custom(String),
+    #[error("Program error: {0}")]
+    Program(#[from] ProgramError),
+}
+
+impl From<CommonError> for ProgramError {
+    /// Wrapped program errors keep their original code; library errors map to the closest builtin
+    fn from(error: CommonError) -> Self {
+        match error {
+            CommonError::Program(error) => error,
+            CommonError::InvalidCalculation => ProgramError::InvalidArgument,
+            CommonError::AccountValidationFailed => ProgramError::InvalidAccountData,
+            CommonError::InsufficientPermissions => ProgramError::MissingRequiredSignature,
+            CommonError::PriceDivergence => ProgramError::InvalidAccountData,
+            CommonError::Custom(_) => ProgramError::InvalidArgument,
+        }
+    }
 }
 
 /// Common result type used across all libraries
@@ -21,6 +39,426 @@ pub mod constants {
     pub const MAX_SEED_LENGTH: usize = 32;
     pub const DEFAULT_DECIMALS: u8 = 6;
     pub const PROGRAM_STATE_SEED: &[u8] = b"program_state";
+    pub const AUTHORITY_HISTORY_SEED: &[u8] = b"authority_history";
+    pub const METRICS_SEED: &[u8] = b"metrics";
+    pub const PROGRAM_CONFIG_SEED: &[u8] = b"program_config";
+    pub const BOUNTY_POOL_SEED: &[u8] = b"bounty_pool";
+    pub const TREASURY_SEED: &[u8] = b"treasury";
+    pub const COMPLIANCE_CONFIG_SEED: &[u8] = b"compliance";
+    pub const AUDIT_LOG_SEED: &[u8] = b"audit_log";
+    pub const RATE_STATE_SEED: &[u8] = b"rate_state";
+    pub const LENDING_CONFIG_SEED: &[u8] = b"lending_config";
+    pub const INSURANCE_FUND_SEED: &[u8] = b"insurance_fund";
+    pub const FEE_SCHEDULE_SEED: &[u8] = b"fee_schedule";
+    pub const DAILY_STATS_SEED: &[u8] = b"daily_stats";
+    pub const RECLAIM_POLICY_SEED: &[u8] = b"reclaim_policy";
+    pub const PROGRAM_AUTHORITY_SEED: &[u8] = b"program_authority";
+    pub const COMPRESSED_TREE_SEED: &[u8] = b"compressed_tree";
+    // Leading seeds of per-owner and per-id PDAs
+    pub const USER_SEED: &[u8] = b"user";
+    pub const ADVANCED_USER_SEED: &[u8] = b"advanced_user";
+    pub const SESSION_KEY_SEED: &[u8] = b"session";
+    pub const IDEMPOTENCY_LOG_SEED: &[u8] = b"idempotency";
+    pub const ACCOUNT_LIMITS_SEED: &[u8] = b"limits";
+    pub const DELEGATIONS_SEED: &[u8] = b"delegations";
+    pub const POSITION_SEED: &[u8] = b"position";
+    pub const LOCK_SEED: &[u8] = b"lock";
+    pub const CRANKER_SEED: &[u8] = b"cranker";
+    pub const SCHEDULED_TRANSFER_SEED: &[u8] = b"scheduled";
+    pub const SUBSCRIPTION_SEED: &[u8] = b"subscription";
+    pub const STREAM_SEED: &[u8] = b"stream";
+    pub const INVOICE_SEED: &[u8] = b"invoice";
+    pub const MULTISIG_SEED: &[u8] = b"multisig";
+    pub const PROPOSAL_SEED: &[u8] = b"proposal";
+    pub const UPLOAD_SEED: &[u8] = b"upload";
+    pub const REGISTRY_PAGE_SEED: &[u8] = b"registry_page";
+    pub const SECONDARY_SEED: &[u8] = b"secondary";
+    pub const METADATA_SEED: &[u8] = b"metadata";
+    pub const ATTESTATION_SEED: &[u8] = b"attestation";
+    pub const VAULT_SEED: &[u8] = b"vault";
+    pub const VESTING_SEED: &[u8] = b"vesting";
+    pub const STAKE_SEED: &[u8] = b"stake";
+    pub const WITHDRAWAL_SEED: &[u8] = b"withdrawal";
+    pub const ALLOWLIST_SEED: &[u8] = b"allowlist";
+
+    /// Every seed prefix above; checked at compile time to fit `MAX_SEED_LENGTH` and to be
+    /// prefix-free (PDA seeds are hashed without separators, so `b"lock"` next to `b"locked"`
+    /// could let two derivations collide)
+    pub const SEED_PREFIXES: &[&[u8]] = &[
+        PROGRAM_STATE_SEED, AUTHORITY_HISTORY_SEED, METRICS_SEED, PROGRAM_CONFIG_SEED,
+        BOUNTY_POOL_SEED, TREASURY_SEED, COMPLIANCE_CONFIG_SEED, AUDIT_LOG_SEED, RATE_STATE_SEED,
+        LENDING_CONFIG_SEED, INSURANCE_FUND_SEED, FEE_SCHEDULE_SEED, DAILY_STATS_SEED,
+        RECLAIM_POLICY_SEED, PROGRAM_AUTHORITY_SEED, COMPRESSED_TREE_SEED, USER_SEED,
+        ADVANCED_USER_SEED, SESSION_KEY_SEED, IDEMPOTENCY_LOG_SEED, ACCOUNT_LIMITS_SEED,
+        DELEGATIONS_SEED, POSITION_SEED, LOCK_SEED, CRANKER_SEED, SCHEDULED_TRANSFER_SEED,
+        SUBSCRIPTION_SEED, STREAM_SEED, INVOICE_SEED, MULTISIG_SEED,
+        PROPOSAL_SEED, UPLOAD_SEED, REGISTRY_PAGE_SEED, SECONDARY_SEED, METADATA_SEED,
+        ATTESTATION_SEED, VAULT_SEED, VESTING_SEED, STAKE_SEED,
+        WITHDRAWAL_SEED, ALLOWLIST_SEED,
+    ];
+
+    const _: () = assert!(seeds_fit(SEED_PREFIXES), "seed prefix longer than MAX_SEED_LENGTH");
+    const _: () = assert!(seeds_prefix_free(SEED_PREFIXES), "seed prefix duplicates or extends another");
+
+    /// Whether every seed is at most `MAX_SEED_LENGTH` bytes
+    pub const fn seeds_fit(seeds: &[&[u8]]) -> bool {
+        let mut i = 0;
+        while i < seeds.len() {
+            if seeds[i].len() > MAX_SEED_LENGTH {
+                return false;
+            }
+            i += 1;
+        }
+        true
+    }
+
+    /// Whether no seed equals or starts with another
+    pub const fn seeds_prefix_free(seeds: &[&[u8]]) -> bool {
+        let mut i = 0;
+        while i < seeds.len() {
+            let mut j = 0;
+            while j < seeds.len() {
+                if i != j && starts_with(seeds[i], seeds[j]) {
+                    return false;
+                }
+                j += 1;
+            }
+            i += 1;
+        }
+        true
+    }
+
+    const fn starts_with(value: &[u8], prefix: &[u8]) -> bool {
+        if prefix.len() > value.len() {
+            return false;
+        }
+        let mut i = 0;
+        while i < prefix.len() {
+            if value[i] != prefix[i] {
+                return false;
+            }
+            i += 1;
+        }
+        true
+    }
+
+    /// Seeds of the program state PDA, for `static` signer-seed tables
+    pub const fn derive_program_state_seeds() -> [&'static [u8]; 1] {
+        [PROGRAM_STATE_SEED]
+    }
+}
+
+/// Catalog of the errors a transaction can fail with, for wallets and support tooling
+pub mod error_registry {
+    use solana_program::program_error::{INVALID_ACCOUNT_DATA, INVALID_ARGUMENT, MISSING_REQUIRED_SIGNATURES};
+
+    /// One error; `code` is the `u64` form of the `ProgramError` it surfaces as on chain,
+    /// and `name` matches the `InstructionError` variant for builtin errors
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct ErrorEntry {
+        pub code: u64,
+        pub name: &'static str,
+        pub message: &'static str,
+        pub module: &'static str,
+    }
+
+    /// `CommonError` variants under the builtin error each converts to; `Program` keeps the
+    /// code of the error it wraps and has no entry of its own
+    pub const COMMON_ERRORS: &[ErrorEntry] = &[
+        ErrorEntry { code: INVALID_ARGUMENT, name: "InvalidCalculation", message: "Invalid calculation", module: "common" },
+        ErrorEntry {
+            code: INVALID_ACCOUNT_DATA,
+            name: "AccountValidationFailed",
+            message: "Account validation failed",
+            module: "common",
+        },
+        ErrorEntry {
+            code: MISSING_REQUIRED_SIGNATURES,
+            name: "InsufficientPermissions",
+            message: "Insufficient permissions",
+            module: "common",
+        },
+        ErrorEntry {
+            code: INVALID_ACCOUNT_DATA,
+            name: "PriceDivergence",
+            message: "Price sources diverge beyond the allowed threshold",
+            module: "common",
+        },
+        ErrorEntry { code: INVALID_ARGUMENT, name: "Custom", message: "Custom error", module: "common" },
+    ];
+}
+
+/// Named bits of the on-chain `feature_flags` bitfield
+pub mod feature_flags {
+    /// Oracle-priced instructions
+    pub const ORACLE_PRICING: u64 = 1 << 0;
+    /// Transfer hook callbacks
+    pub const TRANSFER_HOOKS: u64 = 1 << 1;
+    /// Transfers above the compliance threshold need a valid attestation of the sender
+    pub const ATTESTATION_GATING: u64 = 1 << 2;
+    /// Events are emitted by CPI to the noop program instead of `sol_log_data`
+    pub const CPI_EVENTS: u64 = 1 << 3;
+    /// Only allowlisted wallets may create user accounts
+    pub const ALLOWLIST_GATING: u64 = 1 << 4;
+
+    /// Every feature bit currently defined
+    pub const ALL: u64 = ORACLE_PRICING | TRANSFER_HOOKS | ATTESTATION_GATING | CPI_EVENTS | ALLOWLIST_GATING;
+}
+
+/// Detection of optional syscalls across clusters with different runtime feature sets.
+/// A syscall is usable when this build has bindings for it and its feature gate account
+/// records an activation. The loader rejects programs linking a syscall the cluster lacks,
+/// so code paths using one must only be reached behind `RuntimeCaps::require`, and builds
+/// for clusters without it must leave those paths out.
+pub mod runtime_caps {
+    use super::*;
+    use solana_program::{account_info::AccountInfo, feature::Feature, pubkey};
+
+    /// Syscalls gated behind runtime features
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Syscall {
+        Secp256k1Recover,
+        Blake3,
+        AltBn128,
+        Poseidon,
+    }
+
+    impl Syscall {
+        pub const ALL: [Syscall; 4] = [Syscall::Secp256k1Recover, Syscall::Blake3, Syscall::AltBn128, Syscall::Poseidon];
+
+        pub fn name(self) -> &'static str {
+            match self {
+                Syscall::Secp256k1Recover => "secp256k1_recover",
+                Syscall::Blake3 => "blake3",
+                Syscall::AltBn128 => "alt_bn128",
+                Syscall::Poseidon => "poseidon",
+            }
+        }
+
+        /// Feature gate account activating the syscall
+        pub fn feature_id(self) -> Pubkey {
+            match self {
+                Syscall::Secp256k1Recover => pubkey!("6RvdSWHh8oh72Dp7wMTS2DBkf3fRPtChfNrAo3cZZoXJ"),
+                Syscall::Blake3 => pubkey!("HTW2pSyErTj4BV6KBM9NZ9VBUJVxt7sacNWcf76wtzb3"),
+                Syscall::AltBn128 => pubkey!("A16q37opZdQMCbe5qJ6xpBB9usykfv8jZaMkxvZQi4GJ"),
+                Syscall::Poseidon => pubkey!("FL9RsQA6TVUoh5xJQ9d936RHSebA1NLQqe3Zv9sXZRpr"),
+            }
+        }
+
+        /// Whether this build has bindings for the syscall; solana-program 1.14 has none for
+        /// alt_bn128 or poseidon
+        pub const fn is_compiled_in(self) -> bool {
+            matches!(self, Syscall::Secp256k1Recover | Syscall::Blake3)
+        }
+
+        fn bit(self) -> u8 {
+            1 << self as u8
+        }
+    }
+
+    /// Whether `feature_info` is the syscall's feature gate and records an activation
+    pub fn is_activated(syscall: Syscall, feature_info: &AccountInfo) -> CommonResult<bool> {
+        if *feature_info.key != syscall.feature_id() {
+            return Err(CommonError::AccountValidationFailed);
+        }
+        // A gate that was never requested is not an account of the feature program
+        if feature_info.data_is_empty() {
+            return Ok(false);
+        }
+        
+        let feature = Feature::from_account_info(feature_info)
+            .map_err(|_| CommonError::AccountValidationFailed)?;
+        Ok(feature.activated_at.is_some())
+    }
+
+    /// Set of syscalls usable by the running program
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub struct RuntimeCaps {
+        available: u8,
+    }
+
+    impl RuntimeCaps {
+        /// Caps with every compiled-in syscall assumed active, as on mainnet
+        pub fn compiled_in() -> Self {
+            Self::from_syscalls(Syscall::ALL.into_iter().filter(|syscall| syscall.is_compiled_in()))
+        }
+
+        /// Caps with exactly `syscalls`, dropping any this build can't call
+        pub fn from_syscalls(syscalls: impl IntoIterator<Item = Syscall>) -> Self {
+            let available = syscalls
+                .into_iter()
+                .filter(|syscall| syscall.is_compiled_in())
+                .fold(0, |bits, syscall| bits | syscall.bit());
+            Self { available }
+        }
+
+        /// Detect caps from feature gate accounts among `accounts`; a compiled-in syscall
+        /// whose gate is not passed counts as unavailable
+        pub fn detect(accounts: &[AccountInfo]) -> CommonResult<Self> {
+            let mut active = Vec::new();
+            for syscall in Syscall::ALL.into_iter().filter(|syscall| syscall.is_compiled_in()) {
+                if let Some(feature_info) = accounts.iter().find(|info| *info.key == syscall.feature_id()) {
+                    if is_activated(syscall, feature_info)? {
+                        active.push(syscall);
+                    }
+                }
+            }
+            Ok(Self::from_syscalls(active))
+        }
+
+        pub fn has(self, syscall: Syscall) -> bool {
+            self.available & syscall.bit() != 0
+        }
+
+        /// Fail cleanly when `syscall` can't be used, instead of aborting the program
+        pub fn require(self, syscall: Syscall) -> CommonResult<()> {
+            if !self.has(syscall) {
+                return Err(CommonError::Custom(
+                    format!("{} syscall is not available on this cluster", syscall.name())
+                ));
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Levelled logging macros with `key = value` fields (`key = ?value` for Debug).
+///
+/// `log_debug!` compiles to nothing, without evaluating its arguments, unless built with
+/// debug assertions or the `debug-logs` feature.
+pub mod log {
+    #[doc(hidden)]
+    pub use solana_program::msg as __msg;
+
+    #[doc(hidden)]
+    #[macro_export]
+    macro_rules! __log {
+        (@fmt [$($fmt:expr),*] [$($args:expr),*]) => {
+            $crate::log::__msg!(concat!($($fmt),*) $(, $args)*)
+        };
+        (@fmt [$($fmt:expr),*] [$($args:expr),*], $key:ident = ?$value:expr $(, $($rest:tt)*)?) => {
+            $crate::__log!(@fmt [$($fmt,)* " ", stringify!($key), "={:?}"] [$($args,)* $value] $(, $($rest)*)?)
+        };
+        (@fmt [$($fmt:expr),*] [$($args:expr),*], $key:ident = $value:expr $(, $($rest:tt)*)?) => {
+            $crate::__log!(@fmt [$($fmt,)* " ", stringify!($key), "={}"] [$($args,)* $value] $(, $($rest)*)?)
+        };
+        ($level:literal, $message:literal $(, $($fields:tt)*)?) => {
+            $crate::__log!(@fmt ["[", $level, "] ", $message] [] $(, $($fields)*)?)
+        };
+    }
+
+    /// Log diagnostic detail; compiled out of release builds
+    #[cfg(any(debug_assertions, feature = "debug-logs"))]
+    #[macro_export]
+    macro_rules! log_debug {
+        ($($arg:tt)*) => { $crate::__log!("DEBUG", $($arg)*) };
+    }
+
+    /// Log diagnostic detail; compiled out of release builds
+    #[cfg(not(any(debug_assertions, feature = "debug-logs")))]
+    #[macro_export]
+    macro_rules! log_debug {
+        ($($arg:tt)*) => {};
+    }
+
+    /// Log a notable state change
+    #[macro_export]
+    macro_rules! log_info {
+        ($($arg:tt)*) => { $crate::__log!("INFO", $($arg)*) };
+    }
+
+    /// Log the reason an instruction is rejected
+    #[macro_export]
+    macro_rules! log_error {
+        ($($arg:tt)*) => { $crate::__log!("ERROR", $($arg)*) };
+    }
+
+    /// Guard logging remaining compute units when created and dropped, for profiling a
+    /// helper's share of the budget; a no-op without the `compute-logs` feature
+    #[must_use = "the scope ends when the guard is dropped"]
+    pub struct ComputeScope {
+        #[cfg_attr(not(feature = "compute-logs"), allow(dead_code))]
+        label: &'static str,
+    }
+
+    impl ComputeScope {
+        /// Enter a labelled scope
+        pub fn new(label: &'static str) -> Self {
+            #[cfg(feature = "compute-logs")]
+            {
+                __msg!("{}: enter", label);
+                solana_program::log::sol_log_compute_units();
+            }
+            Self { label }
+        }
+    }
+
+    #[cfg(feature = "compute-logs")]
+    impl Drop for ComputeScope {
+        fn drop(&mut self) {
+            __msg!("{}: exit", self.label);
+            solana_program::log::sol_log_compute_units();
+        }
+    }
+}
+
+/// Serde adapters writing pubkeys as base58 strings, for `#[serde(with = "...")]`
+#[cfg(feature = "serde")]
+pub mod serde_pubkey {
+    use super::*;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+    use std::str::FromStr;
+
+    pub fn serialize<S: Serializer>(pubkey: &Pubkey, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(pubkey)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Pubkey, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        Pubkey::from_str(&encoded).map_err(D::Error::custom)
+    }
+
+    /// `Vec<Pubkey>` as a list of base58 strings
+    pub mod vec {
+        use super::*;
+        use serde::ser::SerializeSeq;
+
+        pub fn serialize<S: Serializer>(pubkeys: &[Pubkey], serializer: S) -> Result<S::Ok, S::Error> {
+            let mut seq = serializer.serialize_seq(Some(pubkeys.len()))?;
+            for pubkey in pubkeys {
+                seq.serialize_element(&pubkey.to_string())?;
+            }
+            seq.end()
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Pubkey>, D::Error> {
+            Vec::<String>::deserialize(deserializer)?
+                .iter()
+                .map(|encoded| Pubkey::from_str(encoded).map_err(D::Error::custom))
+                .collect()
+        }
+    }
+
+    /// `[Pubkey; N]` as a list of base58 strings
+    pub mod array {
+        use super::*;
+
+        pub fn serialize<S: Serializer, const N: usize>(
+            pubkeys: &[Pubkey; N],
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            vec::serialize(pubkeys, serializer)
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>, const N: usize>(
+            deserializer: D,
+        ) -> Result<[Pubkey; N], D::Error> {
+            let pubkeys = vec::deserialize(deserializer)?;
+            let len = pubkeys.len();
+            pubkeys.try_into()
+                .map_err(|_| D::Error::invalid_length(len, &"a fixed number of pubkeys"))
+        }
+    }
 }
 
 /// Utility functions for working with Pubkeys
@@ -57,4 +495,127 @@ pub mod validation {
         }
         Ok(())
     }
-} 
\ No newline at end of file
+} 
+/// Ethereum-style 20-byte addresses for cross-chain interop
+pub mod evm {
+    use super::*;
+    use borsh::{BorshDeserialize, BorshSerialize};
+    use solana_program::{keccak, secp256k1_recover::{secp256k1_recover, Secp256k1Pubkey}};
+    use std::{fmt, str::FromStr};
+
+    /// An EVM account address
+    #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+    pub struct EvmAddress(pub [u8; 20]);
+
+    impl EvmAddress {
+        /// Derive the address of an uncompressed secp256k1 public key (last 20 bytes of its keccak)
+        pub fn from_secp256k1_pubkey(pubkey: &Secp256k1Pubkey) -> Self {
+            let hash = keccak::hash(&pubkey.0).to_bytes();
+            let mut address = [0u8; 20];
+            address.copy_from_slice(&hash[12..]);
+            Self(address)
+        }
+
+        /// Recover the signer address of a secp256k1 signature over `message_hash`
+        pub fn recover(message_hash: &[u8; 32], recovery_id: u8, signature: &[u8; 64]) -> CommonResult<Self> {
+            let pubkey = secp256k1_recover(message_hash, recovery_id, signature)
+                .map_err(|_| CommonError::InsufficientPermissions)?;
+            Ok(Self::from_secp256k1_pubkey(&pubkey))
+        }
+
+        /// `recover`, failing cleanly where the cluster lacks the secp256k1_recover syscall
+        pub fn recover_with_caps(
+            caps: runtime_caps::RuntimeCaps,
+            message_hash: &[u8; 32],
+            recovery_id: u8,
+            signature: &[u8; 64],
+        ) -> CommonResult<Self> {
+            caps.require(runtime_caps::Syscall::Secp256k1Recover)?;
+            Self::recover(message_hash, recovery_id, signature)
+        }
+
+        /// Left-pad into the 32-byte form used by cross-chain messages
+        pub fn to_bytes32(&self) -> [u8; 32] {
+            let mut bytes = [0u8; 32];
+            bytes[12..].copy_from_slice(&self.0);
+            bytes
+        }
+
+        /// Parse the 32-byte left-padded form, rejecting non-zero padding
+        pub fn from_bytes32(bytes: &[u8; 32]) -> CommonResult<Self> {
+            if bytes[..12].iter().any(|&byte| byte != 0) {
+                return Err(CommonError::Custom("Invalid EVM address padding".to_string()));
+            }
+            
+            let mut address = [0u8; 20];
+            address.copy_from_slice(&bytes[12..]);
+            Ok(Self(address))
+        }
+
+        /// EIP-55 mixed-case checksummed hex, with `0x` prefix
+        pub fn to_checksum_string(&self) -> String {
+            let lower: String = self.0.iter().map(|byte| format!("{:02x}", byte)).collect();
+            let hash = keccak::hash(lower.as_bytes()).to_bytes();
+            
+            let mut out = String::with_capacity(42);
+            out.push_str("0x");
+            for (i, c) in lower.chars().enumerate() {
+                let nibble = (hash[i / 2] >> (if i % 2 == 0 { 4 } else { 0 })) & 0x0f;
+                if nibble >= 8 {
+                    out.push(c.to_ascii_uppercase());
+                } else {
+                    out.push(c);
+                }
+            }
+            out
+        }
+    }
+
+    impl fmt::Display for EvmAddress {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str(&self.to_checksum_string())
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    impl serde::Serialize for EvmAddress {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_str(self)
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    impl<'de> serde::Deserialize<'de> for EvmAddress {
+        fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let encoded = <String as serde::Deserialize>::deserialize(deserializer)?;
+            Self::from_str(&encoded).map_err(serde::de::Error::custom)
+        }
+    }
+
+    impl FromStr for EvmAddress {
+        type Err = CommonError;
+
+        /// Parse hex with optional `0x`; mixed-case input must carry a valid EIP-55 checksum
+        fn from_str(s: &str) -> CommonResult<Self> {
+            let hex = s.strip_prefix("0x").unwrap_or(s);
+            if hex.len() != 40 || !hex.is_ascii() {
+                return Err(CommonError::Custom("Invalid EVM address length".to_string()));
+            }
+            
+            let mut address = [0u8; 20];
+            for (i, byte) in address.iter_mut().enumerate() {
+                *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)
+                    .map_err(|_| CommonError::Custom("Invalid EVM address hex".to_string()))?;
+            }
+            let parsed = Self(address);
+            
+            let has_lower = hex.chars().any(|c| c.is_ascii_lowercase());
+            let has_upper = hex.chars().any(|c| c.is_ascii_uppercase());
+            if has_lower && has_upper && parsed.to_checksum_string()[2..] != *hex {
+                return Err(CommonError::Custom("Invalid EVM address checksum".to_string()));
+            }
+            
+            Ok(parsed)
+        }
+    }
+}
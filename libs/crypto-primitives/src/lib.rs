@@ -1,8 +1,12 @@
 use solana_program::{
     pubkey::Pubkey,
-    keccak,
+    keccak, hash as sha256, blake3,
+};
+use common::{
+    CommonError, CommonResult,
+    runtime_caps::{RuntimeCaps, Syscall},
+    constants::{MAX_SEED_LENGTH, SECONDARY_SEED, METADATA_SEED, ATTESTATION_SEED, VAULT_SEED},
 };
-use common::{CommonError, CommonResult, constants::MAX_SEED_LENGTH};
 
 /// Cryptographic hashing utilities for account operations
 pub mod hashing {
@@ -37,6 +41,138 @@ pub mod hashing {
         data.extend_from_slice(&nonce.to_le_bytes());
         hash_account_data(&data)
     }
+
+    /// Base compute units of one `sol_sha256`/`sol_keccak256`/`sol_blake3` call
+    /// (`ComputeBudget::sha256_base_cost`)
+    pub const HASH_SYSCALL_BASE_COST: u64 = 85;
+    /// Compute units per two bytes hashed (`ComputeBudget::sha256_byte_cost`)
+    pub const HASH_SYSCALL_BYTE_COST: u64 = 1;
+    /// Minimum compute units charged per slice (`ComputeBudget::mem_op_base_cost`)
+    pub const HASH_SYSCALL_MIN_SLICE_COST: u64 = 10;
+
+    /// Hash syscalls available to programs
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum HashAlgorithm {
+        Keccak256,
+        Sha256,
+        /// Gated behind the `blake3_syscall_enabled` runtime feature; programs calling it fail
+        /// to load on clusters where the feature is inactive
+        Blake3,
+    }
+
+    impl HashAlgorithm {
+        /// Algorithms in order of preference when their costs tie: keccak first, matching
+        /// `hash_account_data`
+        pub const ALL: [HashAlgorithm; 3] = [HashAlgorithm::Keccak256, HashAlgorithm::Sha256, HashAlgorithm::Blake3];
+
+        /// Whether the syscall is available on every cluster without a feature activation
+        pub fn is_generally_available(self) -> bool {
+            self.feature_gated_syscall().is_none()
+        }
+
+        /// Runtime feature-gated syscall behind the algorithm, if any
+        pub fn feature_gated_syscall(self) -> Option<Syscall> {
+            match self {
+                HashAlgorithm::Keccak256 | HashAlgorithm::Sha256 => None,
+                HashAlgorithm::Blake3 => Some(Syscall::Blake3),
+            }
+        }
+
+        /// Whether the algorithm can be used under `caps`
+        pub fn is_available(self, caps: RuntimeCaps) -> bool {
+            self.feature_gated_syscall().is_none_or(|syscall| caps.has(syscall))
+        }
+
+        /// `hashv`, failing cleanly where the cluster lacks the syscall
+        pub fn hashv_with_caps(self, caps: RuntimeCaps, vals: &[&[u8]]) -> CommonResult<[u8; 32]> {
+            if let Some(syscall) = self.feature_gated_syscall() {
+                caps.require(syscall)?;
+            }
+            Ok(self.hashv(vals))
+        }
+
+        /// Compute units to hash `slice_lens` in one call, per the runtime's syscall cost model
+        pub fn syscall_cost(self, slice_lens: &[usize]) -> u64 {
+            // The three syscalls share one cost schedule in this runtime; keep the match so
+            // diverging schedules land here
+            let (base, byte, min_slice) = match self {
+                HashAlgorithm::Keccak256 | HashAlgorithm::Sha256 | HashAlgorithm::Blake3 => {
+                    (HASH_SYSCALL_BASE_COST, HASH_SYSCALL_BYTE_COST, HASH_SYSCALL_MIN_SLICE_COST)
+                }
+            };
+            slice_lens.iter().fold(base, |cost, len| {
+                cost.saturating_add(min_slice.max(byte.saturating_mul(*len as u64 / 2)))
+            })
+        }
+
+        /// Hash `vals` as one message
+        pub fn hashv(self, vals: &[&[u8]]) -> [u8; 32] {
+            match self {
+                HashAlgorithm::Keccak256 => keccak::hashv(vals).to_bytes(),
+                HashAlgorithm::Sha256 => sha256::hashv(vals).to_bytes(),
+                HashAlgorithm::Blake3 => blake3::hashv(vals).to_bytes(),
+            }
+        }
+    }
+
+    /// Cheapest generally available hash syscall for a single `payload_len`-byte slice, ties
+    /// going to the earlier entry of `HashAlgorithm::ALL`
+    pub fn recommended_algorithm(payload_len: usize) -> HashAlgorithm {
+        recommended_algorithm_with_caps(payload_len, RuntimeCaps::default())
+    }
+
+    /// Cheapest hash syscall usable under `caps`, falling back to the generally available
+    /// ones where feature-gated syscalls are inactive
+    pub fn recommended_algorithm_with_caps(payload_len: usize, caps: RuntimeCaps) -> HashAlgorithm {
+        HashAlgorithm::ALL
+            .into_iter()
+            .filter(|algorithm| algorithm.is_available(caps))
+            .min_by_key(|algorithm| algorithm.syscall_cost(&[payload_len]))
+            .unwrap_or(HashAlgorithm::Keccak256)
+    }
+}
+
+/// Keccak Merkle proofs laid out like spl-account-compression's concurrent Merkle trees:
+/// leaves are 32-byte values, empty slots hold zeros and a node whose index bit is clear
+/// hashes as the left child
+pub mod merkle {
+    use super::*;
+
+    /// Value of an empty leaf slot
+    pub const EMPTY_LEAF: [u8; 32] = [0; 32];
+
+    /// Parent of `node` and its `sibling`, ordered by which side `node` is on
+    pub fn hash_to_parent(node: &[u8; 32], sibling: &[u8; 32], node_is_left: bool) -> [u8; 32] {
+        if node_is_left {
+            keccak::hashv(&[node, sibling]).to_bytes()
+        } else {
+            keccak::hashv(&[sibling, node]).to_bytes()
+        }
+    }
+
+    /// Root reached from `leaf` at `index` through `proof`, the sibling hashes from the
+    /// leaf level up
+    pub fn compute_root(leaf: &[u8; 32], index: u32, proof: &[[u8; 32]]) -> [u8; 32] {
+        proof.iter().enumerate().fold(*leaf, |node, (level, sibling)| {
+            hash_to_parent(&node, sibling, (index >> level) & 1 == 0)
+        })
+    }
+
+    /// Check that `proof` places `leaf` at `index` under `root`
+    pub fn verify_proof(root: &[u8; 32], leaf: &[u8; 32], index: u32, proof: &[[u8; 32]]) -> CommonResult<()> {
+        if proof.len() > 32 || (proof.len() < 32 && index >> proof.len() != 0) {
+            return Err(CommonError::Custom("Leaf index outside the tree".to_string()));
+        }
+        if compute_root(leaf, index, proof) != *root {
+            return Err(CommonError::AccountValidationFailed);
+        }
+        Ok(())
+    }
+
+    /// Root of a tree of `depth` levels holding only empty leaves
+    pub fn empty_root(depth: u32) -> [u8; 32] {
+        (0..depth).fold(EMPTY_LEAF, |node, _| hash_to_parent(&node, &node, true))
+    }
 }
 
 /// Seed generation utilities for PDA creation
@@ -118,10 +254,7 @@ pub mod address_derivation {
             return Err(CommonError::Custom("Derivation path too long".to_string()));
         }
         
-        let mut seeds = Vec::new();
-        seeds.push(b"secondary".as_ref());
-        seeds.push(primary.as_ref());
-        seeds.push(derivation_path);
+        let seeds = [SECONDARY_SEED, primary.as_ref(), derivation_path];
         
         let seed_refs: Vec<&[u8]> = seeds.iter().map(|s| s.as_ref()).collect();
         
@@ -137,7 +270,7 @@ pub mod address_derivation {
         let type_hash = hashing::hash_account_data(metadata_type.as_bytes());
         
         let seeds = [
-            b"metadata".as_ref(),
+            METADATA_SEED,
             account.as_ref(),
             &type_hash[..8], // Use first 8 bytes of hash
         ];
@@ -145,6 +278,21 @@ pub mod address_derivation {
         Ok(Pubkey::find_program_address(&seeds, program_id))
     }
 
+    /// Derive the address of an attestor's attestation about a user
+    pub fn derive_attestation_address(
+        user: &Pubkey,
+        attestor: &Pubkey,
+        program_id: &Pubkey
+    ) -> CommonResult<(Pubkey, u8)> {
+        let seeds = [
+            ATTESTATION_SEED,
+            user.as_ref(),
+            attestor.as_ref(),
+        ];
+        
+        Ok(Pubkey::find_program_address(&seeds, program_id))
+    }
+
     /// Derive a vault address for secure storage
     pub fn derive_vault_address(
         owner: &Pubkey,
@@ -152,7 +300,7 @@ pub mod address_derivation {
         program_id: &Pubkey
     ) -> CommonResult<(Pubkey, u8)> {
         let seeds = [
-            b"vault".as_ref(),
+            VAULT_SEED,
             owner.as_ref(),
             &vault_id.to_le_bytes(),
         ];
@@ -294,4 +442,289 @@ pub mod security {
         
         Ok(())
     }
-} 
\ No newline at end of file
+
+    /// Count the leading zero bits of a hash
+    pub fn leading_zero_bits(hash: &[u8; 32]) -> u32 {
+        let mut bits = 0;
+        for &byte in hash {
+            bits += byte.leading_zeros();
+            if byte != 0 {
+                break;
+            }
+        }
+        bits
+    }
+
+    /// Verify that `keccak(account || nonce)` has at least `difficulty` leading zero bits
+    pub fn verify_pow(account: &Pubkey, nonce: u64, difficulty: u8) -> CommonResult<()> {
+        let mut data = Vec::new();
+        data.extend_from_slice(account.as_ref());
+        data.extend_from_slice(&nonce.to_le_bytes());
+        
+        let bits = leading_zero_bits(&hashing::hash_account_data(&data));
+        if bits < difficulty as u32 {
+            return Err(CommonError::Custom(
+                format!("Proof of work has {} leading zero bits, {} required", bits, difficulty)
+            ));
+        }
+        
+        Ok(())
+    }
+}
+
+/// Solana off-chain message signing format (version 0), so signed challenges are
+/// wallet-compatible instead of raw bytes
+pub mod offchain_message {
+    use super::*;
+
+    /// Prefix that keeps off-chain messages from ever parsing as transactions
+    pub const SIGNING_DOMAIN: &[u8; 16] = b"\xffsolana offchain";
+
+    /// Supported header version
+    pub const VERSION: u8 = 0;
+
+    /// Signing domain, version, format and little-endian u16 length
+    pub const HEADER_LEN: usize = SIGNING_DOMAIN.len() + 4;
+
+    /// Largest body of any format
+    pub const MAX_LEN: usize = u16::MAX as usize - HEADER_LEN;
+
+    /// Largest body that still fits a transaction packet (hardware wallet formats)
+    pub const MAX_LEN_LEDGER: usize = 1232 - HEADER_LEN;
+
+    /// Body encoding, chosen as the most restrictive format the body fits
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum MessageFormat {
+        RestrictedAscii = 0,
+        LimitedUtf8 = 1,
+        ExtendedUtf8 = 2,
+    }
+
+    impl MessageFormat {
+        fn from_u8(value: u8) -> CommonResult<Self> {
+            match value {
+                0 => Ok(Self::RestrictedAscii),
+                1 => Ok(Self::LimitedUtf8),
+                2 => Ok(Self::ExtendedUtf8),
+                _ => Err(CommonError::Custom(format!("Unknown off-chain message format {}", value))),
+            }
+        }
+
+        /// Check that `message` is valid in this format
+        pub fn validate(self, message: &[u8]) -> CommonResult<()> {
+            let valid = !message.is_empty() && match self {
+                Self::RestrictedAscii => message.len() <= MAX_LEN_LEDGER
+                    && message.iter().all(|&byte| (0x20..=0x7e).contains(&byte)),
+                Self::LimitedUtf8 => message.len() <= MAX_LEN_LEDGER
+                    && std::str::from_utf8(message).is_ok(),
+                Self::ExtendedUtf8 => message.len() <= MAX_LEN
+                    && std::str::from_utf8(message).is_ok(),
+            };
+            
+            if !valid {
+                return Err(CommonError::Custom(format!("Message is not valid {:?}", self)));
+            }
+            Ok(())
+        }
+    }
+
+    /// A version 0 off-chain message
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct OffchainMessage {
+        pub format: MessageFormat,
+        pub message: Vec<u8>,
+    }
+
+    impl OffchainMessage {
+        /// Wrap `message`, picking the most restrictive format it fits
+        pub fn new(message: &[u8]) -> CommonResult<Self> {
+            let format = [MessageFormat::RestrictedAscii, MessageFormat::LimitedUtf8, MessageFormat::ExtendedUtf8]
+                .into_iter()
+                .find(|format| format.validate(message).is_ok())
+                .ok_or_else(|| CommonError::Custom("Invalid off-chain message body".to_string()))?;
+            
+            Ok(Self { format, message: message.to_vec() })
+        }
+
+        /// Bytes a wallet signs: header followed by the body
+        pub fn serialize(&self) -> Vec<u8> {
+            let mut data = Vec::with_capacity(HEADER_LEN + self.message.len());
+            data.extend_from_slice(SIGNING_DOMAIN);
+            data.push(VERSION);
+            data.push(self.format as u8);
+            data.extend_from_slice(&(self.message.len() as u16).to_le_bytes());
+            data.extend_from_slice(&self.message);
+            data
+        }
+
+        /// Parse and validate serialized message bytes
+        pub fn deserialize(data: &[u8]) -> CommonResult<Self> {
+            if data.len() < HEADER_LEN || &data[..SIGNING_DOMAIN.len()] != SIGNING_DOMAIN {
+                return Err(CommonError::Custom("Missing off-chain signing domain".to_string()));
+            }
+            
+            let header = &data[SIGNING_DOMAIN.len()..HEADER_LEN];
+            if header[0] != VERSION {
+                return Err(CommonError::Custom(format!("Unsupported off-chain message version {}", header[0])));
+            }
+            
+            let format = MessageFormat::from_u8(header[1])?;
+            let len = u16::from_le_bytes([header[2], header[3]]) as usize;
+            let message = &data[HEADER_LEN..];
+            if message.len() != len {
+                return Err(CommonError::Custom("Off-chain message length mismatch".to_string()));
+            }
+            format.validate(message)?;
+            
+            Ok(Self { format, message: message.to_vec() })
+        }
+    }
+
+    /// Size of one signature's offsets in ed25519 program instruction data
+    const ED25519_OFFSETS_LEN: usize = 14;
+
+    /// Marker for data held in the ed25519 instruction itself
+    const CURRENT_INSTRUCTION: u16 = u16::MAX;
+
+    /// Verify that ed25519 program instruction data checks `signer`'s signature over
+    /// the serialized `message`; the precompile performs the signature check itself
+    pub fn verify_ed25519_instruction_data(
+        instruction_data: &[u8],
+        signer: &Pubkey,
+        message: &OffchainMessage
+    ) -> CommonResult<()> {
+        let invalid = || CommonError::Custom("Invalid ed25519 instruction".to_string());
+        let read_u16 = |offset: usize| -> CommonResult<u16> {
+            instruction_data.get(offset..offset + 2)
+                .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+                .ok_or_else(invalid)
+        };
+        let slice = |offset: u16, len: usize| -> CommonResult<&[u8]> {
+            instruction_data.get(offset as usize..offset as usize + len).ok_or_else(invalid)
+        };
+        
+        let expected = message.serialize();
+        let count = *instruction_data.first().ok_or_else(invalid)? as usize;
+        for i in 0..count {
+            let base = 2 + i * ED25519_OFFSETS_LEN;
+            let public_key_offset = read_u16(base + 4)?;
+            let message_offset = read_u16(base + 8)?;
+            let message_size = read_u16(base + 10)? as usize;
+            
+            // Only signatures whose key and message live in this instruction are considered
+            if read_u16(base + 2)? != CURRENT_INSTRUCTION
+                || read_u16(base + 6)? != CURRENT_INSTRUCTION
+                || read_u16(base + 12)? != CURRENT_INSTRUCTION
+            {
+                continue;
+            }
+            
+            if slice(public_key_offset, 32)? == signer.as_ref()
+                && message_size == expected.len()
+                && slice(message_offset, message_size)? == expected.as_slice()
+            {
+                return Ok(());
+            }
+        }
+        
+        Err(CommonError::InsufficientPermissions)
+    }
+}
+
+/// Browser bindings for seed generation and address derivation; pubkeys cross as base58 strings
+#[cfg(feature = "wasm")]
+pub mod wasm {
+    use super::*;
+    use std::str::FromStr;
+    use wasm_bindgen::prelude::*;
+
+    fn js_error(err: CommonError) -> JsError {
+        JsError::new(&err.to_string())
+    }
+
+    fn parse_pubkey(value: &str) -> Result<Pubkey, JsError> {
+        Pubkey::from_str(value).map_err(|_| JsError::new(&format!("Invalid pubkey: {}", value)))
+    }
+
+    /// A program-derived address and its bump seed
+    #[wasm_bindgen]
+    pub struct DerivedAddress {
+        address: Pubkey,
+        bump: u8,
+    }
+
+    #[wasm_bindgen]
+    impl DerivedAddress {
+        /// Base58 address
+        #[wasm_bindgen(getter)]
+        pub fn address(&self) -> String {
+            self.address.to_string()
+        }
+
+        /// Bump seed that moves the address off the curve
+        #[wasm_bindgen(getter)]
+        pub fn bump(&self) -> u8 {
+            self.bump
+        }
+    }
+
+    impl From<(Pubkey, u8)> for DerivedAddress {
+        fn from((address, bump): (Pubkey, u8)) -> Self {
+            Self { address, bump }
+        }
+    }
+
+    /// Keccak hash of account data
+    #[wasm_bindgen(js_name = hashAccountData)]
+    pub fn hash_account_data(data: &[u8]) -> Vec<u8> {
+        hashing::hash_account_data(data).to_vec()
+    }
+
+    /// Seed for the `child_index`th child of `child_type` under `parent`
+    #[wasm_bindgen(js_name = createHierarchicalSeed)]
+    pub fn create_hierarchical_seed(parent: &str, child_type: u8, child_index: u16) -> Result<Vec<u8>, JsError> {
+        seed_generation::create_hierarchical_seed(&parse_pubkey(parent)?, child_type, child_index).map_err(js_error)
+    }
+
+    /// Seed for an account tied to `base` and `timestamp`
+    #[wasm_bindgen(js_name = generateTemporalSeed)]
+    pub fn generate_temporal_seed(base: &str, timestamp: i64) -> Result<Vec<u8>, JsError> {
+        Ok(seed_generation::generate_temporal_seed(&parse_pubkey(base)?, timestamp))
+    }
+
+    /// Address derived from `primary` along `derivation_path`
+    #[wasm_bindgen(js_name = deriveSecondaryAddress)]
+    pub fn derive_secondary_address(
+        primary: &str,
+        derivation_path: &[u8],
+        program_id: &str,
+    ) -> Result<DerivedAddress, JsError> {
+        address_derivation::derive_secondary_address(&parse_pubkey(primary)?, derivation_path, &parse_pubkey(program_id)?)
+            .map(DerivedAddress::from)
+            .map_err(js_error)
+    }
+
+    /// Address holding `metadata_type` metadata for `account`
+    #[wasm_bindgen(js_name = deriveMetadataAddress)]
+    pub fn derive_metadata_address(account: &str, metadata_type: &str, program_id: &str) -> Result<DerivedAddress, JsError> {
+        address_derivation::derive_metadata_address(&parse_pubkey(account)?, metadata_type, &parse_pubkey(program_id)?)
+            .map(DerivedAddress::from)
+            .map_err(js_error)
+    }
+
+    /// Address of `attestor`'s attestation about `user`
+    #[wasm_bindgen(js_name = deriveAttestationAddress)]
+    pub fn derive_attestation_address(user: &str, attestor: &str, program_id: &str) -> Result<DerivedAddress, JsError> {
+        address_derivation::derive_attestation_address(&parse_pubkey(user)?, &parse_pubkey(attestor)?, &parse_pubkey(program_id)?)
+            .map(DerivedAddress::from)
+            .map_err(js_error)
+    }
+
+    /// Address of `owner`'s vault number `vault_id`
+    #[wasm_bindgen(js_name = deriveVaultAddress)]
+    pub fn derive_vault_address(owner: &str, vault_id: u64, program_id: &str) -> Result<DerivedAddress, JsError> {
+        address_derivation::derive_vault_address(&parse_pubkey(owner)?, vault_id, &parse_pubkey(program_id)?)
+            .map(DerivedAddress::from)
+            .map_err(js_error)
+    }
+}
@@ -70,7 +70,7 @@ pub mod primitives {
         }
         
         let mut x = n;
-        let mut y = (n + 1) / 2;
+        let mut y = n.div_ceil(2);
         
         while y < x {
             x = y;
@@ -169,7 +169,6 @@ pub mod number_theory {
 /// Validation utilities for mathematical operations
 pub mod validation {
     use super::*;
-    use crate::constants::*;
 
     /// Validate that a value doesn't exceed safe multiplication bounds
     pub fn validate_multiplication_safety(a: u64, b: u64) -> CommonResult<()> {
@@ -25,6 +25,26 @@ pub mod token_math {
         calculate_token_amount(base_amount, DEFAULT_DECIMALS)
     }
 
+    /// Validate that a decimal precision is supported for token amounts
+    pub fn validate_decimals(decimals: u8) -> CommonResult<()> {
+        prim_validation::validate_precision(decimals)
+    }
+
+    /// Rescale an amount between decimal precisions, truncating any lost precision
+    pub fn normalize_amount(amount: u64, from_decimals: u8, to_decimals: u8) -> CommonResult<u64> {
+        prim_validation::validate_precision(from_decimals)?;
+        prim_validation::validate_precision(to_decimals)?;
+        
+        if from_decimals > to_decimals {
+            let divisor = primitives::pow10((from_decimals - to_decimals) as u32)?;
+            Ok(amount / divisor)
+        } else {
+            let multiplier = primitives::pow10((to_decimals - from_decimals) as u32)?;
+            amount.checked_mul(multiplier)
+                .ok_or(CommonError::InvalidCalculation)
+        }
+    }
+
     /// Convert token amount back to base units
     pub fn convert_to_base_units(token_amount: u64, decimals: u8) -> CommonResult<u64> {
         let divisor = 10_u64.pow(decimals as u32);
@@ -53,6 +73,65 @@ pub mod percentage {
         Ok(result)
     }
 
+    /// Validate that a basis points value is at most 100%
+    pub fn validate_basis_points(basis_points: u16) -> CommonResult<()> {
+        primitives::validate_basis_points(basis_points)
+    }
+
+    /// Validate that the shares of a split add up to exactly 100%
+    pub fn validate_split(shares_basis_points: &[u16]) -> CommonResult<()> {
+        let mut total = 0u32;
+        for &share in shares_basis_points {
+            primitives::validate_basis_points(share)?;
+            total += share as u32;
+        }
+        
+        if total != prim_constants::MAX_BASIS_POINTS as u32 {
+            return Err(CommonError::Custom("Split does not add up to 100%".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Largest amount whose sum with its fee fits within `budget`
+    pub fn max_amount_with_fee(budget: u64, fee_basis_points: u16) -> CommonResult<u64> {
+        primitives::validate_basis_points(fee_basis_points)?;
+        
+        let denominator = prim_constants::MAX_BASIS_POINTS as u128 + fee_basis_points as u128;
+        let mut amount = u64::try_from(
+            budget as u128 * prim_constants::MAX_BASIS_POINTS as u128 / denominator
+        ).map_err(|_| CommonError::InvalidCalculation)?;
+        
+        // The fee rounds down, so the estimate can fall a unit or two short
+        while amount < budget {
+            let next = amount + 1;
+            if next as u128 + calculate_percentage(next, fee_basis_points)? as u128 > budget as u128 {
+                break;
+            }
+            amount = next;
+        }
+        
+        Ok(amount)
+    }
+
+    /// Penalty on withdrawing `amount` early, falling linearly from `max_penalty_basis_points`
+    /// at the start of a `duration`-second lock to zero with `remaining` seconds left
+    pub fn early_withdrawal_penalty(
+        amount: u64,
+        max_penalty_basis_points: u16,
+        remaining: u64,
+        duration: u64,
+    ) -> CommonResult<u64> {
+        primitives::validate_basis_points(max_penalty_basis_points)?;
+        
+        if duration == 0 {
+            return Err(CommonError::InvalidCalculation);
+        }
+        
+        let penalty_basis_points = max_penalty_basis_points as u128 * remaining.min(duration) as u128
+            / duration as u128;
+        calculate_percentage(amount, penalty_basis_points as u16)
+    }
+
     /// Calculate compound interest
     pub fn calculate_compound_interest(
         principal: u64, 
@@ -73,6 +152,479 @@ pub mod percentage {
         
         Ok(result)
     }
+
+    /// Fixed-point scale of compound growth factors (1.0 = `COMPOUND_SCALE`)
+    pub const COMPOUND_SCALE: u128 = 1_000_000_000_000_000_000;
+
+    /// Longest compounding run `compound_factor_bps` accepts (100 years of daily periods)
+    pub const MAX_COMPOUND_PERIODS: u32 = 36_500;
+
+    /// (1 + `rate_bps` / 10 000)^`periods` at `COMPOUND_SCALE`, summed as the binomial series
+    /// Σ C(n, k)·r^k with each term derived from the previous one, rounded down and
+    /// overflow-checked at every step. The series stops at the first zero term, so small
+    /// rates over many periods cost only a few dozen steps.
+    pub fn compound_factor_bps(rate_bps: u16, periods: u32) -> CommonResult<u128> {
+        primitives::validate_basis_points(rate_bps)?;
+        if periods > MAX_COMPOUND_PERIODS {
+            return Err(CommonError::InvalidCalculation);
+        }
+        
+        let mut factor = COMPOUND_SCALE;
+        let mut term = COMPOUND_SCALE;
+        for k in 0..periods as u128 {
+            // term(k+1) = term(k) · (n - k) / (k + 1) · r
+            term = term
+                .checked_mul(periods as u128 - k)
+                .and_then(|value| value.checked_mul(rate_bps as u128))
+                .map(|value| value / ((k + 1) * prim_constants::MAX_BASIS_POINTS as u128))
+                .ok_or(CommonError::InvalidCalculation)?;
+            if term == 0 {
+                break;
+            }
+            factor = factor.checked_add(term).ok_or(CommonError::InvalidCalculation)?;
+        }
+        
+        Ok(factor)
+    }
+
+    /// Integer replacement for `calculate_compound_interest`: `principal` grown by
+    /// `compound_factor_bps`, rounded down. It stays within `COMPOUND_MAX_DIVERGENCE_PPB` of the
+    /// float result (plus one unit of truncation) and fails with `InvalidCalculation` rather than
+    /// saturating when the result does not fit in a u64
+    pub fn calculate_compound_interest_fixed(
+        principal: u64,
+        rate_basis_points: u16,
+        periods: u32,
+    ) -> CommonResult<u64> {
+        let factor = compound_factor_bps(rate_basis_points, periods)?;
+        
+        // principal · factor / scale, split so the product never exceeds u128
+        let whole = (principal as u128).checked_mul(factor / COMPOUND_SCALE);
+        let fraction = principal as u128 * (factor % COMPOUND_SCALE) / COMPOUND_SCALE;
+        whole
+            .and_then(|whole| whole.checked_add(fraction))
+            .and_then(|result| u64::try_from(result).ok())
+            .ok_or(CommonError::InvalidCalculation)
+    }
+
+    /// Largest relative gap, in parts per billion, between `calculate_compound_interest_fixed`
+    /// and the f64 `calculate_compound_interest` for growth factors below 10^6 (checked by
+    /// `tests/compound_interest.rs`)
+    pub const COMPOUND_MAX_DIVERGENCE_PPB: u64 = 1;
+}
+
+/// Time-based accrual and proration
+pub mod accrual {
+    use super::*;
+
+    /// Portion of `amount` earned after `elapsed` out of `period` seconds (rounded down)
+    pub fn prorate(amount: u64, elapsed: u64, period: u64) -> CommonResult<u64> {
+        if period == 0 {
+            return Err(CommonError::InvalidCalculation);
+        }
+        
+        let elapsed = elapsed.min(period);
+        let result = (amount as u128)
+            .checked_mul(elapsed as u128)
+            .and_then(|val| val.checked_div(period as u128))
+            .and_then(|val| u64::try_from(val).ok())
+            .ok_or(CommonError::InvalidCalculation)?;
+        
+        Ok(result)
+    }
+
+    /// Scale of fixed-point flow rates (amount per second × `RATE_SCALE`)
+    pub const RATE_SCALE: u128 = 1_000_000_000_000;
+
+    /// Fixed-point per-second rate that releases `total` evenly over `duration` seconds
+    pub fn flow_rate(total: u64, duration: u64) -> CommonResult<u128> {
+        if duration == 0 {
+            return Err(CommonError::InvalidCalculation);
+        }
+        
+        (total as u128)
+            .checked_mul(RATE_SCALE)
+            .map(|scaled| scaled / duration as u128)
+            .ok_or(CommonError::InvalidCalculation)
+    }
+
+    /// Amount accrued at a fixed-point `rate` over `elapsed` seconds, capped at `cap`
+    pub fn accrued_at_rate(rate: u128, elapsed: u64, cap: u64) -> CommonResult<u64> {
+        let accrued = rate
+            .checked_mul(elapsed as u128)
+            .map(|scaled| scaled / RATE_SCALE)
+            .ok_or(CommonError::InvalidCalculation)?;
+        
+        Ok(accrued.min(cap as u128) as u64)
+    }
+
+    /// Increase of a reward-per-share accumulator (scaled by `RATE_SCALE`) when `rate`
+    /// tokens per second are shared by `total_shares` for `elapsed` seconds
+    pub fn reward_per_share(rate: u64, elapsed: u64, total_shares: u64) -> CommonResult<u128> {
+        if total_shares == 0 {
+            return Err(CommonError::InvalidCalculation);
+        }
+        
+        (rate as u128)
+            .checked_mul(elapsed as u128)
+            .and_then(|emitted| emitted.checked_mul(RATE_SCALE))
+            .map(|scaled| scaled / total_shares as u128)
+            .ok_or(CommonError::InvalidCalculation)
+    }
+
+    /// Rewards earned by `shares` while the accumulator moved from `paid` to `current`
+    pub fn earned(shares: u64, current: u128, paid: u128) -> CommonResult<u64> {
+        current
+            .checked_sub(paid)
+            .and_then(|delta| delta.checked_mul(shares as u128))
+            .map(|scaled| scaled / RATE_SCALE)
+            .and_then(|earned| u64::try_from(earned).ok())
+            .ok_or(CommonError::InvalidCalculation)
+    }
+
+    /// Number of whole intervals completed between `start` and `now`
+    pub fn periods_elapsed(start: i64, now: i64, interval: u64) -> CommonResult<u64> {
+        if interval == 0 {
+            return Err(CommonError::InvalidCalculation);
+        }
+        
+        if now <= start {
+            return Ok(0);
+        }
+        
+        Ok((now - start) as u64 / interval)
+    }
+}
+
+/// Interest index accounting: balances stored as scaled units of a growing global index
+pub mod index_math {
+    use super::*;
+
+    /// Fixed-point scale of an interest index; `INDEX_SCALE` is an index of 1.0
+    pub const INDEX_SCALE: u128 = 1_000_000_000_000;
+
+    /// Seconds in the 365-day year annual rates are quoted over
+    pub const SECONDS_PER_YEAR: u64 = 365 * 24 * 60 * 60;
+
+    /// Scaled units worth `face` at `index` (rounded down)
+    pub fn to_scaled(face: u64, index: u128) -> CommonResult<u64> {
+        if index == 0 {
+            return Err(CommonError::InvalidCalculation);
+        }
+        
+        (face as u128)
+            .checked_mul(INDEX_SCALE)
+            .map(|val| val / index)
+            .and_then(|val| u64::try_from(val).ok())
+            .ok_or(CommonError::InvalidCalculation)
+    }
+
+    /// Scaled units worth `face` at `index` (rounded up, as owed debt rounds)
+    pub fn to_scaled_up(face: u64, index: u128) -> CommonResult<u64> {
+        if index == 0 {
+            return Err(CommonError::InvalidCalculation);
+        }
+        
+        (face as u128)
+            .checked_mul(INDEX_SCALE)
+            .map(|val| val.div_ceil(index))
+            .and_then(|val| u64::try_from(val).ok())
+            .ok_or(CommonError::InvalidCalculation)
+    }
+
+    /// Face amount of `scaled` units at `index` (rounded up, as owed debt rounds)
+    pub fn to_face_up(scaled: u64, index: u128) -> CommonResult<u64> {
+        (scaled as u128)
+            .checked_mul(index)
+            .map(|val| val.div_ceil(INDEX_SCALE))
+            .and_then(|val| u64::try_from(val).ok())
+            .ok_or(CommonError::InvalidCalculation)
+    }
+
+    /// Face amount of `scaled` units at `index` (rounded down)
+    pub fn to_face(scaled: u64, index: u128) -> CommonResult<u64> {
+        (scaled as u128)
+            .checked_mul(index)
+            .map(|val| val / INDEX_SCALE)
+            .and_then(|val| u64::try_from(val).ok())
+            .ok_or(CommonError::InvalidCalculation)
+    }
+
+    /// Index after `elapsed` seconds of simple interest at `rate_bps` per year (rounded down)
+    pub fn accrue(index: u128, rate_bps: u16, elapsed: u64) -> CommonResult<u128> {
+        let growth = index
+            .checked_mul(rate_bps as u128)
+            .and_then(|val| val.checked_mul(elapsed as u128))
+            .map(|val| val / (10_000 * SECONDS_PER_YEAR as u128))
+            .ok_or(CommonError::InvalidCalculation)?;
+        
+        index.checked_add(growth).ok_or(CommonError::InvalidCalculation)
+    }
+}
+
+/// Exchange rates as Q64.64 fixed point (`rate / 2^64` units of quote per unit of base)
+pub mod rate_math {
+    use super::*;
+
+    /// Q64.64 representation of 1.0
+    pub const Q64_ONE: u128 = 1 << 64;
+
+    /// Q64.64 rate of `quote` per `base` (rounded down)
+    pub fn rate_from_ratio(quote: u64, base: u64) -> CommonResult<u128> {
+        if base == 0 {
+            return Err(CommonError::InvalidCalculation);
+        }
+        
+        Ok(((quote as u128) << 64) / base as u128)
+    }
+
+    /// Quote amount for `base_amount` at `rate` (rounded down)
+    pub fn base_to_quote(base_amount: u64, rate: u128) -> CommonResult<u64> {
+        let whole = (base_amount as u128)
+            .checked_mul(rate >> 64)
+            .ok_or(CommonError::InvalidCalculation)?;
+        let fraction = ((base_amount as u128) * (rate & (Q64_ONE - 1))) >> 64;
+        
+        whole.checked_add(fraction)
+            .and_then(|val| u64::try_from(val).ok())
+            .ok_or(CommonError::InvalidCalculation)
+    }
+
+    /// Circuit breaker for two sources of the same price (e.g. mark and index TWAPs): fails
+    /// with `PriceDivergence` when `price` differs from `reference` by more than
+    /// `max_divergence_bps` of `reference`
+    pub fn check_divergence(price: u128, reference: u128, max_divergence_bps: u16) -> CommonResult<()> {
+        primitives::validate_basis_points(max_divergence_bps)?;
+        
+        if reference == 0 {
+            return Err(CommonError::InvalidCalculation);
+        }
+        
+        let difference = price.abs_diff(reference);
+        let diverged = match difference.checked_mul(prim_constants::MAX_BASIS_POINTS as u128) {
+            Some(scaled) => scaled > reference.saturating_mul(max_divergence_bps as u128),
+            None => true,
+        };
+        
+        if diverged {
+            return Err(CommonError::PriceDivergence);
+        }
+        Ok(())
+    }
+
+    /// Base amount for `quote_amount` at `rate` (rounded down)
+    pub fn quote_to_base(quote_amount: u64, rate: u128) -> CommonResult<u64> {
+        if rate == 0 {
+            return Err(CommonError::InvalidCalculation);
+        }
+        
+        u64::try_from(((quote_amount as u128) << 64) / rate)
+            .map_err(|_| CommonError::InvalidCalculation)
+    }
+}
+
+/// Collateralized lending risk math; values are in a common quote unit and every result
+/// rounds in the protocol's favor
+pub mod liquidation_math {
+    use super::*;
+
+    /// Fixed-point scale of health factors; `HEALTH_SCALE` is a health factor of 1.0
+    pub const HEALTH_SCALE: u128 = 1_000_000_000_000;
+
+    /// Collateral value × liquidation threshold / debt value (rounded down), or `u128::MAX`
+    /// without debt; below `HEALTH_SCALE` the position can be liquidated
+    pub fn health_factor(
+        collateral_value: u64,
+        debt_value: u64,
+        liquidation_threshold_bps: u16,
+    ) -> CommonResult<u128> {
+        primitives::validate_basis_points(liquidation_threshold_bps)?;
+        
+        if debt_value == 0 {
+            return Ok(u128::MAX);
+        }
+        
+        (collateral_value as u128)
+            .checked_mul(liquidation_threshold_bps as u128)
+            .and_then(|val| val.checked_mul(HEALTH_SCALE))
+            .map(|val| val / (debt_value as u128 * prim_constants::MAX_BASIS_POINTS as u128))
+            .ok_or(CommonError::InvalidCalculation)
+    }
+
+    /// Whether a position is below a health factor of 1.0
+    pub fn is_liquidatable(
+        collateral_value: u64,
+        debt_value: u64,
+        liquidation_threshold_bps: u16,
+    ) -> CommonResult<bool> {
+        Ok(health_factor(collateral_value, debt_value, liquidation_threshold_bps)? < HEALTH_SCALE)
+    }
+
+    /// Loan-to-value ratio in basis points (rounded up); `u64::MAX` for debt without collateral
+    pub fn loan_to_value_bps(collateral_value: u64, debt_value: u64) -> CommonResult<u64> {
+        if debt_value == 0 {
+            return Ok(0);
+        }
+        if collateral_value == 0 {
+            return Ok(u64::MAX);
+        }
+        
+        let numerator = debt_value as u128 * prim_constants::MAX_BASIS_POINTS as u128;
+        u64::try_from(numerator.div_ceil(collateral_value as u128))
+            .map_err(|_| CommonError::InvalidCalculation)
+    }
+
+    /// Largest total debt `collateral_value` can back at `max_ltv_bps` (rounded down)
+    pub fn max_borrow(collateral_value: u64, max_ltv_bps: u16) -> CommonResult<u64> {
+        percentage::calculate_percentage(collateral_value, max_ltv_bps)
+    }
+
+    /// Largest part of `debt` a single liquidation may repay under `close_factor_bps`
+    /// (rounded down)
+    pub fn max_repayable(debt: u64, close_factor_bps: u16) -> CommonResult<u64> {
+        percentage::calculate_percentage(debt, close_factor_bps)
+    }
+
+    /// Split of a loss between the insurance fund and the socialized remainder
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct LossAllocation {
+        pub insurance: u64,
+        pub socialized: u64,
+    }
+
+    /// Loss waterfall: the insurance fund absorbs as much of `loss` as its balance covers,
+    /// and only the remainder is socialized
+    pub fn loss_waterfall(loss: u64, insurance_balance: u64) -> LossAllocation {
+        let insurance = loss.min(insurance_balance);
+        LossAllocation { insurance, socialized: loss - insurance }
+    }
+
+    /// Collateral value paid to a liquidator repaying `repay_value`: the repayment plus a
+    /// `bonus_bps` bonus (rounded down), capped at `collateral_value`
+    pub fn liquidation_collateral(
+        repay_value: u64,
+        bonus_bps: u16,
+        collateral_value: u64,
+    ) -> CommonResult<u64> {
+        let bonus = percentage::calculate_percentage(repay_value, bonus_bps)?;
+        let seized = repay_value.checked_add(bonus)
+            .ok_or(CommonError::InvalidCalculation)?;
+        
+        Ok(seized.min(collateral_value))
+    }
+}
+
+/// Funding rates for perpetual-style products, as signed fixed point
+pub mod funding_math {
+    use super::*;
+
+    /// Fixed-point scale of funding rates; `FUNDING_SCALE` is a rate of 100%
+    pub const FUNDING_SCALE: i128 = 1_000_000_000_000;
+
+    /// Period the mark/index premium is paid over, in seconds
+    pub const FUNDING_PERIOD_SECONDS: u64 = 8 * 60 * 60;
+
+    /// Configured bounds of a single interval's funding rate
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct FundingBounds {
+        pub min_rate: i128,
+        pub max_rate: i128,
+    }
+
+    /// Funding rate for an `interval` of seconds: the mark/index premium prorated over
+    /// `FUNDING_PERIOD_SECONDS` (rounded toward zero), clamped to `bounds`. Positive rates
+    /// mean longs pay shorts.
+    pub fn compute_funding_rate(
+        mark_twap: u64,
+        index_twap: u64,
+        interval: u64,
+        bounds: FundingBounds,
+    ) -> CommonResult<i128> {
+        if index_twap == 0 || bounds.min_rate > bounds.max_rate {
+            return Err(CommonError::InvalidCalculation);
+        }
+        
+        let premium = (mark_twap as i128 - index_twap as i128)
+            .checked_mul(FUNDING_SCALE)
+            .ok_or(CommonError::InvalidCalculation)?
+            / index_twap as i128;
+        let rate = premium
+            .checked_mul(interval as i128)
+            .ok_or(CommonError::InvalidCalculation)?
+            / FUNDING_PERIOD_SECONDS as i128;
+        
+        Ok(rate.clamp(bounds.min_rate, bounds.max_rate))
+    }
+
+    /// Funding owed by a position of `size` base units (negative for shorts) at `price` and
+    /// `rate`; positive amounts are paid by the position, negative ones received. Rounded up,
+    /// so payers never pay less and receivers never receive more than the exact amount.
+    pub fn funding_payment(size: i64, price: u64, rate: i128) -> CommonResult<i128> {
+        let owed = (size as i128)
+            .checked_mul(price as i128)
+            .and_then(|notional| notional.checked_mul(rate))
+            .ok_or(CommonError::InvalidCalculation)?;
+        
+        // Ceiling division: -floor(-owed / scale)
+        owed.checked_neg()
+            .map(|negated| -negated.div_euclid(FUNDING_SCALE))
+            .ok_or(CommonError::InvalidCalculation)
+    }
+}
+
+/// Amount limits: per-operation maximums and fixed-window usage caps (epoch budgets,
+/// daily transfer limits)
+pub mod limits {
+    use super::*;
+
+    /// Check `amount` against a per-operation `max`, where zero means unlimited
+    pub fn check_max(amount: u64, max: u64) -> CommonResult<()> {
+        if max != 0 && amount > max {
+            return Err(CommonError::Custom(format!("Amount {} exceeds limit {}", amount, max)));
+        }
+        Ok(())
+    }
+
+    /// Index of the fixed window of `window_len` units containing `position` (e.g. a slot)
+    pub fn window_of(position: u64, window_len: u64) -> CommonResult<u64> {
+        if window_len == 0 {
+            return Err(CommonError::InvalidCalculation);
+        }
+        Ok(position / window_len)
+    }
+
+    /// Usage counted against `current_window`, given `used` was recorded during `window`
+    pub fn usage_in_window(window: u64, used: u64, current_window: u64) -> u64 {
+        if window == current_window { used } else { 0 }
+    }
+
+    /// Capacity left in `current_window` under `cap`
+    pub fn remaining(window: u64, used: u64, current_window: u64, cap: u64) -> u64 {
+        cap.saturating_sub(usage_in_window(window, used, current_window))
+    }
+
+    /// Add `amount` to the usage of `current_window`, returning the new usage
+    pub fn consume(
+        window: u64,
+        used: u64,
+        current_window: u64,
+        amount: u64,
+        cap: u64,
+    ) -> CommonResult<u64> {
+        let prior = usage_in_window(window, used, current_window);
+        let used = prior.checked_add(amount)
+            .ok_or(CommonError::InvalidCalculation)?;
+        
+        if used > cap {
+            return Err(CommonError::Custom(
+                format!("Rate limit exceeded: {} of {} left in window", cap.saturating_sub(prior), cap)
+            ));
+        }
+        
+        Ok(used)
+    }
 }
 
 /// Safe arithmetic operations
@@ -137,4 +689,121 @@ pub mod advanced_math {
     pub fn is_perfect_liquidity_amount(amount: u64) -> CommonResult<bool> {
         number_theory::is_perfect_square(amount)
     }
-} 
\ No newline at end of file
+} 
+/// Browser bindings for the fee and token math; `u64` amounts cross as `BigInt`
+#[cfg(feature = "wasm")]
+pub mod wasm {
+    use super::*;
+    use wasm_bindgen::prelude::*;
+
+    fn js_error(err: CommonError) -> JsError {
+        JsError::new(&err.to_string())
+    }
+
+    /// Fee charged on `amount` at `fee_basis_points`
+    #[wasm_bindgen(js_name = calculateFee)]
+    pub fn calculate_fee(amount: u64, fee_basis_points: u16) -> Result<u64, JsError> {
+        percentage::calculate_percentage(amount, fee_basis_points).map_err(js_error)
+    }
+
+    /// Largest transfer whose amount plus fee fits within `budget`
+    #[wasm_bindgen(js_name = maxAmountWithFee)]
+    pub fn max_amount_with_fee(budget: u64, fee_basis_points: u16) -> Result<u64, JsError> {
+        percentage::max_amount_with_fee(budget, fee_basis_points).map_err(js_error)
+    }
+
+    /// Penalty on withdrawing `amount` from a lock with `remaining` of `duration` seconds left
+    #[wasm_bindgen(js_name = earlyWithdrawalPenalty)]
+    pub fn early_withdrawal_penalty(
+        amount: u64,
+        max_penalty_basis_points: u16,
+        remaining: u64,
+        duration: u64,
+    ) -> Result<u64, JsError> {
+        percentage::early_withdrawal_penalty(amount, max_penalty_basis_points, remaining, duration)
+            .map_err(js_error)
+    }
+
+    /// Scale a whole-token amount to base units
+    #[wasm_bindgen(js_name = calculateTokenAmount)]
+    pub fn calculate_token_amount(base_amount: u64, decimals: u8) -> Result<u64, JsError> {
+        token_math::calculate_token_amount(base_amount, decimals).map_err(js_error)
+    }
+
+    /// Re-express `amount` from `from_decimals` to `to_decimals`
+    #[wasm_bindgen(js_name = normalizeAmount)]
+    pub fn normalize_amount(amount: u64, from_decimals: u8, to_decimals: u8) -> Result<u64, JsError> {
+        token_math::normalize_amount(amount, from_decimals, to_decimals).map_err(js_error)
+    }
+
+    /// Share of `amount` vested after `elapsed` of `period` seconds
+    #[wasm_bindgen]
+    pub fn prorate(amount: u64, elapsed: u64, period: u64) -> Result<u64, JsError> {
+        accrual::prorate(amount, elapsed, period).map_err(js_error)
+    }
+}
+
+/// Kani proof harnesses for the arithmetic core (`cargo kani -p math-utils`)
+///
+/// Each harness checks all inputs: the function never panics, and it returns the exact
+/// mathematical result whenever that is representable and an error otherwise.
+#[cfg(kani)]
+mod verification {
+    use super::*;
+
+    #[kani::proof]
+    fn safe_add_is_exact_or_errors() {
+        let (a, b): (u64, u64) = (kani::any(), kani::any());
+        let exact = a as u128 + b as u128;
+        match safe_math::safe_add(a, b) {
+            Ok(sum) => assert!(sum as u128 == exact),
+            Err(_) => assert!(exact > u64::MAX as u128),
+        }
+    }
+
+    #[kani::proof]
+    fn safe_sub_is_exact_or_errors() {
+        let (a, b): (u64, u64) = (kani::any(), kani::any());
+        match safe_math::safe_sub(a, b) {
+            Ok(difference) => assert!(b <= a && difference + b == a),
+            Err(_) => assert!(b > a),
+        }
+    }
+
+    #[kani::proof]
+    fn safe_mul_is_exact_or_errors() {
+        let (a, b): (u64, u64) = (kani::any(), kani::any());
+        let exact = a as u128 * b as u128;
+        match safe_math::safe_mul(a, b) {
+            Ok(product) => assert!(product as u128 == exact),
+            Err(_) => assert!(exact > u64::MAX as u128),
+        }
+    }
+
+    #[kani::proof]
+    fn safe_div_rounds_down_or_errors_on_zero() {
+        let (a, b): (u64, u64) = (kani::any(), kani::any());
+        match safe_math::safe_div(a, b) {
+            Ok(quotient) => {
+                assert!(b != 0);
+                assert!(quotient <= a);
+                assert!(quotient as u128 * b as u128 <= a as u128);
+                assert!((quotient as u128 + 1) * b as u128 > a as u128);
+            }
+            Err(_) => assert!(b == 0),
+        }
+    }
+
+    #[kani::proof]
+    fn calculate_percentage_stays_within_amount() {
+        let (amount, basis_points): (u64, u16) = (kani::any(), kani::any());
+        match percentage::calculate_percentage(amount, basis_points) {
+            Ok(share) => {
+                assert!(basis_points <= prim_constants::MAX_BASIS_POINTS);
+                assert!(share <= amount);
+                assert!(share as u128 == amount as u128 * basis_points as u128 / 10_000);
+            }
+            Err(_) => assert!(basis_points > prim_constants::MAX_BASIS_POINTS),
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use math_utils::percentage::{
+    calculate_compound_interest, calculate_compound_interest_fixed, compound_factor_bps, COMPOUND_MAX_DIVERGENCE_PPB,
+    COMPOUND_SCALE, MAX_COMPOUND_PERIODS,
+};
+
+/// Deterministic xorshift64* so failures reproduce without a seed log
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+    
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+}
+
+/// Inputs whose growth factor stays under a million and whose result fits comfortably in a u64
+fn sample(rng: &mut Rng) -> (u64, u16, u32) {
+    loop {
+        let principal = 1 + rng.below(1_000_000_000_000_000);
+        let rate_basis_points = rng.below(10_001) as u16;
+        let periods = rng.below(3_651) as u32;
+        let factor = (1.0 + rate_basis_points as f64 / 10_000.0).powi(periods as i32);
+        if factor < 1e6 && principal as f64 * factor < 1e19 {
+            return (principal, rate_basis_points, periods);
+        }
+    }
+}
+
+#[test]
+fn fixed_point_matches_float_within_documented_divergence() {
+    let mut rng = Rng(0x5eed_cafe_f00d_beef);
+    let mut worst = (0u64, (0, 0, 0));
+    for _ in 0..200_000 {
+        let (principal, rate_basis_points, periods) = sample(&mut rng);
+        let float = calculate_compound_interest(principal, rate_basis_points, periods).unwrap();
+        let fixed = calculate_compound_interest_fixed(principal, rate_basis_points, periods).unwrap();
+        
+        // One unit of slack for the float's own truncation
+        let gap = float.abs_diff(fixed).saturating_sub(1) as u128;
+        let ppb = (gap * 1_000_000_000).div_ceil(float.max(1) as u128) as u64;
+        if ppb > worst.0 {
+            worst = (ppb, (principal, rate_basis_points, periods));
+        }
+    }
+    assert!(worst.0 <= COMPOUND_MAX_DIVERGENCE_PPB, "{} ppb at {:?}", worst.0, worst.1);
+}
+
+#[test]
+fn fixed_point_is_exact_without_growth() {
+    let mut rng = Rng(0x0dd_ba11);
+    for _ in 0..1_000 {
+        let principal = rng.next();
+        assert_eq!(calculate_compound_interest_fixed(principal, 0, rng.below(10_000) as u32).unwrap(), principal);
+        assert_eq!(calculate_compound_interest_fixed(principal, rng.below(10_001) as u16, 0).unwrap(), principal);
+    }
+}
+
+#[test]
+fn fixed_point_fails_where_float_saturates() {
+    // 100% for 64 periods: the float result saturates at u64::MAX, the fixed one refuses
+    assert_eq!(calculate_compound_interest(1, 10_000, 64).unwrap(), u64::MAX);
+    assert!(calculate_compound_interest_fixed(1, 10_000, 64).is_err());
+    assert!(calculate_compound_interest_fixed(1, 10_001, 1).is_err());
+}
+
+#[test]
+fn compound_factor_is_exact_when_the_series_terminates() {
+    assert_eq!(compound_factor_bps(100, 2).unwrap(), COMPOUND_SCALE * 10_201 / 10_000);
+    assert_eq!(compound_factor_bps(10_000, 10).unwrap(), COMPOUND_SCALE * 1_024);
+    assert_eq!(compound_factor_bps(2_500, 0).unwrap(), COMPOUND_SCALE);
+}
+
+#[test]
+fn compound_factor_rejects_unbounded_inputs() {
+    assert!(compound_factor_bps(1, MAX_COMPOUND_PERIODS).is_ok());
+    assert!(compound_factor_bps(0, MAX_COMPOUND_PERIODS + 1).is_err());
+    assert!(compound_factor_bps(10_001, 1).is_err());
+    // 2^128 overflows the u128 factor
+    assert!(compound_factor_bps(10_000, 128).is_err());
+}
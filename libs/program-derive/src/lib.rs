@@ -0,0 +1,215 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use sha2::{Digest, Sha256};
+use syn::{
+    parse::{Parse, ParseStream},
+    parse_macro_input,
+    punctuated::Punctuated,
+    Data, DeriveInput, Error, Expr, Fields, GenericArgument, Ident, LitStr, Path, PathArguments, Result, Token,
+    Type,
+};
+
+/// One element of `#[seeds(...)]`: a string literal, a field of the struct, or a `&[u8]` constant
+enum Seed {
+    Literal(LitStr),
+    Path(Path),
+}
+
+impl Parse for Seed {
+    fn parse(input: ParseStream) -> Result<Self> {
+        if input.peek(LitStr) {
+            input.parse().map(Seed::Literal)
+        } else {
+            input.parse().map(Seed::Path)
+        }
+    }
+}
+
+/// `#[space(auto)]` sizes the Borsh encoding from the field types; `#[space(<expr>)]` is taken as is
+enum Space {
+    Auto,
+    Explicit(Expr),
+}
+
+impl Parse for Space {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let expr: Expr = input.parse()?;
+        match &expr {
+            Expr::Path(path) if path.path.is_ident("auto") => Ok(Space::Auto),
+            _ => Ok(Space::Explicit(expr)),
+        }
+    }
+}
+
+/// Borsh-encoded size of a fixed-size field type
+fn borsh_size(ty: &Type) -> Result<TokenStream2> {
+    match ty {
+        Type::Array(array) => {
+            let element = borsh_size(&array.elem)?;
+            let len = &array.len;
+            Ok(quote! { (#len) * (#element) })
+        }
+        Type::Path(path) => {
+            let segment = path.path.segments.last().ok_or_else(|| Error::new_spanned(ty, "empty type path"))?;
+            let size: usize = match segment.ident.to_string().as_str() {
+                "bool" | "u8" | "i8" => 1,
+                "u16" | "i16" => 2,
+                "u32" | "i32" => 4,
+                "u64" | "i64" => 8,
+                "u128" | "i128" => 16,
+                "Pubkey" => 32,
+                "Option" => {
+                    let PathArguments::AngleBracketed(arguments) = &segment.arguments else {
+                        return Err(Error::new_spanned(ty, "Option without a type argument"));
+                    };
+                    let Some(GenericArgument::Type(inner)) = arguments.args.first() else {
+                        return Err(Error::new_spanned(ty, "Option without a type argument"));
+                    };
+                    let inner = borsh_size(inner)?;
+                    return Ok(quote! { 1 + (#inner) });
+                }
+                _ => {
+                    return Err(Error::new_spanned(
+                        ty,
+                        "#[space(auto)] cannot size this type; give the size with #[space(<expr>)]",
+                    ))
+                }
+            };
+            Ok(quote! { #size })
+        }
+        _ => Err(Error::new_spanned(ty, "#[space(auto)] cannot size this type; give the size with #[space(<expr>)]")),
+    }
+}
+
+/// Whether `ty` is a `Pubkey` or byte array, passed by reference and used as a seed directly
+fn is_byte_seed(ty: &Type) -> bool {
+    match ty {
+        Type::Array(_) => true,
+        Type::Path(path) => path.path.segments.last().is_some_and(|segment| segment.ident == "Pubkey"),
+        _ => false,
+    }
+}
+
+fn expand(input: DeriveInput) -> Result<TokenStream2> {
+    let name = &input.ident;
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new_spanned(&input.ident, "ProgramAccount can only be derived for structs"));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(Error::new_spanned(&input.ident, "ProgramAccount needs named fields"));
+    };
+    let field_type = |ident: &Ident| fields.named.iter().find(|field| field.ident.as_ref() == Some(ident)).map(|field| &field.ty);
+    
+    let mut seeds = None;
+    let mut space = None;
+    for attribute in &input.attrs {
+        if attribute.path().is_ident("seeds") {
+            seeds = Some(attribute.parse_args_with(Punctuated::<Seed, Token![,]>::parse_terminated)?);
+        } else if attribute.path().is_ident("space") {
+            space = Some(attribute.parse_args::<Space>()?);
+        }
+    }
+    
+    let digest = Sha256::digest(format!("account:{}", name).as_bytes());
+    let discriminator = &digest[..8];
+    let mut items = vec![quote! {
+        /// First 8 bytes of `sha256("account:<type name>")`, identifying the type off-chain
+        pub const DISCRIMINATOR: [u8; 8] = [#(#discriminator),*];
+
+        /// Write this account into the start of `info`'s data
+        pub fn save(&self, info: &::solana_program::account_info::AccountInfo) -> ::solana_program::entrypoint::ProgramResult {
+            ::account_utils::account_data::serialize_account_data(info, self)
+                .map_err(|_| ::solana_program::program_error::ProgramError::AccountDataTooSmall)
+        }
+    }];
+    
+    match space {
+        Some(Space::Auto) => {
+            let sizes = fields.named.iter().map(|field| borsh_size(&field.ty)).collect::<Result<Vec<_>>>()?;
+            items.push(quote! {
+                /// Borsh-encoded size of the account data
+                pub const SIZE: usize = 0 #(+ #sizes)*;
+            });
+        }
+        Some(Space::Explicit(expr)) => items.push(quote! {
+            /// Borsh-encoded size of the account data
+            pub const SIZE: usize = #expr;
+        }),
+        None => {}
+    }
+    
+    if let Some(seeds) = seeds {
+        let mut parameters = Vec::new();
+        let mut arguments = Vec::new();
+        let mut seed_exprs = Vec::new();
+        for seed in &seeds {
+            match seed {
+                Seed::Literal(literal) => {
+                    let bytes = syn::LitByteStr::new(literal.value().as_bytes(), literal.span());
+                    seed_exprs.push(quote! { #bytes });
+                }
+                Seed::Path(path) => match path.get_ident().and_then(|ident| field_type(ident).map(|ty| (ident, ty))) {
+                    Some((ident, ty)) if is_byte_seed(ty) => {
+                        parameters.push(quote! { #ident: &#ty });
+                        arguments.push(quote! { #ident });
+                        seed_exprs.push(quote! { #ident.as_ref() });
+                    }
+                    Some((ident, ty)) => {
+                        parameters.push(quote! { #ident: #ty });
+                        arguments.push(quote! { #ident });
+                        seed_exprs.push(quote! { &#ident.to_le_bytes() });
+                    }
+                    None => seed_exprs.push(quote! { #path }),
+                },
+            }
+        }
+        
+        items.push(quote! {
+            /// Address and bump of the account's PDA
+            pub fn find_address(
+                #(#parameters,)*
+                program_id: &::solana_program::pubkey::Pubkey,
+            ) -> ::common::CommonResult<(::solana_program::pubkey::Pubkey, u8)> {
+                ::account_utils::account_creation::create_pda_with_validation(&[#(#seed_exprs),*], program_id)
+            }
+
+            /// Load the account from `info`, verifying it lives at its PDA
+            pub fn load(
+                program_id: &::solana_program::pubkey::Pubkey,
+                info: &::solana_program::account_info::AccountInfo,
+                #(#parameters,)*
+            ) -> Result<Self, ::solana_program::program_error::ProgramError> {
+                let (expected_pda, _bump) = Self::find_address(#(#arguments,)* program_id)
+                    .map_err(|_| ::solana_program::program_error::ProgramError::InvalidSeeds)?;
+                
+                if info.key != &expected_pda {
+                    return Err(::solana_program::program_error::ProgramError::InvalidSeeds);
+                }
+                
+                ::account_utils::account_data::deserialize_account_data::<Self>(info)
+                    .map_err(|_| ::solana_program::program_error::ProgramError::InvalidAccountData)
+            }
+        });
+    }
+    
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics #name #type_generics #where_clause {
+            #(#items)*
+        }
+    })
+}
+
+/// Generate the metadata and helpers of a program account
+///
+/// - `DISCRIMINATOR`: first 8 bytes of `sha256("account:<Name>")`
+/// - `SIZE` with `#[space(auto)]` (summed from fixed-size field types) or `#[space(<expr>)]`
+/// - with `#[seeds(...)]`, `find_address(<seed fields>, program_id)` and
+///   `load(program_id, info, <seed fields>)`; seeds are string literals, `&[u8]` constants, or
+///   field names (pubkeys and byte arrays by reference, integers as little-endian bytes)
+/// - `save(&self, info)`
+#[proc_macro_derive(ProgramAccount, attributes(seeds, space))]
+pub fn derive_program_account(input: TokenStream) -> TokenStream {
+    expand(parse_macro_input!(input as DeriveInput)).unwrap_or_else(Error::into_compile_error).into()
+}
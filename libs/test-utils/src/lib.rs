@@ -0,0 +1,430 @@
+use solana_program::{
+    account_info::AccountInfo,
+    pubkey::Pubkey,
+    system_program,
+};
+use borsh::{BorshDeserialize, BorshSerialize};
+use account_utils::account_creation;
+use math_utils::index_math;
+use my_solana_program::{
+    reclaim::ReclaimPolicy, treasury::Treasury, Metrics, ProgramConfig, ProgramState, UserAccount, PROGRAM_STATE_VERSION,
+    USER_ACCOUNT_VERSION,
+};
+
+/// Owned account storage that hands out `AccountInfo`s borrowing from itself
+#[derive(Debug, Clone)]
+pub struct MockAccount {
+    pub key: Pubkey,
+    pub owner: Pubkey,
+    pub lamports: u64,
+    pub data: Vec<u8>,
+    pub is_signer: bool,
+    pub is_writable: bool,
+    pub executable: bool,
+    pub rent_epoch: u64,
+}
+
+impl MockAccount {
+    /// Writable, system-owned account with a unique key and no data
+    pub fn new() -> Self {
+        Self {
+            key: Pubkey::new_unique(),
+            owner: system_program::id(),
+            lamports: 0,
+            data: Vec::new(),
+            is_signer: false,
+            is_writable: true,
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    pub fn key(mut self, key: Pubkey) -> Self {
+        self.key = key;
+        self
+    }
+
+    pub fn owner(mut self, owner: Pubkey) -> Self {
+        self.owner = owner;
+        self
+    }
+
+    pub fn lamports(mut self, lamports: u64) -> Self {
+        self.lamports = lamports;
+        self
+    }
+
+    pub fn data(mut self, data: Vec<u8>) -> Self {
+        self.data = data;
+        self
+    }
+
+    /// Use the Borsh encoding of `value` as the account data
+    pub fn borsh<T: BorshSerialize>(self, value: &T) -> Self {
+        self.data(value.try_to_vec().expect("fixture serializes"))
+    }
+
+    pub fn signer(mut self) -> Self {
+        self.is_signer = true;
+        self
+    }
+
+    pub fn readonly(mut self) -> Self {
+        self.is_writable = false;
+        self
+    }
+
+    pub fn executable(mut self) -> Self {
+        self.executable = true;
+        self
+    }
+
+    /// Borrow as an `AccountInfo`; writes through it land in this mock
+    pub fn info(&mut self) -> AccountInfo<'_> {
+        AccountInfo::new(
+            &self.key,
+            self.is_signer,
+            self.is_writable,
+            &mut self.lamports,
+            &mut self.data,
+            &self.owner,
+            self.executable,
+            self.rent_epoch,
+        )
+    }
+
+    /// Decode the account data
+    pub fn decode<T: BorshDeserialize>(&self) -> T {
+        T::try_from_slice(&self.data).expect("account data decodes")
+    }
+}
+
+impl Default for MockAccount {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Borrow several mocks at once, in instruction account order; also installs the
+/// `sysvars` clock so handlers can call `Clock::get`
+pub fn infos(accounts: &mut [MockAccount]) -> Vec<AccountInfo<'_>> {
+    sysvars::install();
+    accounts.iter_mut().map(MockAccount::info).collect()
+}
+
+/// Clock sysvar for handlers run off-chain, kept per test thread so tests can warp
+/// independently
+pub mod sysvars {
+    use solana_program::{clock::Clock, entrypoint::SUCCESS, program_stubs::{self, SyscallStubs}};
+    use std::{cell::RefCell, sync::Once};
+
+    /// Slot each test thread's clock starts at
+    pub const START_SLOT: u64 = 1_000;
+
+    thread_local! {
+        static CLOCK: RefCell<Clock> = RefCell::new(Clock { slot: START_SLOT, ..Clock::default() });
+    }
+
+    struct ThreadClock;
+
+    impl SyscallStubs for ThreadClock {
+        fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+            let clock = CLOCK.with(|clock| clock.borrow().clone());
+            unsafe { std::ptr::write(var_addr.cast::<Clock>(), clock) };
+            SUCCESS
+        }
+    }
+
+    /// Route `Clock::get` to the calling thread's clock
+    pub fn install() {
+        static INSTALL: Once = Once::new();
+        INSTALL.call_once(|| {
+            program_stubs::set_syscall_stubs(Box::new(ThreadClock));
+        });
+    }
+
+    /// Move this thread's clock to `slot`
+    pub fn warp_to_slot(slot: u64) {
+        CLOCK.with(|clock| clock.borrow_mut().slot = slot);
+    }
+
+    /// Move this thread's clock to `unix_timestamp`
+    pub fn warp_to_timestamp(unix_timestamp: i64) {
+        CLOCK.with(|clock| clock.borrow_mut().unix_timestamp = unix_timestamp);
+    }
+
+    /// Move this thread's clock to `epoch`
+    pub fn warp_to_epoch(epoch: u64) {
+        CLOCK.with(|clock| clock.borrow_mut().epoch = epoch);
+    }
+
+    /// This thread's current clock
+    pub fn clock() -> Clock {
+        CLOCK.with(|clock| clock.borrow().clone())
+    }
+}
+
+/// Canonical program accounts at their expected PDAs
+pub mod fixtures {
+    use super::*;
+
+    /// Initialized, unpaused program state
+    pub fn program_state(authority: &Pubkey) -> ProgramState {
+        ProgramState {
+            authority: *authority,
+            total_supply: 1_000_000,
+            is_initialized: true,
+            paused: false,
+            emergency_guardian: Pubkey::default(),
+            pow_difficulty: 0,
+            interest_index: index_math::INDEX_SCALE,
+            interest_rate_bps: 0,
+            last_accrual_at: 0,
+            reward_rate: 0,
+            reward_per_share: 0,
+            total_staked: 0,
+            last_reward_at: 0,
+            withdrawal_delay: 0,
+            version: PROGRAM_STATE_VERSION,
+        }
+    }
+
+    /// User account belonging to `owner`
+    pub fn user_account(program_id: &Pubkey, owner: &Pubkey, balance: u64) -> UserAccount {
+        UserAccount {
+            owner: *owner,
+            balance,
+            program_state: program_state_address(program_id),
+            flags: 0,
+            bump: pda_with_bump(account_creation::create_user_pda(owner, program_id)).1,
+            nonce: 0,
+            last_activity_slot: 0,
+            dormant_since_slot: 0,
+            version: USER_ACCOUNT_VERSION,
+        }
+    }
+
+    /// Address of the program state PDA
+    pub fn program_state_address(program_id: &Pubkey) -> Pubkey {
+        pda(account_creation::create_program_state_pda(program_id))
+    }
+
+    /// Signing wallet; account validation rejects empty accounts, so it carries one data byte
+    pub fn wallet() -> MockAccount {
+        MockAccount::new().lamports(1_000_000_000).data(vec![0]).signer()
+    }
+
+    pub fn program_state_account(program_id: &Pubkey, state: &ProgramState) -> MockAccount {
+        program_account(program_id, program_state_address(program_id), state)
+    }
+
+    pub fn program_config_account(program_id: &Pubkey, config: &ProgramConfig) -> MockAccount {
+        program_account(program_id, pda(account_creation::create_program_config_pda(program_id)), config)
+    }
+
+    pub fn metrics_account(program_id: &Pubkey) -> MockAccount {
+        program_account(program_id, pda(account_creation::create_metrics_pda(program_id)), &Metrics::default())
+    }
+
+    pub fn treasury_account(program_id: &Pubkey) -> MockAccount {
+        program_account(program_id, pda(account_creation::create_treasury_pda(program_id)), &Treasury::default())
+    }
+
+    pub fn user_account_account(program_id: &Pubkey, user: &UserAccount) -> MockAccount {
+        program_account(program_id, pda(account_creation::create_user_pda(&user.owner, program_id)), user)
+    }
+
+    pub fn reclaim_policy_account(program_id: &Pubkey, policy: &ReclaimPolicy) -> MockAccount {
+        program_account(program_id, pda(ReclaimPolicy::find_address(program_id)), policy)
+    }
+
+    /// Zeroed account at `key`, sized for `value`'s encoding
+    pub fn empty_account_for<T: BorshSerialize>(program_id: &Pubkey, key: Pubkey, value: &T) -> MockAccount {
+        let len = value.try_to_vec().expect("fixture serializes").len();
+        MockAccount::new().key(key).owner(*program_id).data(vec![0; len])
+    }
+
+    fn program_account<T: BorshSerialize>(program_id: &Pubkey, key: Pubkey, value: &T) -> MockAccount {
+        MockAccount::new().key(key).owner(*program_id).borsh(value)
+    }
+
+    fn pda<E: std::fmt::Debug>(result: Result<(Pubkey, u8), E>) -> Pubkey {
+        pda_with_bump(result).0
+    }
+
+    fn pda_with_bump<E: std::fmt::Debug>(result: Result<(Pubkey, u8), E>) -> (Pubkey, u8) {
+        result.expect("fixture PDA derives")
+    }
+}
+
+/// Field-level comparison of account data before and after an instruction
+pub mod debug {
+    use super::*;
+    use borsh::{
+        schema::{BorshSchemaContainer, Definition, Fields},
+        BorshSchema,
+    };
+    use std::collections::HashMap;
+
+    /// One changed leaf field, addressed by a path such as `entries[3].amount`;
+    /// `None` marks a field present on one side only (a resized vector or a switched variant)
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct FieldChange {
+        pub path: String,
+        pub before: Option<String>,
+        pub after: Option<String>,
+    }
+
+    /// Decode both encodings with `T`'s Borsh schema and list the leaf fields that differ,
+    /// in declaration order
+    pub fn diff_accounts<T: BorshDeserialize + BorshSchema>(before: &[u8], after: &[u8]) -> Vec<FieldChange> {
+        T::try_from_slice(before).expect("before data decodes");
+        T::try_from_slice(after).expect("after data decodes");
+        
+        let container = T::schema_container();
+        let before = leaves(&container, before);
+        let after = leaves(&container, after);
+        let after_by_path: HashMap<&str, &str> =
+            after.iter().map(|(path, value)| (path.as_str(), value.as_str())).collect();
+        let before_by_path: HashMap<&str, &str> =
+            before.iter().map(|(path, value)| (path.as_str(), value.as_str())).collect();
+        
+        let mut changes = Vec::new();
+        for (path, value) in &before {
+            let after = after_by_path.get(path.as_str()).copied();
+            if after != Some(value.as_str()) {
+                changes.push(FieldChange { path: path.clone(), before: Some(value.clone()), after: after.map(str::to_string) });
+            }
+        }
+        for (path, value) in &after {
+            if !before_by_path.contains_key(path.as_str()) {
+                changes.push(FieldChange { path: path.clone(), before: None, after: Some(value.clone()) });
+            }
+        }
+        changes
+    }
+
+    /// Paths of the fields that differ between the two encodings
+    pub fn changed_fields<T: BorshDeserialize + BorshSchema>(before: &[u8], after: &[u8]) -> Vec<String> {
+        diff_accounts::<T>(before, after).into_iter().map(|change| change.path).collect()
+    }
+
+    /// Flatten an encoding into `(path, rendered value)` pairs following the schema
+    fn leaves(container: &BorshSchemaContainer, data: &[u8]) -> Vec<(String, String)> {
+        let mut walker = Walker { definitions: &container.definitions, data, leaves: Vec::new() };
+        walker.walk(&container.declaration, String::new());
+        walker.leaves
+    }
+    
+    struct Walker<'a> {
+        definitions: &'a HashMap<String, Definition>,
+        data: &'a [u8],
+        leaves: Vec<(String, String)>,
+    }
+
+    impl Walker<'_> {
+        fn take(&mut self, len: usize) -> &[u8] {
+            let (head, tail) = self.data.split_at(len);
+            self.data = tail;
+            head
+        }
+
+        fn take_u32(&mut self) -> usize {
+            u32::from_le_bytes(self.take(4).try_into().unwrap()) as usize
+        }
+
+        fn leaf(&mut self, path: String, value: String) {
+            self.leaves.push((path, value));
+        }
+
+        fn walk(&mut self, declaration: &str, path: String) {
+            macro_rules! int {
+                ($ty:ty) => {{
+                    let value = <$ty>::from_le_bytes(self.take(std::mem::size_of::<$ty>()).try_into().unwrap());
+                    self.leaf(path, value.to_string())
+                }};
+            }
+            
+            match declaration {
+                "nil" => {}
+                "bool" => {
+                    let value = self.take(1)[0] != 0;
+                    self.leaf(path, value.to_string())
+                }
+                "u8" => int!(u8),
+                "u16" => int!(u16),
+                "u32" => int!(u32),
+                "u64" => int!(u64),
+                "u128" => int!(u128),
+                "i8" => int!(i8),
+                "i16" => int!(i16),
+                "i32" => int!(i32),
+                "i64" => int!(i64),
+                "i128" => int!(i128),
+                "string" => {
+                    let len = self.take_u32();
+                    let value = String::from_utf8_lossy(self.take(len)).into_owned();
+                    self.leaf(path, format!("{:?}", value))
+                }
+                "Pubkey" => {
+                    let key = Pubkey::new_from_array(self.take(32).try_into().unwrap());
+                    self.leaf(path, key.to_string())
+                }
+                _ => self.walk_definition(declaration, path),
+            }
+        }
+
+        fn walk_definition(&mut self, declaration: &str, path: String) {
+            let definition = self.definitions.get(declaration).unwrap_or_else(|| panic!("{} has no definition", declaration));
+            match definition {
+                Definition::Array { length, elements } => self.walk_elements(elements, *length as usize, path),
+                Definition::Sequence { elements } => {
+                    let len = self.take_u32();
+                    self.walk_elements(elements, len, path)
+                }
+                Definition::Tuple { elements } | Definition::Struct { fields: Fields::UnnamedFields(elements) } => {
+                    for (index, element) in elements.iter().enumerate() {
+                        self.walk(element, join(&path, &index.to_string()));
+                    }
+                }
+                Definition::Struct { fields: Fields::NamedFields(fields) } => {
+                    for (name, field) in fields {
+                        self.walk(field, join(&path, name));
+                    }
+                }
+                Definition::Struct { fields: Fields::Empty } => {}
+                Definition::Enum { variants } => {
+                    let (name, variant) = &variants[self.take(1)[0] as usize];
+                    // Options collapse to their value; other enums record the variant name
+                    if name == "None" {
+                        self.leaf(path, "None".to_string());
+                    } else if name == "Some" {
+                        self.walk(variant, path);
+                    } else {
+                        self.leaf(path.clone(), name.clone());
+                        self.walk(variant, path);
+                    }
+                }
+            }
+        }
+
+        /// Byte arrays and vectors render as one hex leaf; other elements are indexed
+        fn walk_elements(&mut self, elements: &str, len: usize, path: String) {
+            if elements == "u8" {
+                let hex: String = self.take(len).iter().map(|byte| format!("{:02x}", byte)).collect();
+                return self.leaf(path, hex);
+            }
+            for index in 0..len {
+                self.walk(elements, format!("{}[{}]", path, index));
+            }
+        }
+    }
+
+    fn join(path: &str, field: &str) -> String {
+        if path.is_empty() {
+            field.to_string()
+        } else {
+            format!("{}.{}", path, field)
+        }
+    }
+}
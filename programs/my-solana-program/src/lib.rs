@@ -1,22 +1,63 @@
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
-    entrypoint,
+    clock::Clock,
     entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction},
     msg,
+    log::sol_log_data,
+    program::{invoke, invoke_signed, set_return_data},
     program_error::ProgramError,
     pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    system_program,
+    sysvar::Sysvar,
 };
 use borsh::{BorshDeserialize, BorshSerialize};
+use program_derive::ProgramAccount;
 
 // Import our path dependencies
-use account_utils::{account_creation, account_validation, account_data};
-use math_utils::{token_math, percentage, safe_math};
+use account_utils::{account_creation, account_validation, account_data, custody::ProgramAuthority, merkle, migration};
+use math_utils::{token_math, percentage, safe_math, accrual, limits, index_math, rate_math, liquidation_math};
 
 // This also brings in common transitively through our dependencies
-use common::{CommonResult, CommonError};
+use common::{CommonResult, CommonError, log_debug, log_info, log_error, log::ComputeScope, constants::{DEFAULT_DECIMALS, PROGRAM_STATE_SEED, PROGRAM_CONFIG_SEED, USER_SEED, METRICS_SEED, IDEMPOTENCY_LOG_SEED, DAILY_STATS_SEED, SCHEDULED_TRANSFER_SEED, SUBSCRIPTION_SEED, STREAM_SEED, INVOICE_SEED, PROPOSAL_SEED, SESSION_KEY_SEED, AUTHORITY_HISTORY_SEED, LOCK_SEED, AUDIT_LOG_SEED, COMPLIANCE_CONFIG_SEED, ACCOUNT_LIMITS_SEED, ATTESTATION_SEED, DELEGATIONS_SEED, RATE_STATE_SEED, LENDING_CONFIG_SEED, INSURANCE_FUND_SEED, POSITION_SEED, VAULT_SEED, TREASURY_SEED, MULTISIG_SEED, CRANKER_SEED, BOUNTY_POOL_SEED, FEE_SCHEDULE_SEED, RECLAIM_POLICY_SEED, UPLOAD_SEED, REGISTRY_PAGE_SEED, COMPRESSED_TREE_SEED, VESTING_SEED, STAKE_SEED, WITHDRAWAL_SEED, ALLOWLIST_SEED}, feature_flags, evm::EvmAddress};
+
+pub use instruction::ProgramInstruction;
 
 // Declare and export the program's entrypoint
-entrypoint!(process_instruction);
+#[cfg(not(any(feature = "no-entrypoint", feature = "lazy-entrypoint")))]
+solana_program::entrypoint!(process_instruction);
+
+// Manual entrypoint that decodes the instruction before materializing any account
+#[cfg(all(feature = "lazy-entrypoint", not(feature = "no-entrypoint")))]
+solana_program::custom_heap_default!();
+#[cfg(all(feature = "lazy-entrypoint", not(feature = "no-entrypoint")))]
+solana_program::custom_panic_default!();
+
+/// # Safety
+///
+/// Called by the runtime with the serialized input of the aligned BPF loader.
+#[cfg(all(feature = "lazy-entrypoint", not(feature = "no-entrypoint")))]
+#[no_mangle]
+pub unsafe extern "C" fn entrypoint(input: *mut u8) -> u64 {
+    let input = lazy_entrypoint::LazyInput::parse(input);
+    let result = ProgramInstruction::unpack(input.instruction_data()).and_then(|instruction| {
+        let program_id = input.program_id();
+        let accounts = input.into_accounts();
+        dispatch_instruction(program_id, &accounts, instruction)
+    });
+    
+    match result {
+        Ok(()) => solana_program::entrypoint::SUCCESS,
+        Err(error) => error.into(),
+    }
+}
+
+// Replace the default 32KB heap allocator when `custom-heap` is enabled
+#[cfg(all(target_os = "solana", feature = "custom-heap", not(feature = "no-entrypoint")))]
+#[global_allocator]
+static ALLOCATOR: allocator::BumpAllocator = allocator::BumpAllocator::new();
 
 // Program entrypoint's implementation
 pub fn process_instruction(
@@ -24,21 +65,435 @@ pub fn process_instruction(
     accounts: &[AccountInfo],
     instruction_data: &[u8],
 ) -> ProgramResult {
-    let instruction = ProgramInstruction::try_from_slice(instruction_data)
-        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    let instruction = ProgramInstruction::unpack(instruction_data)?;
+    dispatch_instruction(program_id, accounts, instruction)
+}
 
+/// Route a decoded instruction to its handler
+fn dispatch_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction: ProgramInstruction,
+) -> ProgramResult {
+    if instruction.is_pausable() {
+        check_pause_hook(program_id, accounts)?;
+    }
+    
     match instruction {
         ProgramInstruction::Initialize { initial_amount } => {
             msg!("Instruction: Initialize");
             process_initialize(program_id, accounts, initial_amount)
         }
-        ProgramInstruction::CreateUserAccount { initial_balance } => {
+        ProgramInstruction::CreateUserAccount { initial_balance, pow_nonce } => {
             msg!("Instruction: CreateUserAccount");
-            process_create_user_account(program_id, accounts, initial_balance)
+            process_create_user_account(program_id, accounts, initial_balance, pow_nonce, 0)
         }
-        ProgramInstruction::TransferWithFee { amount, fee_basis_points } => {
+        ProgramInstruction::TransferWithFee { amount, fee_basis_points, idempotency_key } => {
             msg!("Instruction: TransferWithFee");
-            process_transfer_with_fee(program_id, accounts, amount, fee_basis_points)
+            process_transfer_with_fee(program_id, accounts, amount, fee_basis_points, idempotency_key)
+        }
+        ProgramInstruction::CreateSessionKey { delegate, scope, per_operation_limit, expires_at } => {
+            msg!("Instruction: CreateSessionKey");
+            process_create_session_key(
+                program_id,
+                accounts,
+                delegate,
+                scope,
+                per_operation_limit,
+                expires_at,
+            )
+        }
+        ProgramInstruction::RotateAuthority { new_authority } => {
+            msg!("Instruction: RotateAuthority");
+            process_rotate_authority(program_id, accounts, new_authority)
+        }
+        ProgramInstruction::SetEmergencyGuardian { guardian } => {
+            msg!("Instruction: SetEmergencyGuardian");
+            process_set_emergency_guardian(program_id, accounts, guardian)
+        }
+        ProgramInstruction::Pause => {
+            msg!("Instruction: Pause");
+            process_pause(program_id, accounts)
+        }
+        ProgramInstruction::Unpause => {
+            msg!("Instruction: Unpause");
+            process_unpause(program_id, accounts)
+        }
+        ProgramInstruction::SetPowDifficulty { difficulty } => {
+            msg!("Instruction: SetPowDifficulty");
+            process_set_pow_difficulty(program_id, accounts, difficulty)
+        }
+        ProgramInstruction::UpdateConfig { config } => {
+            msg!("Instruction: UpdateConfig");
+            process_update_config(program_id, accounts, config)
+        }
+        ProgramInstruction::SetFeatureFlag { feature, enabled } => {
+            msg!("Instruction: SetFeatureFlag");
+            process_set_feature_flag(program_id, accounts, feature, enabled)
+        }
+        ProgramInstruction::TransferUpTo { max_amount, min_amount } => {
+            msg!("Instruction: TransferUpTo");
+            process_transfer_up_to(program_id, accounts, max_amount, min_amount)
+        }
+        ProgramInstruction::ScheduleTransfer { schedule_id, execute_after, amount, recipient, bounty } => {
+            msg!("Instruction: ScheduleTransfer");
+            process_schedule_transfer(program_id, accounts, schedule_id, execute_after, amount, recipient, bounty)
+        }
+        ProgramInstruction::ExecuteScheduled => {
+            msg!("Instruction: ExecuteScheduled");
+            process_execute_scheduled(program_id, accounts)
+        }
+        ProgramInstruction::RegisterCranker => {
+            msg!("Instruction: RegisterCranker");
+            process_register_cranker(program_id, accounts)
+        }
+        ProgramInstruction::FundBountyPool { amount } => {
+            msg!("Instruction: FundBountyPool");
+            process_fund_bounty_pool(program_id, accounts, amount)
+        }
+        ProgramInstruction::SetTaskBounty { task, bounty } => {
+            msg!("Instruction: SetTaskBounty");
+            process_set_task_bounty(program_id, accounts, task, bounty)
+        }
+        ProgramInstruction::CreateSubscription { subscription_id, merchant, amount, interval } => {
+            msg!("Instruction: CreateSubscription");
+            process_create_subscription(program_id, accounts, subscription_id, merchant, amount, interval)
+        }
+        ProgramInstruction::CancelSubscription => {
+            msg!("Instruction: CancelSubscription");
+            process_cancel_subscription(program_id, accounts)
+        }
+        ProgramInstruction::CollectSubscription => {
+            msg!("Instruction: CollectSubscription");
+            process_collect_subscription(program_id, accounts)
+        }
+        ProgramInstruction::CreateStream { stream_id, recipient, deposit, start_time, stop_time } => {
+            msg!("Instruction: CreateStream");
+            process_create_stream(program_id, accounts, stream_id, recipient, deposit, start_time, stop_time)
+        }
+        ProgramInstruction::WithdrawFromStream => {
+            msg!("Instruction: WithdrawFromStream");
+            process_withdraw_from_stream(program_id, accounts)
+        }
+        ProgramInstruction::CancelStream => {
+            msg!("Instruction: CancelStream");
+            process_cancel_stream(program_id, accounts)
+        }
+        ProgramInstruction::CreateInvoice { invoice_id, amount, memo_hash, expiry } => {
+            msg!("Instruction: CreateInvoice");
+            process_create_invoice(program_id, accounts, invoice_id, amount, memo_hash, expiry)
+        }
+        ProgramInstruction::PayInvoice { amount } => {
+            msg!("Instruction: PayInvoice");
+            process_pay_invoice(program_id, accounts, amount)
+        }
+        ProgramInstruction::AtomicSwap { amount_a, amount_b, fee_basis_points } => {
+            msg!("Instruction: AtomicSwap");
+            process_atomic_swap(program_id, accounts, amount_a, amount_b, fee_basis_points)
+        }
+        ProgramInstruction::MigrateUserAccountV2 => {
+            msg!("Instruction: MigrateUserAccountV2");
+            process_migrate_user_account_v2(program_id, accounts)
+        }
+        ProgramInstruction::CreateMultisig { multisig_id, signers, weights, threshold } => {
+            msg!("Instruction: CreateMultisig");
+            process_create_multisig(program_id, accounts, multisig_id, signers, weights, threshold)
+        }
+        ProgramInstruction::CreateProposal { instruction_data, accounts: proposal_accounts } => {
+            msg!("Instruction: CreateProposal");
+            process_create_proposal(program_id, accounts, instruction_data, proposal_accounts)
+        }
+        ProgramInstruction::ApproveProposal => {
+            msg!("Instruction: ApproveProposal");
+            process_approve_proposal(program_id, accounts)
+        }
+        ProgramInstruction::ExecuteProposal => {
+            msg!("Instruction: ExecuteProposal");
+            process_execute_proposal(program_id, accounts)
+        }
+        ProgramInstruction::ConfigureTreasury { governance, epoch_budget, allowed_programs } => {
+            msg!("Instruction: ConfigureTreasury");
+            process_configure_treasury(program_id, accounts, governance, epoch_budget, allowed_programs)
+        }
+        ProgramInstruction::FundTreasury { amount } => {
+            msg!("Instruction: FundTreasury");
+            process_fund_treasury(program_id, accounts, amount)
+        }
+        ProgramInstruction::TreasuryTransfer { amount } => {
+            msg!("Instruction: TreasuryTransfer");
+            process_treasury_transfer(program_id, accounts, amount)
+        }
+        ProgramInstruction::TreasuryInvest { amount, instruction_data } => {
+            msg!("Instruction: TreasuryInvest");
+            process_treasury_invest(program_id, accounts, amount, instruction_data)
+        }
+        ProgramInstruction::LockTokens { lock_id, amount, unlock_at } => {
+            msg!("Instruction: LockTokens");
+            process_lock_tokens(program_id, accounts, lock_id, amount, unlock_at)
+        }
+        ProgramInstruction::UnlockTokens => {
+            msg!("Instruction: UnlockTokens");
+            process_unlock_tokens(program_id, accounts)
+        }
+        ProgramInstruction::ConfigureCompliance { authority, attestor, attestation_threshold, clawback_authority } => {
+            msg!("Instruction: ConfigureCompliance");
+            process_configure_compliance(program_id, accounts, authority, attestor, attestation_threshold, clawback_authority)
+        }
+        ProgramInstruction::SetAccountLimits { max_per_transfer, max_per_day } => {
+            msg!("Instruction: SetAccountLimits");
+            process_set_account_limits(program_id, accounts, max_per_transfer, max_per_day)
+        }
+        ProgramInstruction::IssueAttestation { user, expires_at } => {
+            msg!("Instruction: IssueAttestation");
+            process_issue_attestation(program_id, accounts, user, expires_at)
+        }
+        ProgramInstruction::CreateSoulboundAccount { initial_balance, pow_nonce } => {
+            msg!("Instruction: CreateSoulboundAccount");
+            process_create_user_account(program_id, accounts, initial_balance, pow_nonce, user_flags::SOULBOUND)
+        }
+        ProgramInstruction::SetDelegation { delegate, scope, amount_cap, expires_at } => {
+            msg!("Instruction: SetDelegation");
+            process_set_delegation(program_id, accounts, delegate, scope, amount_cap, expires_at)
+        }
+        ProgramInstruction::RevokeDelegation { delegate } => {
+            msg!("Instruction: RevokeDelegation");
+            process_revoke_delegation(program_id, accounts, delegate)
+        }
+        ProgramInstruction::SetAccountFlagged { flagged, reason_code } => {
+            msg!("Instruction: SetAccountFlagged");
+            process_set_account_flagged(program_id, accounts, flagged, reason_code)
+        }
+        ProgramInstruction::Clawback { amount, reason_code } => {
+            msg!("Instruction: Clawback");
+            process_clawback(program_id, accounts, amount, reason_code)
+        }
+        ProgramInstruction::SweepDust { threshold } => {
+            msg!("Instruction: SweepDust");
+            process_sweep_dust(program_id, accounts, threshold)
+        }
+        ProgramInstruction::SetInterestRate { rate_bps } => {
+            msg!("Instruction: SetInterestRate");
+            process_set_interest_rate(program_id, accounts, rate_bps)
+        }
+        ProgramInstruction::AccrueInterest => {
+            msg!("Instruction: AccrueInterest");
+            process_accrue_interest(program_id, accounts)
+        }
+        ProgramInstruction::SetInterestBearing { enabled } => {
+            msg!("Instruction: SetInterestBearing");
+            process_set_interest_bearing(program_id, accounts, enabled)
+        }
+        ProgramInstruction::ConfigureRateState { updater } => {
+            msg!("Instruction: ConfigureRateState");
+            process_configure_rate_state(program_id, accounts, updater)
+        }
+        ProgramInstruction::UpdateRate { rate } => {
+            msg!("Instruction: UpdateRate");
+            process_update_rate(program_id, accounts, rate)
+        }
+        ProgramInstruction::ConfigureLending {
+            max_ltv_bps,
+            liquidation_threshold_bps,
+            close_factor_bps,
+            liquidation_bonus_bps,
+            max_rate_age,
+        } => {
+            msg!("Instruction: ConfigureLending");
+            process_configure_lending(
+                program_id,
+                accounts,
+                lending::LendingConfig {
+                    max_ltv_bps,
+                    liquidation_threshold_bps,
+                    close_factor_bps,
+                    liquidation_bonus_bps,
+                    max_rate_age,
+                },
+            )
+        }
+        ProgramInstruction::DepositCollateral { amount } => {
+            msg!("Instruction: DepositCollateral");
+            process_deposit_collateral(program_id, accounts, amount)
+        }
+        ProgramInstruction::WithdrawCollateral { amount } => {
+            msg!("Instruction: WithdrawCollateral");
+            process_withdraw_collateral(program_id, accounts, amount)
+        }
+        ProgramInstruction::Borrow { amount } => {
+            msg!("Instruction: Borrow");
+            process_borrow(program_id, accounts, amount)
+        }
+        ProgramInstruction::Repay { amount } => {
+            msg!("Instruction: Repay");
+            process_repay(program_id, accounts, amount)
+        }
+        ProgramInstruction::Liquidate { repay_amount } => {
+            msg!("Instruction: Liquidate");
+            process_liquidate(program_id, accounts, repay_amount)
+        }
+        ProgramInstruction::ConfigureInsuranceFund { authority, fee_share_bps } => {
+            msg!("Instruction: ConfigureInsuranceFund");
+            process_configure_insurance_fund(program_id, accounts, authority, fee_share_bps)
+        }
+        ProgramInstruction::InsurancePayout { amount } => {
+            msg!("Instruction: InsurancePayout");
+            process_insurance_payout(program_id, accounts, amount)
+        }
+        ProgramInstruction::ConfigureFeeSchedule { stakers_bps, insurance_bps, treasury_bps } => {
+            msg!("Instruction: ConfigureFeeSchedule");
+            process_configure_fee_schedule(program_id, accounts, stakers_bps, insurance_bps, treasury_bps)
+        }
+        ProgramInstruction::DistributeEpochFees => {
+            msg!("Instruction: DistributeEpochFees");
+            process_distribute_epoch_fees(program_id, accounts)
+        }
+        ProgramInstruction::ConfigureReclaimPolicy { inactivity_slots, grace_slots, cranker_share_bps } => {
+            msg!("Instruction: ConfigureReclaimPolicy");
+            process_configure_reclaim_policy(program_id, accounts, inactivity_slots, grace_slots, cranker_share_bps)
+        }
+        ProgramInstruction::FlagDormantAccount => {
+            msg!("Instruction: FlagDormantAccount");
+            process_flag_dormant_account(program_id, accounts)
+        }
+        ProgramInstruction::ReclaimDormantAccount => {
+            msg!("Instruction: ReclaimDormantAccount");
+            process_reclaim_dormant_account(program_id, accounts)
+        }
+        ProgramInstruction::Batch { ops } => {
+            msg!("Instruction: Batch");
+            process_batch(program_id, accounts, ops)
+        }
+        ProgramInstruction::BeginUpload => {
+            msg!("Instruction: BeginUpload");
+            process_begin_upload(program_id, accounts)
+        }
+        ProgramInstruction::WriteChunk { offset, bytes } => {
+            msg!("Instruction: WriteChunk");
+            process_write_chunk(program_id, accounts, offset, bytes)
+        }
+        ProgramInstruction::FinalizeUpload { expected_hash } => {
+            msg!("Instruction: FinalizeUpload");
+            process_finalize_upload(program_id, accounts, expected_hash)
+        }
+        ProgramInstruction::InitCompressedTree { max_depth, max_buffer_size } => {
+            msg!("Instruction: InitCompressedTree");
+            process_init_compressed_tree(program_id, accounts, max_depth, max_buffer_size)
+        }
+        ProgramInstruction::CreateCompressedUserAccount { pow_nonce } => {
+            msg!("Instruction: CreateCompressedUserAccount");
+            process_create_compressed_user_account(program_id, accounts, pow_nonce)
+        }
+        ProgramInstruction::DecompressUserRecord { root, record, leaf_index } => {
+            msg!("Instruction: DecompressUserRecord");
+            process_decompress_user_record(program_id, accounts, root, record, leaf_index)
+        }
+        ProgramInstruction::CompressUserAccount => {
+            msg!("Instruction: CompressUserAccount");
+            process_compress_user_account(program_id, accounts)
+        }
+        ProgramInstruction::CloseUserAccount => {
+            msg!("Instruction: CloseUserAccount");
+            process_close_user_account(program_id, accounts)
+        }
+        ProgramInstruction::Approve { delegate, amount } => {
+            msg!("Instruction: Approve");
+            process_set_delegation(program_id, accounts, delegate, session_scope::TRANSFER, amount, i64::MAX)
+        }
+        ProgramInstruction::Revoke { delegate } => {
+            msg!("Instruction: Revoke");
+            process_revoke_delegation(program_id, accounts, delegate)
+        }
+        ProgramInstruction::TransferFrom { amount, fee_basis_points } => {
+            msg!("Instruction: TransferFrom");
+            process_transfer_from(program_id, accounts, amount, fee_basis_points)
+        }
+        ProgramInstruction::CreateVesting { beneficiary, total, start_ts, cliff, duration } => {
+            msg!("Instruction: CreateVesting");
+            process_create_vesting(program_id, accounts, beneficiary, total, start_ts, cliff, duration)
+        }
+        ProgramInstruction::ClaimVested => {
+            msg!("Instruction: ClaimVested");
+            process_claim_vested(program_id, accounts)
+        }
+        ProgramInstruction::CreateEscrow { escrow_id, counterparty, arbiter, expires_at } => {
+            msg!("Instruction: CreateEscrow");
+            process_create_escrow(program_id, accounts, escrow_id, counterparty, arbiter, expires_at)
+        }
+        ProgramInstruction::FundEscrow { amount } => {
+            msg!("Instruction: FundEscrow");
+            process_fund_escrow(program_id, accounts, amount)
+        }
+        ProgramInstruction::ReleaseEscrow => {
+            msg!("Instruction: ReleaseEscrow");
+            process_release_escrow(program_id, accounts)
+        }
+        ProgramInstruction::CancelEscrow => {
+            msg!("Instruction: CancelEscrow");
+            process_cancel_escrow(program_id, accounts)
+        }
+        ProgramInstruction::SetRewardRate { reward_rate } => {
+            msg!("Instruction: SetRewardRate");
+            process_set_reward_rate(program_id, accounts, reward_rate)
+        }
+        ProgramInstruction::Stake { amount } => {
+            msg!("Instruction: Stake");
+            process_stake(program_id, accounts, amount)
+        }
+        ProgramInstruction::Unstake { amount } => {
+            msg!("Instruction: Unstake");
+            process_unstake(program_id, accounts, amount)
+        }
+        ProgramInstruction::ClaimRewards => {
+            msg!("Instruction: ClaimRewards");
+            process_claim_rewards(program_id, accounts)
+        }
+        ProgramInstruction::ReallocUserAccount => {
+            msg!("Instruction: ReallocUserAccount");
+            process_realloc_user_account(program_id, accounts)
+        }
+        ProgramInstruction::FreezeAccount => {
+            msg!("Instruction: FreezeAccount");
+            process_set_account_frozen(program_id, accounts, true)
+        }
+        ProgramInstruction::ThawAccount => {
+            msg!("Instruction: ThawAccount");
+            process_set_account_frozen(program_id, accounts, false)
+        }
+        ProgramInstruction::SetWithdrawalDelay { delay } => {
+            msg!("Instruction: SetWithdrawalDelay");
+            process_set_withdrawal_delay(program_id, accounts, delay)
+        }
+        ProgramInstruction::RequestWithdrawal { amount } => {
+            msg!("Instruction: RequestWithdrawal");
+            process_request_withdrawal(program_id, accounts, amount)
+        }
+        ProgramInstruction::ExecuteWithdrawal => {
+            msg!("Instruction: ExecuteWithdrawal");
+            process_execute_withdrawal(program_id, accounts)
+        }
+        ProgramInstruction::CollectFees => {
+            msg!("Instruction: CollectFees");
+            process_collect_fees(program_id, accounts)
+        }
+        ProgramInstruction::AddToAllowlist { wallet } => {
+            msg!("Instruction: AddToAllowlist");
+            process_set_allowlisted(program_id, accounts, wallet, true)
+        }
+        ProgramInstruction::RemoveFromAllowlist { wallet } => {
+            msg!("Instruction: RemoveFromAllowlist");
+            process_set_allowlisted(program_id, accounts, wallet, false)
+        }
+        ProgramInstruction::MigrateAccount => {
+            msg!("Instruction: MigrateAccount");
+            process_migrate_account(program_id, accounts)
+        }
+        ProgramInstruction::CancelWithdrawal => {
+            msg!("Instruction: CancelWithdrawal");
+            process_cancel_withdrawal(program_id, accounts)
+        }
+        ProgramInstruction::Extension { id, payload } => {
+            msg!("Instruction: Extension {}", id);
+            extensions::dispatch(id, program_id, accounts, &payload)
         }
     }
 }
@@ -51,13 +506,17 @@ fn process_initialize(
     let account_info_iter = &mut accounts.iter();
     let program_state_info = next_account_info(account_info_iter)?;
     let payer_info = next_account_info(account_info_iter)?;
+    let program_config_info = next_account_info(account_info_iter)?;
+    let treasury_info = next_account_info(account_info_iter)?;
     
-    // Use math-utils to calculate token amount with default decimals
-    let token_amount = token_math::calculate_default_token_amount(initial_amount)
+    let program_config = ProgramConfig::default();
+    
+    // Use math-utils to calculate token amount with the configured decimals
+    let token_amount = token_math::calculate_token_amount(initial_amount, program_config.default_decimals)
         .map_err(|_| ProgramError::InvalidArgument)?;
     
     // Create program state using account-utils
-    let (expected_pda, _bump) = account_creation::create_program_state_pda(program_id)
+    let (expected_pda, state_bump) = account_creation::create_program_state_pda(program_id)
         .map_err(|_| ProgramError::InvalidSeeds)?;
     
     if program_state_info.key != &expected_pda {
@@ -72,15 +531,65 @@ fn process_initialize(
         authority: *payer_info.key,
         total_supply: token_amount,
         is_initialized: true,
+        paused: false,
+        emergency_guardian: Pubkey::default(),
+        pow_difficulty: 0,
+        interest_index: index_math::INDEX_SCALE,
+        interest_rate_bps: 0,
+        last_accrual_at: 0,
+        reward_rate: 0,
+        reward_per_share: 0,
+        total_staked: 0,
+        last_reward_at: 0,
+        withdrawal_delay: 0,
+        version: PROGRAM_STATE_VERSION,
     };
     
     // Serialize and save the program state
     let data = program_state.try_to_vec()
         .map_err(|_| ProgramError::BorshIoError("Failed to serialize program state".to_string()))?;
     
+    create_pda_account(program_id, payer_info, program_state_info, accounts, data.len(), &[PROGRAM_STATE_SEED, &[state_bump]])?;
     program_state_info.data.borrow_mut()[..data.len()].copy_from_slice(&data);
     
-    msg!("Program initialized with total supply: {}", token_amount);
+    let (expected_config, config_bump) = account_creation::create_program_config_pda(program_id)
+        .map_err(|_| ProgramError::InvalidSeeds)?;
+    
+    if program_config_info.key != &expected_config {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    
+    let config_len = account_data::calculate_account_size(&program_config)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    create_pda_account(program_id, payer_info, program_config_info, accounts, config_len, &[PROGRAM_CONFIG_SEED, &[config_bump]])?;
+    account_data::serialize_account_data(program_config_info, &program_config)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    // Fee transfers require the treasury, so it exists from the start; governance is set
+    // later through `ConfigureTreasury`
+    let (expected_treasury, treasury_bump) = account_creation::create_treasury_pda(program_id)
+        .map_err(|_| ProgramError::InvalidSeeds)?;
+    
+    if treasury_info.key != &expected_treasury {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    
+    let treasury = treasury::Treasury { bump: treasury_bump, ..Default::default() };
+    let treasury_len = account_data::calculate_account_size(&treasury)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    create_pda_account(program_id, payer_info, treasury_info, accounts, treasury_len, &[TREASURY_SEED, &[treasury_bump]])?;
+    account_data::serialize_account_data(treasury_info, &treasury)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    let event = InitializeEvent {
+        authority: *payer_info.key,
+        total_supply: token_amount,
+    };
+    let event_data = event.try_to_vec()
+        .map_err(|_| ProgramError::BorshIoError("Failed to serialize initialize event".to_string()))?;
+    sol_log_data(&[InitializeEvent::TAG, &event_data]);
+    
+    log_info!("Program initialized", total_supply = token_amount);
     Ok(())
 }
 
@@ -88,71 +597,130 @@ fn process_create_user_account(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
     initial_balance: u64,
+    pow_nonce: u64,
+    flags: u32,
 ) -> ProgramResult {
     let account_info_iter = &mut accounts.iter();
     let user_account_info = next_account_info(account_info_iter)?;
     let user_info = next_account_info(account_info_iter)?;
     let program_state_info = next_account_info(account_info_iter)?;
+    let metrics_info = next_account_info(account_info_iter)?;
+    let program_config_info = next_account_info(account_info_iter)?;
+    let registry_page_info = next_account_info(account_info_iter)?;
+    let remaining_accounts = account_info_iter.as_slice();
+    
+    let program_state = load_program_state(program_id, program_state_info)?;
+    require_not_paused(&program_state)?;
+    let program_config = load_program_config(program_id, program_config_info)?;
     
     // Use math-utils for safe arithmetic
-    let balance = safe_math::safe_mul(initial_balance, 1000)
+    let balance = safe_math::safe_mul(initial_balance, program_config.account_balance_multiplier)
         .map_err(|_| ProgramError::InvalidArgument)?;
     
     // Validate using account-utils
     account_validation::validate_signer(user_info)
         .map_err(|_| ProgramError::MissingRequiredSignature)?;
     
+    // Throttle sybil account farming when a difficulty is configured
+    account_validation::validate_account_creation_pow(
+        user_info.key,
+        pow_nonce,
+        program_state.pow_difficulty,
+    )
+    .map_err(|_| ProgramError::InvalidArgument)?;
+    
+    enforce_allowlist(program_id, &program_config, user_info.key, remaining_accounts)?;
+    
     // Create user PDA using account-utils
-    let (expected_pda, _bump) = account_creation::create_user_pda(user_info.key, program_id)
+    let (expected_pda, bump) = account_creation::create_user_pda(user_info.key, program_id)
         .map_err(|_| ProgramError::InvalidSeeds)?;
     
     if user_account_info.key != &expected_pda {
         return Err(ProgramError::InvalidSeeds);
     }
     
+    create_pda_account(program_id, user_info, user_account_info, accounts, USER_ACCOUNT_LEN, &[USER_SEED, user_info.key.as_ref(), &[bump]])?;
+    
+    // Accounts sized for a legacy layout are still accepted and keep only that layout's fields
     let user_account = UserAccount {
         owner: *user_info.key,
         balance,
         program_state: *program_state_info.key,
+        flags,
+        bump: if user_account_info.data_len() == USER_ACCOUNT_V1_LEN { 0 } else { bump },
+        nonce: 0,
+        last_activity_slot: 0,
+        dormant_since_slot: 0,
+        version: USER_ACCOUNT_VERSION,
     };
+    save_user_account(user_account_info, &user_account)?;
     
-    // Serialize and save the user account
-    let data = user_account.try_to_vec()
-        .map_err(|_| ProgramError::BorshIoError("Failed to serialize user account".to_string()))?;
+    // The first user brings the metrics PDA into being
+    if metrics_info.data_len() == 0 {
+        let (expected_metrics, metrics_bump) = account_creation::create_metrics_pda(program_id)
+            .map_err(|_| ProgramError::InvalidSeeds)?;
+        
+        if metrics_info.key != &expected_metrics {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        
+        let metrics_len = account_data::calculate_account_size(&Metrics::default())
+            .map_err(|_| ProgramError::InvalidArgument)?;
+        create_pda_account(program_id, user_info, metrics_info, accounts, metrics_len, &[METRICS_SEED, &[metrics_bump]])?;
+    }
     
-    user_account_info.data.borrow_mut()[..data.len()].copy_from_slice(&data);
+    let mut position = 0;
+    update_metrics(program_id, metrics_info, |metrics| {
+        position = metrics.unique_users;
+        metrics.unique_users = metrics.unique_users.saturating_add(1);
+    })?;
+    registry::append_user(program_id, user_info, registry_page_info, accounts, position, user_info.key)?;
     
-    msg!("User account created with balance: {}", balance);
+    log_debug!("User account created", balance = balance);
     Ok(())
 }
 
 fn process_transfer_with_fee(
-    _program_id: &Pubkey,
+    program_id: &Pubkey,
     accounts: &[AccountInfo],
     amount: u64,
     fee_basis_points: u16,
+    idempotency_key: Option<[u8; 16]>,
 ) -> ProgramResult {
     let account_info_iter = &mut accounts.iter();
     let from_account_info = next_account_info(account_info_iter)?;
     let to_account_info = next_account_info(account_info_iter)?;
     let owner_info = next_account_info(account_info_iter)?;
+    // Every later account, required (program state, metrics, program config, treasury) or
+    // optional (session key, idempotency log, ...), is located by address
+    let remaining_accounts = account_info_iter.as_slice();
     
     // Validate signer
     account_validation::validate_signer(owner_info)
         .map_err(|_| ProgramError::MissingRequiredSignature)?;
     
-    // Deserialize accounts using account-utils
-    let mut from_account = account_data::deserialize_account_data::<UserAccount>(from_account_info)
-        .map_err(|_| ProgramError::InvalidAccountData)?;
+    let (program_state_pda, _bump) = account_creation::create_program_state_pda(program_id)
+        .map_err(|_| ProgramError::InvalidSeeds)?;
+    let (metrics_pda, _bump) = account_creation::create_metrics_pda(program_id)
+        .map_err(|_| ProgramError::InvalidSeeds)?;
+    let program_state_info = find_required_account(remaining_accounts, &program_state_pda)?;
+    let metrics_info = find_required_account(remaining_accounts, &metrics_pda)?;
     
-    let mut to_account = account_data::deserialize_account_data::<UserAccount>(to_account_info)
-        .map_err(|_| ProgramError::InvalidAccountData)?;
+    let program_state = load_program_state(program_id, program_state_info)?;
+    require_not_paused(&program_state)?;
+    let program_config = find_program_config(program_id, remaining_accounts)?;
     
-    // Validate ownership
-    if from_account.owner != *owner_info.key {
+    if amount > program_config.max_transfer_amount
+        || fee_basis_points < program_config.min_fee_basis_points
+    {
         return Err(ProgramError::InvalidArgument);
     }
     
+    // Deserialize accounts using account-utils
+    let mut from_account = load_user_account(from_account_info)?;
+    
+    let mut to_account = load_user_account(to_account_info)?;
+    
     // Calculate fee using math-utils percentage module
     let fee = percentage::calculate_percentage(amount, fee_basis_points)
         .map_err(|_| ProgramError::InvalidArgument)?;
@@ -160,6 +728,80 @@ fn process_transfer_with_fee(
     let total_amount = safe_math::safe_add(amount, fee)
         .map_err(|_| ProgramError::InvalidArgument)?;
     
+    // Validate ownership, either directly or through the signer's session key
+    if from_account.owner != *owner_info.key {
+        let (expected_pda, _bump) = account_creation::create_session_key_pda(
+            &from_account.owner,
+            owner_info.key,
+            program_id,
+        )
+        .map_err(|_| ProgramError::InvalidSeeds)?;
+        let now = Clock::get()?.unix_timestamp;
+        
+        // A session key takes precedence; otherwise the signer needs a delegate entry
+        if let Some(session_key_info) = find_account(remaining_accounts, &expected_pda) {
+            let session_key = account_data::deserialize_account_data::<SessionKey>(session_key_info)
+                .map_err(|_| ProgramError::InvalidAccountData)?;
+            
+            validate_session_key(&session_key, owner_info.key, session_scope::TRANSFER, total_amount, now)
+                .map_err(|_| ProgramError::InvalidArgument)?;
+        } else {
+            delegation::spend_allowance(
+                program_id,
+                &from_account.owner,
+                owner_info.key,
+                remaining_accounts,
+                session_scope::TRANSFER,
+                total_amount,
+                now,
+            )?;
+        }
+    }
+    
+    // Reject retries of a payment that already went through
+    if let Some(idempotency_key) = idempotency_key {
+        let (expected_pda, bump) = account_creation::create_idempotency_log_pda(
+            &from_account.owner,
+            program_id,
+        )
+        .map_err(|_| ProgramError::InvalidSeeds)?;
+        
+        let log_info = find_account(remaining_accounts, &expected_pda)
+            .ok_or(ProgramError::InvalidArgument)?;
+        
+        // The sender's first keyed payment creates the log, paid for by the signer
+        if log_info.data_len() == 0 {
+            let log_len = account_data::calculate_account_size(&IdempotencyLog::default())
+                .map_err(|_| ProgramError::InvalidArgument)?;
+            create_pda_account(
+                program_id,
+                owner_info,
+                log_info,
+                remaining_accounts,
+                log_len,
+                &[IDEMPOTENCY_LOG_SEED, from_account.owner.as_ref(), &[bump]],
+            )?;
+        }
+        
+        let mut log = account_data::deserialize_account_data::<IdempotencyLog>(log_info)
+            .map_err(|_| ProgramError::InvalidAccountData)?;
+        
+        log.record(idempotency_key, Clock::get()?.unix_timestamp)
+            .map_err(|_| ProgramError::InvalidArgument)?;
+        
+        account_data::serialize_account_data(log_info, &log)
+            .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    }
+    
+    require_transferable(&from_account)?;
+    require_not_frozen(&to_account)?;
+    
+    enforce_account_limits(program_id, from_account_info, &from_account, remaining_accounts, total_amount)?;
+    enforce_attestation(program_id, &program_config, &from_account.owner, remaining_accounts, amount)?;
+    
+    let touched = [from_account_info, to_account_info];
+    let snapshot = invariants::Snapshot::capture(&touched, program_state.total_supply)?;
+    
     // Validate sufficient balance
     if from_account.balance < total_amount {
         return Err(ProgramError::InsufficientFunds);
@@ -173,49 +815,9207 @@ fn process_transfer_with_fee(
         .map_err(|_| ProgramError::InvalidArgument)?;
     
     // Serialize and save the updated accounts
-    let from_data = from_account.try_to_vec()
-        .map_err(|_| ProgramError::BorshIoError("Failed to serialize from account".to_string()))?;
+    save_user_account(from_account_info, &from_account)?;
+    save_user_account(to_account_info, &to_account)?;
+    snapshot.assert_conserved(&touched, program_state.total_supply, fee)?;
     
-    let to_data = to_account.try_to_vec()
-        .map_err(|_| ProgramError::BorshIoError("Failed to serialize to account".to_string()))?;
+    update_metrics(program_id, metrics_info, |metrics| {
+        metrics.transfer_count = metrics.transfer_count.saturating_add(1);
+        metrics.total_volume = metrics.total_volume.saturating_add(amount);
+        metrics.fees_collected = metrics.fees_collected.saturating_add(fee);
+    })?;
+    let insured = insurance::contribute_fee_share(program_id, remaining_accounts, fee)?;
+    treasury::accrue_fees(program_id, remaining_accounts, fee - insured)?;
+    analytics::update_daily_stats(program_id, owner_info, remaining_accounts, |stats| {
+        stats.transfer_count = stats.transfer_count.saturating_add(1);
+        stats.volume = stats.volume.saturating_add(amount);
+        stats.fees = stats.fees.saturating_add(fee);
+        stats.mark_active(&from_account.owner);
+    })?;
     
-    from_account_info.data.borrow_mut()[..from_data.len()].copy_from_slice(&from_data);
-    to_account_info.data.borrow_mut()[..to_data.len()].copy_from_slice(&to_data);
+    emit_transfer_event(program_id, &program_config, remaining_accounts, from_account_info, to_account_info, amount, fee)?;
     
-    msg!("Transferred {} tokens with fee: {}", amount, fee);
+    log_debug!("Transferred", amount = amount, fee = fee);
     Ok(())
 }
 
-/// Program instruction enum
-#[derive(BorshSerialize, BorshDeserialize, Debug)]
-pub enum ProgramInstruction {
-    /// Initialize the program state
-    Initialize {
-        initial_amount: u64,
-    },
-    /// Create a user account
-    CreateUserAccount {
-        initial_balance: u64,
-    },
-    /// Transfer tokens between users with fee calculation
-    TransferWithFee {
-        amount: u64,
-        fee_basis_points: u16,
-    },
+fn process_transfer_up_to(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    max_amount: u64,
+    min_amount: u64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let from_account_info = next_account_info(account_info_iter)?;
+    let to_account_info = next_account_info(account_info_iter)?;
+    let owner_info = next_account_info(account_info_iter)?;
+    let program_state_info = next_account_info(account_info_iter)?;
+    let metrics_info = next_account_info(account_info_iter)?;
+    let program_config_info = next_account_info(account_info_iter)?;
+    // The sender's limits account, if it has limits
+    let remaining_accounts = account_info_iter.as_slice();
+    
+    account_validation::validate_signer(owner_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    if min_amount > max_amount {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let program_state = load_program_state(program_id, program_state_info)?;
+    require_not_paused(&program_state)?;
+    let program_config = load_program_config(program_id, program_config_info)?;
+    
+    let mut from_account = load_user_account(from_account_info)?;
+    
+    let mut to_account = load_user_account(to_account_info)?;
+    
+    if from_account.owner != *owner_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    require_transferable(&from_account)?;
+    require_not_frozen(&to_account)?;
+    
+    // Fill as much as the balance covers once the default fee is included
+    let fee_basis_points = program_config.default_fee_basis_points;
+    let affordable = percentage::max_amount_with_fee(from_account.balance, fee_basis_points)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    let mut amount = max_amount.min(affordable).min(program_config.max_transfer_amount);
+    
+    // Fill only what the sender's limits still allow, fee included
+    if let Some((_limits_info, limits)) = load_account_limits(program_id, from_account_info, &from_account, remaining_accounts)? {
+        let available = limits.available(Clock::get()?.slot)
+            .map_err(|_| ProgramError::InvalidArgument)?;
+        amount = amount.min(
+            percentage::max_amount_with_fee(available, fee_basis_points)
+                .map_err(|_| ProgramError::InvalidArgument)?,
+        );
+    }
+    
+    if amount < min_amount {
+        return Err(ProgramError::InsufficientFunds);
+    }
+    
+    enforce_attestation(program_id, &program_config, &from_account.owner, remaining_accounts, amount)?;
+    
+    let fee = percentage::calculate_percentage(amount, fee_basis_points)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    
+    let total_amount = safe_math::safe_add(amount, fee)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    
+    enforce_account_limits(program_id, from_account_info, &from_account, remaining_accounts, total_amount)?;
+    
+    from_account.balance = safe_math::safe_sub(from_account.balance, total_amount)
+        .map_err(|_| ProgramError::InsufficientFunds)?;
+    
+    to_account.balance = safe_math::safe_add(to_account.balance, amount)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    
+    save_user_account(from_account_info, &from_account)?;
+    save_user_account(to_account_info, &to_account)?;
+    
+    update_metrics(program_id, metrics_info, |metrics| {
+        metrics.transfer_count = metrics.transfer_count.saturating_add(1);
+        metrics.total_volume = metrics.total_volume.saturating_add(amount);
+        metrics.fees_collected = metrics.fees_collected.saturating_add(fee);
+    })?;
+    let insured = insurance::contribute_fee_share(program_id, remaining_accounts, fee)?;
+    treasury::accrue_fees(program_id, remaining_accounts, fee - insured)?;
+    analytics::update_daily_stats(program_id, owner_info, remaining_accounts, |stats| {
+        stats.transfer_count = stats.transfer_count.saturating_add(1);
+        stats.volume = stats.volume.saturating_add(amount);
+        stats.fees = stats.fees.saturating_add(fee);
+        stats.mark_active(&from_account.owner);
+    })?;
+    
+    let event = TransferFilledEvent {
+        from: *from_account_info.key,
+        to: *to_account_info.key,
+        max_amount,
+        filled_amount: amount,
+        fee,
+    };
+    let event_data = event.try_to_vec()
+        .map_err(|_| ProgramError::BorshIoError("Failed to serialize fill event".to_string()))?;
+    
+    events::emit(program_id, Some(&program_config), remaining_accounts, TransferFilledEvent::TAG, &event_data)?;
+    set_return_data(&amount.to_le_bytes());
+    
+    log_debug!("Filled transfer", amount = amount, max_amount = max_amount, fee = fee);
+    Ok(())
 }
 
-/// Program state account
-#[derive(BorshSerialize, BorshDeserialize, Debug)]
-pub struct ProgramState {
-    pub authority: Pubkey,
-    pub total_supply: u64,
-    pub is_initialized: bool,
+fn process_schedule_transfer(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    schedule_id: u64,
+    execute_after: i64,
+    amount: u64,
+    recipient: Pubkey,
+    bounty: u64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let scheduled_transfer_info = next_account_info(account_info_iter)?;
+    let from_account_info = next_account_info(account_info_iter)?;
+    let owner_info = next_account_info(account_info_iter)?;
+    let program_state_info = next_account_info(account_info_iter)?;
+    // The program config, then the sender's limits and attestation accounts
+    let remaining_accounts = account_info_iter.as_slice();
+    
+    account_validation::validate_signer(owner_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let program_state = load_program_state(program_id, program_state_info)?;
+    require_not_paused(&program_state)?;
+    
+    if amount == 0 || execute_after <= Clock::get()?.unix_timestamp {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let (expected_pda, bump) = account_creation::create_scheduled_transfer_pda(
+        owner_info.key,
+        schedule_id,
+        program_id,
+    )
+    .map_err(|_| ProgramError::InvalidSeeds)?;
+    
+    if scheduled_transfer_info.key != &expected_pda {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    
+    // A pending transfer still holds its escrowed amount and bounty
+    if existing_record::<ScheduledTransfer>(program_id, scheduled_transfer_info)
+        .is_some_and(|pending| pending.amount != 0 && !pending.executed)
+    {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    
+    let mut from_account = load_user_account(from_account_info)?;
+    
+    if from_account.owner != *owner_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    require_transferable(&from_account)?;
+    
+    // Escrow the amount and the cranker's bounty until execution
+    let escrowed = safe_math::safe_add(amount, bounty)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    enforce_account_limits(program_id, from_account_info, &from_account, remaining_accounts, escrowed)?;
+    let program_config = find_program_config(program_id, remaining_accounts)?;
+    enforce_attestation(program_id, &program_config, owner_info.key, remaining_accounts, escrowed)?;
+    
+    from_account.balance = safe_math::safe_sub(from_account.balance, escrowed)
+        .map_err(|_| ProgramError::InsufficientFunds)?;
+    
+    let scheduled_transfer = ScheduledTransfer {
+        owner: *owner_info.key,
+        recipient,
+        amount,
+        bounty,
+        execute_after,
+        executed: false,
+    };
+    
+    if scheduled_transfer_info.data_len() == 0 {
+        let len = account_data::calculate_account_size(&scheduled_transfer)
+            .map_err(|_| ProgramError::InvalidArgument)?;
+        create_pda_account(
+            program_id,
+            owner_info,
+            scheduled_transfer_info,
+            accounts,
+            len,
+            &[SCHEDULED_TRANSFER_SEED, owner_info.key.as_ref(), &schedule_id.to_le_bytes(), &[bump]],
+        )?;
+    }
+    account_data::serialize_account_data(scheduled_transfer_info, &scheduled_transfer)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    save_user_account(from_account_info, &from_account)?;
+    
+    log_debug!("Transfer scheduled", amount = amount, execute_after = execute_after);
+    Ok(())
 }
 
-/// User account structure
-#[derive(BorshSerialize, BorshDeserialize, Debug)]
-pub struct UserAccount {
-    pub owner: Pubkey,
-    pub balance: u64,
-    pub program_state: Pubkey,
-} 
\ No newline at end of file
+fn process_execute_scheduled(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let scheduled_transfer_info = next_account_info(account_info_iter)?;
+    let recipient_account_info = next_account_info(account_info_iter)?;
+    let cranker_account_info = next_account_info(account_info_iter)?;
+    let cranker_info = next_account_info(account_info_iter)?;
+    let program_state_info = next_account_info(account_info_iter)?;
+    let cranker_registration_info = next_account_info(account_info_iter)?;
+    let bounty_pool_info = next_account_info(account_info_iter)?;
+    
+    // Any registered cranker may execute, but bounties go to the signer's own user account
+    account_validation::validate_signer(cranker_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let program_state = load_program_state(program_id, program_state_info)?;
+    require_not_paused(&program_state)?;
+    
+    let mut cranker = crank::Cranker::load(program_id, cranker_registration_info, cranker_info.key)?;
+    let mut bounty_pool = crank::BountyPool::load(program_id, bounty_pool_info)?;
+    
+    let mut scheduled_transfer = account_data::deserialize_account_data::<ScheduledTransfer>(scheduled_transfer_info)
+        .map_err(|_| ProgramError::InvalidAccountData)?;
+    
+    if scheduled_transfer_info.owner != program_id {
+        return Err(ProgramError::IllegalOwner);
+    }
+    
+    if scheduled_transfer.executed || recipient_account_info.key != &scheduled_transfer.recipient {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    crank::validate_task_ready(scheduled_transfer.execute_after, Clock::get()?.unix_timestamp)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    
+    let mut recipient_account = load_user_account(recipient_account_info)?;
+    require_not_frozen(&recipient_account)?;
+    
+    recipient_account.balance = safe_math::safe_add(recipient_account.balance, scheduled_transfer.amount)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    save_user_account(recipient_account_info, &recipient_account)?;
+    
+    // Re-read the cranker account so a cranker paying itself sees the credit above
+    let mut cranker_account = load_user_account(cranker_account_info)?;
+    
+    if cranker_account.owner != *cranker_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    require_not_frozen(&cranker_account)?;
+    
+    // The escrowed bounty is topped up by the pool's per-task reward, as far as the pool covers it
+    let pool_bounty = bounty_pool.pay(crank::CrankTask::ScheduledTransfer);
+    let bounty = safe_math::safe_add(scheduled_transfer.bounty, pool_bounty)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    
+    cranker_account.balance = safe_math::safe_add(cranker_account.balance, bounty)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    save_user_account(cranker_account_info, &cranker_account)?;
+    
+    cranker.record_task(bounty);
+    cranker.save(cranker_registration_info)?;
+    bounty_pool.save(bounty_pool_info)?;
+    
+    scheduled_transfer.executed = true;
+    account_data::serialize_account_data(scheduled_transfer_info, &scheduled_transfer)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    log_debug!("Scheduled transfer executed", amount = scheduled_transfer.amount, bounty = bounty);
+    Ok(())
+}
+
+fn process_register_cranker(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let cranker_registration_info = next_account_info(account_info_iter)?;
+    let cranker_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(cranker_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let (expected_pda, bump) = crank::Cranker::find_address(cranker_info.key, program_id)
+        .map_err(|_| ProgramError::InvalidSeeds)?;
+    
+    if cranker_registration_info.key != &expected_pda {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    
+    if cranker_registration_info.data_len() == 0 {
+        create_pda_account(
+            program_id,
+            cranker_info,
+            cranker_registration_info,
+            accounts,
+            crank::Cranker::SIZE,
+            &[CRANKER_SEED, cranker_info.key.as_ref(), &[bump]],
+        )?;
+    }
+    
+    let cranker = crank::Cranker {
+        authority: *cranker_info.key,
+        registered_at: Clock::get()?.unix_timestamp,
+        tasks_completed: 0,
+        bounties_earned: 0,
+    };
+    
+    cranker.save(cranker_registration_info)?;
+    
+    log_info!("Cranker registered", cranker = cranker_info.key);
+    Ok(())
+}
+
+fn process_fund_bounty_pool(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: u64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let bounty_pool_info = next_account_info(account_info_iter)?;
+    let from_account_info = next_account_info(account_info_iter)?;
+    let owner_info = next_account_info(account_info_iter)?;
+    // The program config, then the funder's limits and attestation accounts
+    let remaining_accounts = account_info_iter.as_slice();
+    
+    account_validation::validate_signer(owner_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    create_bounty_pool_if_missing(program_id, owner_info, bounty_pool_info, accounts)?;
+    let mut bounty_pool = crank::BountyPool::load(program_id, bounty_pool_info)?;
+    
+    let mut from_account = load_user_account(from_account_info)?;
+    
+    if from_account.owner != *owner_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    require_transferable(&from_account)?;
+    enforce_account_limits(program_id, from_account_info, &from_account, remaining_accounts, amount)?;
+    let program_config = find_program_config(program_id, remaining_accounts)?;
+    enforce_attestation(program_id, &program_config, owner_info.key, remaining_accounts, amount)?;
+    
+    from_account.balance = safe_math::safe_sub(from_account.balance, amount)
+        .map_err(|_| ProgramError::InsufficientFunds)?;
+    bounty_pool.balance = safe_math::safe_add(bounty_pool.balance, amount)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    
+    save_user_account(from_account_info, &from_account)?;
+    bounty_pool.save(bounty_pool_info)?;
+    
+    log_debug!("Bounty pool funded", amount = amount);
+    Ok(())
+}
+
+fn process_set_task_bounty(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    task: crank::CrankTask,
+    bounty: u64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let program_state_info = next_account_info(account_info_iter)?;
+    let bounty_pool_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(authority_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let program_state = load_program_state(program_id, program_state_info)?;
+    
+    if program_state.authority != *authority_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    create_bounty_pool_if_missing(program_id, authority_info, bounty_pool_info, accounts)?;
+    let mut bounty_pool = crank::BountyPool::load(program_id, bounty_pool_info)?;
+    bounty_pool.bounty_per_task[task as usize] = bounty;
+    
+    bounty_pool.save(bounty_pool_info)?;
+    
+    log_info!("Task bounty set", task = ?task, bounty = bounty);
+    Ok(())
+}
+
+/// Create the bounty pool PDA on its first use, funded by `payer_info`
+fn create_bounty_pool_if_missing<'a>(
+    program_id: &Pubkey,
+    payer_info: &AccountInfo<'a>,
+    bounty_pool_info: &AccountInfo<'a>,
+    accounts: &[AccountInfo<'a>],
+) -> ProgramResult {
+    if bounty_pool_info.data_len() > 0 {
+        return Ok(());
+    }
+    
+    let (expected_pda, bump) = crank::BountyPool::find_address(program_id)
+        .map_err(|_| ProgramError::InvalidSeeds)?;
+    
+    if bounty_pool_info.key != &expected_pda {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    
+    create_pda_account(program_id, payer_info, bounty_pool_info, accounts, crank::BountyPool::SIZE, &[BOUNTY_POOL_SEED, &[bump]])
+}
+
+fn process_create_subscription(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    subscription_id: u64,
+    merchant: Pubkey,
+    amount: u64,
+    interval: u64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let subscription_info = next_account_info(account_info_iter)?;
+    let payer_info = next_account_info(account_info_iter)?;
+    let program_state_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(payer_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let program_state = load_program_state(program_id, program_state_info)?;
+    require_not_paused(&program_state)?;
+    
+    if amount == 0 || interval == 0 || interval > i64::MAX as u64 {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let (expected_pda, bump) = account_creation::create_subscription_pda(payer_info.key, subscription_id, program_id)
+        .map_err(|_| ProgramError::InvalidSeeds)?;
+    
+    if subscription_info.key != &expected_pda {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    
+    // Re-creating an active subscription would erase the periods it still owes
+    if existing_record::<Subscription>(program_id, subscription_info).is_some_and(|subscription| subscription.active) {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    
+    // Payments are in arrears: the first one falls due after a full interval
+    let next_due = Clock::get()?.unix_timestamp
+        .checked_add(interval as i64)
+        .ok_or(ProgramError::InvalidArgument)?;
+    
+    let subscription = Subscription {
+        payer: *payer_info.key,
+        merchant,
+        amount,
+        interval,
+        next_due,
+        active: true,
+    };
+    
+    if subscription_info.data_len() == 0 {
+        let len = account_data::calculate_account_size(&subscription)
+            .map_err(|_| ProgramError::InvalidArgument)?;
+        create_pda_account(
+            program_id,
+            payer_info,
+            subscription_info,
+            accounts,
+            len,
+            &[SUBSCRIPTION_SEED, payer_info.key.as_ref(), &subscription_id.to_le_bytes(), &[bump]],
+        )?;
+    }
+    account_data::serialize_account_data(subscription_info, &subscription)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    log_debug!("Subscription created", amount = amount, interval = interval);
+    Ok(())
+}
+
+fn process_cancel_subscription(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let subscription_info = next_account_info(account_info_iter)?;
+    let payer_account_info = next_account_info(account_info_iter)?;
+    let merchant_account_info = next_account_info(account_info_iter)?;
+    let payer_info = next_account_info(account_info_iter)?;
+    // The program config, then the payer's limits and attestation accounts
+    let remaining_accounts = account_info_iter.as_slice();
+    
+    account_validation::validate_signer(payer_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let mut subscription = load_subscription(program_id, subscription_info)?;
+    
+    if subscription.payer != *payer_info.key || merchant_account_info.key != &subscription.merchant {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    // Settle everything owed up to now, including the prorated current period
+    let now = Clock::get()?.unix_timestamp;
+    let (owed, periods) = subscription_amount_owed(&subscription, now)?;
+    
+    // Start of the period still running at `now`
+    let period_start = safe_math::safe_mul(periods, subscription.interval)
+        .ok()
+        .and_then(|settled| i64::try_from(settled).ok())
+        .and_then(|settled| subscription.next_due.checked_add(settled))
+        .and_then(|end| end.checked_sub(subscription.interval as i64))
+        .ok_or(ProgramError::InvalidArgument)?;
+    let partial = accrual::prorate(
+        subscription.amount,
+        now.saturating_sub(period_start).max(0) as u64,
+        subscription.interval,
+    )
+    .map_err(|_| ProgramError::InvalidArgument)?;
+    let total = safe_math::safe_add(owed, partial)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    
+    // Nothing owed is forgiven: a payer in arrears has to top up before cancelling
+    let payer_account = load_user_account(payer_account_info)?;
+    if payer_account.balance < total {
+        log_error!("Subscription is in arrears", owed = total, balance = payer_account.balance);
+        return Err(ProgramError::InsufficientFunds);
+    }
+    enforce_account_limits(program_id, payer_account_info, &payer_account, remaining_accounts, total)?;
+    let program_config = find_program_config(program_id, remaining_accounts)?;
+    enforce_attestation(program_id, &program_config, &subscription.payer, remaining_accounts, total)?;
+    settle_subscription(payer_account_info, merchant_account_info, &subscription, total)?;
+    
+    subscription.active = false;
+    account_data::serialize_account_data(subscription_info, &subscription)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    log_debug!("Subscription cancelled", final_payment = total);
+    Ok(())
+}
+
+fn process_collect_subscription(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let subscription_info = next_account_info(account_info_iter)?;
+    let payer_account_info = next_account_info(account_info_iter)?;
+    let merchant_account_info = next_account_info(account_info_iter)?;
+    let program_state_info = next_account_info(account_info_iter)?;
+    // The program config, then the payer's limits and attestation accounts
+    let remaining_accounts = account_info_iter.as_slice();
+    
+    // Permissionless: anyone may collect once a payment is due
+    let program_state = load_program_state(program_id, program_state_info)?;
+    require_not_paused(&program_state)?;
+    
+    let mut subscription = load_subscription(program_id, subscription_info)?;
+    
+    if merchant_account_info.key != &subscription.merchant {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let now = Clock::get()?.unix_timestamp;
+    let (owed, periods) = subscription_amount_owed(&subscription, now)?;
+    
+    if periods == 0 {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let payer_account = load_user_account(payer_account_info)?;
+    enforce_account_limits(program_id, payer_account_info, &payer_account, remaining_accounts, owed)?;
+    let program_config = find_program_config(program_id, remaining_accounts)?;
+    enforce_attestation(program_id, &program_config, &subscription.payer, remaining_accounts, owed)?;
+    settle_subscription(payer_account_info, merchant_account_info, &subscription, owed)?;
+    
+    let advance = safe_math::safe_mul(periods, subscription.interval)
+        .ok()
+        .and_then(|advance| i64::try_from(advance).ok())
+        .ok_or(ProgramError::InvalidArgument)?;
+    subscription.next_due = subscription.next_due
+        .checked_add(advance)
+        .ok_or(ProgramError::InvalidArgument)?;
+    
+    account_data::serialize_account_data(subscription_info, &subscription)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    log_debug!("Subscription collected", amount = owed, periods = periods);
+    Ok(())
+}
+
+/// Load an active subscription owned by this program
+fn load_subscription(
+    program_id: &Pubkey,
+    subscription_info: &AccountInfo,
+) -> Result<Subscription, ProgramError> {
+    if subscription_info.owner != program_id {
+        return Err(ProgramError::IllegalOwner);
+    }
+    
+    let subscription = account_data::deserialize_account_data::<Subscription>(subscription_info)
+        .map_err(|_| ProgramError::InvalidAccountData)?;
+    
+    if !subscription.active {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    Ok(subscription)
+}
+
+/// Amount owed for whole periods due by `now`, and the number of such periods
+fn subscription_amount_owed(
+    subscription: &Subscription,
+    now: i64,
+) -> Result<(u64, u64), ProgramError> {
+    if now < subscription.next_due {
+        return Ok((0, 0));
+    }
+    
+    // The period ending at `next_due` plus any further whole periods since
+    let periods = accrual::periods_elapsed(subscription.next_due, now, subscription.interval)
+        .and_then(|extra| safe_math::safe_add(extra, 1))
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    let owed = safe_math::safe_mul(subscription.amount, periods)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    
+    Ok((owed, periods))
+}
+
+/// Move `amount` from the subscription payer's user account to the merchant's
+fn settle_subscription(
+    payer_account_info: &AccountInfo,
+    merchant_account_info: &AccountInfo,
+    subscription: &Subscription,
+    amount: u64,
+) -> ProgramResult {
+    let mut payer_account = load_user_account(payer_account_info)?;
+    
+    if payer_account.owner != subscription.payer {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    require_transferable(&payer_account)?;
+    
+    payer_account.balance = safe_math::safe_sub(payer_account.balance, amount)
+        .map_err(|_| ProgramError::InsufficientFunds)?;
+    save_user_account(payer_account_info, &payer_account)?;
+    
+    let mut merchant_account = load_user_account(merchant_account_info)?;
+    require_not_frozen(&merchant_account)?;
+    
+    merchant_account.balance = safe_math::safe_add(merchant_account.balance, amount)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    save_user_account(merchant_account_info, &merchant_account)
+}
+
+fn process_create_stream(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    stream_id: u64,
+    recipient: Pubkey,
+    deposit: u64,
+    start_time: i64,
+    stop_time: i64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let stream_info = next_account_info(account_info_iter)?;
+    let sender_account_info = next_account_info(account_info_iter)?;
+    let sender_info = next_account_info(account_info_iter)?;
+    let program_state_info = next_account_info(account_info_iter)?;
+    // The program config, then the sender's limits and attestation accounts
+    let remaining_accounts = account_info_iter.as_slice();
+    
+    account_validation::validate_signer(sender_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let program_state = load_program_state(program_id, program_state_info)?;
+    require_not_paused(&program_state)?;
+    
+    if deposit == 0 || stop_time <= start_time {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let (expected_pda, bump) = account_creation::create_stream_pda(sender_info.key, stream_id, program_id)
+        .map_err(|_| ProgramError::InvalidSeeds)?;
+    
+    if stream_info.key != &expected_pda {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    
+    // Re-creating an active stream would take back what has already streamed
+    if existing_record::<Stream>(program_id, stream_info).is_some_and(|stream| stream.active) {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    
+    let flow_rate = accrual::flow_rate(deposit, (stop_time - start_time) as u64)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    
+    let mut sender_account = load_user_account(sender_account_info)?;
+    
+    if sender_account.owner != *sender_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    require_transferable(&sender_account)?;
+    enforce_account_limits(program_id, sender_account_info, &sender_account, remaining_accounts, deposit)?;
+    let program_config = find_program_config(program_id, remaining_accounts)?;
+    enforce_attestation(program_id, &program_config, sender_info.key, remaining_accounts, deposit)?;
+    
+    sender_account.balance = safe_math::safe_sub(sender_account.balance, deposit)
+        .map_err(|_| ProgramError::InsufficientFunds)?;
+    
+    let stream = Stream {
+        sender: *sender_info.key,
+        recipient,
+        deposit,
+        flow_rate,
+        start_time,
+        stop_time,
+        withdrawn: 0,
+        active: true,
+    };
+    
+    if stream_info.data_len() == 0 {
+        let len = account_data::calculate_account_size(&stream)
+            .map_err(|_| ProgramError::InvalidArgument)?;
+        create_pda_account(
+            program_id,
+            sender_info,
+            stream_info,
+            accounts,
+            len,
+            &[STREAM_SEED, sender_info.key.as_ref(), &stream_id.to_le_bytes(), &[bump]],
+        )?;
+    }
+    account_data::serialize_account_data(stream_info, &stream)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    save_user_account(sender_account_info, &sender_account)?;
+    
+    log_debug!("Stream created", deposit = deposit, start_time = start_time, stop_time = stop_time);
+    Ok(())
+}
+
+fn process_withdraw_from_stream(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let stream_info = next_account_info(account_info_iter)?;
+    let recipient_account_info = next_account_info(account_info_iter)?;
+    let recipient_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(recipient_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let mut stream = load_stream(program_id, stream_info)?;
+    
+    if recipient_account_info.key != &stream.recipient {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let mut recipient_account = load_user_account(recipient_account_info)?;
+    
+    if recipient_account.owner != *recipient_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    require_not_frozen(&recipient_account)?;
+    
+    let withdrawable = stream.withdrawable(Clock::get()?.unix_timestamp)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    
+    stream.withdrawn = safe_math::safe_add(stream.withdrawn, withdrawable)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    recipient_account.balance = safe_math::safe_add(recipient_account.balance, withdrawable)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    
+    account_data::serialize_account_data(stream_info, &stream)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    save_user_account(recipient_account_info, &recipient_account)?;
+    
+    log_debug!("Stream withdrawal", amount = withdrawable);
+    Ok(())
+}
+
+fn process_cancel_stream(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let stream_info = next_account_info(account_info_iter)?;
+    let sender_account_info = next_account_info(account_info_iter)?;
+    let recipient_account_info = next_account_info(account_info_iter)?;
+    let signer_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(signer_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let mut stream = load_stream(program_id, stream_info)?;
+    
+    if recipient_account_info.key != &stream.recipient {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let sender_account = load_user_account(sender_account_info)?;
+    
+    if sender_account.owner != stream.sender {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let mut recipient_account = load_user_account(recipient_account_info)?;
+    
+    // Either party may cancel
+    if *signer_info.key != stream.sender && *signer_info.key != recipient_account.owner {
+        return Err(ProgramError::InvalidArgument);
+    }
+    require_not_frozen(&recipient_account)?;
+    
+    // Fair split: the recipient keeps what has streamed, the sender gets the rest back
+    let now = Clock::get()?.unix_timestamp;
+    let recipient_share = stream.withdrawable(now)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    let streamed = safe_math::safe_add(stream.withdrawn, recipient_share)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    let sender_share = safe_math::safe_sub(stream.deposit, streamed)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    
+    recipient_account.balance = safe_math::safe_add(recipient_account.balance, recipient_share)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    save_user_account(recipient_account_info, &recipient_account)?;
+    
+    // Re-read in case the sender streams to their own account
+    let mut sender_account = if sender_account_info.key == recipient_account_info.key {
+        load_user_account(sender_account_info)?
+    } else {
+        sender_account
+    };
+    sender_account.balance = safe_math::safe_add(sender_account.balance, sender_share)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    save_user_account(sender_account_info, &sender_account)?;
+    
+    stream.withdrawn = streamed;
+    stream.active = false;
+    account_data::serialize_account_data(stream_info, &stream)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    log_debug!("Stream cancelled", recipient_share = recipient_share, refunded = sender_share);
+    Ok(())
+}
+
+/// Load an active stream owned by this program
+fn load_stream(
+    program_id: &Pubkey,
+    stream_info: &AccountInfo,
+) -> Result<Stream, ProgramError> {
+    if stream_info.owner != program_id {
+        return Err(ProgramError::IllegalOwner);
+    }
+    
+    let stream = account_data::deserialize_account_data::<Stream>(stream_info)
+        .map_err(|_| ProgramError::InvalidAccountData)?;
+    
+    if !stream.active {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    Ok(stream)
+}
+
+fn process_create_invoice(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    invoice_id: u64,
+    amount: u64,
+    memo_hash: [u8; 32],
+    expiry: i64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let invoice_info = next_account_info(account_info_iter)?;
+    let merchant_account_info = next_account_info(account_info_iter)?;
+    let merchant_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(merchant_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    if amount == 0 || expiry <= Clock::get()?.unix_timestamp {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let (expected_pda, bump) = account_creation::create_invoice_pda(merchant_info.key, invoice_id, program_id)
+        .map_err(|_| ProgramError::InvalidSeeds)?;
+    
+    if invoice_info.key != &expected_pda {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    
+    // Invoices are paid exactly once; re-creating one would reset `paid`
+    if existing_record::<Invoice>(program_id, invoice_info).is_some_and(|invoice| invoice.merchant != Pubkey::default()) {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    
+    // Payments are credited to the merchant's own user account
+    let merchant_account = load_user_account(merchant_account_info)?;
+    
+    if merchant_account.owner != *merchant_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let invoice = Invoice {
+        merchant: *merchant_info.key,
+        merchant_account: *merchant_account_info.key,
+        invoice_id,
+        amount,
+        memo_hash,
+        expiry,
+        paid: false,
+        payer: Pubkey::default(),
+        paid_at: 0,
+    };
+    
+    if invoice_info.data_len() == 0 {
+        let len = account_data::calculate_account_size(&invoice)
+            .map_err(|_| ProgramError::InvalidArgument)?;
+        create_pda_account(
+            program_id,
+            merchant_info,
+            invoice_info,
+            accounts,
+            len,
+            &[INVOICE_SEED, merchant_info.key.as_ref(), &invoice_id.to_le_bytes(), &[bump]],
+        )?;
+    }
+    account_data::serialize_account_data(invoice_info, &invoice)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    log_debug!("Invoice created", invoice_id = invoice_id, amount = amount);
+    Ok(())
+}
+
+fn process_pay_invoice(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: u64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let invoice_info = next_account_info(account_info_iter)?;
+    let payer_account_info = next_account_info(account_info_iter)?;
+    let merchant_account_info = next_account_info(account_info_iter)?;
+    let payer_info = next_account_info(account_info_iter)?;
+    let program_state_info = next_account_info(account_info_iter)?;
+    // The program config, the payer's limits and attestation accounts, and the noop
+    // program to emit the event by CPI
+    let remaining_accounts = account_info_iter.as_slice();
+    
+    account_validation::validate_signer(payer_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let program_state = load_program_state(program_id, program_state_info)?;
+    require_not_paused(&program_state)?;
+    
+    if invoice_info.owner != program_id {
+        return Err(ProgramError::IllegalOwner);
+    }
+    
+    let mut invoice = account_data::deserialize_account_data::<Invoice>(invoice_info)
+        .map_err(|_| ProgramError::InvalidAccountData)?;
+    
+    let now = Clock::get()?.unix_timestamp;
+    
+    // The payer states the amount they expect so a changed invoice can't overcharge
+    if invoice.paid || amount != invoice.amount || now > invoice.expiry {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    if merchant_account_info.key != &invoice.merchant_account {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let mut payer_account = load_user_account(payer_account_info)?;
+    
+    if payer_account.owner != *payer_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    require_transferable(&payer_account)?;
+    
+    enforce_account_limits(program_id, payer_account_info, &payer_account, remaining_accounts, amount)?;
+    let program_config = find_program_config(program_id, remaining_accounts)?;
+    enforce_attestation(program_id, &program_config, payer_info.key, remaining_accounts, amount)?;
+    
+    payer_account.balance = safe_math::safe_sub(payer_account.balance, amount)
+        .map_err(|_| ProgramError::InsufficientFunds)?;
+    save_user_account(payer_account_info, &payer_account)?;
+    
+    let mut merchant_account = load_user_account(merchant_account_info)?;
+    require_not_frozen(&merchant_account)?;
+    
+    merchant_account.balance = safe_math::safe_add(merchant_account.balance, amount)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    save_user_account(merchant_account_info, &merchant_account)?;
+    
+    invoice.paid = true;
+    invoice.payer = *payer_info.key;
+    invoice.paid_at = now;
+    account_data::serialize_account_data(invoice_info, &invoice)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    let event = InvoicePaidEvent {
+        invoice: *invoice_info.key,
+        merchant: invoice.merchant,
+        payer: invoice.payer,
+        amount,
+        memo_hash: invoice.memo_hash,
+        paid_at: now,
+    };
+    let event_data = event.try_to_vec()
+        .map_err(|_| ProgramError::BorshIoError("Failed to serialize invoice event".to_string()))?;
+    
+    events::emit(program_id, Some(&program_config), remaining_accounts, InvoicePaidEvent::TAG, &event_data)?;
+    
+    log_debug!("Invoice paid", invoice_id = invoice.invoice_id);
+    Ok(())
+}
+
+fn process_atomic_swap(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount_a: u64,
+    amount_b: u64,
+    fee_basis_points: u16,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let account_a_info = next_account_info(account_info_iter)?;
+    let account_b_info = next_account_info(account_info_iter)?;
+    let owner_a_info = next_account_info(account_info_iter)?;
+    let owner_b_info = next_account_info(account_info_iter)?;
+    let program_state_info = next_account_info(account_info_iter)?;
+    let metrics_info = next_account_info(account_info_iter)?;
+    let program_config_info = next_account_info(account_info_iter)?;
+    // Limits accounts of either side, if they have limits
+    let remaining_accounts = account_info_iter.as_slice();
+    
+    // Both parties must sign
+    account_validation::validate_signer(owner_a_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    account_validation::validate_signer(owner_b_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let program_state = load_program_state(program_id, program_state_info)?;
+    require_not_paused(&program_state)?;
+    let program_config = load_program_config(program_id, program_config_info)?;
+    
+    if account_a_info.key == account_b_info.key
+        || amount_a > program_config.max_transfer_amount
+        || amount_b > program_config.max_transfer_amount
+        || fee_basis_points < program_config.min_fee_basis_points
+    {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let mut account_a = load_user_account(account_a_info)?;
+    let mut account_b = load_user_account(account_b_info)?;
+    
+    if account_a.owner != *owner_a_info.key || account_b.owner != *owner_b_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    require_transferable(&account_a)?;
+    require_transferable(&account_b)?;
+    
+    // Each side pays the fee on the leg it sends
+    let fee_a = percentage::calculate_percentage(amount_a, fee_basis_points)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    let fee_b = percentage::calculate_percentage(amount_b, fee_basis_points)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    let combined_fee = safe_math::safe_add(fee_a, fee_b)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    let debit_a = safe_math::safe_add(amount_a, fee_a)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    let debit_b = safe_math::safe_add(amount_b, fee_b)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    
+    enforce_account_limits(program_id, account_a_info, &account_a, remaining_accounts, debit_a)?;
+    enforce_account_limits(program_id, account_b_info, &account_b, remaining_accounts, debit_b)?;
+    enforce_attestation(program_id, &program_config, &account_a.owner, remaining_accounts, amount_a)?;
+    enforce_attestation(program_id, &program_config, &account_b.owner, remaining_accounts, amount_b)?;
+    
+    let touched = [account_a_info, account_b_info];
+    let snapshot = invariants::Snapshot::capture(&touched, program_state.total_supply)?;
+    
+    // Compute both new balances before writing either, so the swap is all-or-nothing
+    account_a.balance = safe_math::safe_sub(account_a.balance, debit_a)
+        .and_then(|balance| safe_math::safe_add(balance, amount_b))
+        .map_err(|_| ProgramError::InsufficientFunds)?;
+    account_b.balance = safe_math::safe_sub(account_b.balance, debit_b)
+        .and_then(|balance| safe_math::safe_add(balance, amount_a))
+        .map_err(|_| ProgramError::InsufficientFunds)?;
+    
+    save_user_account(account_a_info, &account_a)?;
+    save_user_account(account_b_info, &account_b)?;
+    snapshot.assert_conserved(&touched, program_state.total_supply, combined_fee)?;
+    
+    update_metrics(program_id, metrics_info, |metrics| {
+        metrics.transfer_count = metrics.transfer_count.saturating_add(2);
+        metrics.total_volume = metrics.total_volume
+            .saturating_add(amount_a)
+            .saturating_add(amount_b);
+        metrics.fees_collected = metrics.fees_collected.saturating_add(combined_fee);
+    })?;
+    let insured = insurance::contribute_fee_share(program_id, remaining_accounts, combined_fee)?;
+    treasury::accrue_fees(program_id, remaining_accounts, combined_fee - insured)?;
+    analytics::update_daily_stats(program_id, owner_a_info, remaining_accounts, |stats| {
+        stats.transfer_count = stats.transfer_count.saturating_add(2);
+        stats.volume = stats.volume.saturating_add(amount_a).saturating_add(amount_b);
+        stats.fees = stats.fees.saturating_add(combined_fee);
+        stats.mark_active(owner_a_info.key);
+        stats.mark_active(owner_b_info.key);
+    })?;
+    
+    log_debug!("Swapped", amount_a = amount_a, amount_b = amount_b, fee = combined_fee);
+    Ok(())
+}
+
+fn process_create_session_key(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    delegate: Pubkey,
+    scope: u32,
+    per_operation_limit: u64,
+    expires_at: i64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let session_key_info = next_account_info(account_info_iter)?;
+    let owner_info = next_account_info(account_info_iter)?;
+    
+    // Only the owner may hand out a session key
+    account_validation::validate_signer(owner_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    if scope == 0 || scope & !session_scope::ALL != 0 {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    if expires_at <= Clock::get()?.unix_timestamp {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    // Create session key PDA using account-utils
+    let (expected_pda, bump) = account_creation::create_session_key_pda(owner_info.key, &delegate, program_id)
+        .map_err(|_| ProgramError::InvalidSeeds)?;
+    
+    if session_key_info.key != &expected_pda {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    
+    let session_key = SessionKey {
+        owner: *owner_info.key,
+        delegate,
+        scope,
+        per_operation_limit,
+        expires_at,
+    };
+    
+    if session_key_info.data_len() == 0 {
+        let len = account_data::calculate_account_size(&session_key)
+            .map_err(|_| ProgramError::InvalidArgument)?;
+        create_pda_account(
+            program_id,
+            owner_info,
+            session_key_info,
+            accounts,
+            len,
+            &[SESSION_KEY_SEED, owner_info.key.as_ref(), delegate.as_ref(), &[bump]],
+        )?;
+    }
+    account_data::serialize_account_data(session_key_info, &session_key)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    log_info!("Session key created", delegate = delegate);
+    Ok(())
+}
+
+fn process_rotate_authority(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    new_authority: Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let program_state_info = next_account_info(account_info_iter)?;
+    let history_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(authority_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let (expected_history, history_bump) = account_creation::create_authority_history_pda(program_id)
+        .map_err(|_| ProgramError::InvalidSeeds)?;
+    
+    if history_info.key != &expected_history {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    
+    let mut program_state = load_program_state(program_id, program_state_info)?;
+    
+    if program_state.authority != *authority_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    // The first rotation creates the history, which starts out empty
+    if history_info.data_len() == 0 {
+        let len = account_data::calculate_account_size(&AuthorityHistory::default())
+            .map_err(|_| ProgramError::InvalidArgument)?;
+        create_pda_account(program_id, authority_info, history_info, accounts, len, &[AUTHORITY_HISTORY_SEED, &[history_bump]])?;
+    }
+    
+    let mut history = account_data::deserialize_account_data::<AuthorityHistory>(history_info)
+        .map_err(|_| ProgramError::InvalidAccountData)?;
+    
+    if new_authority == Pubkey::default()
+        || new_authority == program_state.authority
+        || history.contains(&new_authority)
+    {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    history.record(AuthorityRotation {
+        previous_authority: program_state.authority,
+        new_authority,
+        rotated_at: Clock::get()?.unix_timestamp,
+    });
+    program_state.authority = new_authority;
+    
+    account_data::serialize_account_data(history_info, &history)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    account_data::serialize_account_data(program_state_info, &program_state)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    log_info!("Authority rotated", new_authority = new_authority);
+    Ok(())
+}
+
+fn process_set_emergency_guardian(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    guardian: Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let program_state_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(authority_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let mut program_state = load_program_state(program_id, program_state_info)?;
+    
+    if program_state.authority != *authority_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    // Passing the default pubkey removes the guardian
+    program_state.emergency_guardian = guardian;
+    
+    account_data::serialize_account_data(program_state_info, &program_state)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    log_info!("Emergency guardian set", guardian = guardian);
+    Ok(())
+}
+
+fn process_pause(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let program_state_info = next_account_info(account_info_iter)?;
+    let signer_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(signer_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let mut program_state = load_program_state(program_id, program_state_info)?;
+    
+    // Either the authority or the emergency guardian may halt the program
+    let is_guardian = program_state.emergency_guardian != Pubkey::default()
+        && program_state.emergency_guardian == *signer_info.key;
+    
+    if program_state.authority != *signer_info.key && !is_guardian {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    program_state.paused = true;
+    
+    account_data::serialize_account_data(program_state_info, &program_state)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    log_info!("Program paused", by = signer_info.key);
+    Ok(())
+}
+
+fn process_unpause(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let program_state_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(authority_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let mut program_state = load_program_state(program_id, program_state_info)?;
+    
+    // Recovery is reserved for the authority; the guardian can only pause
+    if program_state.authority != *authority_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    program_state.paused = false;
+    
+    account_data::serialize_account_data(program_state_info, &program_state)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    log_info!("Program unpaused");
+    Ok(())
+}
+
+fn process_set_pow_difficulty(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    difficulty: u8,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let program_state_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(authority_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let mut program_state = load_program_state(program_id, program_state_info)?;
+    
+    if program_state.authority != *authority_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    // Keep the search within reach of a u64 nonce
+    if difficulty > MAX_POW_DIFFICULTY {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    program_state.pow_difficulty = difficulty;
+    
+    account_data::serialize_account_data(program_state_info, &program_state)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    log_info!("Account creation difficulty set", difficulty = difficulty);
+    Ok(())
+}
+
+fn process_update_config(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    config: ProgramConfig,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let program_state_info = next_account_info(account_info_iter)?;
+    let program_config_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(authority_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let program_state = load_program_state(program_id, program_state_info)?;
+    
+    if program_state.authority != *authority_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    // Loading checks the PDA and that the account holds a config already
+    load_program_config(program_id, program_config_info)?;
+    
+    config.validate()
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    
+    account_data::serialize_account_data(program_config_info, &config)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    log_info!("Program config updated");
+    Ok(())
+}
+
+fn process_set_feature_flag(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    feature: u64,
+    enabled: bool,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let program_state_info = next_account_info(account_info_iter)?;
+    let program_config_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(authority_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let program_state = load_program_state(program_id, program_state_info)?;
+    
+    if program_state.authority != *authority_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    // Only bits named in common::feature_flags may be toggled
+    if feature == 0 || feature & !feature_flags::ALL != 0 {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let mut program_config = load_program_config(program_id, program_config_info)?;
+    
+    if enabled {
+        program_config.feature_flags |= feature;
+    } else {
+        program_config.feature_flags &= !feature;
+    }
+    
+    account_data::serialize_account_data(program_config_info, &program_config)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    log_info!("Feature flag set", feature = feature, enabled = enabled);
+    Ok(())
+}
+
+fn process_migrate_user_account_v2(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    realloc_user_account(program_id, accounts, &[USER_ACCOUNT_V1_LEN])
+}
+
+fn process_realloc_user_account(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    realloc_user_account(program_id, accounts, &[USER_ACCOUNT_V1_LEN, USER_ACCOUNT_V2_LEN, USER_ACCOUNT_V3_LEN])
+}
+
+/// Grow a user account stored in one of the `legacy_lens` layouts to `USER_ACCOUNT_LEN`,
+/// topping up its rent from the owner
+fn realloc_user_account(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    legacy_lens: &[usize],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let user_account_info = next_account_info(account_info_iter)?;
+    let owner_info = next_account_info(account_info_iter)?;
+    let system_program_info = next_account_info(account_info_iter)?;
+    
+    // The owner funds the rent top-up, so it must be a plain system account
+    if !owner_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let (expected_pda, bump) = account_creation::create_user_pda(owner_info.key, program_id)
+        .map_err(|_| ProgramError::InvalidSeeds)?;
+    
+    if user_account_info.key != &expected_pda {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    
+    if user_account_info.owner != program_id {
+        return Err(ProgramError::IllegalOwner);
+    }
+    
+    if !legacy_lens.contains(&user_account_info.data_len()) {
+        log_error!("User account is already migrated");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    
+    let user_account = load_user_account(user_account_info)?;
+    if user_account.owner != *owner_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let from_version = user_account.version;
+    migration::grow_account(user_account_info, owner_info, system_program_info, USER_ACCOUNT_LEN)?;
+    save_user_account(user_account_info, &UserAccount { bump, version: USER_ACCOUNT_VERSION, ..user_account })?;
+    
+    log_info!("User account reallocated", account = user_account_info.key, from_version = from_version);
+    Ok(())
+}
+
+fn process_create_multisig(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    multisig_id: u64,
+    signers: Vec<Pubkey>,
+    weights: Vec<u16>,
+    threshold: u16,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let multisig_info = next_account_info(account_info_iter)?;
+    let creator_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(creator_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let (expected_pda, bump) = account_creation::create_multisig_pda(creator_info.key, multisig_id, program_id)
+        .map_err(|_| ProgramError::InvalidSeeds)?;
+    
+    if multisig_info.key != &expected_pda {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    
+    if multisig_info.data_len() == 0 {
+        let len = account_data::calculate_account_size(&multisig::Multisig::default())
+            .map_err(|_| ProgramError::InvalidArgument)?;
+        create_pda_account(
+            program_id,
+            creator_info,
+            multisig_info,
+            accounts,
+            len,
+            &[MULTISIG_SEED, creator_info.key.as_ref(), &multisig_id.to_le_bytes(), &[bump]],
+        )?;
+    }
+    
+    let existing = account_data::deserialize_account_data::<multisig::Multisig>(multisig_info)
+        .map_err(|_| ProgramError::InvalidAccountData)?;
+    if existing.signer_count != 0 {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    
+    let multisig = multisig::Multisig::new(*creator_info.key, multisig_id, bump, &signers, &weights, threshold)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    account_data::serialize_account_data(multisig_info, &multisig)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    log_info!("Multisig created", signers = signers.len(), threshold = threshold);
+    Ok(())
+}
+
+fn process_create_proposal(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: Vec<u8>,
+    proposal_accounts: Vec<multisig::ProposalAccount>,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let proposal_info = next_account_info(account_info_iter)?;
+    let multisig_info = next_account_info(account_info_iter)?;
+    let proposer_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(proposer_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let mut multisig = multisig::load_multisig(program_id, multisig_info)?;
+    let signer_index = multisig.signer_index(proposer_info.key)
+        .ok_or(ProgramError::MissingRequiredSignature)?;
+    
+    // Only well-formed instructions can be proposed, and proposals cannot execute proposals
+    if matches!(ProgramInstruction::unpack(&instruction_data)?, ProgramInstruction::ExecuteProposal) {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    
+    let index = multisig.proposal_count;
+    let (expected_pda, bump) = account_creation::create_proposal_pda(multisig_info.key, index, program_id)
+        .map_err(|_| ProgramError::InvalidSeeds)?;
+    
+    if proposal_info.key != &expected_pda {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    
+    let mut proposal = multisig::Proposal {
+        multisig: *multisig_info.key,
+        index,
+        proposer: *proposer_info.key,
+        instruction_data,
+        accounts: proposal_accounts,
+        approvals: 0,
+        executed: false,
+    };
+    proposal.approve(signer_index);
+    if proposal_info.data_len() == 0 {
+        let len = account_data::calculate_account_size(&proposal)
+            .map_err(|_| ProgramError::InvalidArgument)?;
+        create_pda_account(
+            program_id,
+            proposer_info,
+            proposal_info,
+            accounts,
+            len,
+            &[PROPOSAL_SEED, multisig_info.key.as_ref(), &index.to_le_bytes(), &[bump]],
+        )?;
+    }
+    account_data::serialize_account_data(proposal_info, &proposal)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    multisig.proposal_count = multisig.proposal_count.saturating_add(1);
+    account_data::serialize_account_data(multisig_info, &multisig)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    log_info!("Proposal created", index = index);
+    Ok(())
+}
+
+fn process_approve_proposal(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let proposal_info = next_account_info(account_info_iter)?;
+    let multisig_info = next_account_info(account_info_iter)?;
+    let signer_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(signer_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let multisig = multisig::load_multisig(program_id, multisig_info)?;
+    let mut proposal = multisig::load_proposal(program_id, proposal_info, multisig_info.key)?;
+    let signer_index = multisig.signer_index(signer_info.key)
+        .ok_or(ProgramError::MissingRequiredSignature)?;
+    
+    if proposal.executed {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    
+    proposal.approve(signer_index);
+    account_data::serialize_account_data(proposal_info, &proposal)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    log_debug!("Proposal approved", index = proposal.index, weight = multisig.approved_weight(proposal.approvals));
+    Ok(())
+}
+
+fn process_execute_proposal(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let proposal_info = next_account_info(account_info_iter)?;
+    let multisig_info = next_account_info(account_info_iter)?;
+    let program_info = next_account_info(account_info_iter)?;
+    let inner_accounts = account_info_iter.as_slice();
+    
+    if program_info.key != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    
+    let multisig = multisig::load_multisig(program_id, multisig_info)?;
+    let mut proposal = multisig::load_proposal(program_id, proposal_info, multisig_info.key)?;
+    
+    if proposal.executed {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    
+    if !multisig.is_approved(proposal.approvals) {
+        log_error!("Proposal is not approved", index = proposal.index);
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let instruction = proposal.instruction(program_id);
+    for meta in &instruction.accounts {
+        if find_account(inner_accounts, &meta.pubkey).is_none() {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+    }
+    
+    // Mark executed before the self-CPI so the proposal cannot be replayed from within it
+    proposal.executed = true;
+    account_data::serialize_account_data(proposal_info, &proposal)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    let multisig_id = multisig.multisig_id.to_le_bytes();
+    let signer_seeds: &[&[u8]] = &[MULTISIG_SEED, multisig.creator.as_ref(), &multisig_id, &[multisig.bump]];
+    invoke_signed(&instruction, accounts, &[signer_seeds])?;
+    
+    log_info!("Proposal executed", index = proposal.index);
+    Ok(())
+}
+
+fn process_configure_treasury(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    governance: Pubkey,
+    epoch_budget: u64,
+    allowed_programs: Vec<Pubkey>,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let treasury_info = next_account_info(account_info_iter)?;
+    let program_state_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(authority_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let mut treasury = treasury::load_treasury(program_id, treasury_info)?;
+    
+    // Until governance is set, the program authority bootstraps the treasury
+    let expected_authority = if treasury.is_configured() {
+        treasury.governance
+    } else {
+        load_program_state(program_id, program_state_info)?.authority
+    };
+    if expected_authority != *authority_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    if governance == Pubkey::default() || allowed_programs.len() > treasury::MAX_ALLOWED_PROGRAMS {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    treasury.governance = governance;
+    treasury.epoch_budget = epoch_budget;
+    treasury.allowed_program_count = allowed_programs.len() as u8;
+    treasury.allowed_programs = [Pubkey::default(); treasury::MAX_ALLOWED_PROGRAMS];
+    treasury.allowed_programs[..allowed_programs.len()].copy_from_slice(&allowed_programs);
+    
+    account_data::serialize_account_data(treasury_info, &treasury)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    log_info!("Treasury configured", governance = governance, epoch_budget = epoch_budget);
+    Ok(())
+}
+
+fn process_fund_treasury(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: u64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let treasury_info = next_account_info(account_info_iter)?;
+    let from_account_info = next_account_info(account_info_iter)?;
+    let owner_info = next_account_info(account_info_iter)?;
+    // The program config, then the owner's limits and attestation accounts
+    let remaining_accounts = account_info_iter.as_slice();
+    
+    account_validation::validate_signer(owner_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let mut treasury = treasury::load_treasury(program_id, treasury_info)?;
+    
+    let mut from_account = load_user_account(from_account_info)?;
+    
+    if from_account.owner != *owner_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    require_transferable(&from_account)?;
+    enforce_account_limits(program_id, from_account_info, &from_account, remaining_accounts, amount)?;
+    let program_config = find_program_config(program_id, remaining_accounts)?;
+    enforce_attestation(program_id, &program_config, &from_account.owner, remaining_accounts, amount)?;
+    
+    from_account.balance = safe_math::safe_sub(from_account.balance, amount)
+        .map_err(|_| ProgramError::InsufficientFunds)?;
+    treasury.balance = safe_math::safe_add(treasury.balance, amount)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    
+    save_user_account(from_account_info, &from_account)?;
+    account_data::serialize_account_data(treasury_info, &treasury)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    log_debug!("Treasury funded", amount = amount);
+    Ok(())
+}
+
+fn process_treasury_transfer(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: u64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let treasury_info = next_account_info(account_info_iter)?;
+    let governance_info = next_account_info(account_info_iter)?;
+    let recipient_info = next_account_info(account_info_iter)?;
+    
+    let mut treasury = treasury::load_governed_treasury(program_id, treasury_info, governance_info)?;
+    
+    treasury.spend(amount, Clock::get()?.epoch)
+        .map_err(|err| {
+            log_error!("Treasury spend rejected", amount = amount, reason = ?err);
+            ProgramError::InsufficientFunds
+        })?;
+    
+    let mut recipient = load_user_account(recipient_info)?;
+    require_not_frozen(&recipient)?;
+    recipient.balance = safe_math::safe_add(recipient.balance, amount)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    
+    save_user_account(recipient_info, &recipient)?;
+    account_data::serialize_account_data(treasury_info, &treasury)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    log_info!("Treasury transfer", recipient = recipient_info.key, amount = amount);
+    Ok(())
+}
+
+fn process_treasury_invest(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: u64,
+    instruction_data: Vec<u8>,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let treasury_info = next_account_info(account_info_iter)?;
+    let governance_info = next_account_info(account_info_iter)?;
+    let target_program_info = next_account_info(account_info_iter)?;
+    let target_accounts = account_info_iter.as_slice();
+    
+    let mut treasury = treasury::load_governed_treasury(program_id, treasury_info, governance_info)?;
+    
+    if target_program_info.key == program_id || !treasury.is_allowed_program(target_program_info.key) {
+        log_error!("Investment target not allowlisted", program = target_program_info.key);
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    
+    treasury.spend(amount, Clock::get()?.epoch)
+        .map_err(|err| {
+            log_error!("Treasury spend rejected", amount = amount, reason = ?err);
+            ProgramError::InsufficientFunds
+        })?;
+    treasury.invested = treasury.invested.saturating_add(amount);
+    
+    account_data::serialize_account_data(treasury_info, &treasury)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    let instruction = Instruction {
+        program_id: *target_program_info.key,
+        accounts: target_accounts.iter()
+            .map(|account_info| AccountMeta {
+                pubkey: *account_info.key,
+                is_signer: account_info.is_signer || account_info.key == treasury_info.key,
+                is_writable: account_info.is_writable,
+            })
+            .collect(),
+        data: instruction_data,
+    };
+    invoke_signed(&instruction, accounts, &[&[TREASURY_SEED, &[treasury.bump]]])?;
+    
+    log_info!("Treasury invested", program = target_program_info.key, amount = amount);
+    Ok(())
+}
+
+fn process_lock_tokens(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    lock_id: u16,
+    amount: u64,
+    unlock_at: i64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let lock_info = next_account_info(account_info_iter)?;
+    let user_account_info = next_account_info(account_info_iter)?;
+    let owner_info = next_account_info(account_info_iter)?;
+    // The program config, then the owner's limits and attestation accounts
+    let remaining_accounts = account_info_iter.as_slice();
+    
+    account_validation::validate_signer(owner_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let now = Clock::get()?.unix_timestamp;
+    if amount == 0 || unlock_at <= now {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let (expected_pda, bump) = account_creation::create_lock_pda(owner_info.key, lock_id, program_id)
+        .map_err(|_| ProgramError::InvalidSeeds)?;
+    
+    if lock_info.key != &expected_pda {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    
+    if lock_info.data_len() == 0 {
+        let lock_seed = account_creation::lock_seed(owner_info.key, lock_id)
+            .map_err(|_| ProgramError::InvalidSeeds)?;
+        create_pda_account(
+            program_id,
+            owner_info,
+            lock_info,
+            accounts,
+            lockup::Lock::LEN,
+            &[LOCK_SEED, owner_info.key.as_ref(), &lock_seed, &[bump]],
+        )?;
+    }
+    
+    let existing = account_data::deserialize_account_data::<lockup::Lock>(lock_info)
+        .map_err(|_| ProgramError::InvalidAccountData)?;
+    if existing.amount != 0 {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    
+    let mut user_account = load_user_account(user_account_info)?;
+    
+    if user_account.owner != *owner_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    require_transferable(&user_account)?;
+    enforce_account_limits(program_id, user_account_info, &user_account, remaining_accounts, amount)?;
+    let program_config = find_program_config(program_id, remaining_accounts)?;
+    enforce_attestation(program_id, &program_config, &user_account.owner, remaining_accounts, amount)?;
+    
+    user_account.balance = safe_math::safe_sub(user_account.balance, amount)
+        .map_err(|_| ProgramError::InsufficientFunds)?;
+    
+    let lock = lockup::Lock {
+        owner: *owner_info.key,
+        lock_id,
+        amount,
+        locked_at: now,
+        unlock_at,
+    };
+    
+    account_data::serialize_account_data(lock_info, &lock)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    save_user_account(user_account_info, &user_account)?;
+    
+    log_debug!("Tokens locked", amount = amount, unlock_at = unlock_at);
+    Ok(())
+}
+
+fn process_unlock_tokens(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let lock_info = next_account_info(account_info_iter)?;
+    let user_account_info = next_account_info(account_info_iter)?;
+    let owner_info = next_account_info(account_info_iter)?;
+    let treasury_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(owner_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let mut lock = lockup::load_lock(program_id, lock_info, owner_info.key)?;
+    if lock.amount == 0 {
+        return Err(ProgramError::UninitializedAccount);
+    }
+    
+    let mut user_account = load_user_account(user_account_info)?;
+    
+    if user_account.owner != *owner_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    require_not_frozen(&user_account)?;
+    
+    let penalty = lock.early_withdrawal_penalty(Clock::get()?.unix_timestamp)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    let payout = lock.amount - penalty;
+    
+    user_account.balance = safe_math::safe_add(user_account.balance, payout)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    
+    if penalty > 0 {
+        let mut treasury = treasury::load_treasury(program_id, treasury_info)?;
+        treasury.balance = safe_math::safe_add(treasury.balance, penalty)
+            .map_err(|_| ProgramError::InvalidArgument)?;
+        account_data::serialize_account_data(treasury_info, &treasury)
+            .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    }
+    
+    lock.amount = 0;
+    account_data::serialize_account_data(lock_info, &lock)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    save_user_account(user_account_info, &user_account)?;
+    
+    log_debug!("Tokens unlocked", payout = payout, penalty = penalty);
+    Ok(())
+}
+
+fn process_configure_compliance(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    authority: Pubkey,
+    attestor: Pubkey,
+    attestation_threshold: u64,
+    clawback_authority: Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let compliance_config_info = next_account_info(account_info_iter)?;
+    let program_state_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(authority_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let program_state = load_program_state(program_id, program_state_info)?;
+    
+    if program_state.authority != *authority_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    if compliance_config_info.data_len() == 0 {
+        let (expected_pda, bump) = account_creation::create_compliance_config_pda(program_id)
+            .map_err(|_| ProgramError::InvalidSeeds)?;
+        
+        if compliance_config_info.key != &expected_pda {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        
+        let len = account_data::calculate_account_size(&compliance::ComplianceConfig::default())
+            .map_err(|_| ProgramError::InvalidArgument)?;
+        create_pda_account(program_id, authority_info, compliance_config_info, accounts, len, &[COMPLIANCE_CONFIG_SEED, &[bump]])?;
+    }
+    
+    let mut compliance_config = compliance::load_compliance_config(program_id, compliance_config_info)?;
+    compliance_config.authority = authority;
+    compliance_config.attestor = attestor;
+    compliance_config.attestation_threshold = attestation_threshold;
+    compliance_config.clawback_authority = clawback_authority;
+    
+    account_data::serialize_account_data(compliance_config_info, &compliance_config)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    log_info!(
+        "Compliance configured",
+        authority = authority,
+        attestor = attestor,
+        attestation_threshold = attestation_threshold,
+        clawback_authority = clawback_authority
+    );
+    Ok(())
+}
+
+fn process_set_account_limits(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    max_per_transfer: u64,
+    max_per_day: u64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let limits_info = next_account_info(account_info_iter)?;
+    let user_account_info = next_account_info(account_info_iter)?;
+    let signer_info = next_account_info(account_info_iter)?;
+    let compliance_config_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(signer_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let (expected_pda, bump) = account_creation::create_account_limits_pda(user_account_info.key, program_id)
+        .map_err(|_| ProgramError::InvalidSeeds)?;
+    
+    if limits_info.key != &expected_pda {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    
+    let compliance_config = compliance::load_compliance_config(program_id, compliance_config_info)?;
+    let mut user_account = load_user_account(user_account_info)?;
+    if limits_info.data_len() == 0 {
+        let len = account_data::calculate_account_size(&compliance::AccountLimits::default())
+            .map_err(|_| ProgramError::InvalidArgument)?;
+        create_pda_account(
+            program_id,
+            signer_info,
+            limits_info,
+            accounts,
+            len,
+            &[ACCOUNT_LIMITS_SEED, user_account_info.key.as_ref(), &[bump]],
+        )?;
+    }
+    let mut limits = account_data::deserialize_account_data::<compliance::AccountLimits>(limits_info)
+        .map_err(|_| ProgramError::InvalidAccountData)?;
+    
+    let by_compliance = compliance_config.is_authority(signer_info.key);
+    if !by_compliance && (user_account.owner != *signer_info.key || limits.compliance_locked) {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    limits.user_account = *user_account_info.key;
+    limits.max_per_transfer = max_per_transfer;
+    limits.max_per_day = max_per_day;
+    limits.compliance_locked = by_compliance;
+    
+    if limits.is_unlimited() {
+        user_account.flags &= !user_flags::LIMITED;
+    } else {
+        user_account.flags |= user_flags::LIMITED;
+    }
+    
+    account_data::serialize_account_data(limits_info, &limits)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    save_user_account(user_account_info, &user_account)?;
+    
+    log_info!("Account limits set", user_account = user_account_info.key, max_per_transfer = max_per_transfer, max_per_day = max_per_day);
+    Ok(())
+}
+
+fn process_issue_attestation(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    user: Pubkey,
+    expires_at: i64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let attestation_info = next_account_info(account_info_iter)?;
+    let attestor_info = next_account_info(account_info_iter)?;
+    let compliance_config_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(attestor_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let compliance_config = compliance::load_compliance_config(program_id, compliance_config_info)?;
+    
+    if compliance_config.attestor == Pubkey::default() || compliance_config.attestor != *attestor_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    account_validation::validate_attestation_account(attestation_info, &user, attestor_info.key, program_id)
+        .map_err(|_| ProgramError::InvalidSeeds)?;
+    
+    let attestation = compliance::Attestation {
+        user,
+        attestor: *attestor_info.key,
+        issued_at: Clock::get()?.unix_timestamp,
+        expires_at,
+    };
+    
+    if attestation_info.data_len() == 0 {
+        let (_pda, bump) = account_creation::create_attestation_pda(&user, attestor_info.key, program_id)
+            .map_err(|_| ProgramError::InvalidSeeds)?;
+        let len = account_data::calculate_account_size(&attestation)
+            .map_err(|_| ProgramError::InvalidArgument)?;
+        create_pda_account(
+            program_id,
+            attestor_info,
+            attestation_info,
+            accounts,
+            len,
+            &[ATTESTATION_SEED, user.as_ref(), attestor_info.key.as_ref(), &[bump]],
+        )?;
+    }
+    account_data::serialize_account_data(attestation_info, &attestation)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    log_info!("Attestation issued", user = user, expires_at = expires_at);
+    Ok(())
+}
+
+fn process_set_delegation(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    delegate: Pubkey,
+    scope: u32,
+    amount_cap: u64,
+    expires_at: i64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let delegations_info = next_account_info(account_info_iter)?;
+    let owner_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(owner_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    set_delegation(program_id, owner_info, delegations_info, accounts, delegate, scope, amount_cap, expires_at)
+}
+
+/// Add or replace `delegate`'s entry in the delegations PDA of `owner_info`, creating the
+/// PDA on its first entry; the caller checks the owner's signature
+#[allow(clippy::too_many_arguments)]
+fn set_delegation<'a>(
+    program_id: &Pubkey,
+    owner_info: &AccountInfo<'a>,
+    delegations_info: &AccountInfo<'a>,
+    accounts: &[AccountInfo<'a>],
+    delegate: Pubkey,
+    scope: u32,
+    amount_cap: u64,
+    expires_at: i64,
+) -> ProgramResult {
+    let owner = owner_info.key;
+    if scope == 0 || scope & !session_scope::ALL != 0 || delegate == *owner {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    if delegations_info.data_len() == 0 {
+        let (expected_pda, bump) = account_creation::create_delegations_pda(owner, program_id)
+            .map_err(|_| ProgramError::InvalidSeeds)?;
+        
+        if delegations_info.key != &expected_pda {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        
+        create_pda_account(
+            program_id,
+            owner_info,
+            delegations_info,
+            accounts,
+            delegation::DELEGATIONS_LEN,
+            &[DELEGATIONS_SEED, owner.as_ref(), &[bump]],
+        )?;
+    }
+    
+    let mut delegations = delegation::load_delegations(program_id, delegations_info, owner)?;
+    delegations.set(delegation::DelegateEntry { delegate, scope, remaining: amount_cap, expires_at })
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    delegation::save_delegations(delegations_info, &delegations)?;
+    
+    log_info!("Delegation set", delegate = delegate, scope = scope, amount_cap = amount_cap, expires_at = expires_at);
+    Ok(())
+}
+
+fn process_revoke_delegation(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    delegate: Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let delegations_info = next_account_info(account_info_iter)?;
+    let owner_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(owner_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let mut delegations = delegation::load_delegations(program_id, delegations_info, owner_info.key)?;
+    if !delegations.remove(&delegate) {
+        return Err(ProgramError::InvalidArgument);
+    }
+    delegation::save_delegations(delegations_info, &delegations)?;
+    
+    log_info!("Delegation revoked", delegate = delegate);
+    Ok(())
+}
+
+fn process_set_account_flagged(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    flagged: bool,
+    reason_code: u16,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let user_account_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+    let compliance_config_info = next_account_info(account_info_iter)?;
+    let audit_log_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(authority_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let compliance_config = compliance::load_compliance_config(program_id, compliance_config_info)?;
+    
+    if !compliance_config.is_authority(authority_info.key) {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let mut user_account = load_user_account(user_account_info)?;
+    if flagged {
+        user_account.flags |= user_flags::FLAGGED;
+    } else {
+        user_account.flags &= !user_flags::FLAGGED;
+    }
+    save_user_account(user_account_info, &user_account)?;
+    
+    let action = if flagged { compliance::AuditAction::Flag } else { compliance::AuditAction::Unflag };
+    compliance::record_audit(program_id, authority_info, audit_log_info, accounts, compliance::AuditEntry {
+        action,
+        authority: *authority_info.key,
+        target: *user_account_info.key,
+        amount: 0,
+        reason_code,
+        timestamp: Clock::get()?.unix_timestamp,
+    })?;
+    
+    log_info!("Account flag set", user_account = user_account_info.key, flagged = flagged, reason_code = reason_code);
+    Ok(())
+}
+
+fn process_clawback(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: u64,
+    reason_code: u16,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let user_account_info = next_account_info(account_info_iter)?;
+    let treasury_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+    let compliance_config_info = next_account_info(account_info_iter)?;
+    let audit_log_info = next_account_info(account_info_iter)?;
+    // Program config and noop program, to emit the event by CPI
+    let remaining_accounts = account_info_iter.as_slice();
+    
+    account_validation::validate_signer(authority_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let compliance_config = compliance::load_compliance_config(program_id, compliance_config_info)?;
+    
+    if compliance_config.clawback_authority == Pubkey::default()
+        || compliance_config.clawback_authority != *authority_info.key
+    {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    if amount == 0 {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let mut user_account = load_user_account(user_account_info)?;
+    
+    if user_account.flags & user_flags::FLAGGED == 0 {
+        log_error!("Clawback target is not flagged", user_account = user_account_info.key);
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let mut treasury = treasury::load_treasury(program_id, treasury_info)?;
+    
+    user_account.balance = safe_math::safe_sub(user_account.balance, amount)
+        .map_err(|_| ProgramError::InsufficientFunds)?;
+    treasury.balance = safe_math::safe_add(treasury.balance, amount)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    
+    save_user_account(user_account_info, &user_account)?;
+    account_data::serialize_account_data(treasury_info, &treasury)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    let now = Clock::get()?.unix_timestamp;
+    compliance::record_audit(program_id, authority_info, audit_log_info, accounts, compliance::AuditEntry {
+        action: compliance::AuditAction::Clawback,
+        authority: *authority_info.key,
+        target: *user_account_info.key,
+        amount,
+        reason_code,
+        timestamp: now,
+    })?;
+    
+    let event = compliance::ClawbackEvent {
+        user_account: *user_account_info.key,
+        owner: user_account.owner,
+        authority: *authority_info.key,
+        amount,
+        reason_code,
+        clawed_back_at: now,
+    };
+    let event_data = event.try_to_vec()
+        .map_err(|_| ProgramError::BorshIoError("Failed to serialize clawback event".to_string()))?;
+    
+    events::emit(program_id, None, remaining_accounts, compliance::ClawbackEvent::TAG, &event_data)?;
+    
+    log_info!("Clawback", user_account = user_account_info.key, amount = amount, reason_code = reason_code);
+    Ok(())
+}
+
+fn process_sweep_dust(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    threshold: u64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let user_account_info = next_account_info(account_info_iter)?;
+    let owner_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(owner_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let mut user_account = load_user_account(user_account_info)?;
+    
+    if user_account.owner != *owner_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    require_not_frozen(&user_account)?;
+    
+    let now = Clock::get()?.unix_timestamp;
+    let mut swept_balance = 0u64;
+    let mut closed = 0u32;
+    
+    for sub_account_info in account_info_iter {
+        if sub_account_info.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        
+        let Some(balance) = sweepable_balance(program_id, sub_account_info, owner_info.key, threshold, now)? else {
+            log_debug!("Sub-account skipped", account = sub_account_info.key);
+            continue;
+        };
+        
+        swept_balance = safe_math::safe_add(swept_balance, balance)
+            .map_err(|_| ProgramError::InvalidArgument)?;
+        close_program_account(sub_account_info, owner_info)?;
+        closed += 1;
+    }
+    
+    user_account.balance = safe_math::safe_add(user_account.balance, swept_balance)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    save_user_account(user_account_info, &user_account)?;
+    
+    log_debug!("Dust swept", swept_balance = swept_balance, closed = closed);
+    Ok(())
+}
+
+/// Balance of an owner's sub-account if `SweepDust` may close it: a matured lock holding
+/// less than `threshold`, or an expired session key
+fn sweepable_balance(
+    program_id: &Pubkey,
+    sub_account_info: &AccountInfo,
+    owner: &Pubkey,
+    threshold: u64,
+    now: i64,
+) -> Result<Option<u64>, ProgramError> {
+    if sub_account_info.data_len() == lockup::Lock::LEN {
+        let lock = lockup::load_lock(program_id, sub_account_info, owner)?;
+        let sweepable = lock.unlock_at <= now && lock.amount < threshold;
+        return Ok(sweepable.then_some(lock.amount));
+    }
+    
+    let session_key = account_data::deserialize_account_data::<SessionKey>(sub_account_info)
+        .map_err(|_| ProgramError::InvalidAccountData)?;
+    let (expected_pda, _bump) = account_creation::create_session_key_pda(
+        &session_key.owner,
+        &session_key.delegate,
+        program_id,
+    )
+    .map_err(|_| ProgramError::InvalidSeeds)?;
+    
+    if sub_account_info.key != &expected_pda {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    if session_key.owner != *owner {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    Ok((session_key.expires_at <= now).then_some(0))
+}
+
+/// Close a program-owned account, moving its lamports to `destination_info`
+fn close_program_account(account_info: &AccountInfo, destination_info: &AccountInfo) -> ProgramResult {
+    let lamports = account_info.lamports();
+    **destination_info.try_borrow_mut_lamports()? = destination_info.lamports()
+        .checked_add(lamports)
+        .ok_or(ProgramError::InvalidArgument)?;
+    **account_info.try_borrow_mut_lamports()? = 0;
+    account_info.try_borrow_mut_data()?.fill(0);
+    Ok(())
+}
+
+fn process_set_interest_rate(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    rate_bps: u16,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let program_state_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(authority_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let mut program_state = load_program_state(program_id, program_state_info)?;
+    
+    if program_state.authority != *authority_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    program_state.accrue_interest(Clock::get()?.unix_timestamp)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    program_state.interest_rate_bps = rate_bps;
+    
+    account_data::serialize_account_data(program_state_info, &program_state)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    log_info!("Interest rate set", rate_bps = rate_bps);
+    Ok(())
+}
+
+fn process_accrue_interest(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let program_state_info = next_account_info(account_info_iter)?;
+    
+    let mut program_state = load_program_state(program_id, program_state_info)?;
+    
+    program_state.accrue_interest(Clock::get()?.unix_timestamp)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    
+    account_data::serialize_account_data(program_state_info, &program_state)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    log_debug!("Interest accrued", interest_index = program_state.interest_index);
+    Ok(())
+}
+
+fn process_set_interest_bearing(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    enabled: bool,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let user_account_info = next_account_info(account_info_iter)?;
+    let owner_info = next_account_info(account_info_iter)?;
+    let program_state_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(owner_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let program_state = load_program_state(program_id, program_state_info)?;
+    let mut user_account = read_user_account(user_account_info)?;
+    
+    if user_account.owner != *owner_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let interest_bearing = user_account.flags & user_flags::INTEREST_BEARING != 0;
+    if interest_bearing == enabled {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let index = program_state.interest_index;
+    if enabled {
+        user_account.balance = index_math::to_scaled(user_account.balance, index)
+            .map_err(|_| ProgramError::InvalidArgument)?;
+        user_account.flags |= user_flags::INTEREST_BEARING;
+    } else {
+        user_account.balance = index_math::to_face(user_account.balance, index)
+            .map_err(|_| ProgramError::InvalidArgument)?;
+        user_account.flags &= !user_flags::INTEREST_BEARING;
+    }
+    save_user_account(user_account_info, &user_account)?;
+    
+    log_debug!("Interest-bearing mode set", enabled = enabled, balance = user_account.balance);
+    Ok(())
+}
+
+fn process_configure_rate_state(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    updater: Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let rate_state_info = next_account_info(account_info_iter)?;
+    let program_state_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(authority_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let program_state = load_program_state(program_id, program_state_info)?;
+    
+    if program_state.authority != *authority_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    if rate_state_info.data_len() == 0 {
+        let (expected_pda, bump) = account_creation::create_rate_state_pda(program_id)
+            .map_err(|_| ProgramError::InvalidSeeds)?;
+        
+        if rate_state_info.key != &expected_pda {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        
+        let len = account_data::calculate_account_size(&exchange_rate::RateState::default())
+            .map_err(|_| ProgramError::InvalidArgument)?;
+        create_pda_account(program_id, authority_info, rate_state_info, accounts, len, &[RATE_STATE_SEED, &[bump]])?;
+    }
+    
+    let mut rate_state = exchange_rate::load_rate_state(program_id, rate_state_info)?;
+    rate_state.updater = updater;
+    
+    account_data::serialize_account_data(rate_state_info, &rate_state)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    log_info!("Rate updater set", updater = updater);
+    Ok(())
+}
+
+fn process_update_rate(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    rate: u128,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let rate_state_info = next_account_info(account_info_iter)?;
+    let updater_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(updater_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let mut rate_state = exchange_rate::load_rate_state(program_id, rate_state_info)?;
+    
+    if rate_state.updater == Pubkey::default() || rate_state.updater != *updater_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    if rate == 0 {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    rate_state.rate = rate;
+    rate_state.last_update = Clock::get()?.unix_timestamp;
+    
+    account_data::serialize_account_data(rate_state_info, &rate_state)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    log_debug!("Rate updated", rate = rate);
+    Ok(())
+}
+
+fn process_configure_lending(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    config: lending::LendingConfig,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let lending_config_info = next_account_info(account_info_iter)?;
+    let program_state_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(authority_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let program_state = load_program_state(program_id, program_state_info)?;
+    
+    if program_state.authority != *authority_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    config.validate()?;
+    if lending_config_info.data_len() == 0 {
+        let (expected_pda, bump) = account_creation::create_lending_config_pda(program_id)
+            .map_err(|_| ProgramError::InvalidSeeds)?;
+        
+        if lending_config_info.key != &expected_pda {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        
+        let len = account_data::calculate_account_size(&lending::LendingConfig::default())
+            .map_err(|_| ProgramError::InvalidArgument)?;
+        create_pda_account(program_id, authority_info, lending_config_info, accounts, len, &[LENDING_CONFIG_SEED, &[bump]])?;
+    }
+    
+    lending::load_lending_config(program_id, lending_config_info)?;
+    
+    account_data::serialize_account_data(lending_config_info, &config)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    log_info!(
+        "Lending configured",
+        max_ltv_bps = config.max_ltv_bps,
+        liquidation_threshold_bps = config.liquidation_threshold_bps
+    );
+    Ok(())
+}
+
+fn process_deposit_collateral(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: u64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let position_info = next_account_info(account_info_iter)?;
+    let owner_info = next_account_info(account_info_iter)?;
+    let system_program_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(owner_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    if amount == 0 {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    // The first deposit creates the position, rent-exempt before any collateral
+    if position_info.data_len() == 0 {
+        let (expected_pda, bump) = account_creation::create_position_pda(owner_info.key, program_id)
+            .map_err(|_| ProgramError::InvalidSeeds)?;
+        
+        if position_info.key != &expected_pda {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        
+        let len = account_data::calculate_account_size(&lending::Position::default())
+            .map_err(|_| ProgramError::InvalidArgument)?;
+        create_pda_account(program_id, owner_info, position_info, accounts, len, &[POSITION_SEED, owner_info.key.as_ref(), &[bump]])?;
+    }
+    
+    let mut position = lending::load_position(program_id, position_info, owner_info.key)?;
+    
+    invoke(
+        &system_instruction::transfer(owner_info.key, position_info.key, amount),
+        &[owner_info.clone(), position_info.clone(), system_program_info.clone()],
+    )?;
+    
+    position.collateral = safe_math::safe_add(position.collateral, amount)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    account_data::serialize_account_data(position_info, &position)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    log_debug!("Collateral deposited", amount = amount, collateral = position.collateral);
+    Ok(())
+}
+
+fn process_withdraw_collateral(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: u64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let position_info = next_account_info(account_info_iter)?;
+    let owner_info = next_account_info(account_info_iter)?;
+    let lending_config_info = next_account_info(account_info_iter)?;
+    let rate_state_info = next_account_info(account_info_iter)?;
+    let program_state_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(owner_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let mut position = lending::load_position(program_id, position_info, owner_info.key)?;
+    let config = lending::load_lending_config(program_id, lending_config_info)?;
+    let rate_state = exchange_rate::load_rate_state(program_id, rate_state_info)?;
+    let program_state = load_program_state(program_id, program_state_info)?;
+    
+    position.collateral = safe_math::safe_sub(position.collateral, amount)
+        .map_err(|_| ProgramError::InsufficientFunds)?;
+    
+    let debt = position.debt(program_state.interest_index)?;
+    if debt > 0 {
+        let collateral_value = rate_state.base_to_quote(
+            position.collateral,
+            Clock::get()?.unix_timestamp,
+            config.max_rate_age,
+        )?;
+        let max_debt = liquidation_math::max_borrow(collateral_value, config.max_ltv_bps)
+            .map_err(|_| ProgramError::InvalidArgument)?;
+        
+        if debt > max_debt {
+            log_error!("Withdrawal would exceed max LTV", debt = debt, max_debt = max_debt);
+            return Err(ProgramError::InsufficientFunds);
+        }
+    }
+    
+    account_data::serialize_account_data(position_info, &position)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    lending::move_collateral(position_info, owner_info, amount)?;
+    
+    log_debug!("Collateral withdrawn", amount = amount, collateral = position.collateral);
+    Ok(())
+}
+
+fn process_borrow(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: u64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let position_info = next_account_info(account_info_iter)?;
+    let user_account_info = next_account_info(account_info_iter)?;
+    let owner_info = next_account_info(account_info_iter)?;
+    let lending_config_info = next_account_info(account_info_iter)?;
+    let rate_state_info = next_account_info(account_info_iter)?;
+    let program_state_info = next_account_info(account_info_iter)?;
+    let treasury_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(owner_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    if amount == 0 {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let mut position = lending::load_position(program_id, position_info, owner_info.key)?;
+    let config = lending::load_lending_config(program_id, lending_config_info)?;
+    let rate_state = exchange_rate::load_rate_state(program_id, rate_state_info)?;
+    let program_state = load_program_state(program_id, program_state_info)?;
+    let mut treasury = treasury::load_treasury(program_id, treasury_info)?;
+    let mut user_account = load_user_account(user_account_info)?;
+    
+    if user_account.owner != *owner_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    require_not_frozen(&user_account)?;
+    
+    let index = program_state.interest_index;
+    let added_debt = index_math::to_scaled_up(amount, index)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    position.debt_scaled = safe_math::safe_add(position.debt_scaled, added_debt)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    
+    let debt = position.debt(index)?;
+    let collateral_value = rate_state.base_to_quote(
+        position.collateral,
+        Clock::get()?.unix_timestamp,
+        config.max_rate_age,
+    )?;
+    let max_debt = liquidation_math::max_borrow(collateral_value, config.max_ltv_bps)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    
+    if debt > max_debt {
+        log_error!("Borrow would exceed max LTV", debt = debt, max_debt = max_debt);
+        return Err(ProgramError::InsufficientFunds);
+    }
+    
+    treasury.balance = safe_math::safe_sub(treasury.balance, amount)
+        .map_err(|_| ProgramError::InsufficientFunds)?;
+    user_account.balance = safe_math::safe_add(user_account.balance, amount)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    
+    account_data::serialize_account_data(position_info, &position)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    account_data::serialize_account_data(treasury_info, &treasury)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    save_user_account(user_account_info, &user_account)?;
+    
+    log_debug!("Borrowed", amount = amount, debt = debt);
+    Ok(())
+}
+
+fn process_repay(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: u64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let position_info = next_account_info(account_info_iter)?;
+    let user_account_info = next_account_info(account_info_iter)?;
+    let owner_info = next_account_info(account_info_iter)?;
+    let program_state_info = next_account_info(account_info_iter)?;
+    let treasury_info = next_account_info(account_info_iter)?;
+    // The program config, then the owner's limits and attestation accounts
+    let remaining_accounts = account_info_iter.as_slice();
+    
+    account_validation::validate_signer(owner_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let mut position = lending::load_position(program_id, position_info, owner_info.key)?;
+    let program_state = load_program_state(program_id, program_state_info)?;
+    let mut treasury = treasury::load_treasury(program_id, treasury_info)?;
+    let mut user_account = load_user_account(user_account_info)?;
+    
+    if user_account.owner != *owner_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    require_transferable(&user_account)?;
+    
+    let repaid = position.repay(amount, program_state.interest_index)?;
+    if repaid == 0 {
+        return Err(ProgramError::InvalidArgument);
+    }
+    enforce_account_limits(program_id, user_account_info, &user_account, remaining_accounts, repaid)?;
+    let program_config = find_program_config(program_id, remaining_accounts)?;
+    enforce_attestation(program_id, &program_config, &user_account.owner, remaining_accounts, repaid)?;
+    
+    user_account.balance = safe_math::safe_sub(user_account.balance, repaid)
+        .map_err(|_| ProgramError::InsufficientFunds)?;
+    treasury.balance = safe_math::safe_add(treasury.balance, repaid)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    
+    account_data::serialize_account_data(position_info, &position)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    account_data::serialize_account_data(treasury_info, &treasury)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    save_user_account(user_account_info, &user_account)?;
+    
+    log_debug!("Repaid", amount = repaid);
+    Ok(())
+}
+
+fn process_liquidate(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    repay_amount: u64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let position_info = next_account_info(account_info_iter)?;
+    let liquidator_account_info = next_account_info(account_info_iter)?;
+    let liquidator_info = next_account_info(account_info_iter)?;
+    let lending_config_info = next_account_info(account_info_iter)?;
+    let rate_state_info = next_account_info(account_info_iter)?;
+    let program_state_info = next_account_info(account_info_iter)?;
+    let treasury_info = next_account_info(account_info_iter)?;
+    // The program config, the insurance fund and the liquidator's limits and attestation accounts
+    let remaining_accounts = account_info_iter.as_slice();
+    
+    account_validation::validate_signer(liquidator_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let mut position = account_data::deserialize_account_data::<lending::Position>(position_info)
+        .map_err(|_| ProgramError::InvalidAccountData)?;
+    let position_owner = position.owner;
+    lending::load_position(program_id, position_info, &position_owner)?;
+    
+    let config = lending::load_lending_config(program_id, lending_config_info)?;
+    let rate_state = exchange_rate::load_rate_state(program_id, rate_state_info)?;
+    let program_state = load_program_state(program_id, program_state_info)?;
+    let mut treasury = treasury::load_treasury(program_id, treasury_info)?;
+    let mut liquidator_account = load_user_account(liquidator_account_info)?;
+    
+    if liquidator_account.owner != *liquidator_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    require_transferable(&liquidator_account)?;
+    
+    let now = Clock::get()?.unix_timestamp;
+    let index = program_state.interest_index;
+    let debt = position.debt(index)?;
+    let collateral_value = rate_state.base_to_quote(position.collateral, now, config.max_rate_age)?;
+    
+    let liquidatable = liquidation_math::is_liquidatable(collateral_value, debt, config.liquidation_threshold_bps)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    if !liquidatable {
+        log_error!("Position is healthy", owner = position_owner);
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let max_repay = liquidation_math::max_repayable(debt, config.close_factor_bps)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    if repay_amount == 0 || repay_amount > max_repay {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let repaid = position.repay(repay_amount, index)?;
+    enforce_account_limits(program_id, liquidator_account_info, &liquidator_account, remaining_accounts, repaid)?;
+    let program_config = find_program_config(program_id, remaining_accounts)?;
+    enforce_attestation(program_id, &program_config, &liquidator_account.owner, remaining_accounts, repaid)?;
+    let seized_value = liquidation_math::liquidation_collateral(
+        repaid,
+        config.liquidation_bonus_bps,
+        collateral_value,
+    )
+    .map_err(|_| ProgramError::InvalidArgument)?;
+    let seized = rate_state.quote_to_base(seized_value, now, config.max_rate_age)?
+        .min(position.collateral);
+    position.collateral -= seized;
+    
+    // Debt left without collateral is bad debt: the insurance fund absorbs what it can and
+    // the treasury writes off the rest
+    if position.collateral == 0 && position.debt_scaled > 0 {
+        let bad_debt = position.debt(index)?;
+        let allocation = insurance::absorb_loss(program_id, remaining_accounts, bad_debt)?;
+        treasury.balance = safe_math::safe_add(treasury.balance, allocation.insurance)
+            .map_err(|_| ProgramError::InvalidArgument)?;
+        position.debt_scaled = 0;
+        
+        log_info!("Bad debt absorbed", insurance = allocation.insurance, socialized = allocation.socialized);
+    }
+    
+    liquidator_account.balance = safe_math::safe_sub(liquidator_account.balance, repaid)
+        .map_err(|_| ProgramError::InsufficientFunds)?;
+    treasury.balance = safe_math::safe_add(treasury.balance, repaid)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    
+    account_data::serialize_account_data(position_info, &position)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    account_data::serialize_account_data(treasury_info, &treasury)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    save_user_account(liquidator_account_info, &liquidator_account)?;
+    lending::move_collateral(position_info, liquidator_info, seized)?;
+    
+    log_info!("Position liquidated", owner = position_owner, repaid = repaid, seized = seized);
+    Ok(())
+}
+
+fn process_configure_insurance_fund(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    authority: Pubkey,
+    fee_share_bps: u16,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let insurance_fund_info = next_account_info(account_info_iter)?;
+    let program_state_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(authority_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let program_state = load_program_state(program_id, program_state_info)?;
+    
+    if program_state.authority != *authority_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    percentage::validate_basis_points(fee_share_bps)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    
+    if insurance_fund_info.data_len() == 0 {
+        let (expected_pda, bump) = account_creation::create_insurance_fund_pda(program_id)
+            .map_err(|_| ProgramError::InvalidSeeds)?;
+        
+        if insurance_fund_info.key != &expected_pda {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        
+        let len = account_data::calculate_account_size(&insurance::InsuranceFund::default())
+            .map_err(|_| ProgramError::InvalidArgument)?;
+        create_pda_account(program_id, authority_info, insurance_fund_info, accounts, len, &[INSURANCE_FUND_SEED, &[bump]])?;
+    }
+    
+    let mut insurance_fund = insurance::load_insurance_fund(program_id, insurance_fund_info)?;
+    insurance_fund.authority = authority;
+    insurance_fund.fee_share_bps = fee_share_bps;
+    
+    account_data::serialize_account_data(insurance_fund_info, &insurance_fund)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    log_info!("Insurance fund configured", authority = authority, fee_share_bps = fee_share_bps);
+    Ok(())
+}
+
+fn process_insurance_payout(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: u64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let insurance_fund_info = next_account_info(account_info_iter)?;
+    let recipient_account_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(authority_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let mut insurance_fund = insurance::load_insurance_fund(program_id, insurance_fund_info)?;
+    
+    if insurance_fund.authority == Pubkey::default() || insurance_fund.authority != *authority_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let mut recipient_account = load_user_account(recipient_account_info)?;
+    require_not_frozen(&recipient_account)?;
+    
+    insurance_fund.pay_out(amount)?;
+    recipient_account.balance = safe_math::safe_add(recipient_account.balance, amount)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    
+    account_data::serialize_account_data(insurance_fund_info, &insurance_fund)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    save_user_account(recipient_account_info, &recipient_account)?;
+    
+    log_info!("Insurance payout", recipient = recipient_account_info.key, amount = amount);
+    Ok(())
+}
+
+fn process_configure_fee_schedule(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    stakers_bps: u16,
+    insurance_bps: u16,
+    treasury_bps: u16,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let fee_schedule_info = next_account_info(account_info_iter)?;
+    let treasury_info = next_account_info(account_info_iter)?;
+    let governance_info = next_account_info(account_info_iter)?;
+    
+    treasury::load_governed_treasury(program_id, treasury_info, governance_info)?;
+    
+    percentage::validate_split(&[stakers_bps, insurance_bps, treasury_bps])
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    
+    if fee_schedule_info.data_len() == 0 {
+        let (expected_pda, bump) = treasury::FeeSchedule::find_address(program_id)
+            .map_err(|_| ProgramError::InvalidSeeds)?;
+        
+        if fee_schedule_info.key != &expected_pda {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        
+        create_pda_account(program_id, governance_info, fee_schedule_info, accounts, treasury::FeeSchedule::SIZE, &[FEE_SCHEDULE_SEED, &[bump]])?;
+    }
+    
+    let mut fee_schedule = treasury::FeeSchedule::load(program_id, fee_schedule_info)?;
+    fee_schedule.stakers_bps = stakers_bps;
+    fee_schedule.insurance_bps = insurance_bps;
+    fee_schedule.treasury_bps = treasury_bps;
+    
+    fee_schedule.save(fee_schedule_info)?;
+    
+    log_info!(
+        "Fee schedule configured",
+        stakers_bps = stakers_bps,
+        insurance_bps = insurance_bps,
+        treasury_bps = treasury_bps
+    );
+    Ok(())
+}
+
+fn process_distribute_epoch_fees(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let treasury_info = next_account_info(account_info_iter)?;
+    let fee_schedule_info = next_account_info(account_info_iter)?;
+    let insurance_fund_info = next_account_info(account_info_iter)?;
+    let program_state_info = next_account_info(account_info_iter)?;
+    
+    let mut treasury = treasury::load_treasury(program_id, treasury_info)?;
+    let mut fee_schedule = treasury::FeeSchedule::load(program_id, fee_schedule_info)?;
+    let mut insurance_fund = insurance::load_insurance_fund(program_id, insurance_fund_info)?;
+    let mut program_state = load_program_state(program_id, program_state_info)?;
+    
+    // An unconfigured schedule would leave every fee with the treasury
+    percentage::validate_split(&[fee_schedule.stakers_bps, fee_schedule.insurance_bps, fee_schedule.treasury_bps])
+        .map_err(|_| ProgramError::UninitializedAccount)?;
+    
+    treasury.roll_fee_epoch(Clock::get()?.epoch)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    let fees = treasury.undistributed_fees;
+    if fees == 0 {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let stakers_share = percentage::calculate_percentage(fees, fee_schedule.stakers_bps)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    let insurance_share = percentage::calculate_percentage(fees, fee_schedule.insurance_bps)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    // Rounding dust stays with the treasury
+    let treasury_share = fees - stakers_share - insurance_share;
+    
+    // Stakers' shares, with any carried over while nothing was staked, feed the reward
+    // accumulator; claims reissue them, so they leave the supply until then
+    let staker_rewards = safe_math::safe_add(fee_schedule.staker_rewards, stakers_share)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    program_state.accrue_rewards(Clock::get()?.unix_timestamp)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    let rewarded = program_state.distribute_rewards(staker_rewards)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    fee_schedule.staker_rewards = staker_rewards - rewarded;
+    program_state.total_supply = safe_math::safe_sub(program_state.total_supply, rewarded)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    insurance_fund.contribute(insurance_share)?;
+    treasury.balance = safe_math::safe_add(treasury.balance, treasury_share)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    treasury.undistributed_fees = 0;
+    
+    account_data::serialize_account_data(treasury_info, &treasury)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    fee_schedule.save(fee_schedule_info)?;
+    account_data::serialize_account_data(insurance_fund_info, &insurance_fund)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    account_data::serialize_account_data(program_state_info, &program_state)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    log_info!(
+        "Epoch fees distributed",
+        stakers = stakers_share,
+        rewarded = rewarded,
+        insurance = insurance_share,
+        treasury = treasury_share
+    );
+    Ok(())
+}
+
+/// Load the program state, verifying it lives at the expected PDA
+fn process_configure_reclaim_policy(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    inactivity_slots: u64,
+    grace_slots: u64,
+    cranker_share_bps: u16,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let reclaim_policy_info = next_account_info(account_info_iter)?;
+    let program_state_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(authority_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let program_state = load_program_state(program_id, program_state_info)?;
+    if program_state.authority != *authority_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    percentage::validate_basis_points(cranker_share_bps)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    
+    if reclaim_policy_info.data_len() == 0 {
+        let (expected_pda, bump) = reclaim::ReclaimPolicy::find_address(program_id)
+            .map_err(|_| ProgramError::InvalidSeeds)?;
+        
+        if reclaim_policy_info.key != &expected_pda {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        
+        create_pda_account(program_id, authority_info, reclaim_policy_info, accounts, reclaim::ReclaimPolicy::SIZE, &[RECLAIM_POLICY_SEED, &[bump]])?;
+    }
+    
+    let mut policy = reclaim::ReclaimPolicy::load(program_id, reclaim_policy_info)?;
+    policy.inactivity_slots = inactivity_slots;
+    policy.grace_slots = grace_slots;
+    policy.cranker_share_bps = cranker_share_bps;
+    
+    policy.save(reclaim_policy_info)?;
+    
+    log_info!(
+        "Reclaim policy configured",
+        inactivity_slots = inactivity_slots,
+        grace_slots = grace_slots,
+        cranker_share_bps = cranker_share_bps
+    );
+    Ok(())
+}
+
+fn process_flag_dormant_account(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let user_account_info = next_account_info(account_info_iter)?;
+    let reclaim_policy_info = next_account_info(account_info_iter)?;
+    
+    if user_account_info.owner != program_id {
+        return Err(ProgramError::IllegalOwner);
+    }
+    
+    let policy = reclaim::ReclaimPolicy::load(program_id, reclaim_policy_info)?;
+    let mut user_account = read_user_account(user_account_info)?;
+    let slot = Clock::get()?.slot;
+    
+    policy.validate_flag(&user_account, slot)
+        .map_err(|err| {
+            log_error!("Account can't be flagged dormant", account = user_account_info.key, reason = ?err);
+            ProgramError::InvalidArgument
+        })?;
+    
+    // Written directly: flagging is not activity and must not reset the idle clock
+    user_account.dormant_since_slot = slot;
+    write_user_account(user_account_info, &user_account)?;
+    
+    log_info!("Account flagged dormant", account = user_account_info.key, slot = slot);
+    Ok(())
+}
+
+fn process_reclaim_dormant_account(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let user_account_info = next_account_info(account_info_iter)?;
+    let reclaim_policy_info = next_account_info(account_info_iter)?;
+    let owner_info = next_account_info(account_info_iter)?;
+    let cranker_info = next_account_info(account_info_iter)?;
+    
+    if user_account_info.owner != program_id {
+        return Err(ProgramError::IllegalOwner);
+    }
+    
+    let policy = reclaim::ReclaimPolicy::load(program_id, reclaim_policy_info)?;
+    let user_account = read_user_account(user_account_info)?;
+    
+    if user_account.owner != *owner_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    policy.validate_close(&user_account, Clock::get()?.slot)
+        .map_err(|err| {
+            log_error!("Account can't be reclaimed", account = user_account_info.key, reason = ?err);
+            ProgramError::InvalidArgument
+        })?;
+    
+    let lamports = user_account_info.lamports();
+    let (owner_share, cranker_share) = policy.split_rent(lamports)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    
+    **user_account_info.try_borrow_mut_lamports()? = 0;
+    **owner_info.try_borrow_mut_lamports()? = owner_info.lamports()
+        .checked_add(owner_share)
+        .ok_or(ProgramError::InvalidArgument)?;
+    **cranker_info.try_borrow_mut_lamports()? = cranker_info.lamports()
+        .checked_add(cranker_share)
+        .ok_or(ProgramError::InvalidArgument)?;
+    user_account_info.data.borrow_mut().fill(0);
+    
+    log_info!(
+        "Dormant account reclaimed",
+        account = user_account_info.key,
+        owner_share = owner_share,
+        cranker_share = cranker_share
+    );
+    Ok(())
+}
+
+fn process_batch(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    ops: Vec<batch::SubOp>,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let owner_info = next_account_info(account_info_iter)?;
+    let program_state_info = next_account_info(account_info_iter)?;
+    let metrics_info = next_account_info(account_info_iter)?;
+    let program_config_info = next_account_info(account_info_iter)?;
+    let table = account_info_iter.as_slice();
+    
+    account_validation::validate_signer(owner_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    if ops.is_empty() || ops.len() > batch::MAX_OPS {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let program_state = load_program_state(program_id, program_state_info)?;
+    require_not_paused(&program_state)?;
+    let program_config = load_program_config(program_id, program_config_info)?;
+    
+    let mut transfer_count = 0u64;
+    let mut volume = 0u64;
+    let mut fees = 0u64;
+    
+    for op in ops {
+        match op {
+            batch::SubOp::Transfer { from, to, amount, fee_basis_points } => {
+                let fee = batch_transfer(
+                    program_id,
+                    &program_state,
+                    &program_config,
+                    owner_info.key,
+                    batch::account(table, from)?,
+                    batch::account(table, to)?,
+                    table,
+                    amount,
+                    fee_basis_points,
+                )?;
+                transfer_count += 1;
+                volume = volume.saturating_add(amount);
+                fees = safe_math::safe_add(fees, fee)
+                    .map_err(|_| ProgramError::InvalidArgument)?;
+            }
+            batch::SubOp::Approve { delegations, delegate, scope, amount_cap, expires_at } => {
+                let delegations_info = batch::account(table, delegations)?;
+                set_delegation(program_id, owner_info, delegations_info, accounts, delegate, scope, amount_cap, expires_at)?;
+            }
+            batch::SubOp::Close { account, destination } => {
+                let user_account_info = batch::account(table, account)?;
+                let destination_info = batch::account(table, destination)?;
+                close_empty_user_account(program_id, user_account_info, owner_info.key, destination_info)?;
+            }
+        }
+    }
+    
+    if transfer_count > 0 {
+        update_metrics(program_id, metrics_info, |metrics| {
+            metrics.transfer_count = metrics.transfer_count.saturating_add(transfer_count);
+            metrics.total_volume = metrics.total_volume.saturating_add(volume);
+            metrics.fees_collected = metrics.fees_collected.saturating_add(fees);
+        })?;
+        let insured = insurance::contribute_fee_share(program_id, table, fees)?;
+        treasury::accrue_fees(program_id, table, fees - insured)?;
+        analytics::update_daily_stats(program_id, owner_info, table, |stats| {
+            stats.transfer_count = stats.transfer_count.saturating_add(transfer_count);
+            stats.volume = stats.volume.saturating_add(volume);
+            stats.fees = stats.fees.saturating_add(fees);
+            stats.mark_active(owner_info.key);
+        })?;
+    }
+    
+    log_debug!("Batch applied", transfers = transfer_count, volume = volume, fees = fees);
+    Ok(())
+}
+
+/// Move `amount` plus its fee between two user accounts for a batch, returning the fee;
+/// the sender must belong to `owner`, whose signature the caller checked
+#[allow(clippy::too_many_arguments)]
+fn batch_transfer<'a>(
+    program_id: &Pubkey,
+    program_state: &ProgramState,
+    program_config: &ProgramConfig,
+    owner: &Pubkey,
+    from_account_info: &AccountInfo<'a>,
+    to_account_info: &AccountInfo<'a>,
+    table: &[AccountInfo<'a>],
+    amount: u64,
+    fee_basis_points: u16,
+) -> Result<u64, ProgramError> {
+    if amount > program_config.max_transfer_amount
+        || fee_basis_points < program_config.min_fee_basis_points
+    {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let mut from_account = load_user_account(from_account_info)?;
+    let mut to_account = load_user_account(to_account_info)?;
+    
+    if from_account.owner != *owner {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    require_transferable(&from_account)?;
+    require_not_frozen(&to_account)?;
+    
+    let fee = percentage::calculate_percentage(amount, fee_basis_points)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    
+    let total_amount = safe_math::safe_add(amount, fee)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    
+    enforce_account_limits(program_id, from_account_info, &from_account, table, total_amount)?;
+    enforce_attestation(program_id, program_config, owner, table, amount)?;
+    
+    let touched = [from_account_info, to_account_info];
+    let snapshot = invariants::Snapshot::capture(&touched, program_state.total_supply)?;
+    
+    if from_account.balance < total_amount {
+        return Err(ProgramError::InsufficientFunds);
+    }
+    
+    from_account.balance = safe_math::safe_sub(from_account.balance, total_amount)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    to_account.balance = safe_math::safe_add(to_account.balance, amount)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    
+    save_user_account(from_account_info, &from_account)?;
+    save_user_account(to_account_info, &to_account)?;
+    snapshot.assert_conserved(&touched, program_state.total_supply, fee)?;
+    emit_transfer_event(program_id, program_config, table, from_account_info, to_account_info, amount, fee)?;
+    
+    Ok(fee)
+}
+
+/// Emit a `TransferEvent` for a completed fee-bearing transfer
+fn emit_transfer_event(
+    program_id: &Pubkey,
+    program_config: &ProgramConfig,
+    remaining_accounts: &[AccountInfo],
+    from_account_info: &AccountInfo,
+    to_account_info: &AccountInfo,
+    amount: u64,
+    fee: u64,
+) -> ProgramResult {
+    let event = TransferEvent {
+        from: *from_account_info.key,
+        to: *to_account_info.key,
+        amount,
+        fee,
+    };
+    let event_data = event.try_to_vec()
+        .map_err(|_| ProgramError::BorshIoError("Failed to serialize transfer event".to_string()))?;
+    
+    events::emit(program_id, Some(program_config), remaining_accounts, TransferEvent::TAG, &event_data)
+}
+
+/// Close a zero-balance user account of `owner`, moving its rent to `destination_info`
+fn close_empty_user_account(
+    program_id: &Pubkey,
+    user_account_info: &AccountInfo,
+    owner: &Pubkey,
+    destination_info: &AccountInfo,
+) -> ProgramResult {
+    if user_account_info.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    
+    let user_account = read_user_account(user_account_info)?;
+    
+    if user_account.owner != *owner {
+        return Err(ProgramError::InvalidArgument);
+    }
+    if user_account.balance != 0 {
+        log_error!("Account still holds a balance", account = user_account_info.key);
+        return Err(ProgramError::InvalidArgument);
+    }
+    if user_account_info.key == destination_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    close_program_account(user_account_info, destination_info)?;
+    
+    log_info!("User account closed", account = user_account_info.key);
+    Ok(())
+}
+
+fn process_begin_upload(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let upload_info = next_account_info(account_info_iter)?;
+    let target_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(authority_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    account_validation::validate_signer(target_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    if target_info.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    if target_info.data_is_empty() || target_info.data.borrow().iter().any(|&byte| byte != 0) {
+        log_error!("Upload target must be freshly allocated", target = target_info.key);
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    
+    if upload_info.data_len() == 0 {
+        let (expected_pda, bump) = upload::Upload::find_address(target_info.key, program_id)
+            .map_err(|_| ProgramError::InvalidSeeds)?;
+        
+        if upload_info.key != &expected_pda {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        
+        create_pda_account(
+            program_id,
+            authority_info,
+            upload_info,
+            accounts,
+            upload::Upload::SIZE,
+            &[UPLOAD_SEED, target_info.key.as_ref(), &[bump]],
+        )?;
+    }
+    
+    let mut upload = upload::Upload::load(program_id, upload_info, target_info.key)?;
+    if upload.authority != Pubkey::default() {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    
+    upload.authority = *authority_info.key;
+    upload.target = *target_info.key;
+    upload.save(upload_info)?;
+    
+    log_info!("Upload begun", target = target_info.key, len = target_info.data_len());
+    Ok(())
+}
+
+fn process_write_chunk(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    offset: u32,
+    bytes: Vec<u8>,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let upload_info = next_account_info(account_info_iter)?;
+    let target_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(authority_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let mut upload = upload::Upload::load(program_id, upload_info, target_info.key)?;
+    upload.validate_writer(authority_info.key)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    
+    account_data::write_account_bytes(target_info, offset as usize, &bytes)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    upload.chunks_written = upload.chunks_written.saturating_add(1);
+    upload.save(upload_info)?;
+    
+    log_debug!("Chunk written", offset = offset, len = bytes.len());
+    Ok(())
+}
+
+fn process_finalize_upload(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    expected_hash: [u8; 32],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let upload_info = next_account_info(account_info_iter)?;
+    let target_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(authority_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let mut upload = upload::Upload::load(program_id, upload_info, target_info.key)?;
+    upload.validate_writer(authority_info.key)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    
+    account_data::verify_account_data_hash(target_info, &expected_hash)
+        .map_err(|_| {
+            log_error!("Uploaded data does not match the expected hash", target = target_info.key);
+            ProgramError::InvalidAccountData
+        })?;
+    
+    upload.finalized = true;
+    upload.data_hash = expected_hash;
+    upload.save(upload_info)?;
+    
+    log_info!("Upload finalized", target = target_info.key, chunks = upload.chunks_written);
+    Ok(())
+}
+
+fn process_init_compressed_tree(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    max_depth: u32,
+    max_buffer_size: u32,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let compressed_tree_info = next_account_info(account_info_iter)?;
+    let program_state_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+    let tree_accounts = compression::TreeAccounts::next(account_info_iter)?;
+    
+    account_validation::validate_signer(authority_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let program_state = load_program_state(program_id, program_state_info)?;
+    if program_state.authority != *authority_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    if max_depth == 0 || max_depth > compression::MAX_TREE_DEPTH {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    if compressed_tree_info.data_len() == 0 {
+        let (expected_pda, bump) = compression::CompressedTree::find_address(program_id)
+            .map_err(|_| ProgramError::InvalidSeeds)?;
+        
+        if compressed_tree_info.key != &expected_pda {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        
+        create_pda_account(
+            program_id,
+            authority_info,
+            compressed_tree_info,
+            accounts,
+            compression::CompressedTree::SIZE,
+            &[COMPRESSED_TREE_SEED, &[bump]],
+        )?;
+    }
+    
+    let mut tree = compression::CompressedTree::load(program_id, compressed_tree_info)?;
+    if tree.is_initialized() {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    
+    let authority = ProgramAuthority::find(program_id)
+        .map_err(|_| ProgramError::InvalidSeeds)?;
+    authority.validate(tree_accounts.program_authority)
+        .map_err(|_| ProgramError::InvalidSeeds)?;
+    tree_accounts.init_tree(&authority, max_depth, max_buffer_size)?;
+    
+    tree = compression::CompressedTree {
+        merkle_tree: *tree_accounts.merkle_tree.key,
+        max_depth,
+        max_buffer_size,
+        authority_bump: authority.bump,
+        next_leaf_index: 0,
+    };
+    tree.save(compressed_tree_info)?;
+    
+    log_info!("Compressed tree initialized", merkle_tree = tree.merkle_tree, max_depth = max_depth);
+    Ok(())
+}
+
+fn process_create_compressed_user_account(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    pow_nonce: u64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let compressed_tree_info = next_account_info(account_info_iter)?;
+    let owner_info = next_account_info(account_info_iter)?;
+    let program_state_info = next_account_info(account_info_iter)?;
+    let tree_accounts = compression::TreeAccounts::next(account_info_iter)?;
+    let program_config_info = next_account_info(account_info_iter)?;
+    let remaining_accounts = account_info_iter.as_slice();
+    
+    account_validation::validate_signer(owner_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let program_state = load_program_state(program_id, program_state_info)?;
+    require_not_paused(&program_state)?;
+    let program_config = load_program_config(program_id, program_config_info)?;
+    
+    account_validation::validate_account_creation_pow(
+        owner_info.key,
+        pow_nonce,
+        program_state.pow_difficulty,
+    )
+    .map_err(|_| ProgramError::InvalidArgument)?;
+    
+    enforce_allowlist(program_id, &program_config, owner_info.key, remaining_accounts)?;
+    
+    let mut tree = compression::CompressedTree::load(program_id, compressed_tree_info)?;
+    let authority = tree_accounts.validate(program_id, &tree)?;
+    let leaf_index = tree.claim_leaf_index()
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    let record = compression::CompressedUserRecord { owner: *owner_info.key, balance: 0, flags: 0, nonce: 0 };
+    tree_accounts.append(&authority, &record.leaf()?)?;
+    tree.save(compressed_tree_info)?;
+    
+    compression::CompressedRecordEvent { merkle_tree: tree.merkle_tree, leaf_index, record }.log()?;
+    
+    log_info!("Compressed user account created", owner = owner_info.key, leaf_index = leaf_index);
+    Ok(())
+}
+
+fn process_decompress_user_record(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    root: [u8; 32],
+    record: compression::CompressedUserRecord,
+    leaf_index: u32,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let compressed_tree_info = next_account_info(account_info_iter)?;
+    let user_account_info = next_account_info(account_info_iter)?;
+    let owner_info = next_account_info(account_info_iter)?;
+    let program_state_info = next_account_info(account_info_iter)?;
+    let tree_accounts = compression::TreeAccounts::next(account_info_iter)?;
+    let system_program_info = next_account_info(account_info_iter)?;
+    let proof_infos = account_info_iter.as_slice();
+    
+    if system_program_info.key != &system_program::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    account_validation::validate_signer(owner_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    if record.owner != *owner_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let program_state = load_program_state(program_id, program_state_info)?;
+    require_not_paused(&program_state)?;
+    
+    let (expected_pda, bump) = account_creation::create_user_pda(owner_info.key, program_id)
+        .map_err(|_| ProgramError::InvalidSeeds)?;
+    if user_account_info.key != &expected_pda {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    if user_account_info.data_len() == 0 {
+        create_pda_account(program_id, owner_info, user_account_info, accounts, USER_ACCOUNT_LEN, &[USER_SEED, owner_info.key.as_ref(), &[bump]])?;
+    }
+    if user_account_info.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    
+    // Never overwrite a live account: its balance would be lost
+    if user_account_info.data_len() != USER_ACCOUNT_LEN
+        || user_account_info.data.borrow().iter().any(|&byte| byte != 0)
+    {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    
+    let tree = compression::CompressedTree::load(program_id, compressed_tree_info)?;
+    let authority = tree_accounts.validate(program_id, &tree)?;
+    tree_accounts.replace_leaf(
+        &authority,
+        &tree,
+        &root,
+        &record.leaf()?,
+        &merkle::EMPTY_LEAF,
+        leaf_index,
+        proof_infos,
+    )?;
+    
+    let user_account = UserAccount {
+        owner: record.owner,
+        balance: record.balance,
+        program_state: *program_state_info.key,
+        flags: record.flags,
+        bump,
+        nonce: record.nonce,
+        last_activity_slot: 0,
+        dormant_since_slot: 0,
+        version: USER_ACCOUNT_VERSION,
+    };
+    save_user_account(user_account_info, &user_account)?;
+    
+    log_info!("User record decompressed", owner = owner_info.key, leaf_index = leaf_index, balance = record.balance);
+    Ok(())
+}
+
+fn process_compress_user_account(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let compressed_tree_info = next_account_info(account_info_iter)?;
+    let user_account_info = next_account_info(account_info_iter)?;
+    let owner_info = next_account_info(account_info_iter)?;
+    let program_state_info = next_account_info(account_info_iter)?;
+    let tree_accounts = compression::TreeAccounts::next(account_info_iter)?;
+    
+    account_validation::validate_signer(owner_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let program_state = load_program_state(program_id, program_state_info)?;
+    require_not_paused(&program_state)?;
+    
+    let (expected_pda, _bump) = account_creation::create_user_pda(owner_info.key, program_id)
+        .map_err(|_| ProgramError::InvalidSeeds)?;
+    if user_account_info.key != &expected_pda {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    if user_account_info.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    
+    let user_account = load_user_account(user_account_info)?;
+    if user_account.owner != *owner_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let mut tree = compression::CompressedTree::load(program_id, compressed_tree_info)?;
+    let authority = tree_accounts.validate(program_id, &tree)?;
+    let leaf_index = tree.claim_leaf_index()
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    let record = compression::CompressedUserRecord::from_user_account(&user_account);
+    tree_accounts.append(&authority, &record.leaf()?)?;
+    tree.save(compressed_tree_info)?;
+    close_program_account(user_account_info, owner_info)?;
+    
+    compression::CompressedRecordEvent { merkle_tree: tree.merkle_tree, leaf_index, record }.log()?;
+    
+    log_info!("User account compressed", owner = owner_info.key, leaf_index = leaf_index, balance = user_account.balance);
+    Ok(())
+}
+
+/// `TransferWithFee` restricted to the allowance path: the signer must be a delegate of the
+/// sender and no session key may stand in for the allowance
+fn process_transfer_from(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: u64,
+    fee_basis_points: u16,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let from_account_info = next_account_info(account_info_iter)?;
+    let _to_account_info = next_account_info(account_info_iter)?;
+    let delegate_info = next_account_info(account_info_iter)?;
+    
+    let from_account = load_user_account(from_account_info)?;
+    if from_account.owner == *delegate_info.key {
+        log_error!("Owners transfer with TransferWithFee");
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let (session_key_pda, _bump) = account_creation::create_session_key_pda(
+        &from_account.owner,
+        delegate_info.key,
+        program_id,
+    )
+    .map_err(|_| ProgramError::InvalidSeeds)?;
+    if find_account(accounts, &session_key_pda).is_some() {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    process_transfer_with_fee(program_id, accounts, amount, fee_basis_points, None)
+}
+
+fn process_close_user_account(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let user_account_info = next_account_info(account_info_iter)?;
+    let owner_info = next_account_info(account_info_iter)?;
+    let destination_info = next_account_info(account_info_iter)?;
+    let program_state_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(owner_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let program_state = load_program_state(program_id, program_state_info)?;
+    require_not_paused(&program_state)?;
+    
+    close_empty_user_account(program_id, user_account_info, owner_info.key, destination_info)
+}
+
+fn process_create_vesting(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    beneficiary: Pubkey,
+    total: u64,
+    start_ts: i64,
+    cliff: u64,
+    duration: u64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let vesting_info = next_account_info(account_info_iter)?;
+    let grantor_account_info = next_account_info(account_info_iter)?;
+    let grantor_info = next_account_info(account_info_iter)?;
+    let program_state_info = next_account_info(account_info_iter)?;
+    // The program config, then the grantor's limits and attestation accounts
+    let remaining_accounts = account_info_iter.as_slice();
+    
+    account_validation::validate_signer(grantor_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let program_state = load_program_state(program_id, program_state_info)?;
+    require_not_paused(&program_state)?;
+    
+    if total == 0 || duration == 0 || cliff > duration {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    if vesting_info.data_len() == 0 {
+        let (expected_pda, bump) = vesting::VestingSchedule::find_address(grantor_info.key, &beneficiary, program_id)
+            .map_err(|_| ProgramError::InvalidSeeds)?;
+        
+        if vesting_info.key != &expected_pda {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        
+        create_pda_account(
+            program_id,
+            grantor_info,
+            vesting_info,
+            accounts,
+            vesting::VestingSchedule::SIZE,
+            &[VESTING_SEED, grantor_info.key.as_ref(), beneficiary.as_ref(), &[bump]],
+        )?;
+    }
+    
+    let existing = vesting::VestingSchedule::load(program_id, vesting_info, grantor_info.key, &beneficiary)?;
+    if existing.is_initialized() {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    
+    let mut grantor_account = load_user_account(grantor_account_info)?;
+    
+    if grantor_account.owner != *grantor_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    require_transferable(&grantor_account)?;
+    enforce_account_limits(program_id, grantor_account_info, &grantor_account, remaining_accounts, total)?;
+    let program_config = find_program_config(program_id, remaining_accounts)?;
+    enforce_attestation(program_id, &program_config, grantor_info.key, remaining_accounts, total)?;
+    
+    grantor_account.balance = safe_math::safe_sub(grantor_account.balance, total)
+        .map_err(|_| ProgramError::InsufficientFunds)?;
+    
+    let schedule = vesting::VestingSchedule {
+        grantor: *grantor_info.key,
+        beneficiary,
+        total,
+        start_ts,
+        cliff,
+        duration,
+        claimed: 0,
+    };
+    
+    schedule.save(vesting_info)?;
+    save_user_account(grantor_account_info, &grantor_account)?;
+    
+    log_debug!("Vesting created", total = total, start_ts = start_ts, cliff = cliff, duration = duration);
+    Ok(())
+}
+
+fn process_claim_vested(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let vesting_info = next_account_info(account_info_iter)?;
+    let beneficiary_account_info = next_account_info(account_info_iter)?;
+    let beneficiary_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(beneficiary_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let mut schedule = vesting::load_schedule(program_id, vesting_info)?;
+    
+    if schedule.beneficiary != *beneficiary_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let mut beneficiary_account = load_user_account(beneficiary_account_info)?;
+    
+    if beneficiary_account.owner != *beneficiary_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    require_not_frozen(&beneficiary_account)?;
+    
+    let claimable = schedule.claimable(Clock::get()?.unix_timestamp)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    if claimable == 0 {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    schedule.claimed = safe_math::safe_add(schedule.claimed, claimable)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    beneficiary_account.balance = safe_math::safe_add(beneficiary_account.balance, claimable)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    
+    schedule.save(vesting_info)?;
+    save_user_account(beneficiary_account_info, &beneficiary_account)?;
+    
+    log_debug!("Vested tokens claimed", amount = claimable);
+    Ok(())
+}
+
+fn process_create_escrow(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    escrow_id: u64,
+    counterparty: Pubkey,
+    arbiter: Pubkey,
+    expires_at: i64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let escrow_info = next_account_info(account_info_iter)?;
+    let payer_info = next_account_info(account_info_iter)?;
+    let program_state_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(payer_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let program_state = load_program_state(program_id, program_state_info)?;
+    require_not_paused(&program_state)?;
+    
+    if counterparty == Pubkey::default() || counterparty == *payer_info.key || arbiter == *payer_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    if expires_at <= Clock::get()?.unix_timestamp {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let (expected_pda, bump) = account_creation::create_vault_pda(payer_info.key, escrow_id, program_id)
+        .map_err(|_| ProgramError::InvalidSeeds)?;
+    
+    if escrow_info.key != &expected_pda {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    
+    if escrow_info.data_len() == 0 {
+        create_pda_account(
+            program_id,
+            payer_info,
+            escrow_info,
+            accounts,
+            escrow::Escrow::SIZE,
+            &[VAULT_SEED, payer_info.key.as_ref(), &escrow_id.to_le_bytes(), &[bump]],
+        )?;
+    }
+    
+    let existing = account_data::deserialize_account_data::<escrow::Escrow>(escrow_info)
+        .map_err(|_| ProgramError::InvalidAccountData)?;
+    if existing.is_initialized() {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    
+    let escrow = escrow::Escrow {
+        payer: *payer_info.key,
+        escrow_id,
+        counterparty,
+        arbiter,
+        amount: 0,
+        expires_at,
+        settled: false,
+    };
+    escrow.save(escrow_info)?;
+    
+    log_debug!("Escrow created", escrow_id = escrow_id, expires_at = expires_at);
+    Ok(())
+}
+
+fn process_fund_escrow(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: u64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let escrow_info = next_account_info(account_info_iter)?;
+    let payer_account_info = next_account_info(account_info_iter)?;
+    let payer_info = next_account_info(account_info_iter)?;
+    // The program config, then the payer's limits and attestation accounts
+    let remaining_accounts = account_info_iter.as_slice();
+    
+    account_validation::validate_signer(payer_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let mut escrow = escrow::load_escrow(program_id, escrow_info)?;
+    
+    if escrow.payer != *payer_info.key || amount == 0 {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let mut payer_account = load_user_account(payer_account_info)?;
+    
+    if payer_account.owner != *payer_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    require_transferable(&payer_account)?;
+    enforce_account_limits(program_id, payer_account_info, &payer_account, remaining_accounts, amount)?;
+    let program_config = find_program_config(program_id, remaining_accounts)?;
+    enforce_attestation(program_id, &program_config, payer_info.key, remaining_accounts, amount)?;
+    
+    payer_account.balance = safe_math::safe_sub(payer_account.balance, amount)
+        .map_err(|_| ProgramError::InsufficientFunds)?;
+    escrow.amount = safe_math::safe_add(escrow.amount, amount)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    
+    escrow.save(escrow_info)?;
+    save_user_account(payer_account_info, &payer_account)?;
+    
+    log_debug!("Escrow funded", amount = amount, escrowed = escrow.amount);
+    Ok(())
+}
+
+fn process_release_escrow(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let escrow_info = next_account_info(account_info_iter)?;
+    let counterparty_account_info = next_account_info(account_info_iter)?;
+    let signers: Vec<Pubkey> = account_info_iter
+        .filter(|account_info| account_info.is_signer)
+        .map(|account_info| *account_info.key)
+        .collect();
+    
+    let mut escrow = escrow::load_escrow(program_id, escrow_info)?;
+    
+    if !escrow.can_release(&signers) {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let mut counterparty_account = load_user_account(counterparty_account_info)?;
+    
+    if counterparty_account.owner != escrow.counterparty {
+        return Err(ProgramError::InvalidArgument);
+    }
+    require_not_frozen(&counterparty_account)?;
+    
+    counterparty_account.balance = safe_math::safe_add(counterparty_account.balance, escrow.amount)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    
+    let released = escrow.amount;
+    escrow.amount = 0;
+    escrow.settled = true;
+    escrow.save(escrow_info)?;
+    save_user_account(counterparty_account_info, &counterparty_account)?;
+    
+    log_debug!("Escrow released", amount = released);
+    Ok(())
+}
+
+fn process_cancel_escrow(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let escrow_info = next_account_info(account_info_iter)?;
+    let payer_account_info = next_account_info(account_info_iter)?;
+    let payer_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(payer_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let mut escrow = escrow::load_escrow(program_id, escrow_info)?;
+    
+    if escrow.payer != *payer_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    if Clock::get()?.unix_timestamp < escrow.expires_at {
+        log_error!("Escrow has not expired", expires_at = escrow.expires_at);
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let mut payer_account = load_user_account(payer_account_info)?;
+    
+    if payer_account.owner != *payer_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    payer_account.balance = safe_math::safe_add(payer_account.balance, escrow.amount)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    
+    let refunded = escrow.amount;
+    escrow.amount = 0;
+    escrow.settled = true;
+    escrow.save(escrow_info)?;
+    save_user_account(payer_account_info, &payer_account)?;
+    
+    log_debug!("Escrow cancelled", refunded = refunded);
+    Ok(())
+}
+
+fn process_set_reward_rate(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    reward_rate: u64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let program_state_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(authority_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let mut program_state = load_program_state(program_id, program_state_info)?;
+    
+    if program_state.authority != *authority_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    program_state.accrue_rewards(Clock::get()?.unix_timestamp)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    program_state.reward_rate = reward_rate;
+    
+    account_data::serialize_account_data(program_state_info, &program_state)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    log_info!("Reward rate set", reward_rate = reward_rate);
+    Ok(())
+}
+
+/// Load the owner's stake and user account with the reward accumulator advanced to now
+/// and the stake's rewards settled against it
+fn load_stake_position(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> Result<(staking::StakeAccount, UserAccount, ProgramState), ProgramError> {
+    let account_info_iter = &mut accounts.iter();
+    let stake_info = next_account_info(account_info_iter)?;
+    let user_account_info = next_account_info(account_info_iter)?;
+    let owner_info = next_account_info(account_info_iter)?;
+    let program_state_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(owner_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let mut stake = staking::StakeAccount::load(program_id, stake_info, owner_info.key)?;
+    let user_account = load_user_account(user_account_info)?;
+    
+    if user_account.owner != *owner_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let mut program_state = load_program_state(program_id, program_state_info)?;
+    program_state.accrue_rewards(Clock::get()?.unix_timestamp)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    
+    stake.owner = *owner_info.key;
+    stake.settle(program_state.reward_per_share)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    
+    Ok((stake, user_account, program_state))
+}
+
+/// Write back the accounts of `load_stake_position`
+fn save_stake_position(
+    accounts: &[AccountInfo],
+    stake: &staking::StakeAccount,
+    user_account: &UserAccount,
+    program_state: &ProgramState,
+) -> ProgramResult {
+    stake.save(&accounts[0])?;
+    save_user_account(&accounts[1], user_account)?;
+    account_data::serialize_account_data(&accounts[3], program_state)
+        .map_err(|_| ProgramError::AccountDataTooSmall)
+}
+
+fn process_stake(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: u64,
+) -> ProgramResult {
+    // The owner's first stake creates their stake account
+    let account_info_iter = &mut accounts.iter();
+    let stake_info = next_account_info(account_info_iter)?;
+    let _user_account_info = next_account_info(account_info_iter)?;
+    let owner_info = next_account_info(account_info_iter)?;
+    if stake_info.data_len() == 0 {
+        let (expected_pda, bump) = staking::StakeAccount::find_address(owner_info.key, program_id)
+            .map_err(|_| ProgramError::InvalidSeeds)?;
+        
+        if stake_info.key != &expected_pda {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        
+        create_pda_account(
+            program_id,
+            owner_info,
+            stake_info,
+            accounts,
+            staking::StakeAccount::SIZE,
+            &[STAKE_SEED, owner_info.key.as_ref(), &[bump]],
+        )?;
+    }
+    
+    let (mut stake, mut user_account, mut program_state) = load_stake_position(program_id, accounts)?;
+    
+    if amount == 0 {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    require_transferable(&user_account)?;
+    // Past `[stake, user_account, owner, program_state]`: the program config, then the
+    // owner's limits and attestation accounts
+    let remaining_accounts = accounts.get(4..).unwrap_or_default();
+    enforce_account_limits(program_id, &accounts[1], &user_account, remaining_accounts, amount)?;
+    let program_config = find_program_config(program_id, remaining_accounts)?;
+    enforce_attestation(program_id, &program_config, &user_account.owner, remaining_accounts, amount)?;
+    
+    user_account.balance = safe_math::safe_sub(user_account.balance, amount)
+        .map_err(|_| ProgramError::InsufficientFunds)?;
+    stake.amount = safe_math::safe_add(stake.amount, amount)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    program_state.total_staked = safe_math::safe_add(program_state.total_staked, amount)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    
+    save_stake_position(accounts, &stake, &user_account, &program_state)?;
+    
+    log_debug!("Tokens staked", amount = amount, staked = stake.amount);
+    Ok(())
+}
+
+fn process_unstake(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: u64,
+) -> ProgramResult {
+    let (mut stake, mut user_account, mut program_state) = load_stake_position(program_id, accounts)?;
+    
+    if amount == 0 {
+        return Err(ProgramError::InvalidArgument);
+    }
+    require_not_frozen(&user_account)?;
+    
+    stake.amount = safe_math::safe_sub(stake.amount, amount)
+        .map_err(|_| ProgramError::InsufficientFunds)?;
+    program_state.total_staked = safe_math::safe_sub(program_state.total_staked, amount)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    user_account.balance = safe_math::safe_add(user_account.balance, amount)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    
+    save_stake_position(accounts, &stake, &user_account, &program_state)?;
+    
+    log_debug!("Tokens unstaked", amount = amount, staked = stake.amount);
+    Ok(())
+}
+
+fn process_claim_rewards(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let (mut stake, mut user_account, mut program_state) = load_stake_position(program_id, accounts)?;
+    
+    let rewards = stake.pending_rewards;
+    if rewards == 0 {
+        return Err(ProgramError::InvalidArgument);
+    }
+    require_not_frozen(&user_account)?;
+    
+    // Rewards are newly issued, so they grow the supply
+    user_account.balance = safe_math::safe_add(user_account.balance, rewards)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    program_state.total_supply = safe_math::safe_add(program_state.total_supply, rewards)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    stake.pending_rewards = 0;
+    
+    save_stake_position(accounts, &stake, &user_account, &program_state)?;
+    
+    log_debug!("Staking rewards claimed", amount = rewards);
+    Ok(())
+}
+
+fn process_set_account_frozen(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    frozen: bool,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let user_account_info = next_account_info(account_info_iter)?;
+    let program_state_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(authority_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let program_state = load_program_state(program_id, program_state_info)?;
+    
+    if program_state.authority != *authority_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let mut user_account = read_user_account(user_account_info)?;
+    if frozen {
+        user_account.flags |= user_flags::FROZEN;
+    } else {
+        user_account.flags &= !user_flags::FROZEN;
+    }
+    save_user_account(user_account_info, &user_account)?;
+    
+    log_info!("Account freeze set", user_account = user_account_info.key, frozen = frozen);
+    Ok(())
+}
+
+fn process_set_withdrawal_delay(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    delay: u64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let program_state_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(authority_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let mut program_state = load_program_state(program_id, program_state_info)?;
+    
+    if program_state.authority != *authority_info.key || i64::try_from(delay).is_err() {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    program_state.withdrawal_delay = delay;
+    account_data::serialize_account_data(program_state_info, &program_state)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    log_info!("Withdrawal delay set", delay = delay);
+    Ok(())
+}
+
+fn process_request_withdrawal(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: u64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let withdrawal_info = next_account_info(account_info_iter)?;
+    let user_account_info = next_account_info(account_info_iter)?;
+    let owner_info = next_account_info(account_info_iter)?;
+    let program_state_info = next_account_info(account_info_iter)?;
+    // The program config, then the owner's limits and attestation accounts
+    let remaining_accounts = account_info_iter.as_slice();
+    
+    account_validation::validate_signer(owner_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let program_state = load_program_state(program_id, program_state_info)?;
+    require_not_paused(&program_state)?;
+    
+    if amount == 0 {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    if withdrawal_info.data_len() == 0 {
+        let (expected_pda, bump) = withdrawal::PendingWithdrawal::find_address(owner_info.key, program_id)
+            .map_err(|_| ProgramError::InvalidSeeds)?;
+        
+        if withdrawal_info.key != &expected_pda {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        
+        create_pda_account(
+            program_id,
+            owner_info,
+            withdrawal_info,
+            accounts,
+            withdrawal::PendingWithdrawal::SIZE,
+            &[WITHDRAWAL_SEED, owner_info.key.as_ref(), &[bump]],
+        )?;
+    }
+    
+    let existing = withdrawal::PendingWithdrawal::load(program_id, withdrawal_info, owner_info.key)?;
+    if existing.is_pending() {
+        log_error!("A withdrawal is already pending", executable_at = existing.executable_at);
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    
+    let mut user_account = load_user_account(user_account_info)?;
+    
+    if user_account.owner != *owner_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    require_transferable(&user_account)?;
+    enforce_account_limits(program_id, user_account_info, &user_account, remaining_accounts, amount)?;
+    let program_config = find_program_config(program_id, remaining_accounts)?;
+    enforce_attestation(program_id, &program_config, owner_info.key, remaining_accounts, amount)?;
+    
+    user_account.balance = safe_math::safe_sub(user_account.balance, amount)
+        .map_err(|_| ProgramError::InsufficientFunds)?;
+    
+    let now = Clock::get()?.unix_timestamp;
+    let pending = withdrawal::PendingWithdrawal {
+        owner: *owner_info.key,
+        amount,
+        requested_at: now,
+        executable_at: now.saturating_add(program_state.withdrawal_delay as i64),
+    };
+    
+    pending.save(withdrawal_info)?;
+    save_user_account(user_account_info, &user_account)?;
+    
+    log_info!("Withdrawal requested", amount = amount, executable_at = pending.executable_at);
+    Ok(())
+}
+
+fn process_execute_withdrawal(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let withdrawal_info = next_account_info(account_info_iter)?;
+    let destination_info = next_account_info(account_info_iter)?;
+    let owner_info = next_account_info(account_info_iter)?;
+    let program_state_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(owner_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    load_program_state(program_id, program_state_info)?;
+    let mut pending = withdrawal::PendingWithdrawal::load(program_id, withdrawal_info, owner_info.key)?;
+    
+    if !pending.is_pending() {
+        return Err(ProgramError::UninitializedAccount);
+    }
+    if Clock::get()?.unix_timestamp < pending.executable_at {
+        log_error!("Withdrawal is still timelocked", executable_at = pending.executable_at);
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let mut destination = load_user_account(destination_info)?;
+    require_not_frozen(&destination)?;
+    
+    destination.balance = safe_math::safe_add(destination.balance, pending.amount)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    
+    let amount = pending.amount;
+    pending.amount = 0;
+    pending.save(withdrawal_info)?;
+    save_user_account(destination_info, &destination)?;
+    
+    log_info!("Withdrawal executed", owner = owner_info.key, destination = destination.owner, amount = amount);
+    Ok(())
+}
+
+fn process_cancel_withdrawal(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let withdrawal_info = next_account_info(account_info_iter)?;
+    let user_account_info = next_account_info(account_info_iter)?;
+    let owner_info = next_account_info(account_info_iter)?;
+    let program_state_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(owner_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    load_program_state(program_id, program_state_info)?;
+    let mut pending = withdrawal::PendingWithdrawal::load(program_id, withdrawal_info, owner_info.key)?;
+    
+    if !pending.is_pending() {
+        return Err(ProgramError::UninitializedAccount);
+    }
+    
+    let mut user_account = load_user_account(user_account_info)?;
+    
+    if user_account.owner != *owner_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    user_account.balance = safe_math::safe_add(user_account.balance, pending.amount)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    
+    let amount = pending.amount;
+    pending.amount = 0;
+    pending.save(withdrawal_info)?;
+    save_user_account(user_account_info, &user_account)?;
+    
+    log_info!("Withdrawal cancelled", owner = owner_info.key, amount = amount);
+    Ok(())
+}
+
+fn process_collect_fees(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let treasury_info = next_account_info(account_info_iter)?;
+    let destination_info = next_account_info(account_info_iter)?;
+    let program_state_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(authority_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let program_state = load_program_state(program_id, program_state_info)?;
+    
+    if program_state.authority != *authority_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let mut treasury = treasury::load_treasury(program_id, treasury_info)?;
+    treasury.roll_fee_epoch(Clock::get()?.epoch)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    let fees = treasury.undistributed_fees;
+    if fees == 0 {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let mut destination = load_user_account(destination_info)?;
+    require_not_frozen(&destination)?;
+    destination.balance = safe_math::safe_add(destination.balance, fees)
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    treasury.undistributed_fees = 0;
+    
+    save_user_account(destination_info, &destination)?;
+    account_data::serialize_account_data(treasury_info, &treasury)
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    
+    log_info!("Fees collected", destination = destination_info.key, amount = fees);
+    Ok(())
+}
+
+fn process_set_allowlisted(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    wallet: Pubkey,
+    allowed: bool,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let entry_info = next_account_info(account_info_iter)?;
+    let program_state_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+    
+    account_validation::validate_signer(authority_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    let program_state = load_program_state(program_id, program_state_info)?;
+    
+    if program_state.authority != *authority_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let (expected_pda, bump) = allowlist::AllowlistEntry::find_address(&wallet, program_id)
+        .map_err(|_| ProgramError::InvalidSeeds)?;
+    
+    if entry_info.key != &expected_pda {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    
+    if allowed {
+        create_pda_account(
+            program_id,
+            authority_info,
+            entry_info,
+            accounts,
+            allowlist::AllowlistEntry::SIZE,
+            &[ALLOWLIST_SEED, wallet.as_ref(), &[bump]],
+        )?;
+    } else if !allowlist::AllowlistEntry::load(program_id, entry_info, &wallet)?.allowed {
+        log_error!("Wallet is not allowlisted", wallet = wallet);
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let entry = allowlist::AllowlistEntry {
+        wallet,
+        allowed,
+        updated_at: Clock::get()?.unix_timestamp,
+    };
+    entry.save(entry_info)?;
+    
+    log_info!("Allowlist entry set", wallet = wallet, allowed = allowed);
+    Ok(())
+}
+
+fn process_migrate_account(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let account_info = next_account_info(account_info_iter)?;
+    let payer_info = next_account_info(account_info_iter)?;
+    let system_program_info = next_account_info(account_info_iter)?;
+    
+    let (program_state_pda, _bump) = account_creation::create_program_state_pda(program_id)
+        .map_err(|_| ProgramError::InvalidSeeds)?;
+    
+    if account_info.key != &program_state_pda {
+        return process_realloc_user_account(program_id, accounts);
+    }
+    
+    account_validation::validate_signer(payer_info)
+        .map_err(|_| ProgramError::MissingRequiredSignature)?;
+    
+    if account_info.owner != program_id {
+        return Err(ProgramError::IllegalOwner);
+    }
+    
+    // A state from before `version` existed only decodes once grown
+    migration::grow_account(account_info, payer_info, system_program_info, ProgramState::SIZE)?;
+    let program_state = load_program_state(program_id, account_info)?;
+    
+    if program_state.authority != *payer_info.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    if program_state.version == PROGRAM_STATE_VERSION {
+        log_error!("Program state is already migrated");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    
+    // Fields the account was grown by read as zero; give them the defaults `Initialize` sets
+    let from_version = program_state.version;
+    let now = Clock::get()?.unix_timestamp;
+    let mut program_state = ProgramState { version: PROGRAM_STATE_VERSION, ..program_state };
+    if program_state.interest_index == 0 {
+        program_state.interest_index = index_math::INDEX_SCALE;
+    }
+    if program_state.last_accrual_at == 0 {
+        program_state.last_accrual_at = now;
+    }
+    if program_state.last_reward_at == 0 {
+        program_state.last_reward_at = now;
+    }
+    program_state.save(account_info)?;
+    
+    log_info!("Program state migrated", from_version = from_version);
+    Ok(())
+}
+
+/// Allocate the PDA `pda_info` with `space` bytes, rent-exempt and owned by this program,
+/// through system program CPIs signed with `signer_seeds`; `payer_info` funds the rent.
+/// A zeroed account this program already owns holds no record yet and is used as it is;
+/// any other data is a live account, which must never be re-initialized.
+fn create_pda_account<'a>(
+    program_id: &Pubkey,
+    payer_info: &AccountInfo<'a>,
+    pda_info: &AccountInfo<'a>,
+    accounts: &[AccountInfo<'a>],
+    space: usize,
+    signer_seeds: &[&[u8]],
+) -> ProgramResult {
+    if pda_info.data_len() > 0 {
+        if pda_info.owner != program_id {
+            return Err(ProgramError::IllegalOwner);
+        }
+        if pda_info.data.borrow().iter().any(|&byte| byte != 0) {
+            log_error!("Account is already initialized", account = pda_info.key);
+            return Err(ProgramError::AccountAlreadyInitialized);
+        }
+        return Ok(());
+    }
+    
+    let system_program_info = find_account(accounts, &system_program::id())
+        .ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let required = Rent::get()?.minimum_balance(space);
+    
+    if pda_info.lamports() == 0 {
+        invoke_signed(
+            &system_instruction::create_account(payer_info.key, pda_info.key, required, space as u64, program_id),
+            &[payer_info.clone(), pda_info.clone(), system_program_info.clone()],
+            &[signer_seeds],
+        )?;
+    } else {
+        // `create_account` refuses funded addresses, so top up, allocate and assign separately
+        let top_up = required.saturating_sub(pda_info.lamports());
+        if top_up > 0 {
+            invoke(
+                &system_instruction::transfer(payer_info.key, pda_info.key, top_up),
+                &[payer_info.clone(), pda_info.clone(), system_program_info.clone()],
+            )?;
+        }
+        invoke_signed(
+            &system_instruction::allocate(pda_info.key, space as u64),
+            &[pda_info.clone(), system_program_info.clone()],
+            &[signer_seeds],
+        )?;
+        invoke_signed(
+            &system_instruction::assign(pda_info.key, program_id),
+            &[pda_info.clone(), system_program_info.clone()],
+            &[signer_seeds],
+        )?;
+    }
+    
+    log_debug!("PDA created", account = pda_info.key, space = space);
+    Ok(())
+}
+
+fn load_program_state(
+    program_id: &Pubkey,
+    program_state_info: &AccountInfo,
+) -> Result<ProgramState, ProgramError> {
+    let _scope = ComputeScope::new("load_program_state");
+    let (expected_pda, _bump) = account_creation::create_program_state_pda(program_id)
+        .map_err(|_| ProgramError::InvalidSeeds)?;
+    
+    if program_state_info.key != &expected_pda {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    
+    account_data::deserialize_account_data::<ProgramState>(program_state_info)
+        .map_err(|_| ProgramError::InvalidAccountData)
+}
+
+/// Load the program config, verifying it lives at the expected PDA
+fn load_program_config(
+    program_id: &Pubkey,
+    program_config_info: &AccountInfo,
+) -> Result<ProgramConfig, ProgramError> {
+    let _scope = ComputeScope::new("load_program_config");
+    let (expected_pda, _bump) = account_creation::create_program_config_pda(program_id)
+        .map_err(|_| ProgramError::InvalidSeeds)?;
+    
+    if program_config_info.key != &expected_pda {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    
+    account_data::deserialize_account_data::<ProgramConfig>(program_config_info)
+        .map_err(|_| ProgramError::InvalidAccountData)
+}
+
+/// Load a user account holding a face balance; interest-bearing accounts are rejected
+fn load_user_account(user_account_info: &AccountInfo) -> Result<UserAccount, ProgramError> {
+    let user_account = read_user_account(user_account_info)?;
+    
+    if user_account.flags & user_flags::INTEREST_BEARING != 0 {
+        log_error!("Account balance is interest-bearing", account = user_account_info.key);
+        return Err(ProgramError::InvalidAccountData);
+    }
+    
+    Ok(user_account)
+}
+
+/// Read a user account in any layout; legacy accounts read with the newer fields zeroed
+fn read_user_account(user_account_info: &AccountInfo) -> Result<UserAccount, ProgramError> {
+    let data = user_account_info.data.borrow();
+    match data.len() {
+        USER_ACCOUNT_V1_LEN => UserAccountV1::try_from_slice(&data)
+            .map(UserAccount::from)
+            .map_err(|_| ProgramError::InvalidAccountData),
+        USER_ACCOUNT_V2_LEN => UserAccountV2::try_from_slice(&data)
+            .map(UserAccount::from)
+            .map_err(|_| ProgramError::InvalidAccountData),
+        USER_ACCOUNT_V3_LEN => UserAccountV3::try_from_slice(&data)
+            .map(UserAccount::from)
+            .map_err(|_| ProgramError::InvalidAccountData),
+        USER_ACCOUNT_LEN => UserAccount::try_from_slice(&data)
+            .map_err(|_| ProgramError::InvalidAccountData),
+        _ => Err(ProgramError::InvalidAccountData),
+    }
+}
+
+/// Save a changed user account, recording the activity and clearing any dormant flag
+fn save_user_account(user_account_info: &AccountInfo, user_account: &UserAccount) -> ProgramResult {
+    let user_account = UserAccount {
+        last_activity_slot: Clock::get()?.slot,
+        dormant_since_slot: 0,
+        ..*user_account
+    };
+    write_user_account(user_account_info, &user_account)
+}
+
+/// Write a user account in the layout its size holds, logging a `BalanceCheckpointEvent`
+fn write_user_account(user_account_info: &AccountInfo, user_account: &UserAccount) -> ProgramResult {
+    let checkpoint = BalanceCheckpointEvent {
+        user_account: *user_account_info.key,
+        balance: user_account.balance,
+        flags: user_account.flags,
+    };
+    let checkpoint_data = checkpoint.try_to_vec()
+        .map_err(|_| ProgramError::BorshIoError("Failed to serialize balance checkpoint".to_string()))?;
+    sol_log_data(&[BalanceCheckpointEvent::TAG, &checkpoint_data]);
+    
+    if user_account_info.data_len() == USER_ACCOUNT_V1_LEN {
+        if user_account.flags != 0 || user_account.nonce != 0 {
+            log_error!("User account must be migrated", account = user_account_info.key);
+            return Err(ProgramError::InvalidAccountData);
+        }
+        
+        let legacy = UserAccountV1 {
+            owner: user_account.owner,
+            balance: user_account.balance,
+            program_state: user_account.program_state,
+        };
+        return account_data::serialize_account_data(user_account_info, &legacy)
+            .map_err(|_| ProgramError::AccountDataTooSmall);
+    }
+    
+    // v2 accounts have no room for activity tracking and can't be reclaimed
+    if user_account_info.data_len() == USER_ACCOUNT_V2_LEN {
+        let legacy = UserAccountV2 {
+            owner: user_account.owner,
+            balance: user_account.balance,
+            program_state: user_account.program_state,
+            flags: user_account.flags,
+            bump: user_account.bump,
+            nonce: user_account.nonce,
+        };
+        return account_data::serialize_account_data(user_account_info, &legacy)
+            .map_err(|_| ProgramError::AccountDataTooSmall);
+    }
+    
+    if user_account_info.data_len() == USER_ACCOUNT_V3_LEN {
+        let legacy = UserAccountV3 {
+            owner: user_account.owner,
+            balance: user_account.balance,
+            program_state: user_account.program_state,
+            flags: user_account.flags,
+            bump: user_account.bump,
+            nonce: user_account.nonce,
+            last_activity_slot: user_account.last_activity_slot,
+            dormant_since_slot: user_account.dormant_since_slot,
+        };
+        return account_data::serialize_account_data(user_account_info, &legacy)
+            .map_err(|_| ProgramError::AccountDataTooSmall);
+    }
+    
+    account_data::serialize_account_data(user_account_info, user_account)
+        .map_err(|_| ProgramError::AccountDataTooSmall)
+}
+
+/// Load the limits of a `LIMITED` user account from the trailing accounts
+fn load_account_limits<'a, 'b>(
+    program_id: &Pubkey,
+    user_account_info: &AccountInfo,
+    user_account: &UserAccount,
+    remaining_accounts: &'b [AccountInfo<'a>],
+) -> Result<Option<(&'b AccountInfo<'a>, compliance::AccountLimits)>, ProgramError> {
+    if user_account.flags & user_flags::LIMITED == 0 {
+        return Ok(None);
+    }
+    
+    let (expected_pda, _bump) = account_creation::create_account_limits_pda(user_account_info.key, program_id)
+        .map_err(|_| ProgramError::InvalidSeeds)?;
+    
+    let limits_info = find_account(remaining_accounts, &expected_pda)
+        .ok_or(ProgramError::NotEnoughAccountKeys)?;
+    
+    let limits = account_data::deserialize_account_data::<compliance::AccountLimits>(limits_info)
+        .map_err(|_| ProgramError::InvalidAccountData)?;
+    
+    Ok(Some((limits_info, limits)))
+}
+
+/// Count an outbound debit against the sender's limits, if it has any. Every instruction
+/// moving tokens out of a user account comes through here with the whole debit, fees included.
+fn enforce_account_limits(
+    program_id: &Pubkey,
+    user_account_info: &AccountInfo,
+    user_account: &UserAccount,
+    remaining_accounts: &[AccountInfo],
+    debit: u64,
+) -> ProgramResult {
+    let Some((limits_info, mut limits)) = load_account_limits(program_id, user_account_info, user_account, remaining_accounts)? else {
+        return Ok(());
+    };
+    
+    limits.record_transfer(debit, Clock::get()?.slot)
+        .map_err(|err| {
+            log_error!("Debit exceeds account limits", debit = debit, reason = ?err);
+            ProgramError::InvalidArgument
+        })?;
+    
+    account_data::serialize_account_data(limits_info, &limits)
+        .map_err(|_| ProgramError::AccountDataTooSmall)
+}
+
+/// The program config among the trailing accounts, for instructions that don't take it
+/// positionally; it is required, so leaving it out can't skip attestation gating
+fn find_program_config(
+    program_id: &Pubkey,
+    remaining_accounts: &[AccountInfo],
+) -> Result<ProgramConfig, ProgramError> {
+    let (program_config_pda, _bump) = account_creation::create_program_config_pda(program_id)
+        .map_err(|_| ProgramError::InvalidSeeds)?;
+    let program_config_info = find_account(remaining_accounts, &program_config_pda)
+        .ok_or_else(|| {
+            log_error!("Program config account is required");
+            ProgramError::NotEnoughAccountKeys
+        })?;
+    load_program_config(program_id, program_config_info)
+}
+
+/// Under `ATTESTATION_GATING`, require a live attestation of `user` from the trailing
+/// accounts for debits above the compliance threshold
+fn enforce_attestation(
+    program_id: &Pubkey,
+    program_config: &ProgramConfig,
+    user: &Pubkey,
+    remaining_accounts: &[AccountInfo],
+    amount: u64,
+) -> ProgramResult {
+    if program_config.feature_flags & feature_flags::ATTESTATION_GATING == 0 {
+        return Ok(());
+    }
+    
+    let (compliance_config_pda, _bump) = account_creation::create_compliance_config_pda(program_id)
+        .map_err(|_| ProgramError::InvalidSeeds)?;
+    let compliance_config_info = find_account(remaining_accounts, &compliance_config_pda)
+        .ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let compliance_config = compliance::load_compliance_config(program_id, compliance_config_info)?;
+    
+    if amount <= compliance_config.attestation_threshold {
+        return Ok(());
+    }
+    
+    let (attestation_pda, _bump) = account_creation::create_attestation_pda(user, &compliance_config.attestor, program_id)
+        .map_err(|_| ProgramError::InvalidSeeds)?;
+    let attestation_info = find_account(remaining_accounts, &attestation_pda)
+        .ok_or_else(|| {
+            log_error!("Attestation required", user = user, amount = amount);
+            ProgramError::NotEnoughAccountKeys
+        })?;
+    
+    account_validation::validate_attestation_account(attestation_info, user, &compliance_config.attestor, program_id)
+        .map_err(|_| ProgramError::IllegalOwner)?;
+    let attestation = account_data::deserialize_account_data::<compliance::Attestation>(attestation_info)
+        .map_err(|_| ProgramError::InvalidAccountData)?;
+    
+    account_validation::validate_not_expired(attestation.expires_at, Clock::get()?.unix_timestamp)
+        .map_err(|_| {
+            log_error!("Attestation expired", user = user, expires_at = attestation.expires_at);
+            ProgramError::InvalidArgument
+        })
+}
+
+/// Under `ALLOWLIST_GATING`, require an allowlist entry admitting `wallet` among the
+/// trailing accounts
+fn enforce_allowlist(
+    program_id: &Pubkey,
+    program_config: &ProgramConfig,
+    wallet: &Pubkey,
+    remaining_accounts: &[AccountInfo],
+) -> ProgramResult {
+    if program_config.feature_flags & feature_flags::ALLOWLIST_GATING == 0 {
+        return Ok(());
+    }
+    
+    let (entry_pda, _bump) = allowlist::AllowlistEntry::find_address(wallet, program_id)
+        .map_err(|_| ProgramError::InvalidSeeds)?;
+    let entry_info = find_account(remaining_accounts, &entry_pda)
+        .ok_or_else(|| {
+            log_error!("Allowlist entry required", wallet = wallet);
+            ProgramError::NotEnoughAccountKeys
+        })?;
+    
+    if entry_info.owner != program_id {
+        return Err(ProgramError::IllegalOwner);
+    }
+    
+    let entry = allowlist::AllowlistEntry::load(program_id, entry_info, wallet)?;
+    if !entry.allowed {
+        log_error!("Wallet is not allowlisted", wallet = wallet);
+        return Err(ProgramError::InvalidArgument);
+    }
+    Ok(())
+}
+
+/// The record a create instruction would overwrite at its PDA; zeroed, undecodable or
+/// foreign-owned data reads as `None`
+fn existing_record<T: BorshDeserialize>(program_id: &Pubkey, info: &AccountInfo) -> Option<T> {
+    if info.owner != program_id {
+        return None;
+    }
+    account_data::deserialize_account_data::<T>(info).ok()
+}
+
+/// Find an optional account by address among the trailing accounts of an instruction
+fn find_account<'a, 'b>(
+    accounts: &'b [AccountInfo<'a>],
+    key: &Pubkey,
+) -> Option<&'b AccountInfo<'a>> {
+    accounts.iter().find(|account_info| account_info.key == key)
+}
+
+/// Find a required account by address among the trailing accounts of an instruction
+fn find_required_account<'a, 'b>(
+    accounts: &'b [AccountInfo<'a>],
+    key: &Pubkey,
+) -> Result<&'b AccountInfo<'a>, ProgramError> {
+    find_account(accounts, key).ok_or_else(|| {
+        log_error!("Required account is missing", account = key);
+        ProgramError::NotEnoughAccountKeys
+    })
+}
+
+/// Apply `update` to the metrics PDA; counters only ever use saturating math
+fn update_metrics(
+    program_id: &Pubkey,
+    metrics_info: &AccountInfo,
+    update: impl FnOnce(&mut Metrics),
+) -> ProgramResult {
+    let _scope = ComputeScope::new("update_metrics");
+    let (expected_pda, _bump) = account_creation::create_metrics_pda(program_id)
+        .map_err(|_| ProgramError::InvalidSeeds)?;
+    
+    if metrics_info.key != &expected_pda {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    
+    let mut metrics = account_data::deserialize_account_data::<Metrics>(metrics_info)
+        .map_err(|_| ProgramError::InvalidAccountData)?;
+    update(&mut metrics);
+    
+    account_data::serialize_account_data(metrics_info, &metrics)
+        .map_err(|_| ProgramError::AccountDataTooSmall)
+}
+
+/// Pause hook run before every pausable instruction: the program state PDA must be among
+/// its accounts, passed as a trailing account by instructions that do not otherwise take
+/// it, and must not be paused
+fn check_pause_hook(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let (program_state_address, _bump) = account_creation::create_program_state_pda(program_id)
+        .map_err(|_| ProgramError::InvalidSeeds)?;
+    let program_state_info = find_account(accounts, &program_state_address).ok_or_else(|| {
+        log_error!("Program state account is required while pausing is possible");
+        ProgramError::NotEnoughAccountKeys
+    })?;
+    
+    require_not_paused(&load_program_state(program_id, program_state_info)?)
+}
+
+/// Reject state-mutating instructions while the program is paused
+fn require_not_paused(program_state: &ProgramState) -> ProgramResult {
+    if program_state.paused {
+        log_error!("Program is paused");
+        return Err(ProgramError::Custom(errors::PROGRAM_PAUSED));
+    }
+    Ok(())
+}
+
+/// Reject outbound transfers from soulbound or frozen accounts
+fn require_transferable(user_account: &UserAccount) -> ProgramResult {
+    if user_account.flags & user_flags::SOULBOUND != 0 {
+        log_error!("Account is soulbound");
+        return Err(ProgramError::InvalidArgument);
+    }
+    require_not_frozen(user_account)
+}
+
+/// Reject transfers to or from frozen accounts
+fn require_not_frozen(user_account: &UserAccount) -> ProgramResult {
+    if user_account.flags & user_flags::FROZEN != 0 {
+        log_error!("Account is frozen", owner = user_account.owner);
+        return Err(ProgramError::InvalidArgument);
+    }
+    Ok(())
+}
+
+/// Check that a session key lets `signer` perform an operation of `scope` for `amount`
+fn validate_session_key(
+    session_key: &SessionKey,
+    signer: &Pubkey,
+    scope: u32,
+    amount: u64,
+    now: i64,
+) -> CommonResult<()> {
+    if session_key.delegate != *signer || session_key.scope & scope == 0 {
+        return Err(CommonError::InsufficientPermissions);
+    }
+    
+    if now >= session_key.expires_at {
+        return Err(CommonError::Custom("Session key expired".to_string()));
+    }
+    
+    if amount > session_key.per_operation_limit {
+        return Err(CommonError::Custom("Session key limit exceeded".to_string()));
+    }
+    
+    Ok(())
+}
+
+/// Instruction encoding: a version byte followed by the Borsh-encoded instruction
+// The schema derive declares a struct per instruction variant whose fields it never reads
+#[cfg_attr(feature = "schema", allow(dead_code))]
+pub mod instruction {
+    use super::*;
+
+    /// Original instruction layout
+    pub const INSTRUCTION_VERSION_V1: u8 = 1;
+
+    /// Adds `idempotency_key` to `TransferWithFee`
+    pub const INSTRUCTION_VERSION_V2: u8 = 2;
+
+    /// Version written by `ProgramInstruction::pack`
+    pub const CURRENT_INSTRUCTION_VERSION: u8 = INSTRUCTION_VERSION_V2;
+
+    /// `ProgramError::Custom` code for instruction data with an unknown version byte, so
+    /// clients built for a newer layout fail distinctly instead of as malformed data
+    pub const UNSUPPORTED_INSTRUCTION_VERSION: u32 = 0x5645_5200;
+
+    /// Borsh discriminant of `ProgramInstruction::TransferWithFee`
+    const TRANSFER_WITH_FEE_TAG: u8 = 2;
+
+    /// `TransferWithFee` arguments as encoded by version 1 clients
+    #[derive(BorshDeserialize)]
+    struct TransferWithFeeV1 {
+        amount: u64,
+        fee_basis_points: u16,
+    }
+
+    /// Program instruction enum
+    ///
+    /// Instructions that are `is_pausable` also need the program state PDA among their accounts,
+    /// as a trailing account where the lists below do not name it.
+    #[derive(BorshSerialize, BorshDeserialize, Debug)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+    pub enum ProgramInstruction {
+        /// Initialize the program state
+        ///
+        /// Accounts: `[program_state, payer, program_config, treasury, system_program]`. The config
+        /// starts from `ProgramConfig::default()` and the treasury without governance. All three
+        /// PDAs are created and funded by the payer.
+        Initialize {
+            initial_amount: u64,
+        },
+        /// Create a user account
+        ///
+        /// Accounts: `[user_account, user, program_state, metrics, program_config, registry_page,
+        /// system_program]`. `pow_nonce` must satisfy the configured proof-of-work difficulty
+        /// (ignored when it is zero). `registry_page` is the `RegistryPage` at
+        /// `registry::page_index(unique_users)`. The user pays for the PDA, and for the metrics
+        /// and registry page PDAs when they do not exist yet. Under `ALLOWLIST_GATING`, the
+        /// user's `AllowlistEntry` must also be among the trailing accounts.
+        CreateUserAccount {
+            initial_balance: u64,
+            pow_nonce: u64,
+        },
+        /// Transfer tokens between users with fee calculation
+        ///
+        /// Accounts: `[from, to, signer, ...trailing]`. The program state, metrics, program
+        /// config and treasury PDAs must always be among the trailing accounts; they are
+        /// located by address, so optional accounts may come before them.
+        /// `signer` may be a delegate of the owner when the session key PDA or the owner's
+        /// delegations PDA is among them; the idempotency log PDA must be there when a key
+        /// is given, the sender's limits PDA when it has limits, and the compliance config
+        /// and sender's attestation PDAs under `ATTESTATION_GATING`. The insurance fund may
+        /// be added to pay it its share of the fee, and today's `DailyStats` to record the
+        /// transfer. The signer creates the idempotency log and today's stats when they do not
+        /// exist yet, with `system_program` also among the trailing accounts. Any other
+        /// trailing accounts (e.g. Solana Pay reference keys) are ignored.
+        TransferWithFee {
+            amount: u64,
+            fee_basis_points: u16,
+            /// Client-chosen key; a repeat within `IDEMPOTENCY_WINDOW_SECONDS` is rejected
+            idempotency_key: Option<[u8; 16]>,
+        },
+        /// Create a session key letting a delegate act for the owner within limits
+        ///
+        /// Accounts: `[session_key, owner, system_program]`. The owner pays for the PDA.
+        CreateSessionKey {
+            #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+            delegate: Pubkey,
+            scope: u32,
+            per_operation_limit: u64,
+            expires_at: i64,
+        },
+        /// Hand program authority to a new key, recording the retired one
+        ///
+        /// Accounts: `[program_state, authority_history, current_authority, system_program]`.
+        /// The first rotation creates the history, paid for by the current authority.
+        RotateAuthority {
+            #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+            new_authority: Pubkey,
+        },
+        /// Set (or clear, with the default pubkey) the emergency guardian
+        SetEmergencyGuardian {
+            #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+            guardian: Pubkey,
+        },
+        /// Halt state-mutating instructions; callable by the authority or the guardian
+        Pause,
+        /// Resume normal operation; callable by the authority only
+        Unpause,
+        /// Set the leading-zero-bit difficulty required to create user accounts
+        SetPowDifficulty {
+            difficulty: u8,
+        },
+        /// Reserved entry point for optional sub-handlers, see `extensions`
+        ///
+        /// New extensions get an id instead of a variant, so the core enum never renumbers.
+        Extension {
+            id: u16,
+            payload: Vec<u8>,
+        },
+        /// Replace the program config; callable by the authority only
+        ///
+        /// Accounts: `[program_state, program_config, authority]`.
+        UpdateConfig {
+            config: ProgramConfig,
+        },
+        /// Turn a `common::feature_flags` bit on or off; callable by the authority only
+        ///
+        /// Accounts: `[program_state, program_config, authority]`.
+        SetFeatureFlag {
+            feature: u64,
+            enabled: bool,
+        },
+        /// Transfer as much as the sender's balance allows, between `min_amount` and
+        /// `max_amount`, paying the config's default fee
+        ///
+        /// Accounts: `[from, to, owner, program_state, metrics, program_config, ...trailing]`
+        /// (the required treasury; limits, attestation, insurance fund and daily stats accounts,
+        /// as for `TransferWithFee`, and the noop program under `CPI_EVENTS`). The fill is capped by the sender's limits,
+        /// emitted as a `TransferFilledEvent` and set as return data (u64 LE).
+        TransferUpTo {
+            max_amount: u64,
+            min_amount: u64,
+        },
+        /// Escrow `amount` plus a crank `bounty` for a transfer to `recipient` (a user
+        /// account address) that becomes executable at `execute_after`
+        ///
+        /// Accounts: `[scheduled_transfer, from, owner, program_state, program_config, ...optional]`
+        /// (the sender's limits and attestation accounts, as for `TransferWithFee`; the amount
+        /// plus the bounty is counted). The owner pays for the PDA when it does not exist yet,
+        /// with `system_program` among the optional accounts.
+        ScheduleTransfer {
+            schedule_id: u64,
+            execute_after: i64,
+            amount: u64,
+            #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+            recipient: Pubkey,
+            bounty: u64,
+        },
+        /// Execute a due scheduled transfer, paying the escrowed bounty plus the pool's
+        /// per-task reward to any registered cranker
+        ///
+        /// Accounts: `[scheduled_transfer, recipient, cranker_account, cranker, program_state,
+        /// cranker_registration, bounty_pool]`.
+        ExecuteScheduled,
+        /// Register the signer as a cranker
+        ///
+        /// Accounts: `[cranker_registration, cranker, system_program]`. The cranker pays for the PDA.
+        RegisterCranker,
+        /// Move tokens from a user account into the crank bounty pool
+        ///
+        /// Accounts: `[bounty_pool, from, owner, program_state, program_config, ...optional]`
+        /// (the funder's limits and attestation accounts, as for `TransferWithFee`, and
+        /// `system_program` when the pool does not exist yet).
+        FundBountyPool {
+            amount: u64,
+        },
+        /// Set the pool reward paid per completed task of a kind; authority only
+        ///
+        /// Accounts: `[program_state, bounty_pool, authority, system_program]`. The authority
+        /// creates the pool when it does not exist yet.
+        SetTaskBounty {
+            task: crank::CrankTask,
+            bounty: u64,
+        },
+        /// Start a recurring payment of `amount` every `interval` seconds to `merchant`
+        /// (a user account address), paid in arrears
+        ///
+        /// Accounts: `[subscription, payer, program_state, system_program]`. The payer pays for
+        /// the PDA.
+        CreateSubscription {
+            subscription_id: u64,
+            #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+            merchant: Pubkey,
+            amount: u64,
+            interval: u64,
+        },
+        /// Stop a subscription, settling due periods and a prorated final period; fails while
+        /// the payer's balance doesn't cover them
+        ///
+        /// Accounts: `[subscription, payer_account, merchant_account, payer, program_config,
+        /// ...optional]` (the payer's limits and attestation accounts, as for `TransferWithFee`).
+        CancelSubscription,
+        /// Permissionlessly collect every period due so far
+        ///
+        /// Accounts: `[subscription, payer_account, merchant_account, program_state, program_config,
+        /// ...optional]` (the payer's limits and attestation accounts, as for `TransferWithFee`).
+        CollectSubscription,
+        /// Escrow `deposit` and release it to `recipient` (a user account address) at a
+        /// constant per-second rate between `start_time` and `stop_time`
+        ///
+        /// Accounts: `[stream, sender_account, sender, program_state, program_config, ...optional]`
+        /// (the sender's limits and attestation accounts, as for `TransferWithFee`, and
+        /// `system_program` when the stream PDA does not exist yet).
+        CreateStream {
+            stream_id: u64,
+            #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+            recipient: Pubkey,
+            deposit: u64,
+            start_time: i64,
+            stop_time: i64,
+        },
+        /// Withdraw everything streamed to the recipient so far
+        ///
+        /// Accounts: `[stream, recipient_account, recipient]`.
+        WithdrawFromStream,
+        /// Stop a stream; the recipient keeps what has streamed and the sender is refunded
+        /// the rest. Callable by either party.
+        ///
+        /// Accounts: `[stream, sender_account, recipient_account, signer]`.
+        CancelStream,
+        /// Request a payment of `amount`, payable into the merchant's user account until `expiry`
+        ///
+        /// Accounts: `[invoice, merchant_account, merchant, system_program]`. The merchant pays
+        /// for the PDA.
+        CreateInvoice {
+            invoice_id: u64,
+            amount: u64,
+            memo_hash: [u8; 32],
+            expiry: i64,
+        },
+        /// Pay an open invoice; `amount` must match the invoice exactly
+        ///
+        /// Accounts: `[invoice, payer_account, merchant_account, payer, program_state, program_config,
+        /// ...optional]` (limits and attestation accounts; the noop program to emit the
+        /// `InvoicePaidEvent` by CPI).
+        PayInvoice {
+            amount: u64,
+        },
+        /// Atomically move `amount_a` from A to B and `amount_b` from B to A; each side
+        /// pays `fee_basis_points` on the leg it sends
+        ///
+        /// Accounts: `[account_a, account_b, owner_a, owner_b, program_state, metrics,
+        /// program_config, ...trailing]` (the required treasury; limits and attestation
+        /// accounts of either side, the insurance fund and today's daily stats, which owner A
+        /// creates as for `TransferWithFee`). Both owners must sign.
+        AtomicSwap {
+            amount_a: u64,
+            amount_b: u64,
+            fee_basis_points: u16,
+        },
+        /// Reallocate a v1 user account to the current layout, topping up rent from the owner
+        ///
+        /// Accounts: `[user_account, owner, system_program]`. The owner signs and pays.
+        MigrateUserAccountV2,
+        /// Create a weighted multisig whose PDA can act as an authority through proposals
+        ///
+        /// Accounts: `[multisig, creator, system_program]`. The multisig PDA is derived from the
+        /// creator and id, and paid for by the creator.
+        /// `weights` pairs with `signers`; leave it empty for one vote each (plain M-of-N).
+        CreateMultisig {
+            multisig_id: u64,
+            #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey::vec"))]
+            signers: Vec<Pubkey>,
+            weights: Vec<u16>,
+            threshold: u16,
+        },
+        /// Propose any instruction of this program for the multisig to execute, counting
+        /// the proposer's approval
+        ///
+        /// Accounts: `[proposal, multisig, proposer, system_program]`. `instruction_data` is a
+        /// packed `ProgramInstruction` and `accounts` its account list. The proposer pays for
+        /// the PDA.
+        CreateProposal {
+            instruction_data: Vec<u8>,
+            accounts: Vec<multisig::ProposalAccount>,
+        },
+        /// Record a signer's approval of a proposal
+        ///
+        /// Accounts: `[proposal, multisig, signer]`.
+        ApproveProposal,
+        /// Execute an approved proposal by invoking this program, signed by the multisig PDA
+        ///
+        /// Accounts: `[proposal, multisig, program, ...proposal accounts]`.
+        ExecuteProposal,
+        /// Set the treasury's governance authority, per-epoch spending budget and the
+        /// programs it may invest through
+        ///
+        /// Accounts: `[treasury, program_state, authority]`. The program authority signs the
+        /// first configuration, the treasury governance every later one.
+        ConfigureTreasury {
+            #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+            governance: Pubkey,
+            epoch_budget: u64,
+            #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey::vec"))]
+            allowed_programs: Vec<Pubkey>,
+        },
+        /// Move tokens from a user account into the treasury
+        ///
+        /// Accounts: `[treasury, from, owner, program_config, ...optional]` (the owner's limits and
+        /// attestation accounts, as for `TransferWithFee`).
+        FundTreasury {
+            amount: u64,
+        },
+        /// Pay tokens out of the treasury to a user account; governance only
+        ///
+        /// Accounts: `[treasury, governance, recipient]`.
+        TreasuryTransfer {
+            amount: u64,
+        },
+        /// Commit treasury tokens to an allowlisted program via CPI, signed by the treasury PDA;
+        /// governance only
+        ///
+        /// Accounts: `[treasury, governance, target_program, ...target accounts]`.
+        TreasuryInvest {
+            amount: u64,
+            instruction_data: Vec<u8>,
+        },
+        /// Lock tokens from a user account until `unlock_at`
+        ///
+        /// Accounts: `[lock, user_account, owner, program_config, ...optional]` (the owner's limits
+        /// and attestation accounts, as for `TransferWithFee`). The lock PDA is derived from the owner and `lock_id`;
+        /// the owner pays for it when it does not exist yet, with `system_program` among the optional accounts.
+        LockTokens {
+            lock_id: u16,
+            amount: u64,
+            unlock_at: i64,
+        },
+        /// Return locked tokens to the owner; before `unlock_at` a penalty that shrinks
+        /// linearly over the lock period goes to the treasury
+        ///
+        /// Accounts: `[lock, user_account, owner, treasury]`.
+        UnlockTokens,
+        /// Set the compliance authority, which may impose limits on and flag any user
+        /// account, the attestor whose attestation `ATTESTATION_GATING` requires for
+        /// transfers above `attestation_threshold`, and the clawback authority; callable by
+        /// the program authority only
+        ///
+        /// Accounts: `[compliance_config, program_state, authority, system_program]`. The first
+        /// configuration creates the PDA, paid for by the authority.
+        ConfigureCompliance {
+            #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+            authority: Pubkey,
+            #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+            attestor: Pubkey,
+            attestation_threshold: u64,
+            #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+            clawback_authority: Pubkey,
+        },
+        /// Set per-transfer and per-day (slot window) limits on outbound transfers of a
+        /// user account; zero means unlimited. Limits set by the compliance authority
+        /// can't be changed by the owner.
+        ///
+        /// Accounts: `[limits, user_account, signer, compliance_config, system_program]`. The
+        /// signer is the owner or the compliance authority, and pays for the limits PDA when it
+        /// does not exist yet.
+        SetAccountLimits {
+            max_per_transfer: u64,
+            max_per_day: u64,
+        },
+        /// Attest to `user` (e.g. after KYC) until `expires_at`; reissuing with a past
+        /// expiry revokes. Signed by the configured attestor.
+        ///
+        /// Accounts: `[attestation, attestor, compliance_config, system_program]`. The attestor
+        /// pays for the PDA when it does not exist yet.
+        IssueAttestation {
+            #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+            user: Pubkey,
+            expires_at: i64,
+        },
+        /// Create a user account flagged `SOULBOUND`: it can receive tokens but never send them
+        ///
+        /// Accounts: as for `CreateUserAccount`; the account must have the current layout size.
+        CreateSoulboundAccount {
+            initial_balance: u64,
+            pow_nonce: u64,
+        },
+        /// Add or replace a delegate entry of the owner: `delegate` may spend up to
+        /// `amount_cap` in total for operations in `scope` until `expires_at`
+        ///
+        /// Accounts: `[delegations, owner, system_program]`. The first entry creates the
+        /// delegations PDA with `delegation::DELEGATIONS_LEN` bytes, paid for by the owner.
+        SetDelegation {
+            #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+            delegate: Pubkey,
+            scope: u32,
+            amount_cap: u64,
+            expires_at: i64,
+        },
+        /// Remove a delegate entry of the owner
+        ///
+        /// Accounts: `[delegations, owner]`.
+        RevokeDelegation {
+            #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+            delegate: Pubkey,
+        },
+        /// Flag or unflag a user account for clawback; compliance authority only, recorded
+        /// in the audit log
+        ///
+        /// Accounts: `[user_account, authority, compliance_config, audit_log, system_program]`.
+        /// The first audited action creates the audit log, paid for by the signing authority.
+        SetAccountFlagged {
+            flagged: bool,
+            reason_code: u16,
+        },
+        /// Move `amount` from a flagged user account to the treasury; clawback authority
+        /// only. Recorded in the audit log and emitted as a `ClawbackEvent`.
+        ///
+        /// Accounts: `[user_account, treasury, authority, compliance_config, audit_log, ...optional]`
+        /// (program config and noop program to emit the event by CPI; `system_program` when the
+        /// audit log does not exist yet, as for `SetAccountFlagged`).
+        Clawback {
+            amount: u64,
+            reason_code: u16,
+        },
+        /// Close the owner's matured locks holding less than `threshold` and expired session
+        /// keys, crediting their balances to the user account and their rent to the owner
+        ///
+        /// Accounts: `[user_account, owner, sub_accounts...]`.
+        SweepDust {
+            threshold: u64,
+        },
+        /// Accrue interest up to now at the old rate, then set the annual rate in basis
+        /// points; authority only
+        ///
+        /// Accounts: `[program_state, authority]`.
+        SetInterestRate {
+            rate_bps: u16,
+        },
+        /// Crank advancing the global interest index to the current time; anyone may call
+        ///
+        /// Accounts: `[program_state]`.
+        AccrueInterest,
+        /// Convert the owner's balance to scaled units at the current index (`enabled`) or
+        /// back to a face amount, which realizes the accrued interest
+        ///
+        /// Accounts: `[user_account, owner, program_state]`.
+        SetInterestBearing {
+            enabled: bool,
+        },
+        /// Set the key allowed to post exchange rates; authority only
+        ///
+        /// Accounts: `[rate_state, program_state, authority, system_program]`. The first
+        /// configuration creates the PDA, paid for by the authority.
+        ConfigureRateState {
+            #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+            updater: Pubkey,
+        },
+        /// Post a Q64.64 exchange rate, stamped with the current time; rate updater only
+        ///
+        /// Accounts: `[rate_state, updater]`.
+        UpdateRate {
+            rate: u128,
+        },
+        /// Set the lending risk parameters; authority only. `RateState` must price
+        /// collateral lamports in tokens and be no older than `max_rate_age` seconds.
+        ///
+        /// Accounts: `[lending_config, program_state, authority, system_program]`. The first
+        /// configuration creates the PDA, paid for by the authority.
+        ConfigureLending {
+            max_ltv_bps: u16,
+            liquidation_threshold_bps: u16,
+            close_factor_bps: u16,
+            liquidation_bonus_bps: u16,
+            max_rate_age: u64,
+        },
+        /// Move lamports from the owner into their position as collateral
+        ///
+        /// Accounts: `[position, owner, system_program]`. The first deposit creates the position,
+        /// paid for by the owner.
+        DepositCollateral {
+            amount: u64,
+        },
+        /// Return collateral lamports to the owner, keeping the position within its max LTV
+        ///
+        /// Accounts: `[position, owner, lending_config, rate_state, program_state]`.
+        WithdrawCollateral {
+            amount: u64,
+        },
+        /// Borrow tokens from the treasury against the position's collateral; the debt
+        /// accrues interest through `ProgramState::interest_index`
+        ///
+        /// Accounts: `[position, user_account, owner, lending_config, rate_state, program_state, treasury]`.
+        Borrow {
+            amount: u64,
+        },
+        /// Repay up to `amount` of the position's debt from the owner's user account
+        ///
+        /// Accounts: `[position, user_account, owner, program_state, treasury, program_config,
+        /// ...optional]` (the owner's limits and attestation accounts, as for `TransferWithFee`).
+        Repay {
+            amount: u64,
+        },
+        /// Repay part of an unhealthy position's debt from the liquidator's user account in
+        /// exchange for its collateral plus the liquidation bonus
+        ///
+        /// Debt left once the collateral is exhausted is absorbed by the insurance fund,
+        /// which must then be passed as a trailing account, and the rest written off.
+        ///
+        /// Accounts: `[position, liquidator_account, liquidator, lending_config, rate_state, program_state, treasury,
+        /// program_config, ...optional]` (the insurance fund and the liquidator's limits and attestation accounts).
+        Liquidate {
+            repay_amount: u64,
+        },
+        /// Set the insurance fund authority and the share of transfer and swap fees paid
+        /// into the fund; program authority only
+        ///
+        /// Accounts: `[insurance_fund, program_state, authority, system_program]`. The first
+        /// configuration creates the PDA, paid for by the authority.
+        ConfigureInsuranceFund {
+            #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+            authority: Pubkey,
+            fee_share_bps: u16,
+        },
+        /// Pay `amount` out of the insurance fund to a user account; insurance authority only
+        ///
+        /// Accounts: `[insurance_fund, recipient_account, authority]`.
+        InsurancePayout {
+            amount: u64,
+        },
+        /// Set how closed epochs' fees are split between stakers, the insurance fund and the
+        /// treasury; the shares must add up to 100%. Treasury governance only.
+        ///
+        /// Accounts: `[fee_schedule, treasury, governance, system_program]`. The first
+        /// configuration creates the PDA, paid for by the governance.
+        ConfigureFeeSchedule {
+            stakers_bps: u16,
+            insurance_bps: u16,
+            treasury_bps: u16,
+        },
+        /// Split the fees the treasury accrued in past epochs per the fee schedule; anyone
+        /// may call
+        ///
+        /// Accounts: `[treasury, fee_schedule, insurance_fund, program_state]`. The stakers'
+        /// share is added to the reward accumulator and claimed with `ClaimRewards`.
+        DistributeEpochFees,
+        /// Set when idle user accounts may be flagged dormant and closed, and the cranker's
+        /// share of their rent; program authority only. Zero `inactivity_slots` disables
+        /// reclamation.
+        ///
+        /// Accounts: `[reclaim_policy, program_state, authority, system_program]`. The first
+        /// configuration creates the PDA, paid for by the authority.
+        ConfigureReclaimPolicy {
+            inactivity_slots: u64,
+            grace_slots: u64,
+            cranker_share_bps: u16,
+        },
+        /// Flag an empty user account idle for the policy's inactivity period as dormant,
+        /// starting its grace window; anyone may call
+        ///
+        /// Accounts: `[user_account, reclaim_policy]`.
+        FlagDormantAccount,
+        /// Close a dormant user account whose grace window has passed, splitting its rent
+        /// between the owner and the cranker; anyone may call
+        ///
+        /// Accounts: `[user_account, reclaim_policy, owner, cranker]`.
+        ReclaimDormantAccount,
+        /// Apply up to `batch::MAX_OPS` transfers, approvals and closes signed once by the
+        /// owner; if any op fails, none take effect
+        ///
+        /// Accounts: `[owner, program_state, metrics, program_config, ...account table]`. Ops
+        /// name their accounts by index into the table, which also carries the accounts
+        /// transfers find by address (the treasury, required when the batch transfers; limits,
+        /// attestation, insurance fund and daily stats, as for `TransferWithFee`), and
+        /// `system_program` when an approval or the day's stats create their PDA.
+        Batch {
+            ops: Vec<batch::SubOp>,
+        },
+        /// Start filling a freshly allocated, program-owned account too large for one
+        /// transaction; the signer becomes the upload authority
+        ///
+        /// Accounts: `[upload, target, authority, system_program]`. The target signs, so it can't
+        /// be a PDA of this program, and its data must still be zeroed. The authority pays for
+        /// the upload PDA.
+        BeginUpload,
+        /// Copy `bytes` into the target at `offset`; chunks may arrive in any order and be rewritten
+        ///
+        /// Accounts: `[upload, target, authority]`.
+        WriteChunk {
+            offset: u32,
+            bytes: Vec<u8>,
+        },
+        /// Seal the upload once the target's data hashes (`hash_account_data`) to
+        /// `expected_hash`; no chunk can be written afterwards
+        ///
+        /// Accounts: `[upload, target, authority]`.
+        FinalizeUpload {
+            expected_hash: [u8; 32],
+        },
+        /// Initialize the spl-account-compression tree holding compressed user records, with
+        /// the program authority PDA as its tree authority; program authority only
+        ///
+        /// Accounts: `[compressed_tree, program_state, authority, merkle_tree, program_authority,
+        /// compression_program, noop_program, system_program]`. The client allocates `merkle_tree`,
+        /// owned by the compression program, with the size `max_depth` and `max_buffer_size`
+        /// require; the authority pays for the compressed tree PDA.
+        InitCompressedTree {
+            max_depth: u32,
+            max_buffer_size: u32,
+        },
+        /// Append an empty `CompressedUserRecord` for the signer to the compressed tree, the
+        /// rent-free alternative to `CreateUserAccount`. The record and its leaf index are
+        /// emitted as a `CompressedRecordEvent` for indexers to serve proofs from.
+        ///
+        /// Accounts: `[compressed_tree, owner, program_state, merkle_tree, program_authority,
+        /// compression_program, noop_program, program_config, ...optional]`. `pow_nonce` and,
+        /// under `ALLOWLIST_GATING`, the owner's trailing `AllowlistEntry` are checked as for
+        /// `CreateUserAccount`.
+        CreateCompressedUserAccount {
+            pow_nonce: u64,
+        },
+        /// Move the signer's compressed record at `leaf_index` into their user account PDA,
+        /// emptying the leaf; the proof is checked against `root`
+        ///
+        /// Accounts: `[compressed_tree, user_account, owner, program_state, merkle_tree,
+        /// program_authority, compression_program, noop_program, system_program, ...proof]`, one
+        /// proof account per tree level, leaf level first. The owner pays for the user account
+        /// PDA if it does not exist; an existing one must be zeroed.
+        DecompressUserRecord {
+            root: [u8; 32],
+            record: compression::CompressedUserRecord,
+            leaf_index: u32,
+        },
+        /// Append the signer's user account to the compressed tree as a record and close it,
+        /// refunding its rent to the owner
+        ///
+        /// Accounts: `[compressed_tree, user_account, owner, program_state, merkle_tree,
+        /// program_authority, compression_program, noop_program]`.
+        CompressUserAccount,
+        /// Close the signer's empty user account, zeroing its data and refunding its rent to
+        /// `destination`
+        ///
+        /// Accounts: `[user_account, owner, destination, program_state]`. A remaining balance
+        /// must be moved out first; a `Batch` of `Transfer` then `Close` sweeps and closes atomically.
+        CloseUserAccount,
+        /// Allow `delegate` to transfer up to `amount` in total out of the owner's user account
+        /// with `TransferFrom`, replacing any earlier approval; a `SetDelegation` with the
+        /// transfer scope and no expiry
+        ///
+        /// Accounts: as for `SetDelegation`.
+        Approve {
+            delegate: Pubkey,
+            amount: u64,
+        },
+        /// Withdraw the approval of `delegate`, as `RevokeDelegation`
+        ///
+        /// Accounts: as for `RevokeDelegation`.
+        Revoke {
+            delegate: Pubkey,
+        },
+        /// Transfer as the owner's approved delegate, deducting `amount` from its allowance
+        ///
+        /// Accounts: `[from, to, delegate, program_state, metrics, program_config, delegations,
+        /// ...trailing]`, where `delegations` is the sender's delegations PDA and the trailing
+        /// accounts are those of `TransferWithFee`, the treasury included. Session keys are
+        /// not accepted.
+        TransferFrom {
+            amount: u64,
+            fee_basis_points: u16,
+        },
+        /// Move `total` from the grantor's user account into a vesting schedule for
+        /// `beneficiary`, released linearly over `duration` seconds from `start_ts` once
+        /// `cliff` seconds have passed
+        ///
+        /// Accounts: `[vesting, grantor_account, grantor, program_state, program_config,
+        /// ...optional]` (the grantor's limits and attestation accounts, as for
+        /// `TransferWithFee`, and `system_program`). The vesting PDA is derived from the grantor
+        /// and beneficiary wallets and paid for by the grantor.
+        CreateVesting {
+            beneficiary: Pubkey,
+            total: u64,
+            start_ts: i64,
+            cliff: u64,
+            duration: u64,
+        },
+        /// Credit the beneficiary's user account with everything vested but not yet claimed
+        ///
+        /// Accounts: `[vesting, beneficiary_account, beneficiary, program_state]`.
+        ClaimVested,
+        /// Open an empty escrow from the payer to `counterparty` (a wallet), released by
+        /// `arbiter` or by both parties, and refundable to the payer from `expires_at`
+        ///
+        /// Accounts: `[escrow, payer, program_state, system_program]`. The escrow lives at the
+        /// payer's vault PDA for `escrow_id`, paid for by the payer; pass the default pubkey as
+        /// `arbiter` for mutual release only.
+        CreateEscrow {
+            escrow_id: u64,
+            counterparty: Pubkey,
+            arbiter: Pubkey,
+            expires_at: i64,
+        },
+        /// Move `amount` from the payer's user account into an open escrow
+        ///
+        /// Accounts: `[escrow, payer_account, payer, program_state, program_config, ...optional]`
+        /// (the payer's limits and attestation accounts, as for `TransferWithFee`).
+        FundEscrow {
+            amount: u64,
+        },
+        /// Pay the escrowed balance to the counterparty's user account, signed by the arbiter
+        /// or by both the payer and the counterparty
+        ///
+        /// Accounts: `[escrow, counterparty_account, program_state, ...signers]`.
+        ReleaseEscrow,
+        /// Refund the escrowed balance to the payer once the escrow has expired
+        ///
+        /// Accounts: `[escrow, payer_account, payer, program_state]`.
+        CancelEscrow,
+        /// Accrue staking rewards up to now at the old rate, then set the rewards emitted per
+        /// second across all stakers; authority only
+        ///
+        /// Accounts: `[program_state, authority]`.
+        SetRewardRate {
+            reward_rate: u64,
+        },
+        /// Move `amount` from the owner's user account into their stake PDA
+        ///
+        /// Accounts: `[stake, user_account, owner, program_state, program_config, ...optional]`
+        /// (the owner's limits and attestation accounts, as for `TransferWithFee`, and
+        /// `system_program` when the owner's first stake creates the stake PDA).
+        Stake {
+            amount: u64,
+        },
+        /// Return `amount` of the owner's stake to their user account, keeping the rewards
+        /// earned so far claimable
+        ///
+        /// Accounts: `[stake, user_account, owner, program_state]`.
+        Unstake {
+            amount: u64,
+        },
+        /// Credit the owner's user account with the staking rewards earned so far
+        ///
+        /// Accounts: `[stake, user_account, owner, program_state]`.
+        ClaimRewards,
+        /// Grow a user account in any older layout to the current one, topping up rent from
+        /// the owner; `MigrateUserAccountV2` only accepts v1 accounts
+        ///
+        /// Accounts: `[user_account, owner, system_program, program_state]`. The owner signs
+        /// and pays.
+        ReallocUserAccount,
+        /// Freeze a user account so no transfer can move tokens out of or into it; authority only
+        ///
+        /// Accounts: `[user_account, program_state, authority]`.
+        FreezeAccount,
+        /// Lift a freeze set by `FreezeAccount`; authority only
+        ///
+        /// Accounts: `[user_account, program_state, authority]`.
+        ThawAccount,
+        /// Set how many seconds withdrawals wait between request and execution; authority only
+        ///
+        /// Accounts: `[program_state, authority]`.
+        SetWithdrawalDelay {
+            delay: u64,
+        },
+        /// Move `amount` out of the owner's user account into their pending withdrawal,
+        /// executable once the withdrawal delay has passed
+        ///
+        /// Accounts: `[withdrawal, user_account, owner, program_state, program_config,
+        /// ...optional]` (the owner's limits and attestation accounts, as for `TransferWithFee`,
+        /// and `system_program` when the withdrawal PDA does not exist yet). One withdrawal may
+        /// be pending per owner.
+        RequestWithdrawal {
+            amount: u64,
+        },
+        /// Complete a pending withdrawal whose delay has passed, crediting its amount to
+        /// `destination`, any unfrozen user account
+        ///
+        /// Accounts: `[withdrawal, destination, owner, program_state]`.
+        ExecuteWithdrawal,
+        /// Sweep the fees of closed epochs out of the treasury into `destination`, instead of
+        /// splitting them with `DistributeEpochFees`; authority only
+        ///
+        /// Accounts: `[treasury, destination, program_state, authority]`.
+        CollectFees,
+        /// Let `wallet` create a user account while `ALLOWLIST_GATING` is on; authority only
+        ///
+        /// Accounts: `[allowlist_entry, program_state, authority, system_program]`. The authority
+        /// pays for the entry PDA the first time `wallet` is added.
+        AddToAllowlist {
+            wallet: Pubkey,
+        },
+        /// Revoke an allowlist entry; existing user accounts of `wallet` are unaffected;
+        /// authority only
+        ///
+        /// Accounts: `[allowlist_entry, program_state, authority]`.
+        RemoveFromAllowlist {
+            wallet: Pubkey,
+        },
+        /// Upgrade the program state or a user account stored in an older layout in place,
+        /// growing it with the newer fields at their defaults and stamping the current version
+        ///
+        /// Accounts: `[account, payer, system_program]`. The program state's payer must be its
+        /// authority; a user account's must be its owner, as for `ReallocUserAccount`.
+        MigrateAccount,
+        /// Abandon a pending withdrawal before or after its delay, returning its amount to
+        /// the owner's user account
+        ///
+        /// Accounts: `[withdrawal, user_account, owner, program_state]`.
+        CancelWithdrawal,
+    }
+
+    impl ProgramInstruction {
+        /// Decode versioned instruction data, dispatching on the leading version byte
+        pub fn unpack(instruction_data: &[u8]) -> Result<Self, ProgramError> {
+            let (&version, payload) = instruction_data
+                .split_first()
+                .ok_or(ProgramError::InvalidInstructionData)?;
+            
+            match version {
+                INSTRUCTION_VERSION_V1 => Self::unpack_v1(payload),
+                INSTRUCTION_VERSION_V2 => Self::try_from_slice(payload)
+                    .map_err(|_| ProgramError::InvalidInstructionData),
+                _ => {
+                    log_error!("Unsupported instruction version", version = version);
+                    Err(ProgramError::Custom(UNSUPPORTED_INSTRUCTION_VERSION))
+                }
+            }
+        }
+
+        /// Encode the instruction with the current version prefix
+        pub fn pack(&self) -> Result<Vec<u8>, ProgramError> {
+            let mut data = vec![CURRENT_INSTRUCTION_VERSION];
+            data.extend(self.try_to_vec()
+                .map_err(|_| ProgramError::InvalidInstructionData)?);
+            Ok(data)
+        }
+
+        /// Version 1 payloads share the current layout except for instructions that
+        /// gained fields since; those are decoded with their legacy layout and mapped
+        /// onto the current variant with defaults.
+        fn unpack_v1(payload: &[u8]) -> Result<Self, ProgramError> {
+            match payload.split_first() {
+                Some((&TRANSFER_WITH_FEE_TAG, args)) => {
+                    let legacy = TransferWithFeeV1::try_from_slice(args)
+                        .map_err(|_| ProgramError::InvalidInstructionData)?;
+                    Ok(Self::TransferWithFee {
+                        amount: legacy.amount,
+                        fee_basis_points: legacy.fee_basis_points,
+                        idempotency_key: None,
+                    })
+                }
+                _ => Self::try_from_slice(payload)
+                    .map_err(|_| ProgramError::InvalidInstructionData),
+            }
+        }
+
+        /// Whether the instruction is refused while the program is paused. Governance,
+        /// configuration, oracle and incident-response instructions stay available;
+        /// anything not listed here, including new instructions, is pausable.
+        pub fn is_pausable(&self) -> bool {
+            !matches!(
+                self,
+                Self::Initialize { .. }
+                    | Self::RotateAuthority { .. }
+                    | Self::SetEmergencyGuardian { .. }
+                    | Self::Pause
+                    | Self::Unpause
+                    | Self::SetPowDifficulty { .. }
+                    | Self::UpdateConfig { .. }
+                    | Self::SetFeatureFlag { .. }
+                    | Self::CreateMultisig { .. }
+                    | Self::CreateProposal { .. }
+                    | Self::ApproveProposal { .. }
+                    | Self::ExecuteProposal { .. }
+                    | Self::ConfigureTreasury { .. }
+                    | Self::TreasuryTransfer { .. }
+                    | Self::TreasuryInvest { .. }
+                    | Self::ConfigureCompliance { .. }
+                    | Self::SetAccountLimits { .. }
+                    | Self::IssueAttestation { .. }
+                    | Self::SetAccountFlagged { .. }
+                    | Self::Clawback { .. }
+                    | Self::SetInterestRate { .. }
+                    | Self::SetRewardRate { .. }
+                    | Self::FreezeAccount
+                    | Self::ThawAccount
+                    | Self::SetWithdrawalDelay { .. }
+                    | Self::CollectFees
+                    | Self::AddToAllowlist { .. }
+                    | Self::RemoveFromAllowlist { .. }
+                    | Self::MigrateAccount
+                    | Self::ConfigureRateState { .. }
+                    | Self::UpdateRate { .. }
+                    | Self::ConfigureLending { .. }
+                    | Self::ConfigureInsuranceFund { .. }
+                    | Self::InsurancePayout { .. }
+                    | Self::ConfigureFeeSchedule { .. }
+                    | Self::ConfigureReclaimPolicy { .. }
+                    | Self::InitCompressedTree { .. }
+                    | Self::Extension { .. }
+            )
+        }
+    }
+}
+
+/// Registry of optional instruction handlers reached through `ProgramInstruction::Extension`
+///
+/// Each handler is compiled in by its own cargo feature, so a deployment only carries the
+/// extensions it enables while the core instruction encoding stays unchanged.
+pub mod extensions {
+    use super::*;
+
+    /// Signature shared by every extension handler
+    pub type ExtensionHandler = fn(&Pubkey, &[AccountInfo], &[u8]) -> ProgramResult;
+
+    /// A registered extension
+    pub struct Extension {
+        pub id: u16,
+        pub name: &'static str,
+        pub handler: ExtensionHandler,
+    }
+
+    /// Extension id of the memo handler
+    pub const MEMO_EXTENSION_ID: u16 = 1;
+
+    /// Extensions compiled into this build
+    pub const REGISTRY: &[Extension] = &[
+        #[cfg(feature = "ext-memo")]
+        Extension {
+            id: MEMO_EXTENSION_ID,
+            name: "memo",
+            handler: process_memo,
+        },
+    ];
+
+    /// Look up a registered extension by id
+    pub fn find(id: u16) -> Option<&'static Extension> {
+        REGISTRY.iter().find(|extension| extension.id == id)
+    }
+
+    /// Route an extension instruction to its handler, rejecting ids not compiled in
+    pub fn dispatch(
+        id: u16,
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        payload: &[u8],
+    ) -> ProgramResult {
+        let extension = find(id).ok_or_else(|| {
+            log_error!("Extension is not enabled in this deployment", id = id);
+            ProgramError::InvalidInstructionData
+        })?;
+        
+        msg!("Extension: {}", extension.name);
+        (extension.handler)(program_id, accounts, payload)
+    }
+
+    /// Log a UTF-8 memo signed by the first account
+    #[cfg(feature = "ext-memo")]
+    fn process_memo(
+        _program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        payload: &[u8],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let signer_info = next_account_info(account_info_iter)?;
+        
+        account_validation::validate_signer(signer_info)
+            .map_err(|_| ProgramError::MissingRequiredSignature)?;
+        
+        let memo = std::str::from_utf8(payload)
+            .map_err(|_| ProgramError::InvalidInstructionData)?;
+        
+        msg!("Memo from {}: {}", signer_info.key, memo);
+        Ok(())
+    }
+}
+
+/// Crank framework shared by permissionless maintenance tasks (scheduled transfers,
+/// expired-account reaping, oracle refresh): cranker registration, bounty pool
+/// accounting and task readiness checks
+pub mod crank {
+    use super::*;
+
+    /// Kinds of crankable tasks, each with its own pool bounty
+    #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+    pub enum CrankTask {
+        ScheduledTransfer,
+        AccountReaping,
+        OracleRefresh,
+    }
+
+    /// Number of `CrankTask` kinds
+    pub const CRANK_TASK_COUNT: usize = 3;
+
+    /// Registration and lifetime stats of a cranker
+    #[derive(BorshSerialize, BorshDeserialize, Debug, ProgramAccount)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+    #[seeds(CRANKER_SEED, authority)]
+    #[space(auto)]
+    pub struct Cranker {
+        #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+        pub authority: Pubkey,
+        pub registered_at: i64,
+        pub tasks_completed: u64,
+        pub bounties_earned: u64,
+    }
+
+    impl Cranker {
+        /// Account for one completed task
+        pub fn record_task(&mut self, bounty: u64) {
+            self.tasks_completed = self.tasks_completed.saturating_add(1);
+            self.bounties_earned = self.bounties_earned.saturating_add(bounty);
+        }
+    }
+
+    /// Tokens set aside to reward crankers, with a bounty per task kind
+    #[derive(BorshSerialize, BorshDeserialize, Debug, Default, ProgramAccount)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+    #[seeds(BOUNTY_POOL_SEED)]
+    #[space(auto)]
+    pub struct BountyPool {
+        pub balance: u64,
+        pub bounty_per_task: [u64; CRANK_TASK_COUNT],
+        pub total_paid: u64,
+    }
+
+    impl BountyPool {
+        /// Deduct the bounty for one completed `task`, returning the amount paid; a pool
+        /// running low pays out what it holds instead of holding up the task
+        pub fn pay(&mut self, task: CrankTask) -> u64 {
+            let bounty = self.bounty_per_task[task as usize].min(self.balance);
+            self.balance -= bounty;
+            self.total_paid = self.total_paid.saturating_add(bounty);
+            bounty
+        }
+    }
+
+    /// Check that a task scheduled for `ready_at` may run at `now`
+    pub fn validate_task_ready(ready_at: i64, now: i64) -> CommonResult<()> {
+        if now < ready_at {
+            return Err(CommonError::Custom(
+                format!("Task not ready for another {} seconds", ready_at - now)
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Weighted multisig whose PDA signs arbitrary instructions of this program once a
+/// proposal wrapping them collects enough approvals
+pub mod multisig {
+    use super::*;
+
+    /// Most signers a multisig can have (approvals are tracked in a `u16` bitmap)
+    pub const MAX_SIGNERS: usize = 10;
+
+    /// Multisig membership, per-signer voting weight and weight threshold
+    #[derive(BorshSerialize, BorshDeserialize, Debug, Default)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+    pub struct Multisig {
+        #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+        pub creator: Pubkey,
+        pub multisig_id: u64,
+        pub bump: u8,
+        pub signer_count: u8,
+        #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey::array"))]
+        pub signers: [Pubkey; MAX_SIGNERS],
+        pub weights: [u16; MAX_SIGNERS],
+        pub threshold: u16,
+        pub proposal_count: u64,
+    }
+
+    impl Multisig {
+        /// Validate membership, weights and threshold; empty `weights` gives each signer weight 1
+        pub fn new(
+            creator: Pubkey,
+            multisig_id: u64,
+            bump: u8,
+            signers: &[Pubkey],
+            weights: &[u16],
+            threshold: u16,
+        ) -> CommonResult<Self> {
+            if signers.is_empty() || signers.len() > MAX_SIGNERS {
+                return Err(CommonError::Custom(format!("Multisig needs 1 to {} signers", MAX_SIGNERS)));
+            }
+            
+            if !weights.is_empty() && weights.len() != signers.len() {
+                return Err(CommonError::Custom("Expected one weight per signer".to_string()));
+            }
+            
+            if weights.contains(&0) {
+                return Err(CommonError::Custom("Signer weights must be nonzero".to_string()));
+            }
+            
+            let total_weight: u32 = if weights.is_empty() {
+                signers.len() as u32
+            } else {
+                weights.iter().map(|&weight| weight as u32).sum()
+            };
+            if threshold == 0 || threshold as u32 > total_weight {
+                return Err(CommonError::Custom("Threshold must be between 1 and the total weight".to_string()));
+            }
+            
+            if signers.iter().enumerate().any(|(i, signer)| signers[..i].contains(signer)) {
+                return Err(CommonError::Custom("Duplicate multisig signer".to_string()));
+            }
+            
+            let mut multisig = Self {
+                creator,
+                multisig_id,
+                bump,
+                signer_count: signers.len() as u8,
+                threshold,
+                ..Self::default()
+            };
+            multisig.signers[..signers.len()].copy_from_slice(signers);
+            if weights.is_empty() {
+                multisig.weights[..signers.len()].fill(1);
+            } else {
+                multisig.weights[..signers.len()].copy_from_slice(weights);
+            }
+            Ok(multisig)
+        }
+
+        /// Position of `key` among the signers
+        pub fn signer_index(&self, key: &Pubkey) -> Option<usize> {
+            self.signers[..self.signer_count as usize].iter().position(|signer| signer == key)
+        }
+
+        /// Combined weight of the signers set in an approval bitmap
+        pub fn approved_weight(&self, approvals: u16) -> u32 {
+            (0..self.signer_count as usize)
+                .filter(|&index| approvals & (1 << index) != 0)
+                .map(|index| self.weights[index] as u32)
+                .sum()
+        }
+
+        /// Whether an approval bitmap meets the weight threshold
+        pub fn is_approved(&self, approvals: u16) -> bool {
+            self.approved_weight(approvals) >= self.threshold as u32
+        }
+    }
+
+    /// Account of a proposed instruction
+    #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+    pub struct ProposalAccount {
+        #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+        pub pubkey: Pubkey,
+        pub is_signer: bool,
+        pub is_writable: bool,
+    }
+
+    /// A proposed instruction awaiting approvals
+    #[derive(BorshSerialize, BorshDeserialize, Debug)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+    pub struct Proposal {
+        #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+        pub multisig: Pubkey,
+        pub index: u64,
+        #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+        pub proposer: Pubkey,
+        pub instruction_data: Vec<u8>,
+        pub accounts: Vec<ProposalAccount>,
+        pub approvals: u16,
+        pub executed: bool,
+    }
+
+    impl Proposal {
+        /// Account size for a proposal of the given instruction
+        pub fn space(instruction_data_len: usize, account_count: usize) -> usize {
+            32 + 8 + 32 + 4 + instruction_data_len + 4 + account_count * (32 + 2) + 2 + 1
+        }
+
+        pub fn approve(&mut self, signer_index: usize) {
+            self.approvals |= 1 << signer_index;
+        }
+
+        /// The wrapped instruction, addressed to this program
+        pub fn instruction(&self, program_id: &Pubkey) -> Instruction {
+            Instruction {
+                program_id: *program_id,
+                accounts: self.accounts.iter()
+                    .map(|account| AccountMeta {
+                        pubkey: account.pubkey,
+                        is_signer: account.is_signer,
+                        is_writable: account.is_writable,
+                    })
+                    .collect(),
+                data: self.instruction_data.clone(),
+            }
+        }
+    }
+
+    /// Load a multisig, verifying its PDA
+    pub fn load_multisig(
+        program_id: &Pubkey,
+        multisig_info: &AccountInfo,
+    ) -> Result<Multisig, ProgramError> {
+        let multisig = account_data::deserialize_account_data::<Multisig>(multisig_info)
+            .map_err(|_| ProgramError::InvalidAccountData)?;
+        
+        let (expected_pda, _bump) = account_creation::create_multisig_pda(&multisig.creator, multisig.multisig_id, program_id)
+            .map_err(|_| ProgramError::InvalidSeeds)?;
+        
+        if multisig_info.key != &expected_pda || multisig.signer_count == 0 {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        
+        Ok(multisig)
+    }
+
+    /// Load a proposal of `multisig`, verifying its PDA
+    pub fn load_proposal(
+        program_id: &Pubkey,
+        proposal_info: &AccountInfo,
+        multisig: &Pubkey,
+    ) -> Result<Proposal, ProgramError> {
+        let proposal = account_data::deserialize_account_data::<Proposal>(proposal_info)
+            .map_err(|_| ProgramError::InvalidAccountData)?;
+        
+        let (expected_pda, _bump) = account_creation::create_proposal_pda(multisig, proposal.index, program_id)
+            .map_err(|_| ProgramError::InvalidSeeds)?;
+        
+        if proposal_info.key != &expected_pda || proposal.multisig != *multisig {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        
+        Ok(proposal)
+    }
+}
+
+/// Several operations of one owner signed once and applied atomically, their accounts
+/// named by index into an account table shared by the whole batch
+// The schema derive declares a struct per `SubOp` variant whose fields it never reads
+#[cfg_attr(feature = "schema", allow(dead_code))]
+pub mod batch {
+    use super::*;
+
+    /// Most operations a single `Batch` may carry
+    pub const MAX_OPS: usize = 16;
+
+    /// One operation of a `Batch`; every `u8` field indexes the batch's account table
+    #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+    pub enum SubOp {
+        /// Move `amount` plus the fee from one of the owner's user accounts, as `TransferWithFee`
+        Transfer {
+            from: u8,
+            to: u8,
+            amount: u64,
+            fee_basis_points: u16,
+        },
+        /// Add or replace a delegate entry in the owner's delegations PDA, as `SetDelegation`
+        Approve {
+            delegations: u8,
+            #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+            delegate: Pubkey,
+            scope: u32,
+            amount_cap: u64,
+            expires_at: i64,
+        },
+        /// Close an empty user account of the owner, sending its rent to `destination`
+        Close {
+            account: u8,
+            destination: u8,
+        },
+    }
+
+    /// Resolve an account table index
+    pub fn account<'a, 'b>(table: &'b [AccountInfo<'a>], index: u8) -> Result<&'b AccountInfo<'a>, ProgramError> {
+        table.get(index as usize).ok_or(ProgramError::NotEnoughAccountKeys)
+    }
+}
+
+/// Chunked population of accounts larger than one transaction can carry (Merkle root lists,
+/// config tables): an `Upload` PDA per target tracks the writer and seals the target once
+/// its contents match the hash the writer commits to
+pub mod upload {
+    use super::*;
+
+    /// Progress of an upload into `target`
+    #[derive(BorshSerialize, BorshDeserialize, Debug, Default, ProgramAccount)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+    #[seeds(UPLOAD_SEED, target)]
+    #[space(auto)]
+    pub struct Upload {
+        #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+        pub authority: Pubkey,
+        #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+        pub target: Pubkey,
+        pub chunks_written: u32,
+        pub finalized: bool,
+        /// `hash_account_data` of the target's contents, set on finalization
+        pub data_hash: [u8; 32],
+    }
+
+    impl Upload {
+        /// Check that `authority` may still write to the upload
+        pub fn validate_writer(&self, authority: &Pubkey) -> CommonResult<()> {
+            if self.authority == Pubkey::default() {
+                return Err(CommonError::Custom("Upload has not begun".to_string()));
+            }
+            if self.authority != *authority {
+                return Err(CommonError::InsufficientPermissions);
+            }
+            if self.finalized {
+                return Err(CommonError::Custom("Upload is finalized".to_string()));
+            }
+            Ok(())
+        }
+    }
+
+    /// Load the upload of `target_info`, requiring it finalized; readers of uploaded
+    /// accounts call this before trusting their contents
+    pub fn load_finalized(
+        program_id: &Pubkey,
+        upload_info: &AccountInfo,
+        target_info: &AccountInfo,
+    ) -> Result<Upload, ProgramError> {
+        let upload = Upload::load(program_id, upload_info, target_info.key)?;
+        
+        if !upload.finalized {
+            log_error!("Upload is not finalized", target = target_info.key);
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(upload)
+    }
+}
+
+/// Enumerable user registry: `CreateUserAccount` appends each new user to fixed-capacity pages
+/// at consecutive indices, so clients can list every user without a program-wide account scan
+pub mod registry {
+    use super::*;
+
+    /// Users recorded per page
+    pub const PAGE_CAPACITY: usize = 32;
+
+    /// Wallets of up to `PAGE_CAPACITY` users in creation order; the next page is at `index + 1`
+    #[derive(BorshSerialize, BorshDeserialize, Debug, Default, ProgramAccount)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+    #[seeds(REGISTRY_PAGE_SEED, index)]
+    #[space(auto)]
+    pub struct RegistryPage {
+        pub index: u32,
+        pub count: u32,
+        #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey::array"))]
+        pub users: [Pubkey; PAGE_CAPACITY],
+    }
+
+    impl RegistryPage {
+        /// Recorded users, oldest first
+        pub fn entries(&self) -> &[Pubkey] {
+            &self.users[..(self.count as usize).min(PAGE_CAPACITY)]
+        }
+
+        pub fn is_full(&self) -> bool {
+            self.count as usize >= PAGE_CAPACITY
+        }
+    }
+
+    /// Index of the page recording the user created when `unique_users` was `position`
+    pub fn page_index(position: u64) -> CommonResult<u32> {
+        u32::try_from(position / PAGE_CAPACITY as u64).map_err(|_| CommonError::InvalidCalculation)
+    }
+
+    /// Append `user` to the page for `position`; the page is created, funded by `payer_info`,
+    /// on its first user
+    pub fn append_user<'a>(
+        program_id: &Pubkey,
+        payer_info: &AccountInfo<'a>,
+        page_info: &AccountInfo<'a>,
+        accounts: &[AccountInfo<'a>],
+        position: u64,
+        user: &Pubkey,
+    ) -> ProgramResult {
+        let index = page_index(position)?;
+        if page_info.data_len() == 0 {
+            let (expected_pda, bump) = RegistryPage::find_address(index, program_id)
+                .map_err(|_| ProgramError::InvalidSeeds)?;
+            
+            if page_info.key != &expected_pda {
+                return Err(ProgramError::InvalidSeeds);
+            }
+            
+            create_pda_account(
+                program_id,
+                payer_info,
+                page_info,
+                accounts,
+                RegistryPage::SIZE,
+                &[REGISTRY_PAGE_SEED, &index.to_le_bytes(), &[bump]],
+            )?;
+        }
+        let mut page = RegistryPage::load(program_id, page_info, index)?;
+        
+        if page.is_full() {
+            log_error!("Registry page is full", index = index);
+            return Err(ProgramError::InvalidAccountData);
+        }
+        
+        page.index = index;
+        page.users[page.count as usize] = *user;
+        page.count += 1;
+        page.save(page_info)
+    }
+}
+
+/// State compression: user records kept as leaves of an spl-account-compression concurrent
+/// Merkle tree instead of one rent-paying account each. The program authority PDA is the
+/// tree authority, so leaves only change through this program's CPIs, and proofs are checked
+/// with `merkle` before they are handed to the compression program.
+pub mod compression {
+    use super::*;
+    use solana_program::keccak;
+
+    /// The spl-account-compression program
+    pub const ACCOUNT_COMPRESSION_PROGRAM_ID: Pubkey = solana_program::pubkey!("cmtDvXumGCrqC1Age74AVPhSRVXJMd8PJS91L8KbNCK");
+
+    // Anchor instruction discriminators: sha256("global:<name>")[..8]
+    const INIT_EMPTY_MERKLE_TREE_DISCRIMINATOR: [u8; 8] = [191, 11, 119, 7, 180, 107, 220, 110];
+    const APPEND_DISCRIMINATOR: [u8; 8] = [149, 120, 18, 222, 236, 225, 88, 203];
+    const REPLACE_LEAF_DISCRIMINATOR: [u8; 8] = [204, 165, 76, 100, 73, 147, 0, 128];
+
+    /// Deepest tree supported; proofs are passed in full, so the depth bounds the proof accounts
+    pub const MAX_TREE_DEPTH: u32 = 24;
+
+    /// The program's compressed tree and its append cursor
+    #[derive(BorshSerialize, BorshDeserialize, Debug, Default, ProgramAccount)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+    #[seeds(COMPRESSED_TREE_SEED)]
+    #[space(auto)]
+    pub struct CompressedTree {
+        #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+        pub merkle_tree: Pubkey,
+        pub max_depth: u32,
+        pub max_buffer_size: u32,
+        /// Bump of the program authority PDA, the tree authority
+        pub authority_bump: u8,
+        /// Index the next appended leaf lands at
+        pub next_leaf_index: u32,
+    }
+
+    impl CompressedTree {
+        pub fn is_initialized(&self) -> bool {
+            self.merkle_tree != Pubkey::default()
+        }
+
+        /// Leaves the tree can hold
+        pub fn capacity(&self) -> u64 {
+            1u64 << self.max_depth
+        }
+
+        /// Reserve the next leaf index
+        pub fn claim_leaf_index(&mut self) -> CommonResult<u32> {
+            if self.next_leaf_index as u64 >= self.capacity() {
+                return Err(CommonError::Custom("Compressed tree is full".to_string()));
+            }
+            let index = self.next_leaf_index;
+            self.next_leaf_index += 1;
+            Ok(index)
+        }
+    }
+
+    /// A user record stored as a tree leaf: the `UserAccount` fields that can't be re-derived
+    #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+    pub struct CompressedUserRecord {
+        #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+        pub owner: Pubkey,
+        pub balance: u64,
+        pub flags: u32,
+        pub nonce: u64,
+    }
+
+    impl CompressedUserRecord {
+        /// Record of a user account's owner, balance, flags and nonce
+        pub fn from_user_account(user_account: &UserAccount) -> Self {
+            Self {
+                owner: user_account.owner,
+                balance: user_account.balance,
+                flags: user_account.flags,
+                nonce: user_account.nonce,
+            }
+        }
+
+        /// Tree leaf committing to the record: keccak of its Borsh encoding
+        pub fn leaf(&self) -> Result<[u8; 32], ProgramError> {
+            let data = self.try_to_vec()
+                .map_err(|_| ProgramError::BorshIoError("Failed to serialize compressed record".to_string()))?;
+            Ok(keccak::hash(&data).to_bytes())
+        }
+    }
+
+    /// A record written to the tree, logged via `sol_log_data` as `[TAG, borsh(event)]`
+    #[derive(BorshSerialize, BorshDeserialize, Debug)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+    pub struct CompressedRecordEvent {
+        #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+        pub merkle_tree: Pubkey,
+        pub leaf_index: u32,
+        pub record: CompressedUserRecord,
+    }
+
+    impl CompressedRecordEvent {
+        pub const TAG: &'static [u8] = b"compressed_record";
+
+        pub fn log(&self) -> ProgramResult {
+            let data = self.try_to_vec()
+                .map_err(|_| ProgramError::BorshIoError("Failed to serialize compressed record event".to_string()))?;
+            sol_log_data(&[Self::TAG, &data]);
+            Ok(())
+        }
+    }
+
+    /// `init_empty_merkle_tree` of the compression program
+    pub fn init_empty_merkle_tree_instruction(
+        merkle_tree: &Pubkey,
+        authority: &Pubkey,
+        max_depth: u32,
+        max_buffer_size: u32,
+    ) -> Instruction {
+        let mut data = INIT_EMPTY_MERKLE_TREE_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&max_depth.to_le_bytes());
+        data.extend_from_slice(&max_buffer_size.to_le_bytes());
+        modify_instruction(merkle_tree, authority, data, &[])
+    }
+
+    /// `append` of the compression program
+    pub fn append_instruction(merkle_tree: &Pubkey, authority: &Pubkey, leaf: &[u8; 32]) -> Instruction {
+        let mut data = APPEND_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(leaf);
+        modify_instruction(merkle_tree, authority, data, &[])
+    }
+
+    /// `replace_leaf` of the compression program; `proof` holds the sibling nodes as pubkeys
+    pub fn replace_leaf_instruction(
+        merkle_tree: &Pubkey,
+        authority: &Pubkey,
+        root: &[u8; 32],
+        previous_leaf: &[u8; 32],
+        new_leaf: &[u8; 32],
+        index: u32,
+        proof: &[Pubkey],
+    ) -> Instruction {
+        let mut data = REPLACE_LEAF_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(root);
+        data.extend_from_slice(previous_leaf);
+        data.extend_from_slice(new_leaf);
+        data.extend_from_slice(&index.to_le_bytes());
+        modify_instruction(merkle_tree, authority, data, proof)
+    }
+
+    fn modify_instruction(merkle_tree: &Pubkey, authority: &Pubkey, data: Vec<u8>, proof: &[Pubkey]) -> Instruction {
+        let mut accounts = vec![
+            AccountMeta::new(*merkle_tree, false),
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new_readonly(events::NOOP_PROGRAM_ID, false),
+        ];
+        accounts.extend(proof.iter().map(|node| AccountMeta::new_readonly(*node, false)));
+        Instruction { program_id: ACCOUNT_COMPRESSION_PROGRAM_ID, accounts, data }
+    }
+
+    /// Accounts every compression CPI needs, in instruction order
+    pub struct TreeAccounts<'a, 'b> {
+        pub merkle_tree: &'b AccountInfo<'a>,
+        pub program_authority: &'b AccountInfo<'a>,
+        pub compression_program: &'b AccountInfo<'a>,
+        pub noop_program: &'b AccountInfo<'a>,
+    }
+
+    impl<'a, 'b> TreeAccounts<'a, 'b> {
+        /// Take the four accounts from `iter`, checking the program ids
+        pub fn next(iter: &mut std::slice::Iter<'b, AccountInfo<'a>>) -> Result<Self, ProgramError> {
+            let accounts = Self {
+                merkle_tree: next_account_info(iter)?,
+                program_authority: next_account_info(iter)?,
+                compression_program: next_account_info(iter)?,
+                noop_program: next_account_info(iter)?,
+            };
+            
+            if *accounts.compression_program.key != ACCOUNT_COMPRESSION_PROGRAM_ID
+                || *accounts.noop_program.key != events::NOOP_PROGRAM_ID
+            {
+                return Err(ProgramError::IncorrectProgramId);
+            }
+            Ok(accounts)
+        }
+
+        /// Check that these accounts reach the program's tree, returning its authority
+        pub fn validate(&self, program_id: &Pubkey, tree: &CompressedTree) -> Result<ProgramAuthority, ProgramError> {
+            if !tree.is_initialized() || *self.merkle_tree.key != tree.merkle_tree {
+                return Err(ProgramError::InvalidArgument);
+            }
+            
+            let authority = ProgramAuthority::from_bump(program_id, tree.authority_bump)
+                .map_err(|_| ProgramError::InvalidSeeds)?;
+            authority.validate(self.program_authority)
+                .map_err(|_| ProgramError::InvalidSeeds)?;
+            Ok(authority)
+        }
+
+        /// Initialize the tree account, the program authority becoming its authority
+        pub fn init_tree(&self, authority: &ProgramAuthority, max_depth: u32, max_buffer_size: u32) -> ProgramResult {
+            let instruction = init_empty_merkle_tree_instruction(self.merkle_tree.key, &authority.address, max_depth, max_buffer_size);
+            authority.invoke_signed(&instruction, &self.infos(&[]))
+        }
+
+        /// Append `leaf` to the tree
+        pub fn append(&self, authority: &ProgramAuthority, leaf: &[u8; 32]) -> ProgramResult {
+            let instruction = append_instruction(self.merkle_tree.key, &authority.address, leaf);
+            authority.invoke_signed(&instruction, &self.infos(&[]))
+        }
+
+        /// Replace `previous_leaf` at `index` with `new_leaf`, after checking the proof (one
+        /// account per sibling node, leaf level first) places it under `root`
+        #[allow(clippy::too_many_arguments)]
+        pub fn replace_leaf(
+            &self,
+            authority: &ProgramAuthority,
+            tree: &CompressedTree,
+            root: &[u8; 32],
+            previous_leaf: &[u8; 32],
+            new_leaf: &[u8; 32],
+            index: u32,
+            proof_infos: &[AccountInfo<'a>],
+        ) -> ProgramResult {
+            if proof_infos.len() != tree.max_depth as usize {
+                return Err(ProgramError::NotEnoughAccountKeys);
+            }
+            
+            let proof: Vec<[u8; 32]> = proof_infos.iter().map(|node| node.key.to_bytes()).collect();
+            merkle::verify_proof(root, previous_leaf, index, &proof)
+                .map_err(|_| {
+                    log_error!("Invalid Merkle proof", index = index);
+                    ProgramError::InvalidArgument
+                })?;
+            
+            let proof_keys: Vec<Pubkey> = proof_infos.iter().map(|node| *node.key).collect();
+            let instruction = replace_leaf_instruction(
+                self.merkle_tree.key,
+                &authority.address,
+                root,
+                previous_leaf,
+                new_leaf,
+                index,
+                &proof_keys,
+            );
+            authority.invoke_signed(&instruction, &self.infos(proof_infos))
+        }
+
+        fn infos(&self, proof_infos: &[AccountInfo<'a>]) -> Vec<AccountInfo<'a>> {
+            let mut infos = vec![
+                self.merkle_tree.clone(),
+                self.program_authority.clone(),
+                self.noop_program.clone(),
+                self.compression_program.clone(),
+            ];
+            infos.extend_from_slice(proof_infos);
+            infos
+        }
+    }
+}
+
+/// Permissionless reclamation of abandoned user accounts: a zero-balance account idle for
+/// `inactivity_slots` may be flagged dormant by anyone, and once `grace_slots` pass without
+/// activity, closed by anyone with its rent split between the owner and the cranker
+pub mod reclaim {
+    use super::*;
+
+    /// Reclamation parameters; reclamation is off while `inactivity_slots` is zero
+    #[derive(BorshSerialize, BorshDeserialize, Debug, Default, ProgramAccount)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+    #[seeds(RECLAIM_POLICY_SEED)]
+    #[space(auto)]
+    pub struct ReclaimPolicy {
+        /// Slots without activity before an account may be flagged dormant
+        pub inactivity_slots: u64,
+        /// Slots a dormant account stays open for its owner to return
+        pub grace_slots: u64,
+        /// Share of the reclaimed rent paid to the cranker closing the account
+        pub cranker_share_bps: u16,
+    }
+
+    impl ReclaimPolicy {
+        pub fn is_enabled(&self) -> bool {
+            self.inactivity_slots > 0
+        }
+
+        /// Check that `account` may be flagged dormant at `slot`
+        pub fn validate_flag(&self, account: &UserAccount, slot: u64) -> CommonResult<()> {
+            validate_reclaimable(self, account)?;
+            if account.dormant_since_slot != 0 {
+                return Err(CommonError::Custom("Account is already flagged dormant".to_string()));
+            }
+            
+            let idle = slot.saturating_sub(account.last_activity_slot);
+            if idle < self.inactivity_slots {
+                return Err(CommonError::Custom(
+                    format!("Account is active; dormant in {} slots", self.inactivity_slots - idle)
+                ));
+            }
+            Ok(())
+        }
+
+        /// Check that `account` may be closed at `slot`
+        pub fn validate_close(&self, account: &UserAccount, slot: u64) -> CommonResult<()> {
+            validate_reclaimable(self, account)?;
+            if account.dormant_since_slot == 0 {
+                return Err(CommonError::Custom("Account is not flagged dormant".to_string()));
+            }
+            
+            let closable_at = account.dormant_since_slot.saturating_add(self.grace_slots);
+            if slot < closable_at {
+                return Err(CommonError::Custom(
+                    format!("Grace window open for another {} slots", closable_at - slot)
+                ));
+            }
+            Ok(())
+        }
+
+        /// Split reclaimed `lamports` into the owner's and the cranker's shares
+        pub fn split_rent(&self, lamports: u64) -> CommonResult<(u64, u64)> {
+            let cranker_share = percentage::calculate_percentage(lamports, self.cranker_share_bps)?;
+            Ok((lamports - cranker_share, cranker_share))
+        }
+    }
+
+    /// Only tracked, empty accounts under an enabled policy are reclaimable; tokens are never
+    /// destroyed by a close
+    fn validate_reclaimable(policy: &ReclaimPolicy, account: &UserAccount) -> CommonResult<()> {
+        if !policy.is_enabled() {
+            return Err(CommonError::Custom("Account reclamation is disabled".to_string()));
+        }
+        if account.last_activity_slot == 0 {
+            return Err(CommonError::Custom("Account does not track activity".to_string()));
+        }
+        if account.balance != 0 {
+            return Err(CommonError::Custom("Account still holds a balance".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// Governance-controlled treasury with a per-epoch spending budget
+pub mod treasury {
+    use super::*;
+
+    /// Most programs the treasury may invest through
+    pub const MAX_ALLOWED_PROGRAMS: usize = 4;
+
+    /// Treasury balance, governance authority and spending limits
+    #[derive(BorshSerialize, BorshDeserialize, Debug, Default)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+    pub struct Treasury {
+        #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+        pub governance: Pubkey,
+        pub bump: u8,
+        pub balance: u64,
+        pub invested: u64,
+        pub epoch_budget: u64,
+        pub budget_epoch: u64,
+        pub spent_in_epoch: u64,
+        pub allowed_program_count: u8,
+        #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey::array"))]
+        pub allowed_programs: [Pubkey; MAX_ALLOWED_PROGRAMS],
+        /// Epoch `epoch_fees` were accrued in
+        pub fee_epoch: u64,
+        /// Fees accrued in `fee_epoch`, distributed once that epoch has closed
+        pub epoch_fees: u64,
+        /// Fees of closed epochs awaiting `DistributeEpochFees`
+        pub undistributed_fees: u64,
+    }
+
+    /// Split of closed epochs' fees between stakers, the insurance fund and the treasury
+    #[derive(BorshSerialize, BorshDeserialize, Debug, Default, ProgramAccount)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+    #[seeds(FEE_SCHEDULE_SEED)]
+    #[space(auto)]
+    pub struct FeeSchedule {
+        pub stakers_bps: u16,
+        pub insurance_bps: u16,
+        pub treasury_bps: u16,
+        /// Stakers' share of distributed fees not yet added to the reward accumulator, held
+        /// while nothing is staked
+        pub staker_rewards: u64,
+    }
+
+    impl Treasury {
+        pub fn is_configured(&self) -> bool {
+            self.governance != Pubkey::default()
+        }
+
+        pub fn is_allowed_program(&self, program: &Pubkey) -> bool {
+            self.allowed_programs[..self.allowed_program_count as usize].contains(program)
+        }
+
+        /// Budget still available in `epoch`
+        pub fn remaining_budget(&self, epoch: u64) -> u64 {
+            limits::remaining(self.budget_epoch, self.spent_in_epoch, epoch, self.epoch_budget)
+        }
+
+        /// Close out `fee_epoch` once `epoch` has moved past it
+        pub fn roll_fee_epoch(&mut self, epoch: u64) -> CommonResult<()> {
+            if epoch != self.fee_epoch {
+                self.undistributed_fees = safe_math::safe_add(self.undistributed_fees, self.epoch_fees)?;
+                self.epoch_fees = 0;
+                self.fee_epoch = epoch;
+            }
+            Ok(())
+        }
+
+        /// Record `amount` of fees collected in `epoch`
+        pub fn accrue_fees(&mut self, amount: u64, epoch: u64) -> CommonResult<()> {
+            self.roll_fee_epoch(epoch)?;
+            self.epoch_fees = safe_math::safe_add(self.epoch_fees, amount)?;
+            Ok(())
+        }
+
+        /// Deduct `amount` from the balance and the budget of `epoch`
+        pub fn spend(&mut self, amount: u64, epoch: u64) -> CommonResult<()> {
+            let spent = limits::consume(self.budget_epoch, self.spent_in_epoch, epoch, amount, self.epoch_budget)?;
+            self.balance = safe_math::safe_sub(self.balance, amount)?;
+            self.budget_epoch = epoch;
+            self.spent_in_epoch = spent;
+            Ok(())
+        }
+    }
+
+    /// Load the treasury, verifying its PDA
+    pub fn load_treasury(
+        program_id: &Pubkey,
+        treasury_info: &AccountInfo,
+    ) -> Result<Treasury, ProgramError> {
+        let (expected_pda, bump) = account_creation::create_treasury_pda(program_id)
+            .map_err(|_| ProgramError::InvalidSeeds)?;
+        
+        if treasury_info.key != &expected_pda {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        
+        let mut treasury = account_data::deserialize_account_data::<Treasury>(treasury_info)
+            .map_err(|_| ProgramError::InvalidAccountData)?;
+        treasury.bump = bump;
+        Ok(treasury)
+    }
+
+    /// Accrue `fee` to the treasury's current epoch; the treasury must be among the trailing
+    /// accounts, so fees are never charged without being credited
+    pub fn accrue_fees(
+        program_id: &Pubkey,
+        remaining_accounts: &[AccountInfo],
+        fee: u64,
+    ) -> ProgramResult {
+        let (treasury_pda, _bump) = account_creation::create_treasury_pda(program_id)
+            .map_err(|_| ProgramError::InvalidSeeds)?;
+        let Some(treasury_info) = find_account(remaining_accounts, &treasury_pda) else {
+            log_error!("Treasury account is missing", treasury = treasury_pda);
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+        if fee == 0 {
+            return Ok(());
+        }
+        
+        let mut treasury = load_treasury(program_id, treasury_info)?;
+        treasury.accrue_fees(fee, Clock::get()?.epoch)
+            .map_err(|_| ProgramError::InvalidArgument)?;
+        account_data::serialize_account_data(treasury_info, &treasury)
+            .map_err(|_| ProgramError::AccountDataTooSmall)
+    }
+
+    /// Load a configured treasury whose governance has signed
+    pub fn load_governed_treasury(
+        program_id: &Pubkey,
+        treasury_info: &AccountInfo,
+        governance_info: &AccountInfo,
+    ) -> Result<Treasury, ProgramError> {
+        account_validation::validate_signer(governance_info)
+            .map_err(|_| ProgramError::MissingRequiredSignature)?;
+        
+        let treasury = load_treasury(program_id, treasury_info)?;
+        
+        if !treasury.is_configured() || treasury.governance != *governance_info.key {
+            return Err(ProgramError::InvalidArgument);
+        }
+        
+        Ok(treasury)
+    }
+}
+
+/// Time-locked token deposits with an early-withdrawal penalty
+pub mod lockup {
+    use super::*;
+
+    /// Penalty for withdrawing at the very start of a lock
+    pub const MAX_EARLY_WITHDRAWAL_PENALTY_BPS: u16 = 2_000;
+
+    /// Tokens locked by a user; `amount` is zero once unlocked
+    #[derive(BorshSerialize, BorshDeserialize, Debug, Default)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+    pub struct Lock {
+        #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+        pub owner: Pubkey,
+        pub lock_id: u16,
+        pub amount: u64,
+        pub locked_at: i64,
+        pub unlock_at: i64,
+    }
+
+    impl Lock {
+        /// Serialized size of a lock account
+        pub const LEN: usize = 32 + 2 + 8 + 8 + 8;
+
+        /// Penalty owed when unlocking at `now`, zero once `unlock_at` has passed
+        pub fn early_withdrawal_penalty(&self, now: i64) -> CommonResult<u64> {
+            if now >= self.unlock_at {
+                return Ok(0);
+            }
+            
+            percentage::early_withdrawal_penalty(
+                self.amount,
+                MAX_EARLY_WITHDRAWAL_PENALTY_BPS,
+                (self.unlock_at - now) as u64,
+                (self.unlock_at - self.locked_at) as u64,
+            )
+        }
+    }
+
+    /// Load a lock of `owner`, verifying its PDA
+    pub fn load_lock(
+        program_id: &Pubkey,
+        lock_info: &AccountInfo,
+        owner: &Pubkey,
+    ) -> Result<Lock, ProgramError> {
+        let lock = account_data::deserialize_account_data::<Lock>(lock_info)
+            .map_err(|_| ProgramError::InvalidAccountData)?;
+        
+        let (expected_pda, _bump) = account_creation::create_lock_pda(owner, lock.lock_id, program_id)
+            .map_err(|_| ProgramError::InvalidSeeds)?;
+        
+        if lock_info.key != &expected_pda || lock.owner != *owner {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        
+        Ok(lock)
+    }
+}
+
+/// Compliance controls: the compliance authority and per-account transfer limits
+/// Token grants released to a beneficiary over time
+pub mod vesting {
+    use super::*;
+
+    /// Tokens granted to `beneficiary`, vesting linearly from `start_ts` over `duration`
+    /// seconds with nothing vested before the cliff
+    #[derive(BorshSerialize, BorshDeserialize, Debug, Default, ProgramAccount)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+    #[seeds(VESTING_SEED, grantor, beneficiary)]
+    #[space(auto)]
+    pub struct VestingSchedule {
+        #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+        pub grantor: Pubkey,
+        #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+        pub beneficiary: Pubkey,
+        pub total: u64,
+        pub start_ts: i64,
+        /// Seconds after `start_ts` before anything vests
+        pub cliff: u64,
+        pub duration: u64,
+        pub claimed: u64,
+    }
+
+    impl VestingSchedule {
+        pub fn is_initialized(&self) -> bool {
+            self.total != 0
+        }
+
+        /// Total vested by `now`: zero before the cliff, then the elapsed share of `total`
+        pub fn vested(&self, now: i64) -> CommonResult<u64> {
+            let elapsed = now.saturating_sub(self.start_ts).max(0) as u64;
+            if elapsed < self.cliff {
+                return Ok(0);
+            }
+            
+            accrual::prorate(self.total, elapsed, self.duration)
+        }
+
+        /// Vested but not yet claimed by `now`
+        pub fn claimable(&self, now: i64) -> CommonResult<u64> {
+            safe_math::safe_sub(self.vested(now)?, self.claimed)
+        }
+    }
+
+    /// Load an initialized schedule, verifying it lives at the PDA of its grantor and beneficiary
+    pub fn load_schedule(
+        program_id: &Pubkey,
+        vesting_info: &AccountInfo,
+    ) -> Result<VestingSchedule, ProgramError> {
+        let schedule = account_data::deserialize_account_data::<VestingSchedule>(vesting_info)
+            .map_err(|_| ProgramError::InvalidAccountData)?;
+        if !schedule.is_initialized() {
+            return Err(ProgramError::UninitializedAccount);
+        }
+        
+        let (expected_pda, _bump) = VestingSchedule::find_address(&schedule.grantor, &schedule.beneficiary, program_id)
+            .map_err(|_| ProgramError::InvalidSeeds)?;
+        
+        if vesting_info.key != &expected_pda {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        
+        Ok(schedule)
+    }
+}
+
+/// Balances held between a payer and a counterparty until released or refunded
+pub mod escrow {
+    use super::*;
+
+    /// An escrow at the payer's vault PDA for `escrow_id`; `settled` once paid out either way
+    #[derive(BorshSerialize, BorshDeserialize, Debug, Default, ProgramAccount)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+    #[space(auto)]
+    pub struct Escrow {
+        #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+        pub payer: Pubkey,
+        pub escrow_id: u64,
+        #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+        pub counterparty: Pubkey,
+        /// May release alone; the default pubkey when there is no arbiter
+        #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+        pub arbiter: Pubkey,
+        pub amount: u64,
+        pub expires_at: i64,
+        pub settled: bool,
+    }
+
+    impl Escrow {
+        pub fn is_initialized(&self) -> bool {
+            self.payer != Pubkey::default()
+        }
+
+        pub fn has_arbiter(&self) -> bool {
+            self.arbiter != Pubkey::default()
+        }
+
+        /// Whether `signers` may release: the arbiter alone, or the payer and counterparty together
+        pub fn can_release(&self, signers: &[Pubkey]) -> bool {
+            (self.has_arbiter() && signers.contains(&self.arbiter))
+                || (signers.contains(&self.payer) && signers.contains(&self.counterparty))
+        }
+    }
+
+    /// Load an open escrow, verifying it lives at its payer's vault PDA
+    pub fn load_escrow(
+        program_id: &Pubkey,
+        escrow_info: &AccountInfo,
+    ) -> Result<Escrow, ProgramError> {
+        let escrow = account_data::deserialize_account_data::<Escrow>(escrow_info)
+            .map_err(|_| ProgramError::InvalidAccountData)?;
+        if !escrow.is_initialized() {
+            return Err(ProgramError::UninitializedAccount);
+        }
+        
+        let (expected_pda, _bump) = account_creation::create_vault_pda(&escrow.payer, escrow.escrow_id, program_id)
+            .map_err(|_| ProgramError::InvalidSeeds)?;
+        
+        if escrow_info.key != &expected_pda {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        if escrow.settled {
+            log_error!("Escrow already settled", escrow = escrow_info.key);
+            return Err(ProgramError::InvalidAccountData);
+        }
+        
+        Ok(escrow)
+    }
+}
+
+/// Stakes earning a share of the program's reward rate
+pub mod staking {
+    use super::*;
+
+    /// An owner's staked tokens and the rewards settled to them
+    #[derive(BorshSerialize, BorshDeserialize, Debug, Default, ProgramAccount)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+    #[seeds(STAKE_SEED, owner)]
+    #[space(auto)]
+    pub struct StakeAccount {
+        #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+        pub owner: Pubkey,
+        pub amount: u64,
+        /// `ProgramState::reward_per_share` when rewards were last settled
+        pub reward_per_share_paid: u128,
+        /// Settled rewards not yet claimed
+        pub pending_rewards: u64,
+    }
+
+    impl StakeAccount {
+        /// Settle the rewards earned since the last settlement against the current accumulator
+        pub fn settle(&mut self, reward_per_share: u128) -> CommonResult<()> {
+            let earned = accrual::earned(self.amount, reward_per_share, self.reward_per_share_paid)?;
+            self.pending_rewards = safe_math::safe_add(self.pending_rewards, earned)?;
+            self.reward_per_share_paid = reward_per_share;
+            Ok(())
+        }
+    }
+
+    /// Rewards `stake` could claim once `state` is accrued to `now`
+    pub fn claimable(stake: &StakeAccount, state: &ProgramState, now: i64) -> CommonResult<u64> {
+        let mut state = state.clone();
+        state.accrue_rewards(now)?;
+        let earned = accrual::earned(stake.amount, state.reward_per_share, stake.reward_per_share_paid)?;
+        safe_math::safe_add(stake.pending_rewards, earned)
+    }
+}
+
+/// Balance exits held for the program's withdrawal delay
+pub mod withdrawal {
+    use super::*;
+
+    /// An owner's pending withdrawal; `amount` is zero when none is pending
+    #[derive(BorshSerialize, BorshDeserialize, Debug, Default, ProgramAccount)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+    #[seeds(WITHDRAWAL_SEED, owner)]
+    #[space(auto)]
+    pub struct PendingWithdrawal {
+        #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+        pub owner: Pubkey,
+        pub amount: u64,
+        pub requested_at: i64,
+        pub executable_at: i64,
+    }
+
+    impl PendingWithdrawal {
+        pub fn is_pending(&self) -> bool {
+            self.amount != 0
+        }
+    }
+}
+
+pub mod allowlist {
+    use super::*;
+
+    /// Whether `wallet` may create a user account under `ALLOWLIST_GATING`
+    #[derive(BorshSerialize, BorshDeserialize, Debug, Default, ProgramAccount)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+    #[seeds(ALLOWLIST_SEED, wallet)]
+    #[space(auto)]
+    pub struct AllowlistEntry {
+        #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+        pub wallet: Pubkey,
+        pub allowed: bool,
+        pub updated_at: i64,
+    }
+}
+
+pub mod compliance {
+    use super::*;
+
+    /// Slots in a daily limit window, at 400ms per slot
+    pub const SLOTS_PER_DAY: u64 = 216_000;
+
+    /// Program-wide compliance settings
+    #[derive(BorshSerialize, BorshDeserialize, Debug, Default)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+    pub struct ComplianceConfig {
+        #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+        pub authority: Pubkey,
+        #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+        pub attestor: Pubkey,
+        /// Largest transfer allowed without an attestation under `ATTESTATION_GATING`
+        pub attestation_threshold: u64,
+        /// May reclaim balances of `FLAGGED` accounts
+        #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+        pub clawback_authority: Pubkey,
+    }
+
+    impl ComplianceConfig {
+        pub fn is_authority(&self, key: &Pubkey) -> bool {
+            self.authority != Pubkey::default() && self.authority == *key
+        }
+    }
+
+    /// An attestor's statement about a user (e.g. completed KYC), valid until `expires_at`
+    #[derive(BorshSerialize, BorshDeserialize, Debug)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+    pub struct Attestation {
+        #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+        pub user: Pubkey,
+        #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+        pub attestor: Pubkey,
+        pub issued_at: i64,
+        pub expires_at: i64,
+    }
+
+    /// Outbound transfer limits of a user account; zero limits are unlimited
+    #[derive(BorshSerialize, BorshDeserialize, Debug, Default)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+    pub struct AccountLimits {
+        #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+        pub user_account: Pubkey,
+        pub max_per_transfer: u64,
+        pub max_per_day: u64,
+        pub day_window: u64,
+        pub spent_in_day: u64,
+        pub compliance_locked: bool,
+    }
+
+    impl AccountLimits {
+        pub fn is_unlimited(&self) -> bool {
+            self.max_per_transfer == 0 && self.max_per_day == 0
+        }
+
+        /// Largest transfer allowed at `slot`
+        pub fn available(&self, slot: u64) -> CommonResult<u64> {
+            let mut available = u64::MAX;
+            if self.max_per_transfer != 0 {
+                available = self.max_per_transfer;
+            }
+            if self.max_per_day != 0 {
+                let window = limits::window_of(slot, SLOTS_PER_DAY)?;
+                available = available.min(limits::remaining(self.day_window, self.spent_in_day, window, self.max_per_day));
+            }
+            Ok(available)
+        }
+
+        /// Check a transfer at `slot` and count it against the daily window
+        pub fn record_transfer(&mut self, amount: u64, slot: u64) -> CommonResult<()> {
+            limits::check_max(amount, self.max_per_transfer)?;
+            
+            let window = limits::window_of(slot, SLOTS_PER_DAY)?;
+            let cap = if self.max_per_day == 0 { u64::MAX } else { self.max_per_day };
+            self.spent_in_day = limits::consume(self.day_window, self.spent_in_day, window, amount, cap)?;
+            self.day_window = window;
+            Ok(())
+        }
+    }
+
+    /// Number of entries kept in the `AuditLog`
+    pub const AUDIT_LOG_LEN: usize = 16;
+
+    /// Kinds of audited compliance actions
+    #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Default)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+    pub enum AuditAction {
+        #[default]
+        Flag,
+        Unflag,
+        Clawback,
+    }
+
+    /// One audited compliance action
+    #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, Default)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+    pub struct AuditEntry {
+        pub action: AuditAction,
+        #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+        pub authority: Pubkey,
+        #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+        pub target: Pubkey,
+        pub amount: u64,
+        pub reason_code: u16,
+        pub timestamp: i64,
+    }
+
+    /// Ring buffer of the most recent compliance actions, oldest first
+    #[derive(BorshSerialize, BorshDeserialize, Debug, Default)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+    pub struct AuditLog {
+        pub len: u8,
+        pub entries: [AuditEntry; AUDIT_LOG_LEN],
+    }
+
+    impl AuditLog {
+        /// Recorded entries, oldest first
+        pub fn entries(&self) -> &[AuditEntry] {
+            &self.entries[..(self.len as usize).min(AUDIT_LOG_LEN)]
+        }
+
+        /// Append an entry, dropping the oldest once the log is full
+        pub fn record(&mut self, entry: AuditEntry) {
+            let len = self.entries().len();
+            if len == AUDIT_LOG_LEN {
+                self.entries.rotate_left(1);
+                self.entries[AUDIT_LOG_LEN - 1] = entry;
+            } else {
+                self.entries[len] = entry;
+                self.len += 1;
+            }
+        }
+    }
+
+    /// Clawback of a flagged account, logged via `sol_log_data` as `[TAG, borsh(event)]`
+    #[derive(BorshSerialize, BorshDeserialize, Debug)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+    pub struct ClawbackEvent {
+        #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+        pub user_account: Pubkey,
+        #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+        pub owner: Pubkey,
+        #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+        pub authority: Pubkey,
+        pub amount: u64,
+        pub reason_code: u16,
+        pub clawed_back_at: i64,
+    }
+
+    impl ClawbackEvent {
+        pub const TAG: &'static [u8] = b"clawback";
+    }
+
+    /// Append an entry to the audit log PDA; the first entry creates it, funded by `payer_info`
+    pub fn record_audit<'a>(
+        program_id: &Pubkey,
+        payer_info: &AccountInfo<'a>,
+        audit_log_info: &AccountInfo<'a>,
+        accounts: &[AccountInfo<'a>],
+        entry: AuditEntry,
+    ) -> ProgramResult {
+        let (expected_pda, bump) = account_creation::create_audit_log_pda(program_id)
+            .map_err(|_| ProgramError::InvalidSeeds)?;
+        
+        if audit_log_info.key != &expected_pda {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        
+        if audit_log_info.data_len() == 0 {
+            let len = account_data::calculate_account_size(&AuditLog::default())
+                .map_err(|_| ProgramError::InvalidArgument)?;
+            create_pda_account(program_id, payer_info, audit_log_info, accounts, len, &[AUDIT_LOG_SEED, &[bump]])?;
+        }
+        
+        let mut audit_log = account_data::deserialize_account_data::<AuditLog>(audit_log_info)
+            .map_err(|_| ProgramError::InvalidAccountData)?;
+        audit_log.record(entry);
+        
+        account_data::serialize_account_data(audit_log_info, &audit_log)
+            .map_err(|_| ProgramError::AccountDataTooSmall)
+    }
+
+    /// Load the compliance configuration, verifying its PDA
+    pub fn load_compliance_config(
+        program_id: &Pubkey,
+        compliance_config_info: &AccountInfo,
+    ) -> Result<ComplianceConfig, ProgramError> {
+        let (expected_pda, _bump) = account_creation::create_compliance_config_pda(program_id)
+            .map_err(|_| ProgramError::InvalidSeeds)?;
+        
+        if compliance_config_info.key != &expected_pda {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        
+        account_data::deserialize_account_data::<ComplianceConfig>(compliance_config_info)
+            .map_err(|_| ProgramError::InvalidAccountData)
+    }
+}
+
+/// Multiple scoped delegates per owner, each with a spending cap and expiry, stored as
+/// TLV records so new record types can be added without migrating the account
+pub mod delegation {
+    use super::*;
+    use account_utils::account_data::{read_tlv, write_tlv, TLV_HEADER_LEN};
+
+    /// TLV type of a `DelegateEntry`
+    pub const DELEGATE_ENTRY_TYPE: u16 = 1;
+
+    /// Most delegate entries an owner can hold
+    pub const MAX_DELEGATES: usize = 8;
+
+    /// Encoded size of a `DelegateEntry`
+    pub const DELEGATE_ENTRY_LEN: usize = 32 + 4 + 8 + 8;
+
+    /// Size of a delegations account: the owner followed by the TLV region
+    pub const DELEGATIONS_LEN: usize = 32 + MAX_DELEGATES * (TLV_HEADER_LEN + DELEGATE_ENTRY_LEN);
+
+    /// A delegate's scope (`session_scope` bits), remaining allowance and expiry
+    #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+    pub struct DelegateEntry {
+        #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+        pub delegate: Pubkey,
+        pub scope: u32,
+        pub remaining: u64,
+        pub expires_at: i64,
+    }
+
+    impl DelegateEntry {
+        /// Check an operation of `scope` for `amount` at `now` and deduct it from the allowance
+        pub fn spend(&mut self, scope: u32, amount: u64, now: i64) -> CommonResult<()> {
+            if self.scope & scope == 0 {
+                return Err(CommonError::InsufficientPermissions);
+            }
+            
+            account_validation::validate_not_expired(self.expires_at, now)?;
+            
+            self.remaining = safe_math::safe_sub(self.remaining, amount)
+                .map_err(|_| CommonError::Custom("Delegate allowance exceeded".to_string()))?;
+            Ok(())
+        }
+    }
+
+    /// Decoded delegations account; records of unknown types are kept as-is
+    #[derive(Debug, Default)]
+    pub struct Delegations {
+        pub owner: Pubkey,
+        pub entries: Vec<DelegateEntry>,
+        pub other_records: Vec<(u16, Vec<u8>)>,
+    }
+
+    impl Delegations {
+        pub fn unpack(data: &[u8]) -> CommonResult<Self> {
+            if data.len() < 32 {
+                return Err(CommonError::AccountValidationFailed);
+            }
+            
+            let mut delegations = Self {
+                owner: Pubkey::try_from(&data[..32]).map_err(|_| CommonError::AccountValidationFailed)?,
+                ..Self::default()
+            };
+            for (record_type, value) in read_tlv(&data[32..])? {
+                if record_type == DELEGATE_ENTRY_TYPE {
+                    delegations.entries.push(DelegateEntry::try_from_slice(value)
+                        .map_err(|_| CommonError::AccountValidationFailed)?);
+                } else {
+                    delegations.other_records.push((record_type, value.to_vec()));
+                }
+            }
+            Ok(delegations)
+        }
+
+        pub fn pack_into(&self, data: &mut [u8]) -> CommonResult<()> {
+            if data.len() < 32 {
+                return Err(CommonError::AccountValidationFailed);
+            }
+            
+            let mut records = Vec::with_capacity(self.entries.len() + self.other_records.len());
+            for entry in &self.entries {
+                records.push((DELEGATE_ENTRY_TYPE, entry.try_to_vec().map_err(|_| CommonError::InvalidCalculation)?));
+            }
+            records.extend(self.other_records.iter().cloned());
+            
+            data[..32].copy_from_slice(self.owner.as_ref());
+            write_tlv(&mut data[32..], &records)
+        }
+
+        pub fn find_mut(&mut self, delegate: &Pubkey) -> Option<&mut DelegateEntry> {
+            self.entries.iter_mut().find(|entry| entry.delegate == *delegate)
+        }
+
+        /// Add an entry, replacing any existing one for the same delegate
+        pub fn set(&mut self, entry: DelegateEntry) -> CommonResult<()> {
+            if let Some(existing) = self.find_mut(&entry.delegate) {
+                *existing = entry;
+                return Ok(());
+            }
+            
+            if self.entries.len() >= MAX_DELEGATES {
+                return Err(CommonError::Custom(format!("At most {} delegates", MAX_DELEGATES)));
+            }
+            self.entries.push(entry);
+            Ok(())
+        }
+
+        /// Remove the entry of `delegate`, returning whether there was one
+        pub fn remove(&mut self, delegate: &Pubkey) -> bool {
+            let len = self.entries.len();
+            self.entries.retain(|entry| entry.delegate != *delegate);
+            self.entries.len() != len
+        }
+    }
+
+    /// Load the delegations of `owner`, verifying the PDA; a fresh account decodes as empty
+    pub fn load_delegations(
+        program_id: &Pubkey,
+        delegations_info: &AccountInfo,
+        owner: &Pubkey,
+    ) -> Result<Delegations, ProgramError> {
+        let (expected_pda, _bump) = account_creation::create_delegations_pda(owner, program_id)
+            .map_err(|_| ProgramError::InvalidSeeds)?;
+        
+        if delegations_info.key != &expected_pda {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        
+        if delegations_info.owner != program_id {
+            return Err(ProgramError::IllegalOwner);
+        }
+        
+        let mut delegations = Delegations::unpack(&delegations_info.data.borrow())
+            .map_err(|_| ProgramError::InvalidAccountData)?;
+        delegations.owner = *owner;
+        Ok(delegations)
+    }
+
+    pub fn save_delegations(
+        delegations_info: &AccountInfo,
+        delegations: &Delegations,
+    ) -> ProgramResult {
+        delegations.pack_into(&mut delegations_info.data.borrow_mut())
+            .map_err(|_| ProgramError::AccountDataTooSmall)
+    }
+
+    /// Authorize `delegate` to act for `owner` through the delegations PDA among the
+    /// trailing accounts, deducting `amount` from its allowance
+    pub fn spend_allowance(
+        program_id: &Pubkey,
+        owner: &Pubkey,
+        delegate: &Pubkey,
+        remaining_accounts: &[AccountInfo],
+        scope: u32,
+        amount: u64,
+        now: i64,
+    ) -> ProgramResult {
+        let (expected_pda, _bump) = account_creation::create_delegations_pda(owner, program_id)
+            .map_err(|_| ProgramError::InvalidSeeds)?;
+        
+        let delegations_info = find_account(remaining_accounts, &expected_pda)
+            .ok_or(ProgramError::InvalidArgument)?;
+        
+        let mut delegations = load_delegations(program_id, delegations_info, owner)?;
+        let entry = delegations.find_mut(delegate)
+            .ok_or(ProgramError::InvalidArgument)?;
+        
+        entry.spend(scope, amount, now)
+            .map_err(|err| {
+                log_error!("Delegate not authorized", delegate = delegate, reason = ?err);
+                ProgramError::InvalidArgument
+            })?;
+        
+        save_delegations(delegations_info, &delegations)
+    }
+}
+
+/// Exchange rate posted by a crank, read by instructions that convert between assets
+pub mod exchange_rate {
+    use super::*;
+
+    /// Latest exchange rate and when it was posted
+    #[derive(BorshSerialize, BorshDeserialize, Debug, Default)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+    pub struct RateState {
+        /// Key allowed to post rates via `UpdateRate`
+        #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+        pub updater: Pubkey,
+        /// Quote per base as Q64.64 (see `rate_math`)
+        pub rate: u128,
+        pub last_update: i64,
+    }
+
+    impl RateState {
+        /// The rate, provided it was posted at most `max_age` seconds before `now`
+        pub fn fresh_rate(&self, now: i64, max_age: u64) -> Result<u128, ProgramError> {
+            account_validation::validate_fresh(self.last_update, now, max_age)
+                .map_err(|_| {
+                    log_error!("Exchange rate is stale", last_update = self.last_update);
+                    ProgramError::InvalidAccountData
+                })?;
+            Ok(self.rate)
+        }
+
+        /// Quote amount for `base_amount` at a rate no older than `max_age` seconds
+        pub fn base_to_quote(&self, base_amount: u64, now: i64, max_age: u64) -> Result<u64, ProgramError> {
+            rate_math::base_to_quote(base_amount, self.fresh_rate(now, max_age)?)
+                .map_err(|_| ProgramError::InvalidArgument)
+        }
+
+        /// Base amount for `quote_amount` at a rate no older than `max_age` seconds
+        pub fn quote_to_base(&self, quote_amount: u64, now: i64, max_age: u64) -> Result<u64, ProgramError> {
+            rate_math::quote_to_base(quote_amount, self.fresh_rate(now, max_age)?)
+                .map_err(|_| ProgramError::InvalidArgument)
+        }
+    }
+
+    /// Load the rate state, verifying its PDA
+    pub fn load_rate_state(
+        program_id: &Pubkey,
+        rate_state_info: &AccountInfo,
+    ) -> Result<RateState, ProgramError> {
+        let (expected_pda, _bump) = account_creation::create_rate_state_pda(program_id)
+            .map_err(|_| ProgramError::InvalidSeeds)?;
+        
+        if rate_state_info.key != &expected_pda {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        
+        account_data::deserialize_account_data::<RateState>(rate_state_info)
+            .map_err(|_| ProgramError::InvalidAccountData)
+    }
+}
+
+/// Collateralized borrowing: lamport collateral priced by `RateState`, token debt lent
+/// from the treasury
+pub mod lending {
+    use super::*;
+
+    /// Lending risk parameters
+    #[derive(BorshSerialize, BorshDeserialize, Debug, Default)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+    pub struct LendingConfig {
+        /// Largest debt a borrow or withdrawal may leave, relative to collateral value
+        pub max_ltv_bps: u16,
+        /// Collateral share counted towards the health factor
+        pub liquidation_threshold_bps: u16,
+        /// Largest share of a debt one liquidation may repay
+        pub close_factor_bps: u16,
+        /// Collateral bonus paid to liquidators on top of the repaid value
+        pub liquidation_bonus_bps: u16,
+        /// Oldest `RateState` accepted, in seconds
+        pub max_rate_age: u64,
+    }
+
+    impl LendingConfig {
+        /// Reject parameters under which a fresh borrow could be liquidated at once
+        pub fn validate(&self) -> ProgramResult {
+            if self.max_ltv_bps == 0
+                || self.max_ltv_bps > self.liquidation_threshold_bps
+                || self.close_factor_bps == 0
+                || self.max_rate_age == 0
+            {
+                return Err(ProgramError::InvalidArgument);
+            }
+            for bps in [self.liquidation_threshold_bps, self.close_factor_bps, self.liquidation_bonus_bps] {
+                percentage::validate_basis_points(bps).map_err(|_| ProgramError::InvalidArgument)?;
+            }
+            Ok(())
+        }
+    }
+
+    /// A borrower's collateral lamports and debt
+    #[derive(BorshSerialize, BorshDeserialize, Debug, Default)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+    pub struct Position {
+        #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+        pub owner: Pubkey,
+        /// Collateral lamports held on top of the account's rent
+        pub collateral: u64,
+        /// Debt in units scaled by `ProgramState::interest_index`
+        pub debt_scaled: u64,
+    }
+
+    impl Position {
+        /// Debt owed at `index` (rounded up)
+        pub fn debt(&self, index: u128) -> Result<u64, ProgramError> {
+            index_math::to_face_up(self.debt_scaled, index)
+                .map_err(|_| ProgramError::InvalidArgument)
+        }
+
+        /// Repay up to `amount` of the debt at `index`, returning the amount applied
+        pub fn repay(&mut self, amount: u64, index: u128) -> Result<u64, ProgramError> {
+            let repaid = amount.min(self.debt(index)?);
+            let repaid_scaled = index_math::to_scaled(repaid, index)
+                .map_err(|_| ProgramError::InvalidArgument)?;
+            self.debt_scaled = self.debt_scaled.saturating_sub(repaid_scaled);
+            Ok(repaid)
+        }
+    }
+
+    /// Load the lending configuration, verifying its PDA
+    pub fn load_lending_config(
+        program_id: &Pubkey,
+        lending_config_info: &AccountInfo,
+    ) -> Result<LendingConfig, ProgramError> {
+        let (expected_pda, _bump) = account_creation::create_lending_config_pda(program_id)
+            .map_err(|_| ProgramError::InvalidSeeds)?;
+        
+        if lending_config_info.key != &expected_pda {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        
+        account_data::deserialize_account_data::<LendingConfig>(lending_config_info)
+            .map_err(|_| ProgramError::InvalidAccountData)
+    }
+
+    /// Load an owner's position, verifying its PDA; a fresh position is claimed for `owner`
+    pub fn load_position(
+        program_id: &Pubkey,
+        position_info: &AccountInfo,
+        owner: &Pubkey,
+    ) -> Result<Position, ProgramError> {
+        let (expected_pda, _bump) = account_creation::create_position_pda(owner, program_id)
+            .map_err(|_| ProgramError::InvalidSeeds)?;
+        
+        if position_info.key != &expected_pda || position_info.owner != program_id {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        
+        let mut position = account_data::deserialize_account_data::<Position>(position_info)
+            .map_err(|_| ProgramError::InvalidAccountData)?;
+        if position.owner == Pubkey::default() {
+            position.owner = *owner;
+        }
+        Ok(position)
+    }
+
+    /// Pay collateral lamports out of a position
+    pub fn move_collateral(
+        position_info: &AccountInfo,
+        destination_info: &AccountInfo,
+        amount: u64,
+    ) -> ProgramResult {
+        **position_info.try_borrow_mut_lamports()? = position_info.lamports()
+            .checked_sub(amount)
+            .ok_or(ProgramError::InsufficientFunds)?;
+        **destination_info.try_borrow_mut_lamports()? = destination_info.lamports()
+            .checked_add(amount)
+            .ok_or(ProgramError::InvalidArgument)?;
+        Ok(())
+    }
+}
+
+/// Insurance fund: fed a share of fees, drawn on for payouts and to absorb bad debt
+pub mod insurance {
+    use super::*;
+
+    /// Insurance fund balance and its running totals
+    #[derive(BorshSerialize, BorshDeserialize, Debug, Default)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+    pub struct InsuranceFund {
+        /// Key allowed to make payouts
+        #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+        pub authority: Pubkey,
+        /// Share of transfer and swap fees paid into the fund
+        pub fee_share_bps: u16,
+        pub balance: u64,
+        pub total_contributed: u64,
+        pub total_paid_out: u64,
+    }
+
+    impl InsuranceFund {
+        /// Add `amount` to the fund
+        pub fn contribute(&mut self, amount: u64) -> ProgramResult {
+            self.balance = safe_math::safe_add(self.balance, amount)
+                .map_err(|_| ProgramError::InvalidArgument)?;
+            self.total_contributed = self.total_contributed.saturating_add(amount);
+            Ok(())
+        }
+
+        /// Take `amount` out of the fund
+        pub fn pay_out(&mut self, amount: u64) -> ProgramResult {
+            self.balance = safe_math::safe_sub(self.balance, amount)
+                .map_err(|_| ProgramError::InsufficientFunds)?;
+            self.total_paid_out = self.total_paid_out.saturating_add(amount);
+            Ok(())
+        }
+    }
+
+    /// Load the insurance fund, verifying its PDA
+    pub fn load_insurance_fund(
+        program_id: &Pubkey,
+        insurance_fund_info: &AccountInfo,
+    ) -> Result<InsuranceFund, ProgramError> {
+        let (expected_pda, _bump) = account_creation::create_insurance_fund_pda(program_id)
+            .map_err(|_| ProgramError::InvalidSeeds)?;
+        
+        if insurance_fund_info.key != &expected_pda {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        
+        account_data::deserialize_account_data::<InsuranceFund>(insurance_fund_info)
+            .map_err(|_| ProgramError::InvalidAccountData)
+    }
+
+    /// Pay the fund's share of `fee` into it, if the fund is among the trailing accounts,
+    /// returning the share paid
+    pub fn contribute_fee_share(
+        program_id: &Pubkey,
+        remaining_accounts: &[AccountInfo],
+        fee: u64,
+    ) -> Result<u64, ProgramError> {
+        let (fund_pda, _bump) = account_creation::create_insurance_fund_pda(program_id)
+            .map_err(|_| ProgramError::InvalidSeeds)?;
+        let Some(insurance_fund_info) = find_account(remaining_accounts, &fund_pda) else {
+            return Ok(0);
+        };
+        
+        let mut insurance_fund = load_insurance_fund(program_id, insurance_fund_info)?;
+        let share = percentage::calculate_percentage(fee, insurance_fund.fee_share_bps)
+            .map_err(|_| ProgramError::InvalidArgument)?;
+        if share == 0 {
+            return Ok(0);
+        }
+        
+        insurance_fund.contribute(share)?;
+        account_data::serialize_account_data(insurance_fund_info, &insurance_fund)
+            .map_err(|_| ProgramError::AccountDataTooSmall)?;
+        Ok(share)
+    }
+
+    /// Run `loss` through the waterfall, drawing the insured part from the fund, which must
+    /// be among the trailing accounts
+    pub fn absorb_loss(
+        program_id: &Pubkey,
+        remaining_accounts: &[AccountInfo],
+        loss: u64,
+    ) -> Result<liquidation_math::LossAllocation, ProgramError> {
+        let (fund_pda, _bump) = account_creation::create_insurance_fund_pda(program_id)
+            .map_err(|_| ProgramError::InvalidSeeds)?;
+        let insurance_fund_info = find_account(remaining_accounts, &fund_pda)
+            .ok_or(ProgramError::NotEnoughAccountKeys)?;
+        
+        let mut insurance_fund = load_insurance_fund(program_id, insurance_fund_info)?;
+        let allocation = liquidation_math::loss_waterfall(loss, insurance_fund.balance);
+        
+        insurance_fund.pay_out(allocation.insurance)?;
+        account_data::serialize_account_data(insurance_fund_info, &insurance_fund)
+            .map_err(|_| ProgramError::AccountDataTooSmall)?;
+        Ok(allocation)
+    }
+}
+
+/// Per-day analytics rollups, so dashboards read one account per day instead of replaying history
+pub mod analytics {
+    use super::*;
+
+    pub const SECONDS_PER_DAY: i64 = 86_400;
+
+    /// Bits in the active-user bitmap
+    pub const ACTIVE_USER_BITS: usize = 1024;
+
+    /// Activity of one UTC day, in a PDA derived from the day's start
+    #[derive(BorshSerialize, BorshDeserialize, Debug, Default, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+    pub struct DailyStats {
+        /// Unix timestamp of the day's first second; zero until the first update
+        pub day_start: i64,
+        pub transfer_count: u64,
+        pub volume: u64,
+        pub fees: u64,
+        /// Distinct bits set in `active_bitmap`: a lower bound on the day's active users
+        pub active_users: u64,
+        /// One bit per user, chosen by the user's key
+        pub active_bitmap: [u64; ACTIVE_USER_BITS / 64],
+    }
+
+    impl DailyStats {
+        pub const LEN: usize = 8 + 8 + 8 + 8 + 8 + ACTIVE_USER_BITS / 8;
+
+        /// Count `user` as active today; users sharing a bit are counted once
+        pub fn mark_active(&mut self, user: &Pubkey) {
+            let bit = u16::from_le_bytes([user.as_ref()[0], user.as_ref()[1]]) as usize % ACTIVE_USER_BITS;
+            let (word, mask) = (bit / 64, 1u64 << (bit % 64));
+            if self.active_bitmap[word] & mask == 0 {
+                self.active_bitmap[word] |= mask;
+                self.active_users = self.active_users.saturating_add(1);
+            }
+        }
+    }
+
+    /// Start of the UTC day containing `timestamp`
+    pub fn day_start(timestamp: i64) -> i64 {
+        timestamp.div_euclid(SECONDS_PER_DAY) * SECONDS_PER_DAY
+    }
+
+    /// Apply `update` to today's stats if they are among the trailing accounts; stats of
+    /// another day are left alone, so a transaction straddling midnight still lands. The
+    /// day's first update creates its PDA, funded by `payer_info`.
+    pub fn update_daily_stats<'a>(
+        program_id: &Pubkey,
+        payer_info: &AccountInfo<'a>,
+        remaining_accounts: &[AccountInfo<'a>],
+        update: impl FnOnce(&mut DailyStats),
+    ) -> ProgramResult {
+        // Skip the clock read when no account could be a rollup, existing or yet to be created
+        if remaining_accounts.iter().all(|account_info| account_info.data_len() != DailyStats::LEN && account_info.data_len() != 0) {
+            return Ok(());
+        }
+        
+        let today = day_start(Clock::get()?.unix_timestamp);
+        let (stats_pda, bump) = account_creation::create_daily_stats_pda(today, program_id)
+            .map_err(|_| ProgramError::InvalidSeeds)?;
+        let Some(stats_info) = find_account(remaining_accounts, &stats_pda) else {
+            return Ok(());
+        };
+        
+        if stats_info.data_len() == 0 {
+            let day_seed = account_creation::daily_stats_seed(today, program_id);
+            create_pda_account(
+                program_id,
+                payer_info,
+                stats_info,
+                remaining_accounts,
+                DailyStats::LEN,
+                &[DAILY_STATS_SEED, &day_seed, &[bump]],
+            )?;
+        }
+        
+        let mut stats = account_data::deserialize_account_data::<DailyStats>(stats_info)
+            .map_err(|_| ProgramError::InvalidAccountData)?;
+        // A freshly allocated (zeroed) account starts the day
+        stats.day_start = today;
+        update(&mut stats);
+        
+        account_data::serialize_account_data(stats_info, &stats)
+            .map_err(|_| ProgramError::AccountDataTooSmall)
+    }
+}
+
+/// Error codes this program fails with, described in its own terms
+pub mod errors {
+    use common::error_registry::{ErrorEntry, COMMON_ERRORS};
+    use solana_program::program_error::*;
+
+    const fn entry(code: u64, name: &'static str, message: &'static str) -> ErrorEntry {
+        ErrorEntry { code, name, message, module: "my_solana_program" }
+    }
+
+    /// `ProgramError::Custom` code for any pausable instruction while the program is paused
+    pub const PROGRAM_PAUSED: u32 = 0x5041_5553;
+
+    /// Every error a handler returns: the builtin errors, then the program's custom codes
+    pub const PROGRAM_ERRORS: &[ErrorEntry] = &[
+        entry(
+            INVALID_ARGUMENT,
+            "InvalidArgument",
+            "A parameter was rejected: an amount overflowed or exceeded a limit or cap, the account \
+             cannot send, or the proof of work is missing",
+        ),
+        entry(INVALID_INSTRUCTION_DATA, "InvalidInstructionData", "The instruction could not be decoded"),
+        entry(
+            INVALID_ACCOUNT_DATA,
+            "InvalidAccountData",
+            "An account is in the wrong state for this operation",
+        ),
+        entry(
+            ACCOUNT_DATA_TOO_SMALL,
+            "AccountDataTooSmall",
+            "An account is too small for its data, or a fixed-capacity list in it is full",
+        ),
+        entry(INSUFFICIENT_FUNDS, "InsufficientFunds", "The balance does not cover the amount and its fee"),
+        entry(INCORRECT_PROGRAM_ID, "IncorrectProgramId", "An account passed as a program is not the expected one"),
+        entry(
+            MISSING_REQUIRED_SIGNATURES,
+            "MissingRequiredSignature",
+            "A required signature is missing, or the signer has no authority over the account",
+        ),
+        entry(ACCOUNT_ALREADY_INITIALIZED, "AccountAlreadyInitialized", "The account is already initialized"),
+        entry(UNINITIALIZED_ACCOUNT, "UninitializedAccount", "The account has not been initialized"),
+        entry(NOT_ENOUGH_ACCOUNT_KEYS, "NotEnoughAccountKeys", "The instruction is missing accounts"),
+        entry(INVALID_SEEDS, "InvalidSeeds", "An account is not at the address derived for it"),
+        entry(BORSH_IO_ERROR, "BorshIoError", "Account data could not be serialized"),
+        entry(ILLEGAL_OWNER, "IllegalOwner", "An account is not owned by the expected program"),
+        entry(
+            crate::instruction::UNSUPPORTED_INSTRUCTION_VERSION as u64,
+            "UnsupportedInstructionVersion",
+            "The instruction was encoded with a version this program does not support",
+        ),
+        entry(PROGRAM_PAUSED as u64, "ProgramPaused", "The program is paused"),
+    ];
+
+    /// Program errors followed by the library errors surfacing under their codes
+    pub fn registry() -> Vec<ErrorEntry> {
+        PROGRAM_ERRORS.iter().chain(COMMON_ERRORS).copied().collect()
+    }
+}
+
+/// Event transport: `sol_log_data`, or a CPI to the noop program under `CPI_EVENTS`
+pub mod events {
+    use super::*;
+
+    /// The spl-noop program; its instruction data is kept in transaction metadata, which
+    /// unlike the log is never truncated
+    pub const NOOP_PROGRAM_ID: Pubkey = solana_program::pubkey!("noopb9bDi6u5WgyGVqVRGJ3ki7jnbCbNQ8uSGD4h7a2");
+
+    /// Noop instruction data carrying an event: `[tag_len: u8, tag, borsh(event)]`
+    pub fn cpi_event_data(tag: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut event_data = Vec::with_capacity(1 + tag.len() + data.len());
+        event_data.push(tag.len() as u8);
+        event_data.extend_from_slice(tag);
+        event_data.extend_from_slice(data);
+        event_data
+    }
+
+    /// Emit a serialized event. Under `CPI_EVENTS` in the program config (passed in, or
+    /// found among the trailing accounts) it goes to the noop program, which must then be
+    /// a trailing account; otherwise it is logged as `[tag, data]`.
+    pub fn emit(
+        program_id: &Pubkey,
+        program_config: Option<&ProgramConfig>,
+        remaining_accounts: &[AccountInfo],
+        tag: &[u8],
+        data: &[u8],
+    ) -> ProgramResult {
+        let feature_flags = match program_config {
+            Some(program_config) => program_config.feature_flags,
+            None => {
+                let (program_config_pda, _bump) = account_creation::create_program_config_pda(program_id)
+                    .map_err(|_| ProgramError::InvalidSeeds)?;
+                match find_account(remaining_accounts, &program_config_pda) {
+                    Some(program_config_info) => load_program_config(program_id, program_config_info)?.feature_flags,
+                    None => 0,
+                }
+            }
+        };
+        
+        if feature_flags & feature_flags::CPI_EVENTS == 0 {
+            sol_log_data(&[tag, data]);
+            return Ok(());
+        }
+        
+        let noop_info = find_account(remaining_accounts, &NOOP_PROGRAM_ID)
+            .ok_or(ProgramError::NotEnoughAccountKeys)?;
+        let instruction = Instruction {
+            program_id: NOOP_PROGRAM_ID,
+            accounts: Vec::new(),
+            data: cpi_event_data(tag, data),
+        };
+        invoke(&instruction, std::slice::from_ref(noop_info))
+    }
+}
+
+
+/// Value-conservation guard for instructions touching several user accounts: balances are
+/// snapshotted before any write and re-read from the accounts once the writes are done
+pub mod invariants {
+    use super::*;
+
+    /// Total balance of a set of user accounts and the token supply at one point in time
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct Snapshot {
+        pub total_balance: u128,
+        pub total_supply: u64,
+    }
+
+    impl Snapshot {
+        /// Sum the balances of the distinct user accounts among `user_account_infos`
+        pub fn capture(user_account_infos: &[&AccountInfo], total_supply: u64) -> Result<Self, ProgramError> {
+            let mut seen: Vec<&Pubkey> = Vec::with_capacity(user_account_infos.len());
+            let mut total_balance = 0u128;
+            
+            for user_account_info in user_account_infos {
+                if seen.contains(&user_account_info.key) {
+                    continue;
+                }
+                seen.push(user_account_info.key);
+                total_balance += read_user_account(user_account_info)?.balance as u128;
+            }
+            
+            Ok(Self { total_balance, total_supply })
+        }
+
+        /// Check that the same accounts now hold the captured total less `outflow` (value
+        /// that left them on purpose, e.g. burned fees) and that the supply is unchanged
+        pub fn assert_conserved(
+            &self,
+            user_account_infos: &[&AccountInfo],
+            total_supply: u64,
+            outflow: u64,
+        ) -> ProgramResult {
+            let after = Self::capture(user_account_infos, total_supply)?;
+            let expected = self.total_balance.checked_sub(outflow as u128);
+            
+            if expected != Some(after.total_balance) || after.total_supply != self.total_supply {
+                log_error!(
+                    "Invariant violated",
+                    balance_before = self.total_balance,
+                    balance_after = after.total_balance,
+                    outflow = outflow
+                );
+                return Err(ProgramError::InvalidAccountData);
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Lazy view over the raw entrypoint input used by the `lazy-entrypoint` feature.
+///
+/// Account headers are only skipped while locating the instruction data; the shared
+/// `AccountInfo` handles are built in a single pass once the instruction has decoded.
+pub mod lazy_entrypoint {
+    use super::*;
+    use solana_program::entrypoint::{BPF_ALIGN_OF_U128, MAX_PERMITTED_DATA_INCREASE, NON_DUP_MARKER};
+    use std::{cell::RefCell, mem::size_of, rc::Rc, slice::{from_raw_parts, from_raw_parts_mut}};
+
+    /// Size of a serialized account before its data
+    const ACCOUNT_HEADER_LEN: usize = 8 + 2 * size_of::<Pubkey>() + 2 * size_of::<u64>();
+
+    /// Raw program input with the instruction data and program id located
+    pub struct LazyInput<'a> {
+        input: *mut u8,
+        num_accounts: usize,
+        instruction_data: &'a [u8],
+        program_id: &'a Pubkey,
+    }
+
+    impl<'a> LazyInput<'a> {
+        /// Walk the account headers to find the instruction data and program id
+        ///
+        /// # Safety
+        ///
+        /// `input` must point at the runtime's serialized program input.
+        pub unsafe fn parse(input: *mut u8) -> Self {
+            let num_accounts = *(input as *const u64) as usize;
+            let mut offset = size_of::<u64>();
+            
+            for _ in 0..num_accounts {
+                if *input.add(offset) == NON_DUP_MARKER {
+                    let data_len = *(input.add(offset + ACCOUNT_HEADER_LEN - size_of::<u64>()) as *const u64) as usize;
+                    offset = align(offset + ACCOUNT_HEADER_LEN + data_len + MAX_PERMITTED_DATA_INCREASE);
+                    offset += size_of::<u64>(); // rent epoch
+                } else {
+                    offset += size_of::<u64>(); // duplicate marker and padding
+                }
+            }
+            
+            let instruction_data_len = *(input.add(offset) as *const u64) as usize;
+            offset += size_of::<u64>();
+            let instruction_data = from_raw_parts(input.add(offset), instruction_data_len);
+            let program_id = &*(input.add(offset + instruction_data_len) as *const Pubkey);
+            
+            Self { input, num_accounts, instruction_data, program_id }
+        }
+
+        pub fn instruction_data(&self) -> &'a [u8] {
+            self.instruction_data
+        }
+
+        pub fn program_id(&self) -> &'a Pubkey {
+            self.program_id
+        }
+
+        /// Build the account infos, referencing the input buffer without copying
+        pub fn into_accounts(self) -> Vec<AccountInfo<'a>> {
+            let input = self.input;
+            let mut accounts: Vec<AccountInfo<'a>> = Vec::with_capacity(self.num_accounts);
+            let mut offset = size_of::<u64>();
+            
+            // Safety: `parse` validated the layout, and consuming `self` ensures the
+            // mutable lamports and data borrows are only created once
+            unsafe {
+                for _ in 0..self.num_accounts {
+                    let dup_info = *input.add(offset);
+                    if dup_info != NON_DUP_MARKER {
+                        accounts.push(accounts[dup_info as usize].clone());
+                        offset += size_of::<u64>();
+                        continue;
+                    }
+                    
+                    let is_signer = *input.add(offset + 1) != 0;
+                    let is_writable = *input.add(offset + 2) != 0;
+                    let executable = *input.add(offset + 3) != 0;
+                    let original_data_len = input.add(offset + 4) as *mut u32;
+                    offset += 8;
+                    
+                    let key = &*(input.add(offset) as *const Pubkey);
+                    offset += size_of::<Pubkey>();
+                    let owner = &*(input.add(offset) as *const Pubkey);
+                    offset += size_of::<Pubkey>();
+                    let lamports = Rc::new(RefCell::new(&mut *(input.add(offset) as *mut u64)));
+                    offset += size_of::<u64>();
+                    let data_len = *(input.add(offset) as *const u64) as usize;
+                    offset += size_of::<u64>();
+                    
+                    // Recorded for the runtime's realloc checks, as the default entrypoint does
+                    *original_data_len = data_len as u32;
+                    
+                    let data = Rc::new(RefCell::new(from_raw_parts_mut(input.add(offset), data_len)));
+                    offset = align(offset + data_len + MAX_PERMITTED_DATA_INCREASE);
+                    let rent_epoch = *(input.add(offset) as *const u64);
+                    offset += size_of::<u64>();
+                    
+                    accounts.push(AccountInfo {
+                        key,
+                        is_signer,
+                        is_writable,
+                        lamports,
+                        data,
+                        owner,
+                        executable,
+                        rent_epoch,
+                    });
+                }
+            }
+            
+            accounts
+        }
+    }
+
+    fn align(offset: usize) -> usize {
+        (offset + BPF_ALIGN_OF_U128 - 1) & !(BPF_ALIGN_OF_U128 - 1)
+    }
+}
+
+/// Upward-growing bump allocator for a heap larger than the default 32KB
+pub mod allocator {
+    use solana_program::entrypoint::HEAP_START_ADDRESS;
+    use std::{alloc::{GlobalAlloc, Layout}, mem::size_of, ptr::null_mut};
+
+    /// Largest heap frame a transaction can request through the compute budget program
+    pub const MAX_HEAP_LENGTH: usize = 256 * 1024;
+
+    /// Bump allocator whose cursor is kept in the first word of the heap.
+    ///
+    /// Allocating upward keeps small workloads inside the default 32KB frame, so the
+    /// larger heap only has to be requested by transactions that need it. Memory is
+    /// never reused and the runtime zero-fills the heap, so zeroed allocations skip the memset.
+    pub struct BumpAllocator {
+        start: usize,
+        len: usize,
+    }
+
+    impl BumpAllocator {
+        /// Allocator spanning the full requestable heap frame
+        pub const fn new() -> Self {
+            Self::with_len(MAX_HEAP_LENGTH)
+        }
+
+        /// Allocator limited to `len` bytes of heap
+        pub const fn with_len(len: usize) -> Self {
+            Self { start: HEAP_START_ADDRESS as usize, len }
+        }
+    }
+
+    impl Default for BumpAllocator {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    unsafe impl GlobalAlloc for BumpAllocator {
+        #[inline]
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let cursor = self.start as *mut usize;
+            let position = match *cursor {
+                0 => self.start + size_of::<usize>(),
+                position => position,
+            };
+            
+            let begin = match position.checked_add(layout.align() - 1) {
+                Some(position) => position & !(layout.align() - 1),
+                None => return null_mut(),
+            };
+            match begin.checked_add(layout.size()) {
+                Some(end) if end <= self.start + self.len => {
+                    *cursor = end;
+                    begin as *mut u8
+                }
+                _ => null_mut(),
+            }
+        }
+
+        #[inline]
+        unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+            self.alloc(layout)
+        }
+
+        #[inline]
+        unsafe fn dealloc(&self, _ptr: *mut u8, _layout: Layout) {
+            // Bump allocator: memory is released when the instruction ends
+        }
+    }
+}
+
+/// Cross-chain transfer message encodings (Wormhole token-bridge style) and posted VAA
+/// verification, for a future cross-chain extension
+pub mod bridge {
+    use super::*;
+
+    /// Wormhole chain id of Solana
+    pub const SOLANA_CHAIN_ID: u16 = 1;
+
+    /// Decimals carried on the wire; larger precisions are truncated
+    pub const BRIDGE_DECIMALS: u8 = 8;
+
+    /// Payload id of a token-bridge transfer
+    pub const TRANSFER_PAYLOAD_ID: u8 = 1;
+
+    /// Length of an encoded transfer payload
+    pub const TRANSFER_PAYLOAD_LEN: usize = 133;
+
+    /// Prefix of a posted VAA account
+    pub const POSTED_VAA_MAGIC: &[u8; 3] = b"vaa";
+
+    /// A cross-chain transfer with its amount normalized to `BRIDGE_DECIMALS`
+    #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+    pub struct CrossChainTransfer {
+        pub amount: u64,
+        pub token_address: [u8; 32],
+        pub token_chain: u16,
+        pub recipient: [u8; 32],
+        pub recipient_chain: u16,
+        pub fee: u64,
+    }
+
+    impl CrossChainTransfer {
+        /// Build an outbound transfer from a local amount with `decimals`
+        pub fn new_outbound(
+            amount: u64,
+            decimals: u8,
+            token_address: [u8; 32],
+            recipient: [u8; 32],
+            recipient_chain: u16,
+        ) -> CommonResult<Self> {
+            Ok(Self {
+                amount: normalize_amount(amount, decimals)?,
+                token_address,
+                token_chain: SOLANA_CHAIN_ID,
+                recipient,
+                recipient_chain,
+                fee: 0,
+            })
+        }
+
+        /// Amount in local units for a token with `decimals`
+        pub fn local_amount(&self, decimals: u8) -> CommonResult<u64> {
+            denormalize_amount(self.amount, decimals)
+        }
+
+        /// Recipient as an EVM address, for transfers bound to an EVM chain
+        pub fn evm_recipient(&self) -> CommonResult<EvmAddress> {
+            EvmAddress::from_bytes32(&self.recipient)
+        }
+
+        /// Encode as a big-endian token-bridge transfer payload (u256 amounts)
+        pub fn to_payload(&self) -> Vec<u8> {
+            let mut payload = Vec::with_capacity(TRANSFER_PAYLOAD_LEN);
+            payload.push(TRANSFER_PAYLOAD_ID);
+            payload.extend_from_slice(&encode_u256(self.amount));
+            payload.extend_from_slice(&self.token_address);
+            payload.extend_from_slice(&self.token_chain.to_be_bytes());
+            payload.extend_from_slice(&self.recipient);
+            payload.extend_from_slice(&self.recipient_chain.to_be_bytes());
+            payload.extend_from_slice(&encode_u256(self.fee));
+            payload
+        }
+
+        /// Decode a token-bridge transfer payload
+        pub fn from_payload(payload: &[u8]) -> CommonResult<Self> {
+            if payload.len() != TRANSFER_PAYLOAD_LEN || payload[0] != TRANSFER_PAYLOAD_ID {
+                return Err(CommonError::Custom("Invalid transfer payload".to_string()));
+            }
+            
+            Ok(Self {
+                amount: decode_u256(&payload[1..33])?,
+                token_address: read_bytes32(&payload[33..65]),
+                token_chain: u16::from_be_bytes([payload[65], payload[66]]),
+                recipient: read_bytes32(&payload[67..99]),
+                recipient_chain: u16::from_be_bytes([payload[99], payload[100]]),
+                fee: decode_u256(&payload[101..133])?,
+            })
+        }
+    }
+
+    /// Normalize a local amount to `BRIDGE_DECIMALS`
+    pub fn normalize_amount(amount: u64, decimals: u8) -> CommonResult<u64> {
+        token_math::normalize_amount(amount, decimals, decimals.min(BRIDGE_DECIMALS))
+    }
+
+    /// Convert a normalized amount back to a token with `decimals`
+    pub fn denormalize_amount(amount: u64, decimals: u8) -> CommonResult<u64> {
+        token_math::normalize_amount(amount, decimals.min(BRIDGE_DECIMALS), decimals)
+    }
+
+    /// Header and payload of a posted VAA account (Wormhole `PostedVAAData`)
+    #[derive(BorshSerialize, BorshDeserialize, Debug)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+    pub struct PostedVaa {
+        pub vaa_version: u8,
+        pub consistency_level: u8,
+        pub vaa_time: u32,
+        #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+        pub vaa_signature_account: Pubkey,
+        pub submission_time: u32,
+        pub nonce: u32,
+        pub sequence: u64,
+        pub emitter_chain: u16,
+        pub emitter_address: [u8; 32],
+        pub payload: Vec<u8>,
+    }
+
+    /// Load a posted VAA, checking it is owned by the core bridge and was emitted by
+    /// the registered emitter
+    pub fn verify_posted_vaa(
+        vaa_info: &AccountInfo,
+        core_bridge_program: &Pubkey,
+        emitter_chain: u16,
+        emitter_address: &[u8; 32],
+    ) -> CommonResult<PostedVaa> {
+        if vaa_info.owner != core_bridge_program {
+            return Err(CommonError::AccountValidationFailed);
+        }
+        
+        let data = vaa_info.data.borrow();
+        let body = data.strip_prefix(POSTED_VAA_MAGIC.as_ref())
+            .ok_or(CommonError::AccountValidationFailed)?;
+        
+        // Posted VAA accounts may carry trailing padding, so decode without a length check
+        let vaa = PostedVaa::deserialize(&mut &body[..])
+            .map_err(|_| CommonError::AccountValidationFailed)?;
+        
+        if vaa.emitter_chain != emitter_chain || vaa.emitter_address != *emitter_address {
+            return Err(CommonError::InsufficientPermissions);
+        }
+        
+        Ok(vaa)
+    }
+
+    fn encode_u256(value: u64) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        bytes[24..].copy_from_slice(&value.to_be_bytes());
+        bytes
+    }
+
+    fn decode_u256(bytes: &[u8]) -> CommonResult<u64> {
+        if bytes[..24].iter().any(|&byte| byte != 0) {
+            return Err(CommonError::InvalidCalculation);
+        }
+        
+        let mut value = [0u8; 8];
+        value.copy_from_slice(&bytes[24..32]);
+        Ok(u64::from_be_bytes(value))
+    }
+
+    fn read_bytes32(bytes: &[u8]) -> [u8; 32] {
+        let mut out = [0u8; 32];
+        out.copy_from_slice(bytes);
+        out
+    }
+}
+
+/// Program state account
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, ProgramAccount)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[space(auto)]
+pub struct ProgramState {
+    #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+    pub authority: Pubkey,
+    pub total_supply: u64,
+    pub is_initialized: bool,
+    pub paused: bool,
+    /// Hot key allowed to pause (but never unpause or configure) the program
+    #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+    pub emergency_guardian: Pubkey,
+    /// Leading zero bits required of `keccak(user || pow_nonce)`; zero disables the gate
+    pub pow_difficulty: u8,
+    /// Global `index_math` index against which interest-bearing balances are scaled
+    pub interest_index: u128,
+    /// Annual simple interest applied by `AccrueInterest`, in basis points
+    pub interest_rate_bps: u16,
+    /// When the index was last accrued; zero until the first accrual
+    pub last_accrual_at: i64,
+    /// Staking rewards emitted per second, shared by all stakers
+    pub reward_rate: u64,
+    /// Rewards accrued per staked token, scaled by `accrual::RATE_SCALE`
+    pub reward_per_share: u128,
+    pub total_staked: u64,
+    /// When `reward_per_share` was last advanced
+    pub last_reward_at: i64,
+    /// Seconds a `RequestWithdrawal` must wait before `ExecuteWithdrawal`
+    pub withdrawal_delay: u64,
+    /// Layout version, `PROGRAM_STATE_VERSION` once written or migrated by this program
+    pub version: u8,
+}
+
+/// Current `ProgramState::version`; states written before the field existed are grown to
+/// it with `MigrateAccount`
+pub const PROGRAM_STATE_VERSION: u8 = 1;
+
+impl ProgramState {
+    /// Advance the interest index to `now` at the current rate
+    pub fn accrue_interest(&mut self, now: i64) -> CommonResult<()> {
+        if self.last_accrual_at != 0 && now > self.last_accrual_at {
+            let elapsed = (now - self.last_accrual_at) as u64;
+            self.interest_index = index_math::accrue(self.interest_index, self.interest_rate_bps, elapsed)?;
+        }
+        self.last_accrual_at = self.last_accrual_at.max(now);
+        Ok(())
+    }
+
+    /// Advance the reward accumulator to `now` at the current rate; nothing accrues while
+    /// nothing is staked
+    pub fn accrue_rewards(&mut self, now: i64) -> CommonResult<()> {
+        if self.total_staked > 0 && now > self.last_reward_at {
+            let elapsed = (now - self.last_reward_at) as u64;
+            let increase = accrual::reward_per_share(self.reward_rate, elapsed, self.total_staked)?;
+            self.reward_per_share = self.reward_per_share
+                .checked_add(increase)
+                .ok_or(CommonError::InvalidCalculation)?;
+        }
+        self.last_reward_at = self.last_reward_at.max(now);
+        Ok(())
+    }
+
+    /// Share `amount` among current stakers through the reward accumulator. Returns the
+    /// amount shared: none while nothing is staked, otherwise `amount` less rounding dust
+    pub fn distribute_rewards(&mut self, amount: u64) -> CommonResult<u64> {
+        if self.total_staked == 0 {
+            return Ok(0);
+        }
+        
+        let increase = accrual::reward_per_share(amount, 1, self.total_staked)?;
+        self.reward_per_share = self.reward_per_share
+            .checked_add(increase)
+            .ok_or(CommonError::InvalidCalculation)?;
+        accrual::earned(self.total_staked, increase, 0)
+    }
+}
+
+/// Tunable program parameters, replacing values previously hard-coded in handlers
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct ProgramConfig {
+    /// Decimals applied to the initial supply
+    pub default_decimals: u8,
+    /// Multiplier applied to the initial balance of new user accounts
+    pub account_balance_multiplier: u64,
+    /// Fee suggested to clients building transfers
+    pub default_fee_basis_points: u16,
+    /// Lowest fee a transfer may specify
+    pub min_fee_basis_points: u16,
+    /// Largest amount a single transfer may move
+    pub max_transfer_amount: u64,
+    /// `common::feature_flags` bits enabled in this deployment
+    pub feature_flags: u64,
+}
+
+impl Default for ProgramConfig {
+    fn default() -> Self {
+        Self {
+            default_decimals: DEFAULT_DECIMALS,
+            account_balance_multiplier: 1000,
+            default_fee_basis_points: 0,
+            min_fee_basis_points: 0,
+            max_transfer_amount: u64::MAX,
+            feature_flags: 0,
+        }
+    }
+}
+
+impl ProgramConfig {
+    /// Check that every parameter is usable by the handlers
+    pub fn validate(&self) -> CommonResult<()> {
+        token_math::validate_decimals(self.default_decimals)?;
+        percentage::validate_basis_points(self.default_fee_basis_points)?;
+        percentage::validate_basis_points(self.min_fee_basis_points)?;
+        
+        if self.account_balance_multiplier == 0 || self.max_transfer_amount == 0 {
+            return Err(CommonError::InvalidCalculation);
+        }
+        
+        if self.default_fee_basis_points < self.min_fee_basis_points {
+            return Err(CommonError::Custom(
+                "Default fee is below the minimum fee".to_string()
+            ));
+        }
+        
+        Ok(())
+    }
+}
+
+/// User account structure (v4 layout)
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct UserAccount {
+    #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+    pub owner: Pubkey,
+    pub balance: u64,
+    #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+    pub program_state: Pubkey,
+    /// Account-level `user_flags` bits
+    pub flags: u32,
+    /// Bump of the user PDA
+    pub bump: u8,
+    /// Per-account nonce for replay protection
+    pub nonce: u64,
+    /// Slot of the last change to the account
+    pub last_activity_slot: u64,
+    /// Slot the account was flagged dormant by `FlagDormantAccount`; 0 when not flagged
+    pub dormant_since_slot: u64,
+    /// Layout the account is stored in; legacy accounts read as their layout's version
+    pub version: u8,
+}
+
+/// v3 user account layout, readable and writable until grown with `MigrateAccount` or
+/// `ReallocUserAccount`
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct UserAccountV3 {
+    #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+    pub owner: Pubkey,
+    pub balance: u64,
+    #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+    pub program_state: Pubkey,
+    pub flags: u32,
+    pub bump: u8,
+    pub nonce: u64,
+    pub last_activity_slot: u64,
+    pub dormant_since_slot: u64,
+}
+
+impl From<UserAccountV3> for UserAccount {
+    fn from(legacy: UserAccountV3) -> Self {
+        Self {
+            owner: legacy.owner,
+            balance: legacy.balance,
+            program_state: legacy.program_state,
+            flags: legacy.flags,
+            bump: legacy.bump,
+            nonce: legacy.nonce,
+            last_activity_slot: legacy.last_activity_slot,
+            dormant_since_slot: legacy.dormant_since_slot,
+            version: 3,
+        }
+    }
+}
+
+/// v2 user account layout, readable and writable without activity tracking until grown
+/// with `MigrateAccount` or `ReallocUserAccount`
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct UserAccountV2 {
+    #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+    pub owner: Pubkey,
+    pub balance: u64,
+    #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+    pub program_state: Pubkey,
+    pub flags: u32,
+    pub bump: u8,
+    pub nonce: u64,
+}
+
+impl From<UserAccountV2> for UserAccount {
+    fn from(legacy: UserAccountV2) -> Self {
+        Self {
+            owner: legacy.owner,
+            balance: legacy.balance,
+            program_state: legacy.program_state,
+            flags: legacy.flags,
+            bump: legacy.bump,
+            nonce: legacy.nonce,
+            last_activity_slot: 0,
+            dormant_since_slot: 0,
+            version: 2,
+        }
+    }
+}
+
+/// Original user account layout, readable until migrated with `MigrateUserAccountV2` or
+/// `ReallocUserAccount`
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct UserAccountV1 {
+    #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+    pub owner: Pubkey,
+    pub balance: u64,
+    #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+    pub program_state: Pubkey,
+}
+
+impl From<UserAccountV1> for UserAccount {
+    fn from(legacy: UserAccountV1) -> Self {
+        Self {
+            owner: legacy.owner,
+            balance: legacy.balance,
+            program_state: legacy.program_state,
+            flags: 0,
+            bump: 0,
+            nonce: 0,
+            last_activity_slot: 0,
+            dormant_since_slot: 0,
+            version: 1,
+        }
+    }
+}
+
+/// Size of a v1 user account
+pub const USER_ACCOUNT_V1_LEN: usize = 32 + 8 + 32;
+
+/// Size of a v2 user account
+pub const USER_ACCOUNT_V2_LEN: usize = USER_ACCOUNT_V1_LEN + 4 + 1 + 8;
+
+/// Size of a v3 user account
+pub const USER_ACCOUNT_V3_LEN: usize = USER_ACCOUNT_V2_LEN + 8 + 8;
+
+/// Size of a current user account
+pub const USER_ACCOUNT_LEN: usize = USER_ACCOUNT_V3_LEN + 1;
+
+/// `UserAccount::version` of the current layout
+pub const USER_ACCOUNT_VERSION: u8 = 4;
+
+/// Session key granting a delegate scoped, limited access to an owner's account
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct SessionKey {
+    #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+    pub owner: Pubkey,
+    #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+    pub delegate: Pubkey,
+    pub scope: u32,
+    pub per_operation_limit: u64,
+    pub expires_at: i64,
+}
+
+/// Bits of `UserAccount::flags`
+pub mod user_flags {
+    /// Outbound transfers are checked against the account's `AccountLimits`
+    pub const LIMITED: u32 = 1 << 0;
+    /// Balance can't be transferred out (reputation or credential balances); set at creation
+    pub const SOULBOUND: u32 = 1 << 1;
+    /// Flagged by the compliance authority; the clawback authority may reclaim its balance
+    pub const FLAGGED: u32 = 1 << 2;
+    /// `balance` holds units scaled by `ProgramState::interest_index`; only
+    /// `SetInterestBearing` accepts such accounts
+    pub const INTEREST_BEARING: u32 = 1 << 3;
+    /// Frozen by the program authority; transfers from or to the account are rejected
+    pub const FROZEN: u32 = 1 << 4;
+}
+
+/// Operation bits usable in `SessionKey::scope`
+pub mod session_scope {
+    pub const TRANSFER: u32 = 1 << 0;
+
+    /// Every scope bit currently understood by the program
+    pub const ALL: u32 = TRANSFER;
+}
+
+/// Highest proof-of-work difficulty accepted by `SetPowDifficulty`
+pub const MAX_POW_DIFFICULTY: u8 = 64;
+
+/// Number of recent idempotency keys remembered per sender
+pub const IDEMPOTENCY_LOG_LEN: usize = 16;
+
+/// How long a recorded idempotency key blocks a repeat
+pub const IDEMPOTENCY_WINDOW_SECONDS: i64 = 24 * 60 * 60;
+
+/// An idempotency key and when it was used
+#[derive(BorshSerialize, BorshDeserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct IdempotencyEntry {
+    pub key: [u8; 16],
+    pub recorded_at: i64,
+}
+
+/// Ring buffer of a sender's recent idempotency keys
+#[derive(BorshSerialize, BorshDeserialize, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct IdempotencyLog {
+    pub next: u8,
+    pub entries: [IdempotencyEntry; IDEMPOTENCY_LOG_LEN],
+}
+
+impl IdempotencyLog {
+    /// Record `key`, failing if it was already used within the window. Slots are only reused
+    /// once their key has expired: evicting a live key would let it be replayed, so a log
+    /// whose slots are all live fails closed until the oldest one expires.
+    pub fn record(&mut self, key: [u8; 16], now: i64) -> CommonResult<()> {
+        let live = |entry: &IdempotencyEntry| {
+            entry.recorded_at != 0 && now.saturating_sub(entry.recorded_at) < IDEMPOTENCY_WINDOW_SECONDS
+        };
+        
+        if self.entries.iter().any(|entry| live(entry) && entry.key == key) {
+            return Err(CommonError::Custom("Duplicate idempotency key".to_string()));
+        }
+        
+        let start = self.next as usize % IDEMPOTENCY_LOG_LEN;
+        let slot = (0..IDEMPOTENCY_LOG_LEN)
+            .map(|offset| (start + offset) % IDEMPOTENCY_LOG_LEN)
+            .find(|&slot| !live(&self.entries[slot]))
+            .ok_or_else(|| CommonError::Custom("Idempotency log is full".to_string()))?;
+        self.entries[slot] = IdempotencyEntry { key, recorded_at: now };
+        self.next = ((slot + 1) % IDEMPOTENCY_LOG_LEN) as u8;
+        Ok(())
+    }
+}
+
+/// Number of past rotations kept in `AuthorityHistory`
+pub const AUTHORITY_HISTORY_LEN: usize = 8;
+
+/// A single authority hand-over
+#[derive(BorshSerialize, BorshDeserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct AuthorityRotation {
+    #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+    pub previous_authority: Pubkey,
+    #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+    pub new_authority: Pubkey,
+    pub rotated_at: i64,
+}
+
+/// Companion account to `ProgramState` holding the most recent rotations, oldest first
+#[derive(BorshSerialize, BorshDeserialize, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct AuthorityHistory {
+    pub len: u8,
+    pub rotations: [AuthorityRotation; AUTHORITY_HISTORY_LEN],
+}
+
+impl AuthorityHistory {
+    /// Recorded rotations, oldest first
+    pub fn entries(&self) -> &[AuthorityRotation] {
+        &self.rotations[..(self.len as usize).min(AUTHORITY_HISTORY_LEN)]
+    }
+
+    /// Whether `authority` was retired by one of the recorded rotations
+    pub fn contains(&self, authority: &Pubkey) -> bool {
+        self.entries().iter().any(|r| r.previous_authority == *authority)
+    }
+
+    /// Append a rotation, dropping the oldest once the history is full
+    pub fn record(&mut self, rotation: AuthorityRotation) {
+        let len = self.entries().len();
+        if len == AUTHORITY_HISTORY_LEN {
+            self.rotations.rotate_left(1);
+            self.rotations[AUTHORITY_HISTORY_LEN - 1] = rotation;
+        } else {
+            self.rotations[len] = rotation;
+            self.len += 1;
+        }
+    }
+}
+
+/// Headline program statistics, readable without an indexer
+///
+/// Only user-to-user transfers and user account creation are counted. Payments,
+/// subscriptions, streams, escrows, vesting, staking, scheduled transfers and withdrawals
+/// keep their totals in their own accounts and are not included.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct Metrics {
+    /// One per transfer: each batched `Transfer` and each `AtomicSwap` leg counts separately
+    pub transfer_count: u64,
+    pub total_volume: u64,
+    pub fees_collected: u64,
+    /// Users created with `CreateUserAccount`; also the registry's next position, so
+    /// compressed user accounts are not counted
+    pub unique_users: u64,
+}
+
+/// A transfer escrowed until `execute_after`, executable by any cranker
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct ScheduledTransfer {
+    #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+    pub owner: Pubkey,
+    /// Address of the recipient's user account
+    #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub bounty: u64,
+    pub execute_after: i64,
+    pub executed: bool,
+}
+
+/// Recurring payment from a payer to a merchant's user account
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct Subscription {
+    #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+    pub payer: Pubkey,
+    /// Address of the merchant's user account
+    #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+    pub merchant: Pubkey,
+    pub amount: u64,
+    pub interval: u64,
+    /// End of the current (unpaid) period
+    pub next_due: i64,
+    pub active: bool,
+}
+
+/// Continuous payment releasing `deposit` at a fixed-point per-second `flow_rate`
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct Stream {
+    #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+    pub sender: Pubkey,
+    /// Address of the recipient's user account
+    #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+    pub recipient: Pubkey,
+    pub deposit: u64,
+    /// Tokens per second scaled by `accrual::RATE_SCALE`
+    pub flow_rate: u128,
+    pub start_time: i64,
+    pub stop_time: i64,
+    pub withdrawn: u64,
+    pub active: bool,
+}
+
+impl Stream {
+    /// Total released to the recipient by `now`
+    pub fn streamed(&self, now: i64) -> CommonResult<u64> {
+        if now >= self.stop_time {
+            return Ok(self.deposit);
+        }
+        
+        let elapsed = now.saturating_sub(self.start_time).max(0) as u64;
+        accrual::accrued_at_rate(self.flow_rate, elapsed, self.deposit)
+    }
+
+    /// Released but not yet withdrawn by `now`
+    pub fn withdrawable(&self, now: i64) -> CommonResult<u64> {
+        safe_math::safe_sub(self.streamed(now)?, self.withdrawn)
+    }
+}
+
+/// Merchant payment request, settled at most once
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct Invoice {
+    #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+    pub merchant: Pubkey,
+    /// User account credited on payment
+    #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+    pub merchant_account: Pubkey,
+    pub invoice_id: u64,
+    pub amount: u64,
+    /// Hash of the off-chain memo, for reconciliation
+    pub memo_hash: [u8; 32],
+    pub expiry: i64,
+    pub paid: bool,
+    #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+    pub payer: Pubkey,
+    pub paid_at: i64,
+}
+
+/// Settlement of an `Invoice`, logged via `sol_log_data` as `[TAG, borsh(event)]`
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct InvoicePaidEvent {
+    #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+    pub invoice: Pubkey,
+    #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+    pub merchant: Pubkey,
+    #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+    pub payer: Pubkey,
+    pub amount: u64,
+    pub memo_hash: [u8; 32],
+    pub paid_at: i64,
+}
+
+impl InvoicePaidEvent {
+    pub const TAG: &'static [u8] = b"invoice_paid";
+}
+
+/// Outcome of a `TransferUpTo`, logged via `sol_log_data` as `[TAG, borsh(event)]`
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct TransferFilledEvent {
+    #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+    pub from: Pubkey,
+    #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+    pub to: Pubkey,
+    pub max_amount: u64,
+    pub filled_amount: u64,
+    pub fee: u64,
+}
+
+impl TransferFilledEvent {
+    pub const TAG: &'static [u8] = b"transfer_filled";
+}
+
+/// A completed `TransferWithFee`, `TransferFrom` or batched transfer, logged via
+/// `sol_log_data` as `[TAG, borsh(event)]`
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct TransferEvent {
+    #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+    pub from: Pubkey,
+    #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+    pub to: Pubkey,
+    pub amount: u64,
+    pub fee: u64,
+}
+
+impl TransferEvent {
+    pub const TAG: &'static [u8] = b"transfer";
+}
+
+/// Program initialization, logged via `sol_log_data` as `[TAG, borsh(event)]`
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct InitializeEvent {
+    #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+    pub authority: Pubkey,
+    pub total_supply: u64,
+}
+
+impl InitializeEvent {
+    pub const TAG: &'static [u8] = b"initialize";
+}
+
+/// A user account's balance after a write, logged via `sol_log_data` as `[TAG, borsh(event)]`
+/// on every save so clients can rebuild balance history from transaction logs. Balances
+/// of `INTEREST_BEARING` accounts are in scaled units.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct BalanceCheckpointEvent {
+    #[cfg_attr(feature = "serde", serde(with = "common::serde_pubkey"))]
+    pub user_account: Pubkey,
+    pub balance: u64,
+    pub flags: u32,
+}
+
+impl BalanceCheckpointEvent {
+    pub const TAG: &'static [u8] = b"balance";
+}
@@ -0,0 +1,1530 @@
+use account_utils::{account_creation, custody::ProgramAuthority, merkle};
+use borsh::BorshSerialize;
+use common::feature_flags;
+use math_utils::index_math;
+use my_solana_program::{
+    allowlist::AllowlistEntry,
+    batch::SubOp, compliance::{AccountLimits, ComplianceConfig}, crank::{BountyPool, Cranker}, escrow::Escrow, staking::StakeAccount, compression::{self, CompressedTree, CompressedUserRecord}, delegation::DELEGATIONS_LEN, events, instruction,
+    insurance::InsuranceFund, process_instruction, reclaim::ReclaimPolicy, treasury::{FeeSchedule, Treasury}, registry::{RegistryPage, PAGE_CAPACITY}, upload::Upload, user_flags,
+    withdrawal::PendingWithdrawal,
+    vesting::VestingSchedule, errors, session_scope, IdempotencyLog, SessionKey, Invoice, Metrics, ScheduledTransfer, Stream, Subscription, ProgramConfig, ProgramInstruction, ProgramState, UserAccount, UserAccountV3,
+    IDEMPOTENCY_LOG_LEN, IDEMPOTENCY_WINDOW_SECONDS, PROGRAM_STATE_VERSION, USER_ACCOUNT_V3_LEN,
+};
+use solana_program::{keccak, program_error::ProgramError, pubkey::Pubkey};
+use test_utils::{debug, fixtures, infos, sysvars, MockAccount};
+
+#[test]
+fn initialize_records_payer_as_authority() {
+    let program_id = Pubkey::new_unique();
+    let payer = fixtures::wallet();
+    let state = fixtures::empty_account_for(
+        &program_id,
+        fixtures::program_state_address(&program_id),
+        &fixtures::program_state(&payer.key),
+    );
+    let config = fixtures::empty_account_for(
+        &program_id,
+        account_creation::create_program_config_pda(&program_id).unwrap().0,
+        &ProgramConfig::default(),
+    );
+    let mut accounts = vec![state, payer.clone(), config, fixtures::treasury_account(&program_id)];
+    
+    let data = ProgramInstruction::Initialize { initial_amount: 5 }.pack().unwrap();
+    process_instruction(&program_id, &infos(&mut accounts), &data).unwrap();
+    
+    let state: ProgramState = accounts[0].decode();
+    assert_eq!(state.authority, payer.key);
+    assert!(state.is_initialized);
+}
+
+#[test]
+fn initialize_cannot_be_replayed_to_take_over_the_authority() {
+    let program_id = Pubkey::new_unique();
+    let payer = fixtures::wallet();
+    let state = fixtures::empty_account_for(
+        &program_id,
+        fixtures::program_state_address(&program_id),
+        &fixtures::program_state(&payer.key),
+    );
+    let config = fixtures::empty_account_for(
+        &program_id,
+        account_creation::create_program_config_pda(&program_id).unwrap().0,
+        &ProgramConfig::default(),
+    );
+    let mut accounts = vec![state, payer.clone(), config, fixtures::treasury_account(&program_id)];
+    let data = ProgramInstruction::Initialize { initial_amount: 5 }.pack().unwrap();
+    process_instruction(&program_id, &infos(&mut accounts), &data).unwrap();
+    
+    accounts[1] = fixtures::wallet();
+    assert_eq!(
+        process_instruction(&program_id, &infos(&mut accounts), &data),
+        Err(ProgramError::AccountAlreadyInitialized)
+    );
+    assert_eq!(accounts[0].decode::<ProgramState>().authority, payer.key);
+}
+
+#[test]
+fn transfer_moves_amount_plus_fee() {
+    let program_id = Pubkey::new_unique();
+    let owner = fixtures::wallet();
+    let recipient = Pubkey::new_unique();
+    
+    let mut accounts = vec![
+        fixtures::user_account_account(&program_id, &fixtures::user_account(&program_id, &owner.key, 10_000)),
+        fixtures::user_account_account(&program_id, &fixtures::user_account(&program_id, &recipient, 0)),
+        owner.clone(),
+        fixtures::program_state_account(&program_id, &fixtures::program_state(&owner.key)),
+        fixtures::metrics_account(&program_id),
+        fixtures::program_config_account(&program_id, &ProgramConfig::default()),
+        fixtures::treasury_account(&program_id),
+    ];
+    let sender_before = accounts[0].data.clone();
+    
+    let data = ProgramInstruction::TransferWithFee {
+        amount: 1_000,
+        fee_basis_points: 100,
+        idempotency_key: None,
+    }
+    .pack()
+    .unwrap();
+    process_instruction(&program_id, &infos(&mut accounts), &data).unwrap();
+    
+    assert_eq!(debug::changed_fields::<UserAccount>(&sender_before, &accounts[0].data), ["balance", "last_activity_slot"]);
+    assert_eq!(accounts[0].decode::<UserAccount>().balance, 8_990);
+    assert_eq!(accounts[1].decode::<UserAccount>().balance, 1_000);
+}
+
+#[test]
+fn transfers_without_the_treasury_are_rejected_rather_than_burning_the_fee() {
+    let program_id = Pubkey::new_unique();
+    let owner = fixtures::wallet();
+    
+    let mut accounts = vec![
+        fixtures::user_account_account(&program_id, &fixtures::user_account(&program_id, &owner.key, 10_000)),
+        fixtures::user_account_account(&program_id, &fixtures::user_account(&program_id, &Pubkey::new_unique(), 0)),
+        owner.clone(),
+        fixtures::program_state_account(&program_id, &fixtures::program_state(&owner.key)),
+        fixtures::metrics_account(&program_id),
+        fixtures::program_config_account(&program_id, &ProgramConfig::default()),
+    ];
+    let data = ProgramInstruction::TransferWithFee { amount: 1_000, fee_basis_points: 100, idempotency_key: None }
+        .pack()
+        .unwrap();
+    assert_eq!(
+        process_instruction(&program_id, &infos(&mut accounts), &data),
+        Err(ProgramError::NotEnoughAccountKeys)
+    );
+}
+
+#[test]
+fn a_full_idempotency_log_refuses_new_keys_instead_of_evicting_live_ones() {
+    let program_id = Pubkey::new_unique();
+    let owner = fixtures::wallet();
+    let (log_address, _bump) = account_creation::create_idempotency_log_pda(&owner.key, &program_id).unwrap();
+    sysvars::warp_to_timestamp(1_000);
+    
+    let mut accounts = vec![
+        fixtures::user_account_account(&program_id, &fixtures::user_account(&program_id, &owner.key, 10_000)),
+        fixtures::user_account_account(&program_id, &fixtures::user_account(&program_id, &Pubkey::new_unique(), 0)),
+        owner.clone(),
+        fixtures::program_state_account(&program_id, &fixtures::program_state(&owner.key)),
+        fixtures::metrics_account(&program_id),
+        fixtures::program_config_account(&program_id, &ProgramConfig::default()),
+        fixtures::treasury_account(&program_id),
+        fixtures::empty_account_for(&program_id, log_address, &IdempotencyLog::default()),
+    ];
+    let transfer = |key: u8| {
+        ProgramInstruction::TransferWithFee { amount: 10, fee_basis_points: 0, idempotency_key: Some([key; 16]) }
+            .pack()
+            .unwrap()
+    };
+    for key in 0..IDEMPOTENCY_LOG_LEN as u8 {
+        process_instruction(&program_id, &infos(&mut accounts), &transfer(key)).unwrap();
+    }
+    let full = accounts[7].data.clone();
+    assert_eq!(process_instruction(&program_id, &infos(&mut accounts), &transfer(0)), Err(ProgramError::InvalidArgument));
+    assert_eq!(process_instruction(&program_id, &infos(&mut accounts), &transfer(99)), Err(ProgramError::InvalidArgument));
+    assert_eq!(accounts[7].data, full);
+    
+    sysvars::warp_to_timestamp(1_000 + IDEMPOTENCY_WINDOW_SECONDS);
+    process_instruction(&program_id, &infos(&mut accounts), &transfer(99)).unwrap();
+    process_instruction(&program_id, &infos(&mut accounts), &transfer(0)).unwrap();
+    assert_eq!(accounts[1].decode::<UserAccount>().balance, 180);
+}
+
+#[test]
+fn every_debit_counts_against_the_daily_limit_fees_included() {
+    let program_id = Pubkey::new_unique();
+    let owner = fixtures::wallet();
+    let mut sender = fixtures::user_account(&program_id, &owner.key, 10_000);
+    sender.flags = user_flags::LIMITED;
+    let sender = fixtures::user_account_account(&program_id, &sender);
+    let (limits_address, _bump) = account_creation::create_account_limits_pda(&sender.key, &program_id).unwrap();
+    let limits = MockAccount::new().key(limits_address).owner(program_id).borsh(&AccountLimits {
+        user_account: sender.key,
+        max_per_day: 1_500,
+        ..AccountLimits::default()
+    });
+    let (pool_address, _bump) = BountyPool::find_address(&program_id).unwrap();
+    
+    let mut transfer = vec![
+        sender,
+        fixtures::user_account_account(&program_id, &fixtures::user_account(&program_id, &Pubkey::new_unique(), 0)),
+        owner.clone(),
+        fixtures::program_state_account(&program_id, &fixtures::program_state(&owner.key)),
+        fixtures::metrics_account(&program_id),
+        fixtures::program_config_account(&program_id, &ProgramConfig::default()),
+        fixtures::treasury_account(&program_id),
+        limits,
+    ];
+    let data = ProgramInstruction::TransferWithFee { amount: 1_000, fee_basis_points: 100, idempotency_key: None }
+        .pack()
+        .unwrap();
+    process_instruction(&program_id, &infos(&mut transfer), &data).unwrap();
+    assert_eq!(transfer[7].decode::<AccountLimits>().spent_in_day, 1_010);
+    
+    let mut fund = vec![
+        MockAccount::new().key(pool_address).owner(program_id).borsh(&BountyPool::default()),
+        transfer[0].clone(),
+        owner.clone(),
+        transfer[3].clone(),
+        transfer[5].clone(),
+    ];
+    let fund_pool = |amount| ProgramInstruction::FundBountyPool { amount }.pack().unwrap();
+    assert_eq!(process_instruction(&program_id, &infos(&mut fund), &fund_pool(490)), Err(ProgramError::NotEnoughAccountKeys));
+    fund.push(transfer[7].clone());
+    assert_eq!(process_instruction(&program_id, &infos(&mut fund), &fund_pool(491)), Err(ProgramError::InvalidArgument));
+    process_instruction(&program_id, &infos(&mut fund), &fund_pool(490)).unwrap();
+    assert_eq!(fund[5].decode::<AccountLimits>().spent_in_day, 1_500);
+    assert_eq!(fund[0].decode::<BountyPool>().balance, 490);
+    
+    // Funding the treasury is a debit like any other
+    let mut fund_treasury = vec![fixtures::treasury_account(&program_id), fund[1].clone(), owner.clone(), transfer[3].clone()];
+    let data = ProgramInstruction::FundTreasury { amount: 1 }.pack().unwrap();
+    assert_eq!(process_instruction(&program_id, &infos(&mut fund_treasury), &data), Err(ProgramError::NotEnoughAccountKeys));
+    fund_treasury.push(fund[5].clone());
+    assert_eq!(process_instruction(&program_id, &infos(&mut fund_treasury), &data), Err(ProgramError::InvalidArgument));
+}
+
+#[test]
+fn attestation_gating_covers_debits_beyond_transfers() {
+    let program_id = Pubkey::new_unique();
+    let owner = fixtures::wallet();
+    let (withdrawal_address, _bump) = PendingWithdrawal::find_address(&owner.key, &program_id).unwrap();
+    let (compliance_address, _bump) = account_creation::create_compliance_config_pda(&program_id).unwrap();
+    let config = ProgramConfig { feature_flags: feature_flags::ATTESTATION_GATING, ..ProgramConfig::default() };
+    let compliance = ComplianceConfig { attestor: Pubkey::new_unique(), attestation_threshold: 1_000, ..ComplianceConfig::default() };
+    
+    let mut accounts = vec![
+        fixtures::empty_account_for(&program_id, withdrawal_address, &PendingWithdrawal::default()),
+        fixtures::user_account_account(&program_id, &fixtures::user_account(&program_id, &owner.key, 10_000)),
+        owner.clone(),
+        fixtures::program_state_account(&program_id, &fixtures::program_state(&owner.key)),
+    ];
+    let request = |amount| ProgramInstruction::RequestWithdrawal { amount }.pack().unwrap();
+    // Leaving out the program config can't skip the gate
+    assert_eq!(process_instruction(&program_id, &infos(&mut accounts), &request(500)), Err(ProgramError::NotEnoughAccountKeys));
+    
+    accounts.extend([
+        fixtures::program_config_account(&program_id, &config),
+        MockAccount::new().key(compliance_address).owner(program_id).borsh(&compliance),
+    ]);
+    assert_eq!(process_instruction(&program_id, &infos(&mut accounts), &request(4_000)), Err(ProgramError::NotEnoughAccountKeys));
+    process_instruction(&program_id, &infos(&mut accounts), &request(500)).unwrap();
+    assert_eq!(accounts[1].decode::<UserAccount>().balance, 9_500);
+    
+    let mut fund = vec![fixtures::treasury_account(&program_id), accounts[1].clone(), owner.clone(), accounts[3].clone()];
+    let fund_treasury = |amount| ProgramInstruction::FundTreasury { amount }.pack().unwrap();
+    assert_eq!(process_instruction(&program_id, &infos(&mut fund), &fund_treasury(500)), Err(ProgramError::NotEnoughAccountKeys));
+    fund.extend([accounts[4].clone(), accounts[5].clone()]);
+    assert_eq!(process_instruction(&program_id, &infos(&mut fund), &fund_treasury(4_000)), Err(ProgramError::NotEnoughAccountKeys));
+    process_instruction(&program_id, &infos(&mut fund), &fund_treasury(500)).unwrap();
+    assert_eq!(fund[1].decode::<UserAccount>().balance, 9_000);
+}
+
+#[test]
+fn legacy_v1_transfers_are_routed_and_unknown_versions_rejected() {
+    let program_id = Pubkey::new_unique();
+    let owner = fixtures::wallet();
+    let recipient = Pubkey::new_unique();
+    
+    let mut accounts = vec![
+        fixtures::user_account_account(&program_id, &fixtures::user_account(&program_id, &owner.key, 10_000)),
+        fixtures::user_account_account(&program_id, &fixtures::user_account(&program_id, &recipient, 0)),
+        owner.clone(),
+        fixtures::program_state_account(&program_id, &fixtures::program_state(&owner.key)),
+        fixtures::metrics_account(&program_id),
+        fixtures::program_config_account(&program_id, &ProgramConfig::default()),
+        fixtures::treasury_account(&program_id),
+    ];
+    
+    // V1 `TransferWithFee` predates `idempotency_key`
+    let mut v1 = vec![instruction::INSTRUCTION_VERSION_V1, 2];
+    v1.extend(1_000u64.to_le_bytes());
+    v1.extend(100u16.to_le_bytes());
+    process_instruction(&program_id, &infos(&mut accounts), &v1).unwrap();
+    assert_eq!(accounts[1].decode::<UserAccount>().balance, 1_000);
+    
+    v1[0] = instruction::CURRENT_INSTRUCTION_VERSION + 1;
+    assert_eq!(
+        process_instruction(&program_id, &infos(&mut accounts), &v1),
+        Err(ProgramError::Custom(instruction::UNSUPPORTED_INSTRUCTION_VERSION))
+    );
+    assert_eq!(accounts[1].decode::<UserAccount>().balance, 1_000);
+}
+
+#[test]
+fn v3_user_accounts_keep_their_layout_and_current_accounts_are_not_migrated_again() {
+    let program_id = Pubkey::new_unique();
+    let owner = fixtures::wallet();
+    let recipient = fixtures::wallet();
+    let sender = fixtures::user_account(&program_id, &owner.key, 10_000);
+    let (sender_key, _bump) = account_creation::create_user_pda(&owner.key, &program_id).unwrap();
+    let legacy = UserAccountV3 {
+        owner: sender.owner,
+        balance: sender.balance,
+        program_state: sender.program_state,
+        flags: 0,
+        bump: sender.bump,
+        nonce: 0,
+        last_activity_slot: 0,
+        dormant_since_slot: 0,
+    };
+    
+    let mut accounts = vec![
+        MockAccount::new().key(sender_key).owner(program_id).borsh(&legacy),
+        fixtures::user_account_account(&program_id, &fixtures::user_account(&program_id, &recipient.key, 0)),
+        owner.clone(),
+        fixtures::program_state_account(&program_id, &fixtures::program_state(&owner.key)),
+        fixtures::metrics_account(&program_id),
+        fixtures::program_config_account(&program_id, &ProgramConfig::default()),
+        fixtures::treasury_account(&program_id),
+    ];
+    let data = ProgramInstruction::TransferWithFee { amount: 1_000, fee_basis_points: 100, idempotency_key: None }
+        .pack()
+        .unwrap();
+    process_instruction(&program_id, &infos(&mut accounts), &data).unwrap();
+    
+    assert_eq!(accounts[0].data.len(), USER_ACCOUNT_V3_LEN);
+    assert_eq!(accounts[0].decode::<UserAccountV3>().balance, 8_990);
+    
+    let migrate = ProgramInstruction::MigrateAccount.pack().unwrap();
+    let system_program = MockAccount::new().key(solana_program::system_program::id()).executable();
+    let mut user = vec![accounts[1].clone(), recipient.clone(), system_program.clone()];
+    assert_eq!(process_instruction(&program_id, &infos(&mut user), &migrate), Err(ProgramError::InvalidAccountData));
+    
+    let mut state = vec![accounts[3].clone(), recipient.clone(), system_program.clone()];
+    assert_eq!(process_instruction(&program_id, &infos(&mut state), &migrate), Err(ProgramError::InvalidArgument));
+    state[1] = owner.clone();
+    assert_eq!(process_instruction(&program_id, &infos(&mut state), &migrate), Err(ProgramError::InvalidAccountData));
+}
+
+#[test]
+fn a_program_state_from_before_interest_accrual_migrates_with_working_defaults() {
+    let program_id = Pubkey::new_unique();
+    let authority = fixtures::wallet();
+    let (state_key, _bump) = account_creation::create_program_state_pda(&program_id).unwrap();
+    // authority, total_supply, is_initialized, paused, emergency_guardian, pow_difficulty
+    let mut legacy = (authority.key, 1_000_000u64, true, false, Pubkey::default(), 0u8).try_to_vec().unwrap();
+    // Mocks cannot realloc, so hand over the account as `grow_account` leaves it
+    legacy.resize(ProgramState::SIZE, 0);
+    
+    sysvars::warp_to_timestamp(1_700_000_000);
+    let system_program = MockAccount::new().key(solana_program::system_program::id()).executable();
+    let mut accounts = vec![
+        MockAccount::new().key(state_key).owner(program_id).data(legacy),
+        authority.clone(),
+        system_program,
+    ];
+    let migrate = ProgramInstruction::MigrateAccount.pack().unwrap();
+    process_instruction(&program_id, &infos(&mut accounts), &migrate).unwrap();
+    
+    let state = accounts[0].decode::<ProgramState>();
+    assert_eq!(state.version, PROGRAM_STATE_VERSION);
+    assert_eq!((state.authority, state.total_supply), (authority.key, 1_000_000));
+    assert_eq!(state.interest_index, index_math::INDEX_SCALE);
+    assert_eq!((state.last_accrual_at, state.last_reward_at), (1_700_000_000, 1_700_000_000));
+}
+
+#[test]
+fn transfer_fees_credited_to_the_treasury_are_collected_once_their_epoch_closes() {
+    let program_id = Pubkey::new_unique();
+    let (authority, owner) = (fixtures::wallet(), fixtures::wallet());
+    let (treasury_address, _bump) = account_creation::create_treasury_pda(&program_id).unwrap();
+    let state = fixtures::program_state_account(&program_id, &fixtures::program_state(&authority.key));
+    let collector = fixtures::user_account_account(&program_id, &fixtures::user_account(&program_id, &authority.key, 0));
+    
+    let mut accounts = vec![
+        fixtures::user_account_account(&program_id, &fixtures::user_account(&program_id, &owner.key, 10_000)),
+        fixtures::user_account_account(&program_id, &fixtures::user_account(&program_id, &Pubkey::new_unique(), 0)),
+        owner.clone(),
+        state.clone(),
+        fixtures::metrics_account(&program_id),
+        fixtures::program_config_account(&program_id, &ProgramConfig::default()),
+        fixtures::empty_account_for(&program_id, treasury_address, &Treasury::default()),
+    ];
+    let data = ProgramInstruction::TransferWithFee { amount: 1_000, fee_basis_points: 100, idempotency_key: None }
+        .pack()
+        .unwrap();
+    process_instruction(&program_id, &infos(&mut accounts), &data).unwrap();
+    assert_eq!(accounts[6].decode::<Treasury>().epoch_fees, 10);
+    
+    let mut collect = vec![accounts[6].clone(), collector, state, authority];
+    let data = ProgramInstruction::CollectFees.pack().unwrap();
+    assert_eq!(process_instruction(&program_id, &infos(&mut collect), &data), Err(ProgramError::InvalidArgument));
+    
+    sysvars::warp_to_epoch(1);
+    process_instruction(&program_id, &infos(&mut collect), &data).unwrap();
+    assert_eq!(collect[1].decode::<UserAccount>().balance, 10);
+    assert_eq!(collect[0].decode::<Treasury>().undistributed_fees, 0);
+}
+
+#[test]
+fn transfer_requires_owner_signature() {
+    let program_id = Pubkey::new_unique();
+    let owner = MockAccount::new().data(vec![0]);
+    
+    let mut accounts = vec![
+        fixtures::user_account_account(&program_id, &fixtures::user_account(&program_id, &owner.key, 10_000)),
+        fixtures::user_account_account(&program_id, &fixtures::user_account(&program_id, &Pubkey::new_unique(), 0)),
+        owner.clone(),
+        fixtures::program_state_account(&program_id, &fixtures::program_state(&owner.key)),
+        fixtures::metrics_account(&program_id),
+        fixtures::program_config_account(&program_id, &ProgramConfig::default()),
+        fixtures::treasury_account(&program_id),
+    ];
+    
+    let data = ProgramInstruction::TransferWithFee {
+        amount: 1_000,
+        fee_basis_points: 100,
+        idempotency_key: None,
+    }
+    .pack()
+    .unwrap();
+    assert_eq!(
+        process_instruction(&program_id, &infos(&mut accounts), &data),
+        Err(ProgramError::MissingRequiredSignature)
+    );
+}
+
+#[test]
+fn soulbound_account_can_receive_but_not_send() {
+    let program_id = Pubkey::new_unique();
+    let owner = fixtures::wallet();
+    let soulbound_owner = fixtures::wallet();
+    let mut soulbound = fixtures::user_account(&program_id, &soulbound_owner.key, 5_000);
+    soulbound.flags = user_flags::SOULBOUND;
+    
+    let transfer = |amount| {
+        ProgramInstruction::TransferWithFee { amount, fee_basis_points: 100, idempotency_key: None }
+            .pack()
+            .unwrap()
+    };
+    
+    let mut accounts = vec![
+        fixtures::user_account_account(&program_id, &fixtures::user_account(&program_id, &owner.key, 10_000)),
+        fixtures::user_account_account(&program_id, &soulbound),
+        owner.clone(),
+        fixtures::program_state_account(&program_id, &fixtures::program_state(&owner.key)),
+        fixtures::metrics_account(&program_id),
+        fixtures::program_config_account(&program_id, &ProgramConfig::default()),
+        fixtures::treasury_account(&program_id),
+    ];
+    process_instruction(&program_id, &infos(&mut accounts), &transfer(1_000)).unwrap();
+    assert_eq!(accounts[1].decode::<UserAccount>().balance, 6_000);
+    
+    accounts.swap(0, 1);
+    accounts[2] = soulbound_owner.clone();
+    assert_eq!(
+        process_instruction(&program_id, &infos(&mut accounts), &transfer(1_000)),
+        Err(ProgramError::InvalidArgument)
+    );
+}
+
+#[test]
+fn frozen_accounts_can_neither_send_nor_receive_until_thawed() {
+    let program_id = Pubkey::new_unique();
+    let (authority, owner, recipient) = (fixtures::wallet(), fixtures::wallet(), fixtures::wallet());
+    let state = fixtures::program_state_account(&program_id, &fixtures::program_state(&authority.key));
+    let transfer = ProgramInstruction::TransferWithFee { amount: 1_000, fee_basis_points: 100, idempotency_key: None }
+        .pack()
+        .unwrap();
+    
+    let mut accounts = vec![
+        fixtures::user_account_account(&program_id, &fixtures::user_account(&program_id, &owner.key, 10_000)),
+        fixtures::user_account_account(&program_id, &fixtures::user_account(&program_id, &recipient.key, 0)),
+        owner.clone(),
+        state.clone(),
+        fixtures::metrics_account(&program_id),
+        fixtures::program_config_account(&program_id, &ProgramConfig::default()),
+        fixtures::treasury_account(&program_id),
+    ];
+    let set_frozen = |accounts: &mut [MockAccount], signer: &MockAccount, instruction: ProgramInstruction| {
+        let mut freeze = vec![accounts[1].clone(), state.clone(), signer.clone()];
+        let result = process_instruction(&program_id, &infos(&mut freeze), &instruction.pack().unwrap());
+        accounts[1] = freeze.remove(0);
+        result
+    };
+    
+    assert_eq!(set_frozen(&mut accounts, &owner, ProgramInstruction::FreezeAccount), Err(ProgramError::InvalidArgument));
+    set_frozen(&mut accounts, &authority, ProgramInstruction::FreezeAccount).unwrap();
+    assert_eq!(accounts[1].decode::<UserAccount>().flags, user_flags::FROZEN);
+    assert_eq!(process_instruction(&program_id, &infos(&mut accounts), &transfer), Err(ProgramError::InvalidArgument));
+    
+    // Nor be credited by its owner sweeping dust into it
+    let sweep = ProgramInstruction::SweepDust { threshold: 1 }.pack().unwrap();
+    let mut sweep_accounts = vec![accounts[1].clone(), recipient.clone(), state.clone()];
+    assert_eq!(process_instruction(&program_id, &infos(&mut sweep_accounts), &sweep), Err(ProgramError::InvalidArgument));
+    
+    set_frozen(&mut accounts, &authority, ProgramInstruction::ThawAccount).unwrap();
+    process_instruction(&program_id, &infos(&mut accounts), &transfer).unwrap();
+    assert_eq!(accounts[1].decode::<UserAccount>().balance, 1_000);
+}
+
+#[test]
+fn created_users_are_appended_to_the_registry_page_of_their_position() {
+    let program_id = Pubkey::new_unique();
+    let page = |index| {
+        let (key, _bump) = RegistryPage::find_address(index, &program_id).unwrap();
+        fixtures::empty_account_for(&program_id, key, &RegistryPage::default())
+    };
+    let user_account = |owner: &Pubkey| {
+        let (key, _bump) = account_creation::create_user_pda(owner, &program_id).unwrap();
+        fixtures::empty_account_for(&program_id, key, &fixtures::user_account(&program_id, owner, 0))
+    };
+    let (first, second) = (fixtures::wallet(), fixtures::wallet());
+    let data = ProgramInstruction::CreateUserAccount { initial_balance: 100, pow_nonce: 0 }.pack().unwrap();
+    
+    let mut accounts = vec![
+        user_account(&first.key),
+        first.clone(),
+        fixtures::program_state_account(&program_id, &fixtures::program_state(&first.key)),
+        fixtures::metrics_account(&program_id),
+        fixtures::program_config_account(&program_id, &ProgramConfig::default()),
+        page(0),
+    ];
+    process_instruction(&program_id, &infos(&mut accounts), &data).unwrap();
+    assert_eq!(accounts[5].decode::<RegistryPage>().entries(), [first.key]);
+    
+    // Once page 0 has taken its share of users, new users go to page 1
+    let mut metrics: Metrics = accounts[3].decode();
+    metrics.unique_users = PAGE_CAPACITY as u64;
+    accounts[3] = accounts[3].clone().borsh(&metrics);
+    accounts[0] = user_account(&second.key);
+    accounts[1] = second.clone();
+    assert_eq!(
+        process_instruction(&program_id, &infos(&mut accounts), &data),
+        Err(ProgramError::InvalidSeeds)
+    );
+    
+    // Mock accounts keep the writes of a failed instruction, which the runtime would roll back
+    accounts[0] = user_account(&second.key);
+    accounts[5] = page(1);
+    process_instruction(&program_id, &infos(&mut accounts), &data).unwrap();
+    let page_one: RegistryPage = accounts[5].decode();
+    assert_eq!((page_one.index, page_one.entries()), (1, &[second.key][..]));
+}
+
+#[test]
+fn recreating_a_live_user_account_is_rejected() {
+    let program_id = Pubkey::new_unique();
+    let owner = fixtures::wallet();
+    let (page, _bump) = RegistryPage::find_address(0, &program_id).unwrap();
+    let frozen = UserAccount { flags: user_flags::FROZEN, ..fixtures::user_account(&program_id, &owner.key, 5_000) };
+    let data = ProgramInstruction::CreateUserAccount { initial_balance: 100, pow_nonce: 0 }.pack().unwrap();
+    
+    let mut accounts = vec![
+        fixtures::user_account_account(&program_id, &frozen),
+        owner.clone(),
+        fixtures::program_state_account(&program_id, &fixtures::program_state(&owner.key)),
+        fixtures::metrics_account(&program_id),
+        fixtures::program_config_account(&program_id, &ProgramConfig::default()),
+        fixtures::empty_account_for(&program_id, page, &RegistryPage::default()),
+    ];
+    let before: Vec<Vec<u8>> = accounts.iter().map(|account| account.data.clone()).collect();
+    assert_eq!(
+        process_instruction(&program_id, &infos(&mut accounts), &data),
+        Err(ProgramError::AccountAlreadyInitialized)
+    );
+    assert!(accounts.iter().zip(&before).all(|(account, data)| account.data == *data));
+}
+
+#[test]
+fn allowlist_gating_admits_only_wallets_the_authority_added() {
+    let program_id = Pubkey::new_unique();
+    let (admin, user) = (fixtures::wallet(), fixtures::wallet());
+    let (user_account, _bump) = account_creation::create_user_pda(&user.key, &program_id).unwrap();
+    let (page, _bump) = RegistryPage::find_address(0, &program_id).unwrap();
+    let (entry, _bump) = AllowlistEntry::find_address(&user.key, &program_id).unwrap();
+    let config = ProgramConfig { feature_flags: feature_flags::ALLOWLIST_GATING, ..ProgramConfig::default() };
+    let create = ProgramInstruction::CreateUserAccount { initial_balance: 100, pow_nonce: 0 }.pack().unwrap();
+    
+    let mut accounts = vec![
+        fixtures::empty_account_for(&program_id, user_account, &fixtures::user_account(&program_id, &user.key, 0)),
+        user.clone(),
+        fixtures::program_state_account(&program_id, &fixtures::program_state(&admin.key)),
+        fixtures::metrics_account(&program_id),
+        fixtures::program_config_account(&program_id, &config),
+        fixtures::empty_account_for(&program_id, page, &RegistryPage::default()),
+    ];
+    assert_eq!(process_instruction(&program_id, &infos(&mut accounts), &create), Err(ProgramError::NotEnoughAccountKeys));
+    
+    accounts.push(fixtures::empty_account_for(&program_id, entry, &AllowlistEntry::default()));
+    assert_eq!(process_instruction(&program_id, &infos(&mut accounts), &create), Err(ProgramError::InvalidArgument));
+    
+    let set_allowlisted = |accounts: &mut Vec<MockAccount>, instruction: ProgramInstruction| {
+        let mut admin_accounts = vec![accounts[6].clone(), accounts[2].clone(), admin.clone()];
+        process_instruction(&program_id, &infos(&mut admin_accounts), &instruction.pack().unwrap()).unwrap();
+        accounts[6] = admin_accounts.swap_remove(0);
+    };
+    set_allowlisted(&mut accounts, ProgramInstruction::AddToAllowlist { wallet: user.key });
+    assert!(accounts[6].decode::<AllowlistEntry>().allowed);
+    process_instruction(&program_id, &infos(&mut accounts), &create).unwrap();
+    assert_eq!(accounts[0].decode::<UserAccount>().owner, user.key);
+    
+    set_allowlisted(&mut accounts, ProgramInstruction::RemoveFromAllowlist { wallet: user.key });
+    assert!(!accounts[6].decode::<AllowlistEntry>().allowed);
+    let mut again = vec![accounts[6].clone(), accounts[2].clone(), admin.clone()];
+    let remove = ProgramInstruction::RemoveFromAllowlist { wallet: user.key }.pack().unwrap();
+    assert_eq!(process_instruction(&program_id, &infos(&mut again), &remove), Err(ProgramError::InvalidArgument));
+}
+
+#[test]
+fn transfer_from_spends_the_approved_allowance_until_revoked() {
+    let program_id = Pubkey::new_unique();
+    let owner = fixtures::wallet();
+    let delegate = fixtures::wallet();
+    let (delegations_address, _bump) = account_creation::create_delegations_pda(&owner.key, &program_id).unwrap();
+    let state = fixtures::program_state_account(&program_id, &fixtures::program_state(&owner.key));
+    
+    let mut approvals = vec![
+        MockAccount::new().key(delegations_address).owner(program_id).data(vec![0; DELEGATIONS_LEN]),
+        owner.clone(),
+        state.clone(),
+    ];
+    let approve = ProgramInstruction::Approve { delegate: delegate.key, amount: 1_500 }.pack().unwrap();
+    process_instruction(&program_id, &infos(&mut approvals), &approve).unwrap();
+    
+    let mut accounts = vec![
+        fixtures::user_account_account(&program_id, &fixtures::user_account(&program_id, &owner.key, 10_000)),
+        fixtures::user_account_account(&program_id, &fixtures::user_account(&program_id, &delegate.key, 0)),
+        delegate.clone(),
+        state,
+        fixtures::metrics_account(&program_id),
+        fixtures::program_config_account(&program_id, &ProgramConfig::default()),
+        approvals[0].clone(),
+        fixtures::treasury_account(&program_id),
+    ];
+    let transfer_from = |amount| ProgramInstruction::TransferFrom { amount, fee_basis_points: 100 }.pack().unwrap();
+    process_instruction(&program_id, &infos(&mut accounts), &transfer_from(1_000)).unwrap();
+    assert_eq!(accounts[1].decode::<UserAccount>().balance, 1_000);
+    
+    // 1_010 of the allowance went with the fee, so 490 more only fits without one
+    assert_eq!(
+        process_instruction(&program_id, &infos(&mut accounts), &transfer_from(490)),
+        Err(ProgramError::InvalidArgument)
+    );
+    process_instruction(&program_id, &infos(&mut accounts), &transfer_from(480)).unwrap();
+    assert_eq!(accounts[1].decode::<UserAccount>().balance, 1_480);
+    
+    approvals[0] = accounts[6].clone();
+    let revoke = ProgramInstruction::Revoke { delegate: delegate.key }.pack().unwrap();
+    process_instruction(&program_id, &infos(&mut approvals), &revoke).unwrap();
+    accounts[6] = approvals[0].clone();
+    assert_eq!(
+        process_instruction(&program_id, &infos(&mut accounts), &transfer_from(100)),
+        Err(ProgramError::InvalidArgument)
+    );
+}
+
+#[test]
+fn session_key_limits_count_the_fee_with_the_amount() {
+    let program_id = Pubkey::new_unique();
+    let (owner, delegate, recipient) = (fixtures::wallet(), fixtures::wallet(), fixtures::wallet());
+    let (session_key_address, _bump) =
+        account_creation::create_session_key_pda(&owner.key, &delegate.key, &program_id).unwrap();
+    
+    let mut accounts = vec![
+        fixtures::user_account_account(&program_id, &fixtures::user_account(&program_id, &owner.key, 10_000)),
+        fixtures::user_account_account(&program_id, &fixtures::user_account(&program_id, &recipient.key, 0)),
+        delegate.clone(),
+        fixtures::program_state_account(&program_id, &fixtures::program_state(&owner.key)),
+        fixtures::metrics_account(&program_id),
+        fixtures::program_config_account(&program_id, &ProgramConfig::default()),
+        fixtures::treasury_account(&program_id),
+        MockAccount::new().key(session_key_address).owner(program_id).borsh(&SessionKey {
+            owner: owner.key,
+            delegate: delegate.key,
+            scope: session_scope::TRANSFER,
+            per_operation_limit: 1_000,
+            expires_at: i64::MAX,
+        }),
+    ];
+    let transfer = |amount| {
+        ProgramInstruction::TransferWithFee { amount, fee_basis_points: 100, idempotency_key: None }.pack().unwrap()
+    };
+    
+    // The amount alone is within the limit, but not with its fee
+    assert_eq!(process_instruction(&program_id, &infos(&mut accounts), &transfer(1_000)), Err(ProgramError::InvalidArgument));
+    process_instruction(&program_id, &infos(&mut accounts), &transfer(900)).unwrap();
+    assert_eq!(accounts[0].decode::<UserAccount>().balance, 9_091);
+    assert_eq!(accounts[1].decode::<UserAccount>().balance, 900);
+}
+
+#[test]
+fn transfer_locates_its_required_accounts_after_optional_ones() {
+    let program_id = Pubkey::new_unique();
+    let (owner, delegate, recipient) = (fixtures::wallet(), fixtures::wallet(), fixtures::wallet());
+    let (session_key_address, _bump) =
+        account_creation::create_session_key_pda(&owner.key, &delegate.key, &program_id).unwrap();
+    
+    let mut accounts = vec![
+        fixtures::user_account_account(&program_id, &fixtures::user_account(&program_id, &owner.key, 10_000)),
+        fixtures::user_account_account(&program_id, &fixtures::user_account(&program_id, &recipient.key, 0)),
+        delegate.clone(),
+        MockAccount::new().key(session_key_address).owner(program_id).borsh(&SessionKey {
+            owner: owner.key,
+            delegate: delegate.key,
+            scope: session_scope::TRANSFER,
+            per_operation_limit: 1_000,
+            expires_at: i64::MAX,
+        }),
+        fixtures::treasury_account(&program_id),
+        fixtures::program_config_account(&program_id, &ProgramConfig::default()),
+        fixtures::metrics_account(&program_id),
+        fixtures::program_state_account(&program_id, &fixtures::program_state(&owner.key)),
+    ];
+    let transfer = ProgramInstruction::TransferWithFee { amount: 500, fee_basis_points: 100, idempotency_key: None }
+        .pack()
+        .unwrap();
+    
+    process_instruction(&program_id, &infos(&mut accounts), &transfer).unwrap();
+    assert_eq!(accounts[0].decode::<UserAccount>().balance, 9_495);
+    assert_eq!(accounts[1].decode::<UserAccount>().balance, 500);
+    
+    // The program state is still required
+    let mut missing_state = accounts[..7].to_vec();
+    assert_eq!(
+        process_instruction(&program_id, &infos(&mut missing_state), &transfer),
+        Err(ProgramError::NotEnoughAccountKeys)
+    );
+}
+
+#[test]
+fn transfer_to_the_same_account_fails_the_conservation_check() {
+    let program_id = Pubkey::new_unique();
+    let owner = fixtures::wallet();
+    let user_account = fixtures::user_account(&program_id, &owner.key, 10_000);
+    
+    let mut accounts = vec![
+        fixtures::user_account_account(&program_id, &user_account),
+        fixtures::user_account_account(&program_id, &user_account),
+        owner.clone(),
+        fixtures::program_state_account(&program_id, &fixtures::program_state(&owner.key)),
+        fixtures::metrics_account(&program_id),
+        fixtures::program_config_account(&program_id, &ProgramConfig::default()),
+        fixtures::treasury_account(&program_id),
+    ];
+    let data = ProgramInstruction::TransferWithFee { amount: 1_000, fee_basis_points: 100, idempotency_key: None }
+        .pack()
+        .unwrap();
+    
+    assert_eq!(
+        process_instruction(&program_id, &infos(&mut accounts), &data),
+        Err(ProgramError::InvalidAccountData)
+    );
+}
+
+#[test]
+fn idle_empty_account_is_reclaimed_after_the_grace_window() {
+    let program_id = Pubkey::new_unique();
+    let owner = fixtures::wallet();
+    let cranker = MockAccount::new();
+    let policy = ReclaimPolicy { inactivity_slots: 100, grace_slots: 50, cranker_share_bps: 1_000 };
+    let mut idle = fixtures::user_account(&program_id, &owner.key, 0);
+    idle.last_activity_slot = sysvars::START_SLOT;
+    
+    let mut accounts = vec![
+        fixtures::user_account_account(&program_id, &idle).lamports(10_000),
+        fixtures::reclaim_policy_account(&program_id, &policy),
+        owner.clone(),
+        cranker,
+        fixtures::program_state_account(&program_id, &fixtures::program_state(&owner.key)),
+    ];
+    let flag = ProgramInstruction::FlagDormantAccount.pack().unwrap();
+    let reclaim = ProgramInstruction::ReclaimDormantAccount.pack().unwrap();
+    
+    sysvars::warp_to_slot(sysvars::START_SLOT + 99);
+    assert_eq!(process_instruction(&program_id, &infos(&mut accounts), &flag), Err(ProgramError::InvalidArgument));
+    
+    sysvars::warp_to_slot(sysvars::START_SLOT + 100);
+    process_instruction(&program_id, &infos(&mut accounts), &flag).unwrap();
+    let flagged: UserAccount = accounts[0].decode();
+    assert_eq!((flagged.last_activity_slot, flagged.dormant_since_slot), (sysvars::START_SLOT, sysvars::START_SLOT + 100));
+    
+    sysvars::warp_to_slot(sysvars::START_SLOT + 149);
+    assert_eq!(process_instruction(&program_id, &infos(&mut accounts), &reclaim), Err(ProgramError::InvalidArgument));
+    
+    sysvars::warp_to_slot(sysvars::START_SLOT + 150);
+    process_instruction(&program_id, &infos(&mut accounts), &reclaim).unwrap();
+    assert_eq!(accounts[0].lamports, 0);
+    assert!(accounts[0].data.iter().all(|&byte| byte == 0));
+    assert_eq!(accounts[2].lamports, owner.lamports + 9_000);
+    assert_eq!(accounts[3].lamports, 1_000);
+}
+
+#[test]
+fn accounts_holding_a_balance_are_never_flagged() {
+    let program_id = Pubkey::new_unique();
+    let owner = fixtures::wallet();
+    let policy = ReclaimPolicy { inactivity_slots: 100, grace_slots: 50, cranker_share_bps: 1_000 };
+    let mut idle = fixtures::user_account(&program_id, &owner.key, 1);
+    idle.last_activity_slot = sysvars::START_SLOT;
+    
+    let mut accounts = vec![
+        fixtures::user_account_account(&program_id, &idle),
+        fixtures::reclaim_policy_account(&program_id, &policy),
+        fixtures::program_state_account(&program_id, &fixtures::program_state(&owner.key)),
+    ];
+    sysvars::warp_to_slot(sysvars::START_SLOT + 10_000);
+    
+    let flag = ProgramInstruction::FlagDormantAccount.pack().unwrap();
+    assert_eq!(process_instruction(&program_id, &infos(&mut accounts), &flag), Err(ProgramError::InvalidArgument));
+}
+
+#[test]
+fn batch_applies_every_op_against_the_shared_account_table() {
+    let program_id = Pubkey::new_unique();
+    let owner = fixtures::wallet();
+    let destination = MockAccount::new();
+    
+    let mut accounts = vec![
+        owner.clone(),
+        fixtures::program_state_account(&program_id, &fixtures::program_state(&owner.key)),
+        fixtures::metrics_account(&program_id),
+        fixtures::program_config_account(&program_id, &ProgramConfig::default()),
+        fixtures::user_account_account(&program_id, &fixtures::user_account(&program_id, &owner.key, 10_000)),
+        fixtures::user_account_account(&program_id, &fixtures::user_account(&program_id, &Pubkey::new_unique(), 0)),
+        fixtures::user_account_account(&program_id, &fixtures::user_account(&program_id, &Pubkey::new_unique(), 0)),
+        fixtures::user_account_account(&program_id, &fixtures::user_account(&program_id, &owner.key, 0))
+            .key(Pubkey::new_unique())
+            .lamports(5_000),
+        destination,
+        fixtures::treasury_account(&program_id),
+    ];
+    let data = ProgramInstruction::Batch {
+        ops: vec![
+            SubOp::Transfer { from: 0, to: 1, amount: 1_000, fee_basis_points: 100 },
+            SubOp::Transfer { from: 0, to: 2, amount: 2_000, fee_basis_points: 100 },
+            SubOp::Close { account: 3, destination: 4 },
+        ],
+    }
+    .pack()
+    .unwrap();
+    process_instruction(&program_id, &infos(&mut accounts), &data).unwrap();
+    
+    assert_eq!(accounts[4].decode::<UserAccount>().balance, 6_970);
+    assert_eq!(accounts[5].decode::<UserAccount>().balance, 1_000);
+    assert_eq!(accounts[6].decode::<UserAccount>().balance, 2_000);
+    assert_eq!((accounts[7].lamports, accounts[8].lamports), (0, 5_000));
+    
+    let metrics: Metrics = accounts[2].decode();
+    assert_eq!((metrics.transfer_count, metrics.total_volume, metrics.fees_collected), (2, 3_000, 30));
+}
+
+#[test]
+fn batch_refuses_to_close_an_account_holding_a_balance() {
+    let program_id = Pubkey::new_unique();
+    let owner = fixtures::wallet();
+    
+    let mut accounts = vec![
+        owner.clone(),
+        fixtures::program_state_account(&program_id, &fixtures::program_state(&owner.key)),
+        fixtures::metrics_account(&program_id),
+        fixtures::program_config_account(&program_id, &ProgramConfig::default()),
+        fixtures::user_account_account(&program_id, &fixtures::user_account(&program_id, &owner.key, 1)),
+        MockAccount::new(),
+    ];
+    let data = ProgramInstruction::Batch { ops: vec![SubOp::Close { account: 0, destination: 1 }] }
+        .pack()
+        .unwrap();
+    
+    assert_eq!(
+        process_instruction(&program_id, &infos(&mut accounts), &data),
+        Err(ProgramError::InvalidArgument)
+    );
+}
+
+#[test]
+fn closing_a_user_account_refunds_its_rent_once_it_is_empty() {
+    let program_id = Pubkey::new_unique();
+    let owner = fixtures::wallet();
+    let user = fixtures::user_account(&program_id, &owner.key, 500);
+    
+    let mut accounts = vec![
+        fixtures::user_account_account(&program_id, &user).lamports(2_000_000),
+        owner.clone(),
+        MockAccount::new().lamports(1),
+        fixtures::program_state_account(&program_id, &fixtures::program_state(&owner.key)),
+    ];
+    let data = ProgramInstruction::CloseUserAccount.pack().unwrap();
+    assert_eq!(
+        process_instruction(&program_id, &infos(&mut accounts), &data),
+        Err(ProgramError::InvalidArgument)
+    );
+    
+    accounts[0] = accounts[0].clone().borsh(&UserAccount { balance: 0, ..user });
+    process_instruction(&program_id, &infos(&mut accounts), &data).unwrap();
+    assert_eq!((accounts[0].lamports, accounts[2].lamports), (0, 2_000_001));
+    assert!(accounts[0].data.iter().all(|&byte| byte == 0));
+}
+
+#[test]
+fn an_active_stream_cannot_be_recreated_over_what_has_streamed() {
+    let program_id = Pubkey::new_unique();
+    let sender = fixtures::wallet();
+    let recipient = Pubkey::new_unique();
+    let (stream_key, _bump) = account_creation::create_stream_pda(&sender.key, 7, &program_id).unwrap();
+    let stream = Stream {
+        sender: sender.key,
+        recipient,
+        deposit: 0,
+        flow_rate: 0,
+        start_time: 0,
+        stop_time: 0,
+        withdrawn: 0,
+        active: false,
+    };
+    let create = ProgramInstruction::CreateStream { stream_id: 7, recipient, deposit: 1_000, start_time: 0, stop_time: 100 }
+        .pack()
+        .unwrap();
+    
+    let mut accounts = vec![
+        fixtures::empty_account_for(&program_id, stream_key, &stream),
+        fixtures::user_account_account(&program_id, &fixtures::user_account(&program_id, &sender.key, 5_000)),
+        sender.clone(),
+        fixtures::program_state_account(&program_id, &fixtures::program_state(&sender.key)),
+        fixtures::program_config_account(&program_id, &ProgramConfig::default()),
+    ];
+    process_instruction(&program_id, &infos(&mut accounts), &create).unwrap();
+    assert!(accounts[0].decode::<Stream>().active);
+    
+    assert_eq!(
+        process_instruction(&program_id, &infos(&mut accounts), &create),
+        Err(ProgramError::AccountAlreadyInitialized)
+    );
+    assert_eq!(accounts[1].decode::<UserAccount>().balance, 4_000);
+}
+
+#[test]
+fn subscriptions_cannot_be_recreated_or_cancelled_in_arrears() {
+    let program_id = Pubkey::new_unique();
+    let payer = fixtures::wallet();
+    let merchant = fixtures::wallet();
+    let merchant_account = fixtures::user_account_account(&program_id, &fixtures::user_account(&program_id, &merchant.key, 0));
+    let (subscription_key, _bump) = account_creation::create_subscription_pda(&payer.key, 3, &program_id).unwrap();
+    let subscription = Subscription { payer: payer.key, merchant: merchant_account.key, amount: 0, interval: 0, next_due: 0, active: false };
+    let program_state = fixtures::program_state_account(&program_id, &fixtures::program_state(&payer.key));
+    let create = ProgramInstruction::CreateSubscription { subscription_id: 3, merchant: merchant_account.key, amount: 500, interval: 100 }
+        .pack()
+        .unwrap();
+    
+    sysvars::warp_to_timestamp(1_000);
+    let mut accounts = vec![fixtures::empty_account_for(&program_id, subscription_key, &subscription), payer.clone(), program_state.clone()];
+    process_instruction(&program_id, &infos(&mut accounts), &create).unwrap();
+    assert_eq!(
+        process_instruction(&program_id, &infos(&mut accounts), &create),
+        Err(ProgramError::AccountAlreadyInitialized)
+    );
+    
+    // Two and a half periods are owed, but the payer only holds 700
+    sysvars::warp_to_timestamp(1_250);
+    let mut cancel = vec![
+        accounts[0].clone(),
+        fixtures::user_account_account(&program_id, &fixtures::user_account(&program_id, &payer.key, 700)),
+        merchant_account,
+        payer.clone(),
+        program_state,
+        fixtures::program_config_account(&program_id, &ProgramConfig::default()),
+    ];
+    let cancel_subscription = ProgramInstruction::CancelSubscription.pack().unwrap();
+    assert_eq!(
+        process_instruction(&program_id, &infos(&mut cancel), &cancel_subscription),
+        Err(ProgramError::InsufficientFunds)
+    );
+    assert!(cancel[0].decode::<Subscription>().active);
+    
+    // Once topped up, cancelling settles all 1_250
+    cancel[1] = fixtures::user_account_account(&program_id, &fixtures::user_account(&program_id, &payer.key, 2_000));
+    process_instruction(&program_id, &infos(&mut cancel), &cancel_subscription).unwrap();
+    
+    assert!(!cancel[0].decode::<Subscription>().active);
+    assert_eq!(cancel[1].decode::<UserAccount>().balance, 750);
+    assert_eq!(cancel[2].decode::<UserAccount>().balance, 1_250);
+}
+
+#[test]
+fn a_pending_scheduled_transfer_cannot_be_overwritten() {
+    let program_id = Pubkey::new_unique();
+    let owner = fixtures::wallet();
+    let (scheduled_key, _bump) = account_creation::create_scheduled_transfer_pda(&owner.key, 1, &program_id).unwrap();
+    let pending = ScheduledTransfer { owner: owner.key, recipient: Pubkey::new_unique(), amount: 0, bounty: 0, execute_after: 0, executed: false };
+    let schedule = |amount| {
+        ProgramInstruction::ScheduleTransfer { schedule_id: 1, execute_after: 2_000, amount, recipient: pending.recipient, bounty: 10 }
+            .pack()
+            .unwrap()
+    };
+    
+    sysvars::warp_to_timestamp(1_000);
+    let mut accounts = vec![
+        fixtures::empty_account_for(&program_id, scheduled_key, &pending),
+        fixtures::user_account_account(&program_id, &fixtures::user_account(&program_id, &owner.key, 5_000)),
+        owner.clone(),
+        fixtures::program_state_account(&program_id, &fixtures::program_state(&owner.key)),
+        fixtures::program_config_account(&program_id, &ProgramConfig::default()),
+    ];
+    process_instruction(&program_id, &infos(&mut accounts), &schedule(1_000)).unwrap();
+    assert_eq!(
+        process_instruction(&program_id, &infos(&mut accounts), &schedule(1)),
+        Err(ProgramError::AccountAlreadyInitialized)
+    );
+    assert_eq!(accounts[0].decode::<ScheduledTransfer>().amount, 1_000);
+    assert_eq!(accounts[1].decode::<UserAccount>().balance, 3_990);
+}
+
+#[test]
+fn a_drained_bounty_pool_pays_what_it_holds_without_blocking_execution() {
+    let program_id = Pubkey::new_unique();
+    let (owner, cranker) = (fixtures::wallet(), fixtures::wallet());
+    let recipient = fixtures::user_account_account(&program_id, &fixtures::user_account(&program_id, &Pubkey::new_unique(), 0));
+    let (scheduled_key, _bump) = account_creation::create_scheduled_transfer_pda(&owner.key, 9, &program_id).unwrap();
+    let (registration, _bump) = Cranker::find_address(&cranker.key, &program_id).unwrap();
+    let (pool, _bump) = BountyPool::find_address(&program_id).unwrap();
+    let due = ScheduledTransfer {
+        owner: owner.key,
+        recipient: recipient.key,
+        amount: 1_000,
+        bounty: 10,
+        execute_after: 0,
+        executed: false,
+    };
+    
+    let mut accounts = vec![
+        MockAccount::new().key(scheduled_key).owner(program_id).borsh(&due),
+        recipient,
+        fixtures::user_account_account(&program_id, &fixtures::user_account(&program_id, &cranker.key, 0)),
+        cranker.clone(),
+        fixtures::program_state_account(&program_id, &fixtures::program_state(&owner.key)),
+        MockAccount::new().key(registration).owner(program_id).borsh(&Cranker {
+            authority: cranker.key,
+            registered_at: 0,
+            tasks_completed: 0,
+            bounties_earned: 0,
+        }),
+        MockAccount::new().key(pool).owner(program_id).borsh(&BountyPool { balance: 3, bounty_per_task: [5, 0, 0], total_paid: 0 }),
+    ];
+    process_instruction(&program_id, &infos(&mut accounts), &ProgramInstruction::ExecuteScheduled.pack().unwrap()).unwrap();
+    
+    assert_eq!(accounts[1].decode::<UserAccount>().balance, 1_000);
+    assert_eq!(accounts[2].decode::<UserAccount>().balance, 13);
+    let pool: BountyPool = accounts[6].decode();
+    assert_eq!((pool.balance, pool.total_paid), (0, 3));
+}
+
+#[test]
+fn an_invoice_cannot_be_recreated() {
+    let program_id = Pubkey::new_unique();
+    let merchant = fixtures::wallet();
+    let (invoice_key, _bump) = account_creation::create_invoice_pda(&merchant.key, 4, &program_id).unwrap();
+    let paid = Invoice {
+        merchant: merchant.key,
+        merchant_account: Pubkey::new_unique(),
+        invoice_id: 4,
+        amount: 250,
+        memo_hash: [0; 32],
+        expiry: 2_000,
+        paid: true,
+        payer: Pubkey::new_unique(),
+        paid_at: 1_500,
+    };
+    let create = ProgramInstruction::CreateInvoice { invoice_id: 4, amount: 250, memo_hash: [0; 32], expiry: 3_000 }
+        .pack()
+        .unwrap();
+    
+    sysvars::warp_to_timestamp(1_000);
+    let mut accounts = vec![
+        MockAccount::new().key(invoice_key).owner(program_id).borsh(&paid),
+        fixtures::user_account_account(&program_id, &fixtures::user_account(&program_id, &merchant.key, 0)),
+        merchant.clone(),
+        fixtures::program_state_account(&program_id, &fixtures::program_state(&merchant.key)),
+    ];
+    assert_eq!(
+        process_instruction(&program_id, &infos(&mut accounts), &create),
+        Err(ProgramError::AccountAlreadyInitialized)
+    );
+    assert!(accounts[0].decode::<Invoice>().paid);
+}
+
+#[test]
+fn vested_tokens_are_claimable_from_the_cliff_in_proportion_to_elapsed_time() {
+    let program_id = Pubkey::new_unique();
+    let (grantor, beneficiary) = (fixtures::wallet(), fixtures::wallet());
+    let (vesting_address, _bump) = VestingSchedule::find_address(&grantor.key, &beneficiary.key, &program_id).unwrap();
+    let state = fixtures::program_state_account(&program_id, &fixtures::program_state(&grantor.key));
+    
+    let mut create = vec![
+        fixtures::empty_account_for(&program_id, vesting_address, &VestingSchedule::default()),
+        fixtures::user_account_account(&program_id, &fixtures::user_account(&program_id, &grantor.key, 20_000)),
+        grantor.clone(),
+        state.clone(),
+        fixtures::program_config_account(&program_id, &ProgramConfig::default()),
+    ];
+    let data = ProgramInstruction::CreateVesting {
+        beneficiary: beneficiary.key,
+        total: 10_000,
+        start_ts: 1_000,
+        cliff: 100,
+        duration: 1_000,
+    }
+    .pack()
+    .unwrap();
+    process_instruction(&program_id, &infos(&mut create), &data).unwrap();
+    assert_eq!(create[1].decode::<UserAccount>().balance, 10_000);
+    assert_eq!(process_instruction(&program_id, &infos(&mut create), &data), Err(ProgramError::AccountAlreadyInitialized));
+    
+    let mut claim = vec![
+        create[0].clone(),
+        fixtures::user_account_account(&program_id, &fixtures::user_account(&program_id, &beneficiary.key, 0)),
+        beneficiary.clone(),
+        state,
+    ];
+    let data = ProgramInstruction::ClaimVested.pack().unwrap();
+    sysvars::warp_to_timestamp(1_099);
+    assert_eq!(process_instruction(&program_id, &infos(&mut claim), &data), Err(ProgramError::InvalidArgument));
+    
+    sysvars::warp_to_timestamp(1_250);
+    let mut frozen_beneficiary = fixtures::user_account(&program_id, &beneficiary.key, 0);
+    frozen_beneficiary.flags = user_flags::FROZEN;
+    let mut frozen = claim.clone();
+    frozen[1] = fixtures::user_account_account(&program_id, &frozen_beneficiary);
+    assert_eq!(process_instruction(&program_id, &infos(&mut frozen), &data), Err(ProgramError::InvalidArgument));
+    process_instruction(&program_id, &infos(&mut claim), &data).unwrap();
+    assert_eq!(claim[1].decode::<UserAccount>().balance, 2_500);
+    
+    sysvars::warp_to_timestamp(5_000);
+    process_instruction(&program_id, &infos(&mut claim), &data).unwrap();
+    assert_eq!(claim[1].decode::<UserAccount>().balance, 10_000);
+    assert_eq!(claim[0].decode::<VestingSchedule>().claimed, 10_000);
+}
+
+#[test]
+fn escrow_is_released_on_mutual_signatures_or_refunded_after_expiry() {
+    let program_id = Pubkey::new_unique();
+    let (payer, counterparty) = (fixtures::wallet(), fixtures::wallet());
+    let escrow_account = |escrow_id| {
+        let (key, _bump) = account_creation::create_vault_pda(&payer.key, escrow_id, &program_id).unwrap();
+        fixtures::empty_account_for(&program_id, key, &Escrow::default())
+    };
+    let state = fixtures::program_state_account(&program_id, &fixtures::program_state(&payer.key));
+    let payer_account = fixtures::user_account_account(&program_id, &fixtures::user_account(&program_id, &payer.key, 10_000));
+    sysvars::warp_to_timestamp(1_000);
+    
+    let mut escrows = [escrow_account(0), escrow_account(1)];
+    for (escrow_id, escrow) in escrows.iter_mut().enumerate() {
+        let create = ProgramInstruction::CreateEscrow {
+            escrow_id: escrow_id as u64,
+            counterparty: counterparty.key,
+            arbiter: Pubkey::default(),
+            expires_at: 2_000,
+        };
+        let mut accounts = vec![escrow.clone(), payer.clone(), state.clone()];
+        process_instruction(&program_id, &infos(&mut accounts), &create.pack().unwrap()).unwrap();
+        *escrow = accounts.remove(0);
+    }
+    
+    let fund = ProgramInstruction::FundEscrow { amount: 3_000 }.pack().unwrap();
+    let mut accounts = vec![escrows[0].clone(), payer_account, payer.clone(), state.clone(), fixtures::program_config_account(&program_id, &ProgramConfig::default())];
+    process_instruction(&program_id, &infos(&mut accounts), &fund).unwrap();
+    escrows[0] = std::mem::replace(&mut accounts[0], escrows[1].clone());
+    process_instruction(&program_id, &infos(&mut accounts), &fund).unwrap();
+    assert_eq!(accounts[1].decode::<UserAccount>().balance, 4_000);
+    
+    let release = ProgramInstruction::ReleaseEscrow.pack().unwrap();
+    let mut released = vec![
+        escrows[0].clone(),
+        fixtures::user_account_account(&program_id, &fixtures::user_account(&program_id, &counterparty.key, 0)),
+        state.clone(),
+        counterparty.clone(),
+    ];
+    assert_eq!(process_instruction(&program_id, &infos(&mut released), &release), Err(ProgramError::MissingRequiredSignature));
+    released.push(payer.clone());
+    let mut frozen_counterparty = fixtures::user_account(&program_id, &counterparty.key, 0);
+    frozen_counterparty.flags = user_flags::FROZEN;
+    let mut frozen = released.clone();
+    frozen[1] = fixtures::user_account_account(&program_id, &frozen_counterparty);
+    assert_eq!(process_instruction(&program_id, &infos(&mut frozen), &release), Err(ProgramError::InvalidArgument));
+    process_instruction(&program_id, &infos(&mut released), &release).unwrap();
+    assert_eq!(released[1].decode::<UserAccount>().balance, 3_000);
+    assert!(released[0].decode::<Escrow>().settled);
+    
+    let cancel = ProgramInstruction::CancelEscrow.pack().unwrap();
+    assert_eq!(process_instruction(&program_id, &infos(&mut accounts), &cancel), Err(ProgramError::InvalidArgument));
+    sysvars::warp_to_timestamp(2_000);
+    process_instruction(&program_id, &infos(&mut accounts), &cancel).unwrap();
+    assert_eq!(accounts[1].decode::<UserAccount>().balance, 7_000);
+    assert_eq!(process_instruction(&program_id, &infos(&mut accounts), &cancel), Err(ProgramError::InvalidAccountData));
+}
+
+#[test]
+fn stakers_share_the_reward_rate_in_proportion_to_their_stake() {
+    let program_id = Pubkey::new_unique();
+    let (authority, first, second) = (fixtures::wallet(), fixtures::wallet(), fixtures::wallet());
+    let position = |owner: &MockAccount| {
+        let (key, _bump) = StakeAccount::find_address(&owner.key, &program_id).unwrap();
+        vec![
+            fixtures::empty_account_for(&program_id, key, &StakeAccount::default()),
+            fixtures::user_account_account(&program_id, &fixtures::user_account(&program_id, &owner.key, 10_000)),
+            owner.clone(),
+        ]
+    };
+    sysvars::warp_to_timestamp(1_000);
+    
+    let mut set_rate = vec![
+        fixtures::program_state_account(&program_id, &fixtures::program_state(&authority.key)),
+        authority,
+    ];
+    let data = ProgramInstruction::SetRewardRate { reward_rate: 30 }.pack().unwrap();
+    process_instruction(&program_id, &infos(&mut set_rate), &data).unwrap();
+    
+    let mut state = set_rate.remove(0);
+    let config = fixtures::program_config_account(&program_id, &ProgramConfig::default());
+    let mut run = |accounts: &mut Vec<MockAccount>, instruction: ProgramInstruction| {
+        accounts.extend([state.clone(), config.clone()]);
+        process_instruction(&program_id, &infos(accounts), &instruction.pack().unwrap()).unwrap();
+        accounts.pop();
+        state = accounts.pop().unwrap();
+    };
+    let (mut first_position, mut second_position) = (position(&first), position(&second));
+    
+    // 100s alone at 30/s, then 100s sharing 1:2 with the second staker
+    run(&mut first_position, ProgramInstruction::Stake { amount: 1_000 });
+    sysvars::warp_to_timestamp(1_100);
+    run(&mut second_position, ProgramInstruction::Stake { amount: 2_000 });
+    sysvars::warp_to_timestamp(1_200);
+    run(&mut first_position, ProgramInstruction::Unstake { amount: 1_000 });
+    run(&mut first_position, ProgramInstruction::ClaimRewards);
+    run(&mut second_position, ProgramInstruction::ClaimRewards);
+    
+    assert_eq!(first_position[1].decode::<UserAccount>().balance, 14_000);
+    assert_eq!(second_position[1].decode::<UserAccount>().balance, 10_000);
+    let state: ProgramState = state.decode();
+    assert_eq!((state.total_staked, state.total_supply), (2_000, 1_006_000));
+}
+
+#[test]
+fn stakers_claim_their_share_of_epoch_fees_in_proportion_to_their_stake() {
+    let program_id = Pubkey::new_unique();
+    let (authority, first, second) = (fixtures::wallet(), fixtures::wallet(), fixtures::wallet());
+    let (treasury_address, _bump) = account_creation::create_treasury_pda(&program_id).unwrap();
+    let (fee_schedule_address, _bump) = FeeSchedule::find_address(&program_id).unwrap();
+    let (insurance_fund_address, _bump) = account_creation::create_insurance_fund_pda(&program_id).unwrap();
+    let position = |owner: &MockAccount, amount| {
+        let (key, _bump) = StakeAccount::find_address(&owner.key, &program_id).unwrap();
+        vec![
+            MockAccount::new().key(key).owner(program_id).borsh(&StakeAccount { owner: owner.key, amount, ..StakeAccount::default() }),
+            fixtures::user_account_account(&program_id, &fixtures::user_account(&program_id, &owner.key, 0)),
+            owner.clone(),
+        ]
+    };
+    sysvars::warp_to_epoch(1);
+    
+    let mut distribute = vec![
+        MockAccount::new().key(treasury_address).owner(program_id).borsh(&Treasury { epoch_fees: 1_000, ..Treasury::default() }),
+        MockAccount::new().key(fee_schedule_address).owner(program_id).borsh(&FeeSchedule {
+            stakers_bps: 6_000,
+            insurance_bps: 1_000,
+            treasury_bps: 3_000,
+            staker_rewards: 0,
+        }),
+        MockAccount::new().key(insurance_fund_address).owner(program_id).borsh(&InsuranceFund::default()),
+        fixtures::program_state_account(&program_id, &ProgramState { total_staked: 3_000, ..fixtures::program_state(&authority.key) }),
+    ];
+    process_instruction(&program_id, &infos(&mut distribute), &ProgramInstruction::DistributeEpochFees.pack().unwrap()).unwrap();
+    assert_eq!(distribute[0].decode::<Treasury>().balance, 300);
+    assert_eq!(distribute[1].decode::<FeeSchedule>().staker_rewards, 0);
+    assert_eq!(distribute[3].decode::<ProgramState>().total_supply, 999_400);
+    
+    let config = fixtures::program_config_account(&program_id, &ProgramConfig::default());
+    let mut state = distribute.remove(3);
+    let mut claim = |mut accounts: Vec<MockAccount>| {
+        accounts.extend([state.clone(), config.clone()]);
+        process_instruction(&program_id, &infos(&mut accounts), &ProgramInstruction::ClaimRewards.pack().unwrap()).unwrap();
+        accounts.pop();
+        state = accounts.pop().unwrap();
+        accounts[1].decode::<UserAccount>().balance
+    };
+    assert_eq!(claim(position(&first, 1_000)), 200);
+    assert_eq!(claim(position(&second, 2_000)), 400);
+    assert_eq!(state.decode::<ProgramState>().total_supply, 1_000_000);
+}
+
+#[test]
+fn withdrawals_execute_only_once_the_delay_has_passed() {
+    let program_id = Pubkey::new_unique();
+    let (authority, owner, destination) = (fixtures::wallet(), fixtures::wallet(), fixtures::wallet());
+    let (withdrawal_address, _bump) = PendingWithdrawal::find_address(&owner.key, &program_id).unwrap();
+    sysvars::warp_to_timestamp(1_000);
+    
+    let mut set_delay = vec![
+        fixtures::program_state_account(&program_id, &fixtures::program_state(&authority.key)),
+        authority,
+    ];
+    let data = ProgramInstruction::SetWithdrawalDelay { delay: 100 }.pack().unwrap();
+    process_instruction(&program_id, &infos(&mut set_delay), &data).unwrap();
+    
+    let mut request = vec![
+        fixtures::empty_account_for(&program_id, withdrawal_address, &PendingWithdrawal::default()),
+        fixtures::user_account_account(&program_id, &fixtures::user_account(&program_id, &owner.key, 10_000)),
+        owner.clone(),
+        set_delay.remove(0),
+        fixtures::program_config_account(&program_id, &ProgramConfig::default()),
+    ];
+    let data = ProgramInstruction::RequestWithdrawal { amount: 4_000 }.pack().unwrap();
+    process_instruction(&program_id, &infos(&mut request), &data).unwrap();
+    assert_eq!(request[1].decode::<UserAccount>().balance, 6_000);
+    assert_eq!(process_instruction(&program_id, &infos(&mut request), &data), Err(ProgramError::AccountAlreadyInitialized));
+    
+    let mut execute = vec![
+        request[0].clone(),
+        fixtures::user_account_account(&program_id, &fixtures::user_account(&program_id, &destination.key, 0)),
+        owner.clone(),
+        request[3].clone(),
+    ];
+    let data = ProgramInstruction::ExecuteWithdrawal.pack().unwrap();
+    sysvars::warp_to_timestamp(1_099);
+    assert_eq!(process_instruction(&program_id, &infos(&mut execute), &data), Err(ProgramError::InvalidArgument));
+    
+    sysvars::warp_to_timestamp(1_100);
+    process_instruction(&program_id, &infos(&mut execute), &data).unwrap();
+    assert_eq!(execute[1].decode::<UserAccount>().balance, 4_000);
+    assert_eq!(execute[3].decode::<ProgramState>().total_supply, 1_000_000);
+    assert_eq!(process_instruction(&program_id, &infos(&mut execute), &data), Err(ProgramError::UninitializedAccount));
+    
+    // A cancelled request refunds the owner and can't be executed afterwards
+    request[0] = execute[0].clone();
+    let data = ProgramInstruction::RequestWithdrawal { amount: 1_000 }.pack().unwrap();
+    process_instruction(&program_id, &infos(&mut request), &data).unwrap();
+    let mut cancel = vec![request[0].clone(), request[1].clone(), owner, request[3].clone()];
+    let data = ProgramInstruction::CancelWithdrawal.pack().unwrap();
+    process_instruction(&program_id, &infos(&mut cancel), &data).unwrap();
+    assert_eq!(cancel[1].decode::<UserAccount>().balance, 6_000);
+    assert_eq!(process_instruction(&program_id, &infos(&mut cancel), &data), Err(ProgramError::UninitializedAccount));
+}
+
+#[test]
+fn pause_hook_holds_instructions_that_do_not_take_the_program_state_themselves() {
+    let program_id = Pubkey::new_unique();
+    let authority = fixtures::wallet();
+    let target = MockAccount::new().owner(program_id).data(vec![0; 8]).signer();
+    let upload_address = Upload::find_address(&target.key, &program_id).unwrap().0;
+    let mut paused = fixtures::program_state(&authority.key);
+    paused.paused = true;
+    
+    let mut accounts = vec![
+        fixtures::empty_account_for(&program_id, upload_address, &Upload::default()),
+        target,
+        authority.clone(),
+        fixtures::program_state_account(&program_id, &paused),
+    ];
+    let begin = ProgramInstruction::BeginUpload.pack().unwrap();
+    assert_eq!(
+        process_instruction(&program_id, &infos(&mut accounts[..3]), &begin),
+        Err(ProgramError::NotEnoughAccountKeys)
+    );
+    assert_eq!(process_instruction(&program_id, &infos(&mut accounts), &begin), Err(ProgramError::Custom(errors::PROGRAM_PAUSED)));
+    
+    // Recovery instructions are exempt from the hook
+    let mut recovery = vec![accounts[3].clone(), authority];
+    process_instruction(&program_id, &infos(&mut recovery), &ProgramInstruction::Unpause.pack().unwrap()).unwrap();
+    accounts[3] = recovery[0].clone();
+    process_instruction(&program_id, &infos(&mut accounts), &begin).unwrap();
+}
+
+#[test]
+fn chunked_upload_is_sealed_once_the_hash_matches() {
+    let program_id = Pubkey::new_unique();
+    let authority = fixtures::wallet();
+    let target = MockAccount::new().owner(program_id).data(vec![0; 8]).signer();
+    let upload_address = Upload::find_address(&target.key, &program_id).unwrap().0;
+    
+    let mut accounts = vec![
+        fixtures::empty_account_for(&program_id, upload_address, &Upload::default()),
+        target,
+        authority.clone(),
+        fixtures::program_state_account(&program_id, &fixtures::program_state(&authority.key)),
+    ];
+    let write = |offset, bytes: &[u8]| ProgramInstruction::WriteChunk { offset, bytes: bytes.to_vec() }.pack().unwrap();
+    let finalize = |expected_hash| ProgramInstruction::FinalizeUpload { expected_hash }.pack().unwrap();
+    
+    process_instruction(&program_id, &infos(&mut accounts), &ProgramInstruction::BeginUpload.pack().unwrap()).unwrap();
+    process_instruction(&program_id, &infos(&mut accounts), &write(4, &[5, 6, 7, 8])).unwrap();
+    process_instruction(&program_id, &infos(&mut accounts), &write(0, &[1, 2, 3, 4])).unwrap();
+    assert_eq!(
+        process_instruction(&program_id, &infos(&mut accounts), &write(6, &[9, 9, 9])),
+        Err(ProgramError::AccountDataTooSmall)
+    );
+    
+    let contents = [1, 2, 3, 4, 5, 6, 7, 8];
+    assert_eq!(
+        process_instruction(&program_id, &infos(&mut accounts), &finalize([0; 32])),
+        Err(ProgramError::InvalidAccountData)
+    );
+    process_instruction(&program_id, &infos(&mut accounts), &finalize(keccak::hash(&contents).to_bytes())).unwrap();
+    
+    let upload: Upload = accounts[0].decode();
+    assert!(upload.finalized);
+    assert_eq!(upload.chunks_written, 2);
+    assert_eq!(accounts[1].data, contents);
+    assert_eq!(
+        process_instruction(&program_id, &infos(&mut accounts), &write(0, &[0])),
+        Err(ProgramError::InvalidArgument)
+    );
+}
+
+#[test]
+fn compressed_user_accounts_append_at_the_tree_cursor() {
+    let program_id = Pubkey::new_unique();
+    let owner = fixtures::wallet();
+    let merkle_tree = Pubkey::new_unique();
+    let authority = ProgramAuthority::find(&program_id).unwrap();
+    let tree = CompressedTree {
+        merkle_tree,
+        max_depth: 1,
+        max_buffer_size: 8,
+        authority_bump: authority.bump,
+        next_leaf_index: 0,
+    };
+    
+    let mut accounts = vec![
+        compressed_tree_account(&program_id, &tree),
+        owner.clone(),
+        fixtures::program_state_account(&program_id, &fixtures::program_state(&owner.key)),
+    ];
+    accounts.extend(tree_cpi_accounts(&tree, &authority));
+    accounts.push(fixtures::program_config_account(&program_id, &ProgramConfig::default()));
+    let data = ProgramInstruction::CreateCompressedUserAccount { pow_nonce: 0 }.pack().unwrap();
+    
+    process_instruction(&program_id, &infos(&mut accounts), &data).unwrap();
+    process_instruction(&program_id, &infos(&mut accounts), &data).unwrap();
+    assert_eq!(accounts[0].decode::<CompressedTree>().next_leaf_index, 2);
+    
+    // A depth-1 tree holds two leaves
+    assert_eq!(
+        process_instruction(&program_id, &infos(&mut accounts), &data),
+        Err(ProgramError::AccountDataTooSmall)
+    );
+    
+    accounts[3] = MockAccount::new().owner(compression::ACCOUNT_COMPRESSION_PROGRAM_ID);
+    assert_eq!(
+        process_instruction(&program_id, &infos(&mut accounts), &data),
+        Err(ProgramError::InvalidArgument)
+    );
+}
+
+#[test]
+fn compressed_user_accounts_are_allowlist_gated_like_user_accounts() {
+    let program_id = Pubkey::new_unique();
+    let owner = fixtures::wallet();
+    let authority = ProgramAuthority::find(&program_id).unwrap();
+    let tree = CompressedTree {
+        merkle_tree: Pubkey::new_unique(),
+        max_depth: 1,
+        max_buffer_size: 8,
+        authority_bump: authority.bump,
+        next_leaf_index: 0,
+    };
+    let (entry, _bump) = AllowlistEntry::find_address(&owner.key, &program_id).unwrap();
+    let config = ProgramConfig { feature_flags: feature_flags::ALLOWLIST_GATING, ..ProgramConfig::default() };
+    
+    let mut accounts = vec![
+        compressed_tree_account(&program_id, &tree),
+        owner.clone(),
+        fixtures::program_state_account(&program_id, &fixtures::program_state(&owner.key)),
+    ];
+    accounts.extend(tree_cpi_accounts(&tree, &authority));
+    accounts.push(fixtures::program_config_account(&program_id, &config));
+    let data = ProgramInstruction::CreateCompressedUserAccount { pow_nonce: 0 }.pack().unwrap();
+    assert_eq!(process_instruction(&program_id, &infos(&mut accounts), &data), Err(ProgramError::NotEnoughAccountKeys));
+    
+    let allowed = AllowlistEntry { wallet: owner.key, allowed: true, updated_at: 0 };
+    accounts.push(MockAccount::new().key(entry).owner(program_id).borsh(&allowed));
+    process_instruction(&program_id, &infos(&mut accounts), &data).unwrap();
+    assert_eq!(accounts[0].decode::<CompressedTree>().next_leaf_index, 1);
+}
+
+#[test]
+fn user_accounts_round_trip_through_the_compressed_tree() {
+    let program_id = Pubkey::new_unique();
+    let owner = fixtures::wallet();
+    let authority = ProgramAuthority::find(&program_id).unwrap();
+    let tree = CompressedTree {
+        merkle_tree: Pubkey::new_unique(),
+        max_depth: 1,
+        max_buffer_size: 8,
+        authority_bump: authority.bump,
+        next_leaf_index: 0,
+    };
+    let mut user_account = fixtures::user_account(&program_id, &owner.key, 7_000);
+    user_account.nonce = 4;
+    
+    let mut accounts = vec![
+        compressed_tree_account(&program_id, &tree),
+        fixtures::user_account_account(&program_id, &user_account).lamports(2_000_000),
+        owner.clone(),
+        fixtures::program_state_account(&program_id, &fixtures::program_state(&owner.key)),
+    ];
+    accounts.extend(tree_cpi_accounts(&tree, &authority));
+    
+    let compress = ProgramInstruction::CompressUserAccount.pack().unwrap();
+    process_instruction(&program_id, &infos(&mut accounts), &compress).unwrap();
+    assert_eq!(accounts[0].decode::<CompressedTree>().next_leaf_index, 1);
+    assert_eq!(accounts[1].lamports, 0);
+    assert_eq!(accounts[2].lamports, owner.lamports + 2_000_000);
+    
+    // The record sits at leaf 0 next to an empty leaf 1
+    let record = CompressedUserRecord::from_user_account(&user_account);
+    let empty_sibling = merkle::EMPTY_LEAF;
+    let root = merkle::compute_root(&record.leaf().unwrap(), 0, &[empty_sibling]);
+    accounts.push(MockAccount::new().key(solana_program::system_program::id()).executable());
+    accounts.push(MockAccount::new().key(Pubkey::new_from_array(empty_sibling)));
+    
+    let decompress = |root, leaf_index| {
+        ProgramInstruction::DecompressUserRecord { root, record: record.clone(), leaf_index }.pack().unwrap()
+    };
+    assert_eq!(
+        process_instruction(&program_id, &infos(&mut accounts), &decompress(root, 1)),
+        Err(ProgramError::InvalidArgument)
+    );
+    process_instruction(&program_id, &infos(&mut accounts), &decompress(root, 0)).unwrap();
+    
+    let restored: UserAccount = accounts[1].decode();
+    assert_eq!((restored.owner, restored.balance, restored.nonce), (owner.key, 7_000, 4));
+    assert_eq!(
+        process_instruction(&program_id, &infos(&mut accounts), &decompress(root, 0)),
+        Err(ProgramError::AccountAlreadyInitialized)
+    );
+}
+
+fn compressed_tree_account(program_id: &Pubkey, tree: &CompressedTree) -> MockAccount {
+    MockAccount::new().key(CompressedTree::find_address(program_id).unwrap().0).owner(*program_id).borsh(tree)
+}
+
+/// Merkle tree, tree authority, compression program and noop program, in instruction order
+fn tree_cpi_accounts(tree: &CompressedTree, authority: &ProgramAuthority) -> [MockAccount; 4] {
+    [
+        MockAccount::new().key(tree.merkle_tree).owner(compression::ACCOUNT_COMPRESSION_PROGRAM_ID),
+        MockAccount::new().key(authority.address),
+        MockAccount::new().key(compression::ACCOUNT_COMPRESSION_PROGRAM_ID).executable(),
+        MockAccount::new().key(events::NOOP_PROGRAM_ID).executable(),
+    ]
+}
@@ -0,0 +1,663 @@
+//! Layout freeze: canonical instances of every instruction and account type are encoded
+//! and compared with `tests/fixtures/layout.golden`. A mismatch means a change would break
+//! deployed clients or live accounts. After an intentional layout change, regenerate the
+//! fixtures with `UPDATE_LAYOUT_FIXTURES=1 cargo test -p my-solana-program --test layout`.
+
+use account_utils::account_creation;
+use borsh::BorshSerialize;
+use my_solana_program::{
+    analytics::DailyStats,
+    batch::SubOp,
+    compression::{CompressedRecordEvent, CompressedTree, CompressedUserRecord},
+    bridge::{CrossChainTransfer, PostedVaa},
+    crank::{BountyPool, CrankTask, Cranker},
+    multisig::{Multisig, Proposal, ProposalAccount},
+    reclaim::ReclaimPolicy,
+    treasury::{FeeSchedule, Treasury},
+    registry::{RegistryPage, PAGE_CAPACITY},
+    upload::Upload,
+    lockup::Lock,
+    vesting::VestingSchedule,
+    escrow::Escrow,
+    staking::StakeAccount,
+    withdrawal::PendingWithdrawal,
+    allowlist::AllowlistEntry,
+    compliance::{AccountLimits, Attestation, AuditAction, AuditEntry, AuditLog, ComplianceConfig},
+    delegation::{DelegateEntry, Delegations, DELEGATIONS_LEN},
+    exchange_rate::RateState,
+    insurance::InsuranceFund,
+    lending::{LendingConfig, Position},
+    AuthorityHistory, AuthorityRotation, BalanceCheckpointEvent, IdempotencyEntry, IdempotencyLog, InitializeEvent, Invoice,
+    InvoicePaidEvent, Metrics, ProgramConfig, ProgramInstruction, ProgramState, ScheduledTransfer, SessionKey, Stream,
+    Subscription, TransferEvent, TransferFilledEvent, UserAccount, UserAccountV1, UserAccountV2,
+    UserAccountV3, USER_ACCOUNT_LEN, USER_ACCOUNT_V3_LEN,
+};
+use solana_program::pubkey::Pubkey;
+use std::{collections::{BTreeMap, BTreeSet}, fs, path::PathBuf};
+
+fn key(byte: u8) -> Pubkey {
+    Pubkey::new_from_array([byte; 32])
+}
+
+fn config() -> ProgramConfig {
+    ProgramConfig {
+        default_decimals: 6,
+        account_balance_multiplier: 1_000,
+        default_fee_basis_points: 30,
+        min_fee_basis_points: 5,
+        max_transfer_amount: 1_000_000_000,
+        feature_flags: 3,
+    }
+}
+
+/// Exhaustive, so adding a variant fails to compile until it has a fixture
+fn instruction_name(instruction: &ProgramInstruction) -> &'static str {
+    match instruction {
+        ProgramInstruction::Initialize { .. } => "Initialize",
+        ProgramInstruction::CreateUserAccount { .. } => "CreateUserAccount",
+        ProgramInstruction::TransferWithFee { .. } => "TransferWithFee",
+        ProgramInstruction::CreateSessionKey { .. } => "CreateSessionKey",
+        ProgramInstruction::RotateAuthority { .. } => "RotateAuthority",
+        ProgramInstruction::SetEmergencyGuardian { .. } => "SetEmergencyGuardian",
+        ProgramInstruction::Pause => "Pause",
+        ProgramInstruction::Unpause => "Unpause",
+        ProgramInstruction::SetPowDifficulty { .. } => "SetPowDifficulty",
+        ProgramInstruction::Extension { .. } => "Extension",
+        ProgramInstruction::UpdateConfig { .. } => "UpdateConfig",
+        ProgramInstruction::SetFeatureFlag { .. } => "SetFeatureFlag",
+        ProgramInstruction::TransferUpTo { .. } => "TransferUpTo",
+        ProgramInstruction::ScheduleTransfer { .. } => "ScheduleTransfer",
+        ProgramInstruction::ExecuteScheduled => "ExecuteScheduled",
+        ProgramInstruction::RegisterCranker => "RegisterCranker",
+        ProgramInstruction::FundBountyPool { .. } => "FundBountyPool",
+        ProgramInstruction::SetTaskBounty { .. } => "SetTaskBounty",
+        ProgramInstruction::CreateSubscription { .. } => "CreateSubscription",
+        ProgramInstruction::CancelSubscription => "CancelSubscription",
+        ProgramInstruction::CollectSubscription => "CollectSubscription",
+        ProgramInstruction::CreateStream { .. } => "CreateStream",
+        ProgramInstruction::WithdrawFromStream => "WithdrawFromStream",
+        ProgramInstruction::CancelStream => "CancelStream",
+        ProgramInstruction::CreateInvoice { .. } => "CreateInvoice",
+        ProgramInstruction::PayInvoice { .. } => "PayInvoice",
+        ProgramInstruction::AtomicSwap { .. } => "AtomicSwap",
+        ProgramInstruction::MigrateUserAccountV2 => "MigrateUserAccountV2",
+        ProgramInstruction::CreateMultisig { .. } => "CreateMultisig",
+        ProgramInstruction::CreateProposal { .. } => "CreateProposal",
+        ProgramInstruction::ApproveProposal => "ApproveProposal",
+        ProgramInstruction::ExecuteProposal => "ExecuteProposal",
+        ProgramInstruction::ConfigureTreasury { .. } => "ConfigureTreasury",
+        ProgramInstruction::FundTreasury { .. } => "FundTreasury",
+        ProgramInstruction::TreasuryTransfer { .. } => "TreasuryTransfer",
+        ProgramInstruction::TreasuryInvest { .. } => "TreasuryInvest",
+        ProgramInstruction::LockTokens { .. } => "LockTokens",
+        ProgramInstruction::UnlockTokens => "UnlockTokens",
+        ProgramInstruction::ConfigureCompliance { .. } => "ConfigureCompliance",
+        ProgramInstruction::SetAccountLimits { .. } => "SetAccountLimits",
+        ProgramInstruction::IssueAttestation { .. } => "IssueAttestation",
+        ProgramInstruction::CreateSoulboundAccount { .. } => "CreateSoulboundAccount",
+        ProgramInstruction::SetDelegation { .. } => "SetDelegation",
+        ProgramInstruction::RevokeDelegation { .. } => "RevokeDelegation",
+        ProgramInstruction::SetAccountFlagged { .. } => "SetAccountFlagged",
+        ProgramInstruction::Clawback { .. } => "Clawback",
+        ProgramInstruction::SweepDust { .. } => "SweepDust",
+        ProgramInstruction::SetInterestRate { .. } => "SetInterestRate",
+        ProgramInstruction::AccrueInterest => "AccrueInterest",
+        ProgramInstruction::SetInterestBearing { .. } => "SetInterestBearing",
+        ProgramInstruction::ConfigureRateState { .. } => "ConfigureRateState",
+        ProgramInstruction::UpdateRate { .. } => "UpdateRate",
+        ProgramInstruction::ConfigureLending { .. } => "ConfigureLending",
+        ProgramInstruction::DepositCollateral { .. } => "DepositCollateral",
+        ProgramInstruction::WithdrawCollateral { .. } => "WithdrawCollateral",
+        ProgramInstruction::Borrow { .. } => "Borrow",
+        ProgramInstruction::Repay { .. } => "Repay",
+        ProgramInstruction::Liquidate { .. } => "Liquidate",
+        ProgramInstruction::ConfigureInsuranceFund { .. } => "ConfigureInsuranceFund",
+        ProgramInstruction::InsurancePayout { .. } => "InsurancePayout",
+        ProgramInstruction::ConfigureFeeSchedule { .. } => "ConfigureFeeSchedule",
+        ProgramInstruction::DistributeEpochFees => "DistributeEpochFees",
+        ProgramInstruction::ConfigureReclaimPolicy { .. } => "ConfigureReclaimPolicy",
+        ProgramInstruction::FlagDormantAccount => "FlagDormantAccount",
+        ProgramInstruction::ReclaimDormantAccount => "ReclaimDormantAccount",
+        ProgramInstruction::Batch { .. } => "Batch",
+        ProgramInstruction::BeginUpload => "BeginUpload",
+        ProgramInstruction::WriteChunk { .. } => "WriteChunk",
+        ProgramInstruction::FinalizeUpload { .. } => "FinalizeUpload",
+        ProgramInstruction::InitCompressedTree { .. } => "InitCompressedTree",
+        ProgramInstruction::CreateCompressedUserAccount { .. } => "CreateCompressedUserAccount",
+        ProgramInstruction::DecompressUserRecord { .. } => "DecompressUserRecord",
+        ProgramInstruction::CompressUserAccount => "CompressUserAccount",
+        ProgramInstruction::CloseUserAccount => "CloseUserAccount",
+        ProgramInstruction::Approve { .. } => "Approve",
+        ProgramInstruction::Revoke { .. } => "Revoke",
+        ProgramInstruction::TransferFrom { .. } => "TransferFrom",
+        ProgramInstruction::CreateVesting { .. } => "CreateVesting",
+        ProgramInstruction::ClaimVested => "ClaimVested",
+        ProgramInstruction::CreateEscrow { .. } => "CreateEscrow",
+        ProgramInstruction::FundEscrow { .. } => "FundEscrow",
+        ProgramInstruction::ReleaseEscrow => "ReleaseEscrow",
+        ProgramInstruction::CancelEscrow => "CancelEscrow",
+        ProgramInstruction::SetRewardRate { .. } => "SetRewardRate",
+        ProgramInstruction::Stake { .. } => "Stake",
+        ProgramInstruction::Unstake { .. } => "Unstake",
+        ProgramInstruction::ClaimRewards => "ClaimRewards",
+        ProgramInstruction::ReallocUserAccount => "ReallocUserAccount",
+        ProgramInstruction::FreezeAccount => "FreezeAccount",
+        ProgramInstruction::ThawAccount => "ThawAccount",
+        ProgramInstruction::SetWithdrawalDelay { .. } => "SetWithdrawalDelay",
+        ProgramInstruction::RequestWithdrawal { .. } => "RequestWithdrawal",
+        ProgramInstruction::ExecuteWithdrawal => "ExecuteWithdrawal",
+        ProgramInstruction::CollectFees => "CollectFees",
+        ProgramInstruction::AddToAllowlist { .. } => "AddToAllowlist",
+        ProgramInstruction::RemoveFromAllowlist { .. } => "RemoveFromAllowlist",
+        ProgramInstruction::MigrateAccount => "MigrateAccount",
+        ProgramInstruction::CancelWithdrawal => "CancelWithdrawal",
+    }
+}
+
+fn instructions() -> Vec<ProgramInstruction> {
+    vec![
+        ProgramInstruction::Initialize { initial_amount: 1_000 },
+        ProgramInstruction::CreateUserAccount { initial_balance: 500, pow_nonce: 42 },
+        ProgramInstruction::TransferWithFee { amount: 1_000, fee_basis_points: 30, idempotency_key: Some([7; 16]) },
+        ProgramInstruction::CreateSessionKey { delegate: key(2), scope: 1, per_operation_limit: 100, expires_at: 1_700_000_000 },
+        ProgramInstruction::RotateAuthority { new_authority: key(3) },
+        ProgramInstruction::SetEmergencyGuardian { guardian: key(4) },
+        ProgramInstruction::Pause,
+        ProgramInstruction::Unpause,
+        ProgramInstruction::SetPowDifficulty { difficulty: 12 },
+        ProgramInstruction::Extension { id: 1, payload: b"memo".to_vec() },
+        ProgramInstruction::UpdateConfig { config: config() },
+        ProgramInstruction::SetFeatureFlag { feature: 2, enabled: true },
+        ProgramInstruction::TransferUpTo { max_amount: 900, min_amount: 100 },
+        ProgramInstruction::ScheduleTransfer { schedule_id: 1, execute_after: 1_700_000_000, amount: 250, recipient: key(5), bounty: 3 },
+        ProgramInstruction::ExecuteScheduled,
+        ProgramInstruction::RegisterCranker,
+        ProgramInstruction::FundBountyPool { amount: 10_000 },
+        ProgramInstruction::SetTaskBounty { task: CrankTask::OracleRefresh, bounty: 7 },
+        ProgramInstruction::CreateSubscription { subscription_id: 2, merchant: key(6), amount: 99, interval: 2_592_000 },
+        ProgramInstruction::CancelSubscription,
+        ProgramInstruction::CollectSubscription,
+        ProgramInstruction::CreateStream { stream_id: 3, recipient: key(7), deposit: 86_400, start_time: 1_700_000_000, stop_time: 1_700_086_400 },
+        ProgramInstruction::WithdrawFromStream,
+        ProgramInstruction::CancelStream,
+        ProgramInstruction::CreateInvoice { invoice_id: 4, amount: 1_234, memo_hash: [9; 32], expiry: 1_700_003_600 },
+        ProgramInstruction::PayInvoice { amount: 1_234 },
+        ProgramInstruction::AtomicSwap { amount_a: 500, amount_b: 700, fee_basis_points: 25 },
+        ProgramInstruction::MigrateUserAccountV2,
+        ProgramInstruction::CreateMultisig { multisig_id: 5, signers: vec![key(1), key(2), key(3)], weights: vec![3, 1, 1], threshold: 4 },
+        ProgramInstruction::CreateProposal { instruction_data: vec![0x06], accounts: vec![proposal_account()] },
+        ProgramInstruction::ApproveProposal,
+        ProgramInstruction::ExecuteProposal,
+        ProgramInstruction::ConfigureTreasury { governance: key(18), epoch_budget: 50_000, allowed_programs: vec![key(19)] },
+        ProgramInstruction::FundTreasury { amount: 10_000 },
+        ProgramInstruction::TreasuryTransfer { amount: 2_500 },
+        ProgramInstruction::TreasuryInvest { amount: 5_000, instruction_data: vec![1, 2] },
+        ProgramInstruction::LockTokens { lock_id: 3, amount: 1_000, unlock_at: 1_731_536_000 },
+        ProgramInstruction::UnlockTokens,
+        ProgramInstruction::ConfigureCompliance {
+            authority: key(20),
+            attestor: key(22),
+            attestation_threshold: 10_000,
+            clawback_authority: key(23),
+        },
+        ProgramInstruction::SetAccountLimits { max_per_transfer: 1_000, max_per_day: 5_000 },
+        ProgramInstruction::IssueAttestation { user: key(1), expires_at: 1_731_536_000 },
+        ProgramInstruction::CreateSoulboundAccount { initial_balance: 500, pow_nonce: 42 },
+        ProgramInstruction::SetDelegation { delegate: key(2), scope: 1, amount_cap: 2_000, expires_at: 1_731_536_000 },
+        ProgramInstruction::RevokeDelegation { delegate: key(2) },
+        ProgramInstruction::SetAccountFlagged { flagged: true, reason_code: 7 },
+        ProgramInstruction::Clawback { amount: 500, reason_code: 7 },
+        ProgramInstruction::SweepDust { threshold: 10 },
+        ProgramInstruction::SetInterestRate { rate_bps: 500 },
+        ProgramInstruction::AccrueInterest,
+        ProgramInstruction::SetInterestBearing { enabled: true },
+        ProgramInstruction::ConfigureRateState { updater: key(24) },
+        ProgramInstruction::UpdateRate { rate: 3 << 63 },
+        ProgramInstruction::ConfigureLending {
+            max_ltv_bps: 7_500,
+            liquidation_threshold_bps: 8_000,
+            close_factor_bps: 5_000,
+            liquidation_bonus_bps: 500,
+            max_rate_age: 60,
+        },
+        ProgramInstruction::DepositCollateral { amount: 1_000_000 },
+        ProgramInstruction::WithdrawCollateral { amount: 250_000 },
+        ProgramInstruction::Borrow { amount: 500 },
+        ProgramInstruction::Repay { amount: 200 },
+        ProgramInstruction::Liquidate { repay_amount: 150 },
+        ProgramInstruction::ConfigureInsuranceFund { authority: key(25), fee_share_bps: 2_000 },
+        ProgramInstruction::InsurancePayout { amount: 75 },
+        ProgramInstruction::ConfigureFeeSchedule { stakers_bps: 5_000, insurance_bps: 2_000, treasury_bps: 3_000 },
+        ProgramInstruction::DistributeEpochFees,
+        ProgramInstruction::ConfigureReclaimPolicy { inactivity_slots: 6_480_000, grace_slots: 1_296_000, cranker_share_bps: 1_000 },
+        ProgramInstruction::FlagDormantAccount,
+        ProgramInstruction::ReclaimDormantAccount,
+        ProgramInstruction::Batch {
+            ops: vec![
+                SubOp::Transfer { from: 0, to: 1, amount: 1_000, fee_basis_points: 30 },
+                SubOp::Approve { delegations: 2, delegate: key(2), scope: 1, amount_cap: 2_000, expires_at: 1_731_536_000 },
+                SubOp::Close { account: 3, destination: 4 },
+            ],
+        },
+        ProgramInstruction::BeginUpload,
+        ProgramInstruction::WriteChunk { offset: 900, bytes: vec![1, 2, 3] },
+        ProgramInstruction::FinalizeUpload { expected_hash: [11; 32] },
+        ProgramInstruction::InitCompressedTree { max_depth: 20, max_buffer_size: 64 },
+        ProgramInstruction::CreateCompressedUserAccount { pow_nonce: 42 },
+        ProgramInstruction::DecompressUserRecord {
+            root: [12; 32],
+            record: CompressedUserRecord { owner: key(1), balance: 5_000, flags: 1, nonce: 3 },
+            leaf_index: 9,
+        },
+        ProgramInstruction::CompressUserAccount,
+        ProgramInstruction::CloseUserAccount,
+        ProgramInstruction::Approve { delegate: key(2), amount: 5_000 },
+        ProgramInstruction::Revoke { delegate: key(2) },
+        ProgramInstruction::TransferFrom { amount: 1_000, fee_basis_points: 30 },
+        ProgramInstruction::CreateVesting {
+            beneficiary: key(2),
+            total: 12_000,
+            start_ts: 1_700_000_000,
+            cliff: 7_776_000,
+            duration: 31_536_000,
+        },
+        ProgramInstruction::ClaimVested,
+        ProgramInstruction::CreateEscrow { escrow_id: 4, counterparty: key(2), arbiter: key(20), expires_at: 1_731_536_000 },
+        ProgramInstruction::FundEscrow { amount: 5_000 },
+        ProgramInstruction::ReleaseEscrow,
+        ProgramInstruction::CancelEscrow,
+        ProgramInstruction::SetRewardRate { reward_rate: 100 },
+        ProgramInstruction::Stake { amount: 5_000 },
+        ProgramInstruction::Unstake { amount: 2_000 },
+        ProgramInstruction::ClaimRewards,
+        ProgramInstruction::ReallocUserAccount,
+        ProgramInstruction::FreezeAccount,
+        ProgramInstruction::ThawAccount,
+        ProgramInstruction::SetWithdrawalDelay { delay: 86_400 },
+        ProgramInstruction::RequestWithdrawal { amount: 5_000 },
+        ProgramInstruction::ExecuteWithdrawal,
+        ProgramInstruction::CollectFees,
+        ProgramInstruction::AddToAllowlist { wallet: key(1) },
+        ProgramInstruction::RemoveFromAllowlist { wallet: key(1) },
+        ProgramInstruction::MigrateAccount,
+        ProgramInstruction::CancelWithdrawal,
+    ]
+}
+
+fn proposal_account() -> ProposalAccount {
+    ProposalAccount { pubkey: key(17), is_signer: true, is_writable: false }
+}
+
+fn encode<T: BorshSerialize>(value: &T) -> Vec<u8> {
+    value.try_to_vec().unwrap()
+}
+
+fn accounts() -> Vec<(&'static str, Vec<u8>)> {
+    let rotation = AuthorityRotation { previous_authority: key(1), new_authority: key(3), rotated_at: 1_700_000_000 };
+    let mut rotations = [AuthorityRotation::default(); 8];
+    rotations[0] = rotation;
+    let mut entries = [IdempotencyEntry::default(); 16];
+    entries[0] = IdempotencyEntry { key: [7; 16], recorded_at: 1_700_000_000 };
+    
+    vec![
+        ("ProgramState", encode(&ProgramState {
+            authority: key(1),
+            total_supply: 1_000_000_000,
+            is_initialized: true,
+            paused: false,
+            emergency_guardian: key(4),
+            pow_difficulty: 12,
+            interest_index: 1_050_000_000_000,
+            interest_rate_bps: 500,
+            last_accrual_at: 1_700_000_000,
+            reward_rate: 100,
+            reward_per_share: 2_500_000_000_000,
+            total_staked: 40_000,
+            last_reward_at: 1_700_000_000,
+            withdrawal_delay: 86_400,
+            version: 1,
+        })),
+        ("ProgramConfig", encode(&config())),
+        ("UserAccount", encode(&UserAccount {
+            owner: key(1),
+            balance: 500,
+            program_state: key(10),
+            flags: 1,
+            bump: 254,
+            nonce: 9,
+            last_activity_slot: 250_000_000,
+            dormant_since_slot: 0,
+            version: 4,
+        })),
+        ("UserAccountV3", encode(&UserAccountV3 {
+            owner: key(1),
+            balance: 500,
+            program_state: key(10),
+            flags: 1,
+            bump: 254,
+            nonce: 9,
+            last_activity_slot: 250_000_000,
+            dormant_since_slot: 0,
+        })),
+        ("UserAccountV2", encode(&UserAccountV2 {
+            owner: key(1),
+            balance: 500,
+            program_state: key(10),
+            flags: 1,
+            bump: 254,
+            nonce: 9,
+        })),
+        ("UserAccountV1", encode(&UserAccountV1 { owner: key(1), balance: 500, program_state: key(10) })),
+        ("SessionKey", encode(&SessionKey {
+            owner: key(1),
+            delegate: key(2),
+            scope: 1,
+            per_operation_limit: 100,
+            expires_at: 1_700_000_000,
+        })),
+        ("IdempotencyLog", encode(&IdempotencyLog { next: 1, entries })),
+        ("AuthorityHistory", encode(&AuthorityHistory { len: 1, rotations })),
+        ("Metrics", encode(&Metrics { transfer_count: 3, total_volume: 3_000, fees_collected: 9, unique_users: 2 })),
+        ("ScheduledTransfer", encode(&ScheduledTransfer {
+            owner: key(1),
+            recipient: key(5),
+            amount: 250,
+            bounty: 3,
+            execute_after: 1_700_000_000,
+            executed: false,
+        })),
+        ("Cranker", encode(&Cranker { authority: key(8), registered_at: 1_700_000_000, tasks_completed: 4, bounties_earned: 12 })),
+        ("BountyPool", encode(&BountyPool { balance: 10_000, bounty_per_task: [3, 5, 7], total_paid: 15 })),
+        ("Subscription", encode(&Subscription {
+            payer: key(1),
+            merchant: key(6),
+            amount: 99,
+            interval: 2_592_000,
+            next_due: 1_702_592_000,
+            active: true,
+        })),
+        ("Stream", encode(&Stream {
+            sender: key(1),
+            recipient: key(7),
+            deposit: 86_400,
+            flow_rate: 1_000_000_000_000,
+            start_time: 1_700_000_000,
+            stop_time: 1_700_086_400,
+            withdrawn: 100,
+            active: true,
+        })),
+        ("Invoice", encode(&Invoice {
+            merchant: key(6),
+            merchant_account: key(11),
+            invoice_id: 4,
+            amount: 1_234,
+            memo_hash: [9; 32],
+            expiry: 1_700_003_600,
+            paid: true,
+            payer: key(1),
+            paid_at: 1_700_000_100,
+        })),
+        ("InvoicePaidEvent", encode(&InvoicePaidEvent {
+            invoice: key(12),
+            merchant: key(6),
+            payer: key(1),
+            amount: 1_234,
+            memo_hash: [9; 32],
+            paid_at: 1_700_000_100,
+        })),
+        ("TransferFilledEvent", encode(&TransferFilledEvent { from: key(1), to: key(2), max_amount: 900, filled_amount: 800, fee: 2 })),
+        ("BalanceCheckpointEvent", encode(&BalanceCheckpointEvent { user_account: key(2), balance: 5_000, flags: 1 })),
+        ("TransferEvent", encode(&TransferEvent { from: key(1), to: key(2), amount: 800, fee: 2 })),
+        ("InitializeEvent", encode(&InitializeEvent { authority: key(1), total_supply: 1_000_000_000 })),
+        ("CompressedRecordEvent", encode(&CompressedRecordEvent {
+            merkle_tree: key(27),
+            leaf_index: 9,
+            record: CompressedUserRecord { owner: key(1), balance: 5_000, flags: 1, nonce: 3 },
+        })),
+        ("CrossChainTransfer", encode(&CrossChainTransfer {
+            amount: 12_345_678,
+            token_address: [13; 32],
+            token_chain: 1,
+            recipient: [14; 32],
+            recipient_chain: 2,
+            fee: 0,
+        })),
+        ("PostedVaa", encode(&PostedVaa {
+            vaa_version: 1,
+            consistency_level: 32,
+            vaa_time: 1_700_000_000,
+            vaa_signature_account: key(15),
+            submission_time: 1_700_000_010,
+            nonce: 5,
+            sequence: 6,
+            emitter_chain: 2,
+            emitter_address: [16; 32],
+            payload: vec![1, 2, 3],
+        })),
+        ("Multisig", encode(&Multisig::new(key(1), 5, 253, &[key(1), key(2), key(3)], &[3, 1, 1], 4).unwrap())),
+        ("Proposal", encode(&Proposal {
+            multisig: key(18),
+            index: 0,
+            proposer: key(1),
+            instruction_data: vec![0x06],
+            accounts: vec![proposal_account()],
+            approvals: 0b101,
+            executed: false,
+        })),
+        ("Treasury", encode(&Treasury {
+            governance: key(18),
+            bump: 252,
+            balance: 10_000,
+            invested: 5_000,
+            epoch_budget: 50_000,
+            budget_epoch: 400,
+            spent_in_epoch: 7_500,
+            allowed_program_count: 1,
+            allowed_programs: [key(19), Pubkey::default(), Pubkey::default(), Pubkey::default()],
+            fee_epoch: 401,
+            epoch_fees: 30,
+            undistributed_fees: 120,
+        })),
+        ("FeeSchedule", encode(&FeeSchedule {
+            stakers_bps: 5_000,
+            insurance_bps: 2_000,
+            treasury_bps: 3_000,
+            staker_rewards: 60,
+        })),
+        ("ReclaimPolicy", encode(&ReclaimPolicy { inactivity_slots: 6_480_000, grace_slots: 1_296_000, cranker_share_bps: 1_000 })),
+        ("CompressedTree", encode(&CompressedTree {
+            merkle_tree: key(27),
+            max_depth: 20,
+            max_buffer_size: 64,
+            authority_bump: 255,
+            next_leaf_index: 10,
+        })),
+        ("RegistryPage", encode(&{
+            let mut users = [Pubkey::default(); PAGE_CAPACITY];
+            users[..2].copy_from_slice(&[key(1), key(2)]);
+            RegistryPage { index: 3, count: 2, users }
+        })),
+        ("Upload", encode(&Upload { authority: key(1), target: key(26), chunks_written: 12, finalized: true, data_hash: [11; 32] })),
+        ("DailyStats", encode(&DailyStats {
+            day_start: 1_699_920_000,
+            transfer_count: 42,
+            volume: 1_000_000,
+            fees: 1_000,
+            active_users: 2,
+            active_bitmap: [0b101, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+        })),
+        ("Lock", encode(&Lock { owner: key(1), lock_id: 3, amount: 1_000, locked_at: 1_700_000_000, unlock_at: 1_731_536_000 })),
+        ("VestingSchedule", encode(&VestingSchedule {
+            grantor: key(1),
+            beneficiary: key(2),
+            total: 12_000,
+            start_ts: 1_700_000_000,
+            cliff: 7_776_000,
+            duration: 31_536_000,
+            claimed: 3_000,
+        })),
+        ("Escrow", encode(&Escrow {
+            payer: key(1),
+            escrow_id: 4,
+            counterparty: key(2),
+            arbiter: key(20),
+            amount: 5_000,
+            expires_at: 1_731_536_000,
+            settled: false,
+        })),
+        ("StakeAccount", encode(&StakeAccount {
+            owner: key(1),
+            amount: 5_000,
+            reward_per_share_paid: 2_000_000_000_000,
+            pending_rewards: 750,
+        })),
+        ("PendingWithdrawal", encode(&PendingWithdrawal {
+            owner: key(1),
+            amount: 5_000,
+            requested_at: 1_700_000_000,
+            executable_at: 1_700_086_400,
+        })),
+        ("AllowlistEntry", encode(&AllowlistEntry { wallet: key(1), allowed: true, updated_at: 1_700_000_000 })),
+        (
+            "ComplianceConfig",
+            encode(&ComplianceConfig {
+                authority: key(20),
+                attestor: key(22),
+                attestation_threshold: 10_000,
+                clawback_authority: key(23),
+            }),
+        ),
+        ("Attestation", encode(&Attestation { user: key(1), attestor: key(22), issued_at: 1_700_000_000, expires_at: 1_731_536_000 })),
+        ("AccountLimits", encode(&AccountLimits {
+            user_account: key(21),
+            max_per_transfer: 1_000,
+            max_per_day: 5_000,
+            day_window: 900,
+            spent_in_day: 1_500,
+            compliance_locked: true,
+        })),
+        ("Delegations", delegations()),
+        ("AuditLog", audit_log()),
+        ("RateState", encode(&RateState { updater: key(24), rate: 3 << 63, last_update: 1_700_000_000 })),
+        ("LendingConfig", encode(&LendingConfig {
+            max_ltv_bps: 7_500,
+            liquidation_threshold_bps: 8_000,
+            close_factor_bps: 5_000,
+            liquidation_bonus_bps: 500,
+            max_rate_age: 60,
+        })),
+        ("Position", encode(&Position { owner: key(1), collateral: 1_000_000, debt_scaled: 480 })),
+        ("InsuranceFund", encode(&InsuranceFund {
+            authority: key(25),
+            fee_share_bps: 2_000,
+            balance: 925,
+            total_contributed: 1_000,
+            total_paid_out: 75,
+        })),
+    ]
+}
+
+fn delegations() -> Vec<u8> {
+    let delegations = Delegations {
+        owner: key(1),
+        entries: vec![DelegateEntry { delegate: key(2), scope: 1, remaining: 2_000, expires_at: 1_731_536_000 }],
+        other_records: vec![(0xff00, vec![1, 2, 3])],
+    };
+    let mut data = vec![0; DELEGATIONS_LEN];
+    delegations.pack_into(&mut data).unwrap();
+    data
+}
+
+fn audit_log() -> Vec<u8> {
+    let mut audit_log = AuditLog::default();
+    audit_log.record(AuditEntry {
+        action: AuditAction::Clawback,
+        authority: key(23),
+        target: key(3),
+        amount: 500,
+        reason_code: 7,
+        timestamp: 1_700_000_000,
+    });
+    encode(&audit_log)
+}
+
+fn encodings() -> BTreeMap<String, String> {
+    let mut encodings = BTreeMap::new();
+    for instruction in instructions() {
+        let name = format!("instruction.{}", instruction_name(&instruction));
+        encodings.insert(name, hex(&instruction.pack().unwrap()));
+    }
+    for (name, bytes) in accounts() {
+        encodings.insert(format!("account.{}", name), hex(&bytes));
+    }
+    encodings
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn fixture_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/layout.golden")
+}
+
+#[test]
+fn every_instruction_has_a_fixture() {
+    assert_eq!(instructions().len(), encodings().keys().filter(|name| name.starts_with("instruction.")).count());
+}
+
+#[test]
+fn layouts_match_golden_fixtures() {
+    let actual = encodings();
+    
+    if std::env::var_os("UPDATE_LAYOUT_FIXTURES").is_some() {
+        let contents: String = actual.iter().map(|(name, hex)| format!("{} {}\n", name, hex)).collect();
+        fs::create_dir_all(fixture_path().parent().unwrap()).unwrap();
+        fs::write(fixture_path(), contents).unwrap();
+        return;
+    }
+    
+    let golden: BTreeMap<String, String> = fs::read_to_string(fixture_path())
+        .expect("layout fixtures missing; run with UPDATE_LAYOUT_FIXTURES=1")
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .map(|(name, hex)| (name.to_string(), hex.to_string()))
+        .collect();
+    
+    let mismatches: BTreeSet<&String> = actual
+        .keys()
+        .chain(golden.keys())
+        .filter(|name| actual.get(*name) != golden.get(*name))
+        .collect();
+    assert!(mismatches.is_empty(), "layout changed for: {:?}", mismatches);
+}
+
+#[test]
+fn derived_sizes_and_addresses_match() {
+    let cranker = Cranker { authority: key(8), registered_at: 1_700_000_000, tasks_completed: 4, bounties_earned: 12 };
+    assert_eq!(Cranker::SIZE, encode(&cranker).len());
+    assert_eq!(BountyPool::SIZE, encode(&BountyPool::default()).len());
+    assert_eq!(FeeSchedule::SIZE, encode(&FeeSchedule::default()).len());
+    assert_eq!(ReclaimPolicy::SIZE, encode(&ReclaimPolicy::default()).len());
+    assert_eq!(Upload::SIZE, encode(&Upload::default()).len());
+    assert_eq!(RegistryPage::SIZE, encode(&RegistryPage::default()).len());
+    assert_eq!(VestingSchedule::SIZE, encode(&VestingSchedule::default()).len());
+    assert_eq!(Escrow::SIZE, encode(&Escrow::default()).len());
+    assert_eq!(StakeAccount::SIZE, encode(&StakeAccount::default()).len());
+    assert_eq!(PendingWithdrawal::SIZE, encode(&PendingWithdrawal::default()).len());
+    assert_eq!(AllowlistEntry::SIZE, encode(&AllowlistEntry::default()).len());
+    let accounts: BTreeMap<_, _> = accounts().into_iter().collect();
+    assert_eq!(ProgramState::SIZE, accounts["ProgramState"].len());
+    assert_eq!(USER_ACCOUNT_V3_LEN, accounts["UserAccountV3"].len());
+    assert_eq!(USER_ACCOUNT_LEN, accounts["UserAccount"].len());
+    assert_eq!(CompressedTree::SIZE, encode(&CompressedTree::default()).len());
+    
+    let program_id = key(7);
+    assert_eq!(
+        Cranker::find_address(&key(8), &program_id).unwrap(),
+        account_creation::create_cranker_pda(&key(8), &program_id).unwrap(),
+    );
+    assert_eq!(BountyPool::find_address(&program_id).unwrap(), account_creation::create_bounty_pool_pda(&program_id).unwrap());
+    assert_eq!(FeeSchedule::find_address(&program_id).unwrap(), account_creation::create_fee_schedule_pda(&program_id).unwrap());
+    assert_ne!(Cranker::DISCRIMINATOR, BountyPool::DISCRIMINATOR);
+}
@@ -0,0 +1,45 @@
+//! Instructions that create their PDAs, run against the runtime so the system program CPIs
+//! actually allocate the accounts. Kept apart from `handlers.rs`, whose syscall stubs would
+//! clash with the ones `ProgramTest` installs.
+
+use account_utils::account_creation;
+use borsh::BorshDeserialize;
+use my_solana_program::{process_instruction, treasury::Treasury, ProgramInstruction};
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    system_program,
+};
+use solana_program_test::{processor, tokio, ProgramTest};
+use solana_sdk::{signature::Signer, transaction::Transaction};
+
+fn program_test(program_id: Pubkey) -> ProgramTest {
+    ProgramTest::new("my_solana_program", program_id, processor!(process_instruction))
+}
+
+#[tokio::test]
+async fn initialize_creates_the_treasury() {
+    let program_id = Pubkey::new_unique();
+    let (mut banks_client, payer, recent_blockhash) = program_test(program_id).start().await;
+    
+    let (treasury, treasury_bump) = account_creation::create_treasury_pda(&program_id).unwrap();
+    let instruction = Instruction::new_with_bytes(
+        program_id,
+        &ProgramInstruction::Initialize { initial_amount: 5 }.pack().unwrap(),
+        vec![
+            AccountMeta::new(account_creation::create_program_state_pda(&program_id).unwrap().0, false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(account_creation::create_program_config_pda(&program_id).unwrap().0, false),
+            AccountMeta::new(treasury, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let transaction = Transaction::new_signed_with_payer(&[instruction], Some(&payer.pubkey()), &[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+    
+    let account = banks_client.get_account(treasury).await.unwrap().expect("treasury was not created");
+    assert_eq!(account.owner, program_id);
+    let treasury = Treasury::deserialize(&mut account.data.as_slice()).unwrap();
+    assert_eq!(treasury.bump, treasury_bump);
+    assert!(!treasury.is_configured());
+}
@@ -0,0 +1,243 @@
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use solana_program::pubkey::Pubkey;
+use common::CommonError;
+use account_utils::account_creation;
+use math_utils::{percentage, token_math};
+use my_solana_program::{analytics, ProgramInstruction};
+use my_solana_program_sdk::instructions;
+use std::str::FromStr;
+
+fn value_error(err: CommonError) -> PyErr {
+    PyValueError::new_err(err.to_string())
+}
+
+fn parse_pubkey(value: &str) -> PyResult<Pubkey> {
+    Pubkey::from_str(value).map_err(|_| PyValueError::new_err(format!("Invalid pubkey: {}", value)))
+}
+
+/// Token amounts in base units and decimal conversions
+pub mod token_math_py {
+    use super::*;
+
+    /// Scale a whole-token amount to base units at `decimals`
+    #[pyfunction]
+    pub fn calculate_token_amount(base_amount: u64, decimals: u8) -> PyResult<u64> {
+        token_math::calculate_token_amount(base_amount, decimals).map_err(value_error)
+    }
+
+    /// Rescale `amount` between decimal precisions, truncating lost precision
+    #[pyfunction]
+    pub fn normalize_amount(amount: u64, from_decimals: u8, to_decimals: u8) -> PyResult<u64> {
+        token_math::normalize_amount(amount, from_decimals, to_decimals).map_err(value_error)
+    }
+
+    /// Convert a base-unit amount back to whole tokens
+    #[pyfunction]
+    pub fn convert_to_base_units(token_amount: u64, decimals: u8) -> PyResult<u64> {
+        token_math::convert_to_base_units(token_amount, decimals).map_err(value_error)
+    }
+}
+
+/// Basis-point fees, exactly as the program rounds them
+pub mod percentage_py {
+    use super::*;
+
+    /// `amount` × `basis_points` / 10 000, rounded down
+    #[pyfunction]
+    pub fn calculate_percentage(amount: u64, basis_points: u16) -> PyResult<u64> {
+        percentage::calculate_percentage(amount, basis_points).map_err(value_error)
+    }
+
+    /// Largest amount whose sum with its fee fits within `budget`
+    #[pyfunction]
+    pub fn max_amount_with_fee(budget: u64, fee_basis_points: u16) -> PyResult<u64> {
+        percentage::max_amount_with_fee(budget, fee_basis_points).map_err(value_error)
+    }
+
+    /// Penalty on withdrawing `amount` from a lock with `remaining` of `duration` seconds left
+    #[pyfunction]
+    pub fn early_withdrawal_penalty(
+        amount: u64,
+        max_penalty_basis_points: u16,
+        remaining: u64,
+        duration: u64,
+    ) -> PyResult<u64> {
+        percentage::early_withdrawal_penalty(amount, max_penalty_basis_points, remaining, duration)
+            .map_err(value_error)
+    }
+
+    /// Check that split shares add up to 100%
+    #[pyfunction]
+    pub fn validate_split(shares_basis_points: Vec<u16>) -> PyResult<()> {
+        percentage::validate_split(&shares_basis_points).map_err(value_error)
+    }
+}
+
+/// Program-derived addresses as `(address, bump)` with base58 pubkeys
+pub mod pda_py {
+    use super::*;
+
+    fn derived(result: common::CommonResult<(Pubkey, u8)>) -> PyResult<(String, u8)> {
+        result.map(|(address, bump)| (address.to_string(), bump)).map_err(value_error)
+    }
+
+    /// Program state singleton
+    #[pyfunction]
+    pub fn program_state_pda(program_id: &str) -> PyResult<(String, u8)> {
+        derived(account_creation::create_program_state_pda(&parse_pubkey(program_id)?))
+    }
+
+    /// Program config singleton
+    #[pyfunction]
+    pub fn program_config_pda(program_id: &str) -> PyResult<(String, u8)> {
+        derived(account_creation::create_program_config_pda(&parse_pubkey(program_id)?))
+    }
+
+    /// Metrics singleton
+    #[pyfunction]
+    pub fn metrics_pda(program_id: &str) -> PyResult<(String, u8)> {
+        derived(account_creation::create_metrics_pda(&parse_pubkey(program_id)?))
+    }
+
+    /// Fee treasury singleton
+    #[pyfunction]
+    pub fn treasury_pda(program_id: &str) -> PyResult<(String, u8)> {
+        derived(account_creation::create_treasury_pda(&parse_pubkey(program_id)?))
+    }
+
+    /// Fee schedule singleton
+    #[pyfunction]
+    pub fn fee_schedule_pda(program_id: &str) -> PyResult<(String, u8)> {
+        derived(account_creation::create_fee_schedule_pda(&parse_pubkey(program_id)?))
+    }
+
+    /// User account of `owner`
+    #[pyfunction]
+    pub fn user_pda(owner: &str, program_id: &str) -> PyResult<(String, u8)> {
+        derived(account_creation::create_user_pda(&parse_pubkey(owner)?, &parse_pubkey(program_id)?))
+    }
+
+    /// Session key letting `delegate` act for `owner`
+    #[pyfunction]
+    pub fn session_key_pda(owner: &str, delegate: &str, program_id: &str) -> PyResult<(String, u8)> {
+        derived(account_creation::create_session_key_pda(
+            &parse_pubkey(owner)?,
+            &parse_pubkey(delegate)?,
+            &parse_pubkey(program_id)?,
+        ))
+    }
+
+    /// Lending position of `owner`
+    #[pyfunction]
+    pub fn position_pda(owner: &str, program_id: &str) -> PyResult<(String, u8)> {
+        derived(account_creation::create_position_pda(&parse_pubkey(owner)?, &parse_pubkey(program_id)?))
+    }
+
+    /// Token lock `lock_id` of `owner`
+    #[pyfunction]
+    pub fn lock_pda(owner: &str, lock_id: u16, program_id: &str) -> PyResult<(String, u8)> {
+        derived(account_creation::create_lock_pda(&parse_pubkey(owner)?, lock_id, &parse_pubkey(program_id)?))
+    }
+
+    /// Daily analytics rollup covering unix time `timestamp`
+    #[pyfunction]
+    pub fn daily_stats_pda(timestamp: i64, program_id: &str) -> PyResult<(String, u8)> {
+        derived(account_creation::create_daily_stats_pda(
+            analytics::day_start(timestamp),
+            &parse_pubkey(program_id)?,
+        ))
+    }
+}
+
+/// Instruction data encoding, through the program's own `pack`/`unpack`
+pub mod instruction_py {
+    use super::*;
+
+    /// Account list of an instruction as `(pubkey, is_signer, is_writable)`
+    type AccountList = Vec<(String, bool, bool)>;
+
+    /// Encode an instruction given as serde JSON (e.g. `{"TransferWithFee": {...}}`)
+    #[pyfunction]
+    pub fn pack_instruction<'py>(py: Python<'py>, instruction_json: &str) -> PyResult<&'py PyBytes> {
+        let instruction: ProgramInstruction = serde_json::from_str(instruction_json)
+            .map_err(|err| PyValueError::new_err(format!("Invalid instruction: {}", err)))?;
+        let data = instruction
+            .pack()
+            .map_err(|err| PyValueError::new_err(format!("Failed to pack instruction: {}", err)))?;
+        Ok(PyBytes::new(py, &data))
+    }
+
+    /// Decode instruction data back to serde JSON
+    #[pyfunction]
+    pub fn unpack_instruction(data: &[u8]) -> PyResult<String> {
+        let instruction = ProgramInstruction::unpack(data)
+            .map_err(|err| PyValueError::new_err(format!("Invalid instruction data: {}", err)))?;
+        serde_json::to_string(&instruction).map_err(|err| PyValueError::new_err(err.to_string()))
+    }
+
+    /// Full `TransferWithFee` instruction as `(program_id, accounts, data)`
+    #[pyfunction]
+    #[pyo3(signature = (program_id, owner, recipient, amount, fee_basis_points, idempotency_key=None, references=Vec::new()))]
+    pub fn transfer_with_fee<'py>(
+        py: Python<'py>,
+        program_id: &str,
+        owner: &str,
+        recipient: &str,
+        amount: u64,
+        fee_basis_points: u16,
+        idempotency_key: Option<[u8; 16]>,
+        references: Vec<String>,
+    ) -> PyResult<(String, AccountList, &'py PyBytes)> {
+        let references = references
+            .iter()
+            .map(|reference| parse_pubkey(reference))
+            .collect::<PyResult<Vec<_>>>()?;
+        let instruction = instructions::transfer_with_fee(
+            &parse_pubkey(program_id)?,
+            &parse_pubkey(owner)?,
+            &parse_pubkey(recipient)?,
+            amount,
+            fee_basis_points,
+            idempotency_key,
+            &references,
+        )
+        .map_err(value_error)?;
+        
+        let accounts = instruction
+            .accounts
+            .iter()
+            .map(|meta| (meta.pubkey.to_string(), meta.is_signer, meta.is_writable))
+            .collect();
+        Ok((instruction.program_id.to_string(), accounts, PyBytes::new(py, &instruction.data)))
+    }
+}
+
+#[pymodule]
+fn my_solana_program_py(_py: Python, m: &PyModule) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(token_math_py::calculate_token_amount, m)?)?;
+    m.add_function(wrap_pyfunction!(token_math_py::normalize_amount, m)?)?;
+    m.add_function(wrap_pyfunction!(token_math_py::convert_to_base_units, m)?)?;
+    
+    m.add_function(wrap_pyfunction!(percentage_py::calculate_percentage, m)?)?;
+    m.add_function(wrap_pyfunction!(percentage_py::max_amount_with_fee, m)?)?;
+    m.add_function(wrap_pyfunction!(percentage_py::early_withdrawal_penalty, m)?)?;
+    m.add_function(wrap_pyfunction!(percentage_py::validate_split, m)?)?;
+    
+    m.add_function(wrap_pyfunction!(pda_py::program_state_pda, m)?)?;
+    m.add_function(wrap_pyfunction!(pda_py::program_config_pda, m)?)?;
+    m.add_function(wrap_pyfunction!(pda_py::metrics_pda, m)?)?;
+    m.add_function(wrap_pyfunction!(pda_py::treasury_pda, m)?)?;
+    m.add_function(wrap_pyfunction!(pda_py::fee_schedule_pda, m)?)?;
+    m.add_function(wrap_pyfunction!(pda_py::user_pda, m)?)?;
+    m.add_function(wrap_pyfunction!(pda_py::session_key_pda, m)?)?;
+    m.add_function(wrap_pyfunction!(pda_py::position_pda, m)?)?;
+    m.add_function(wrap_pyfunction!(pda_py::lock_pda, m)?)?;
+    m.add_function(wrap_pyfunction!(pda_py::daily_stats_pda, m)?)?;
+    
+    m.add_function(wrap_pyfunction!(instruction_py::pack_instruction, m)?)?;
+    m.add_function(wrap_pyfunction!(instruction_py::unpack_instruction, m)?)?;
+    m.add_function(wrap_pyfunction!(instruction_py::transfer_with_fee, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,1955 @@
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    system_program,
+};
+use borsh::BorshDeserialize;
+use common::{CommonError, CommonResult, constants::DEFAULT_DECIMALS};
+use account_utils::{account_creation, account_validation, OffchainMessage};
+use my_solana_program::{
+    analytics::{self, DailyStats},
+    AuthorityHistory, Metrics, ProgramInstruction, ProgramState, UserAccount, UserAccountV1,
+    UserAccountV2, UserAccountV3, USER_ACCOUNT_V1_LEN, USER_ACCOUNT_V2_LEN, USER_ACCOUNT_V3_LEN,
+};
+use std::str::FromStr;
+
+/// Human-readable token amounts in `DEFAULT_DECIMALS` decimal places
+pub mod formatting {
+    use super::*;
+
+    /// Format base units as a decimal amount without trailing zeros
+    pub fn format_amount(amount: u64) -> String {
+        let scale = 10u64.pow(DEFAULT_DECIMALS as u32);
+        let fraction = amount % scale;
+        if fraction == 0 {
+            return (amount / scale).to_string();
+        }
+        
+        let digits = format!("{:0width$}", fraction, width = DEFAULT_DECIMALS as usize);
+        format!("{}.{}", amount / scale, digits.trim_end_matches('0'))
+    }
+
+    /// Parse a decimal amount into base units, rejecting excess precision
+    pub fn parse_amount(value: &str) -> CommonResult<u64> {
+        let invalid = || CommonError::Custom(format!("Invalid amount {}", value));
+        let (whole, fraction) = value.split_once('.').unwrap_or((value, ""));
+        if whole.is_empty() || fraction.len() > DEFAULT_DECIMALS as usize
+            || !whole.bytes().chain(fraction.bytes()).all(|b| b.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+        
+        let padded = format!("{:0<width$}", fraction, width = DEFAULT_DECIMALS as usize);
+        let whole: u64 = whole.parse().map_err(|_| invalid())?;
+        let fraction: u64 = padded.parse().map_err(|_| invalid())?;
+        whole.checked_mul(10u64.pow(DEFAULT_DECIMALS as u32))
+            .and_then(|units| units.checked_add(fraction))
+            .ok_or(CommonError::InvalidCalculation)
+    }
+}
+
+/// Decoding helpers for raw account data fetched over RPC
+pub mod accounts {
+    use super::*;
+
+    /// Decode a Borsh-encoded program account
+    pub fn decode_account<T: BorshDeserialize>(data: &[u8]) -> CommonResult<T> {
+        T::try_from_slice(data).map_err(|_| CommonError::AccountValidationFailed)
+    }
+
+    /// Decode a user account in any of the v1, v2, v3 or current layouts
+    pub fn decode_user_account(data: &[u8]) -> CommonResult<UserAccount> {
+        match data.len() {
+            USER_ACCOUNT_V1_LEN => decode_account::<UserAccountV1>(data).map(UserAccount::from),
+            USER_ACCOUNT_V2_LEN => decode_account::<UserAccountV2>(data).map(UserAccount::from),
+            USER_ACCOUNT_V3_LEN => decode_account::<UserAccountV3>(data).map(UserAccount::from),
+            _ => decode_account(data),
+        }
+    }
+}
+
+/// Explanations of failed transactions from the error registry
+pub mod errors {
+    use common::error_registry::{ErrorEntry, COMMON_ERRORS};
+    use my_solana_program::errors::PROGRAM_ERRORS;
+    use solana_program::{instruction::InstructionError, program_error::ProgramError};
+
+    /// The program's entry for `code`, the `u64` form of the `ProgramError` it failed with
+    pub fn explain_error(code: u64) -> Option<&'static ErrorEntry> {
+        PROGRAM_ERRORS.iter().find(|entry| entry.code == code)
+    }
+
+    /// Library errors that surface under `code`, for support tooling narrowing down a failure
+    pub fn library_causes(code: u64) -> impl Iterator<Item = &'static ErrorEntry> {
+        COMMON_ERRORS.iter().filter(move |entry| entry.code == code)
+    }
+
+    /// `explain_error` for the `InstructionError` of a failed transaction
+    pub fn explain_instruction_error(error: &InstructionError) -> Option<&'static ErrorEntry> {
+        ProgramError::try_from(error.clone()).ok().and_then(|error| explain_error(u64::from(error)))
+    }
+}
+
+/// JSON rendering of program accounts for CLIs and explorers
+#[cfg(feature = "json")]
+pub mod json {
+    use super::*;
+    use my_solana_program::{
+        compliance::{AuditLog, ComplianceConfig},
+        compression::CompressedTree,
+        crank::BountyPool,
+        exchange_rate::RateState,
+        insurance::InsuranceFund,
+        lending::{LendingConfig, Position},
+        lockup::Lock,
+        reclaim::ReclaimPolicy,
+        treasury::{FeeSchedule, Treasury},
+        registry::RegistryPage,
+        upload::Upload,
+        vesting::VestingSchedule,
+        escrow::Escrow,
+        staking::StakeAccount,
+        withdrawal::PendingWithdrawal,
+        allowlist::AllowlistEntry,
+        ProgramConfig, SessionKey,
+    };
+    use serde::Serialize;
+    use serde_json::{json, Value};
+
+    /// A decoded account type and its token amount fields
+    struct Decoded {
+        account_type: &'static str,
+        data: Value,
+        amount_fields: &'static [&'static str],
+    }
+
+    /// Decode an account of the program as `{"type", "address", "data"}` JSON, adding a
+    /// decimal `<field>_ui` string next to every token amount in `data`.
+    ///
+    /// Accounts carry no type tag, so the type is identified by address: singleton PDAs
+    /// by their fixed seeds, per-owner accounts by decoding the candidate layouts and
+    /// re-deriving the address from the decoded fields.
+    pub fn decode_account_json(program_id: &Pubkey, address: &Pubkey, data: &[u8]) -> CommonResult<Value> {
+        let Decoded { account_type, mut data, amount_fields } = identify(program_id, address, data)?;
+        
+        if let Some(fields) = data.as_object_mut() {
+            for &field in amount_fields {
+                if let Some(amount) = fields.get(field).and_then(Value::as_u64) {
+                    fields.insert(format!("{}_ui", field), Value::String(formatting::format_amount(amount)));
+                }
+            }
+        }
+        
+        Ok(json!({ "type": account_type, "address": address.to_string(), "data": data }))
+    }
+
+    fn identify(program_id: &Pubkey, address: &Pubkey, data: &[u8]) -> CommonResult<Decoded> {
+        let is = |pda: CommonResult<(Pubkey, u8)>| pda.is_ok_and(|(pda, _bump)| pda == *address);
+        
+        if is(account_creation::create_program_state_pda(program_id)) {
+            return decoded::<ProgramState>("ProgramState", data, &["total_supply"]);
+        }
+        if is(account_creation::create_program_config_pda(program_id)) {
+            return decoded::<ProgramConfig>("ProgramConfig", data, &["max_transfer_amount"]);
+        }
+        if is(account_creation::create_metrics_pda(program_id)) {
+            return decoded::<Metrics>("Metrics", data, &["total_volume", "fees_collected"]);
+        }
+        if is(account_creation::create_authority_history_pda(program_id)) {
+            return decoded::<AuthorityHistory>("AuthorityHistory", data, &[]);
+        }
+        if is(account_creation::create_bounty_pool_pda(program_id)) {
+            return decoded::<BountyPool>("BountyPool", data, &["balance", "total_paid"]);
+        }
+        if is(account_creation::create_treasury_pda(program_id)) {
+            return decoded::<Treasury>(
+                "Treasury",
+                data,
+                &["balance", "invested", "epoch_budget", "spent_in_epoch", "epoch_fees", "undistributed_fees"],
+            );
+        }
+        if is(account_creation::create_compliance_config_pda(program_id)) {
+            return decoded::<ComplianceConfig>("ComplianceConfig", data, &["attestation_threshold"]);
+        }
+        if is(account_creation::create_audit_log_pda(program_id)) {
+            return decoded::<AuditLog>("AuditLog", data, &[]);
+        }
+        if is(account_creation::create_rate_state_pda(program_id)) {
+            return decoded::<RateState>("RateState", data, &[]);
+        }
+        if is(account_creation::create_lending_config_pda(program_id)) {
+            return decoded::<LendingConfig>("LendingConfig", data, &[]);
+        }
+        if is(account_creation::create_insurance_fund_pda(program_id)) {
+            return decoded::<InsuranceFund>(
+                "InsuranceFund",
+                data,
+                &["balance", "total_contributed", "total_paid_out"],
+            );
+        }
+        if is(account_creation::create_fee_schedule_pda(program_id)) {
+            return decoded::<FeeSchedule>("FeeSchedule", data, &["staker_rewards"]);
+        }
+        if is(ReclaimPolicy::find_address(program_id)) {
+            return decoded::<ReclaimPolicy>("ReclaimPolicy", data, &[]);
+        }
+        if is(CompressedTree::find_address(program_id)) {
+            return decoded::<CompressedTree>("CompressedTree", data, &[]);
+        }
+        
+        // Per-owner accounts: the address must derive from the decoded fields
+        if let Ok(user_account) = accounts::decode_user_account(data) {
+            if is(account_creation::create_user_pda(&user_account.owner, program_id)) {
+                return to_decoded("UserAccount", &user_account, &["balance"]);
+            }
+        }
+        if let Ok(session_key) = accounts::decode_account::<SessionKey>(data) {
+            if is(account_creation::create_session_key_pda(&session_key.owner, &session_key.delegate, program_id)) {
+                return to_decoded("SessionKey", &session_key, &["per_operation_limit"]);
+            }
+        }
+        if let Ok(lock) = accounts::decode_account::<Lock>(data) {
+            if is(account_creation::create_lock_pda(&lock.owner, lock.lock_id, program_id)) {
+                return to_decoded("Lock", &lock, &["amount"]);
+            }
+        }
+        if let Ok(position) = accounts::decode_account::<Position>(data) {
+            if is(account_creation::create_position_pda(&position.owner, program_id)) {
+                return to_decoded("Position", &position, &[]);
+            }
+        }
+        if let Ok(upload) = accounts::decode_account::<Upload>(data) {
+            if is(Upload::find_address(&upload.target, program_id)) {
+                return to_decoded("Upload", &upload, &[]);
+            }
+        }
+        if let Ok(page) = accounts::decode_account::<RegistryPage>(data) {
+            if is(RegistryPage::find_address(page.index, program_id)) {
+                return to_decoded("RegistryPage", &page, &[]);
+            }
+        }
+        if let Ok(schedule) = accounts::decode_account::<VestingSchedule>(data) {
+            if is(VestingSchedule::find_address(&schedule.grantor, &schedule.beneficiary, program_id)) {
+                return to_decoded("VestingSchedule", &schedule, &["total", "claimed"]);
+            }
+        }
+        if let Ok(escrow) = accounts::decode_account::<Escrow>(data) {
+            if is(account_creation::create_vault_pda(&escrow.payer, escrow.escrow_id, program_id)) {
+                return to_decoded("Escrow", &escrow, &["amount"]);
+            }
+        }
+        if let Ok(stake) = accounts::decode_account::<StakeAccount>(data) {
+            if is(StakeAccount::find_address(&stake.owner, program_id)) {
+                return to_decoded("StakeAccount", &stake, &["amount", "pending_rewards"]);
+            }
+        }
+        if let Ok(pending) = accounts::decode_account::<PendingWithdrawal>(data) {
+            if is(PendingWithdrawal::find_address(&pending.owner, program_id)) {
+                return to_decoded("PendingWithdrawal", &pending, &["amount"]);
+            }
+        }
+        if let Ok(entry) = accounts::decode_account::<AllowlistEntry>(data) {
+            if is(AllowlistEntry::find_address(&entry.wallet, program_id)) {
+                return to_decoded("AllowlistEntry", &entry, &[]);
+            }
+        }
+        if let Ok(stats) = accounts::decode_account::<DailyStats>(data) {
+            if is(account_creation::create_daily_stats_pda(stats.day_start, program_id)) {
+                return to_decoded("DailyStats", &stats, &["volume", "fees"]);
+            }
+        }
+        
+        Err(CommonError::Custom(format!("Unrecognized account {}", address)))
+    }
+
+    fn decoded<T: BorshDeserialize + Serialize>(
+        account_type: &'static str,
+        data: &[u8],
+        amount_fields: &'static [&'static str],
+    ) -> CommonResult<Decoded> {
+        to_decoded(account_type, &accounts::decode_account::<T>(data)?, amount_fields)
+    }
+
+    fn to_decoded<T: Serialize>(
+        account_type: &'static str,
+        value: &T,
+        amount_fields: &'static [&'static str],
+    ) -> CommonResult<Decoded> {
+        let data = serde_json::to_value(value)
+            .map_err(|err| CommonError::Custom(err.to_string()))?;
+        Ok(Decoded { account_type, data, amount_fields })
+    }
+}
+
+/// Authority rotation helpers
+pub mod authority {
+    use super::*;
+
+    /// Verify the recorded rotations form an unbroken chain ending at the current authority
+    pub fn verify_rotation_chain(
+        state: &ProgramState,
+        history: &AuthorityHistory
+    ) -> CommonResult<()> {
+        let rotations = history.entries();
+        
+        for pair in rotations.windows(2) {
+            if pair[0].new_authority != pair[1].previous_authority {
+                return Err(CommonError::Custom("Broken authority rotation chain".to_string()));
+            }
+            if pair[1].rotated_at < pair[0].rotated_at {
+                return Err(CommonError::Custom("Authority rotations out of order".to_string()));
+            }
+        }
+        
+        if let Some(latest) = rotations.last() {
+            if latest.new_authority != state.authority {
+                return Err(CommonError::Custom(
+                    "Latest rotation does not match current authority".to_string()
+                ));
+            }
+        }
+        
+        Ok(())
+    }
+
+    /// Authorities that held control before the current one, oldest first
+    pub fn retired_authorities(history: &AuthorityHistory) -> Vec<Pubkey> {
+        history.entries().iter().map(|r| r.previous_authority).collect()
+    }
+}
+
+/// Admin actions for a program whose authority is a multisig PDA
+///
+/// After `RotateAuthority` hands `ProgramState.authority` to a multisig PDA, each admin
+/// instruction is built as usual with the multisig as its authority, then proposed,
+/// approved and executed through the multisig.
+pub mod multisig {
+    use super::*;
+    use my_solana_program::multisig::ProposalAccount;
+
+    /// Build a `CreateProposal` that stores `action` as proposal `index` of `multisig`
+    pub fn propose_admin_action(
+        program_id: &Pubkey,
+        multisig: &Pubkey,
+        proposer: &Pubkey,
+        index: u64,
+        action: &Instruction,
+    ) -> CommonResult<Instruction> {
+        if action.program_id != *program_id {
+            return Err(CommonError::Custom("Admin action targets another program".to_string()));
+        }
+        let (proposal, _) = account_creation::create_proposal_pda(multisig, index, program_id)?;
+        
+        let accounts = action.accounts.iter()
+            .map(|meta| ProposalAccount {
+                pubkey: meta.pubkey,
+                is_signer: meta.is_signer,
+                is_writable: meta.is_writable,
+            })
+            .collect();
+        let data = ProgramInstruction::CreateProposal { instruction_data: action.data.clone(), accounts }
+            .pack()
+            .map_err(|_| CommonError::Custom("Failed to pack instruction".to_string()))?;
+        
+        Ok(Instruction {
+            program_id: *program_id,
+            accounts: vec![
+                AccountMeta::new(proposal, false),
+                AccountMeta::new(*multisig, false),
+                AccountMeta::new_readonly(*proposer, true),
+            ],
+            data,
+        })
+    }
+
+    /// Build an `ApproveProposal` recording `signer`'s approval of proposal `index`
+    pub fn approve_admin_action(
+        program_id: &Pubkey,
+        multisig: &Pubkey,
+        index: u64,
+        signer: &Pubkey,
+    ) -> CommonResult<Instruction> {
+        let (proposal, _) = account_creation::create_proposal_pda(multisig, index, program_id)?;
+        let data = ProgramInstruction::ApproveProposal
+            .pack()
+            .map_err(|_| CommonError::Custom("Failed to pack instruction".to_string()))?;
+        
+        Ok(Instruction {
+            program_id: *program_id,
+            accounts: vec![
+                AccountMeta::new(proposal, false),
+                AccountMeta::new_readonly(*multisig, false),
+                AccountMeta::new_readonly(*signer, true),
+            ],
+            data,
+        })
+    }
+
+    /// Build an `ExecuteProposal` for proposal `index`, passing the accounts `action` needs
+    ///
+    /// The multisig signs the inner instruction through the program, so no account is
+    /// marked as a transaction signer here.
+    pub fn execute_admin_action(
+        program_id: &Pubkey,
+        multisig: &Pubkey,
+        index: u64,
+        action: &Instruction,
+    ) -> CommonResult<Instruction> {
+        let (proposal, _) = account_creation::create_proposal_pda(multisig, index, program_id)?;
+        let data = ProgramInstruction::ExecuteProposal
+            .pack()
+            .map_err(|_| CommonError::Custom("Failed to pack instruction".to_string()))?;
+        
+        let mut accounts = vec![
+            AccountMeta::new(proposal, false),
+            AccountMeta::new_readonly(*multisig, false),
+            AccountMeta::new_readonly(*program_id, false),
+        ];
+        accounts.extend(action.accounts.iter().map(|meta| AccountMeta {
+            pubkey: meta.pubkey,
+            is_signer: false,
+            is_writable: meta.is_writable,
+        }));
+        
+        Ok(Instruction { program_id: *program_id, accounts, data })
+    }
+}
+
+/// Accessors for the on-chain `Metrics` account
+pub mod metrics {
+    use super::*;
+
+    /// Address of the metrics PDA
+    pub fn metrics_address(program_id: &Pubkey) -> CommonResult<Pubkey> {
+        account_creation::create_metrics_pda(program_id).map(|(address, _bump)| address)
+    }
+
+    /// Decode raw metrics account data
+    pub fn decode_metrics(data: &[u8]) -> CommonResult<Metrics> {
+        accounts::decode_account(data)
+    }
+
+    /// Address of the `DailyStats` PDA of the UTC day containing `timestamp`
+    pub fn daily_stats_address(program_id: &Pubkey, timestamp: i64) -> CommonResult<Pubkey> {
+        account_creation::create_daily_stats_pda(analytics::day_start(timestamp), program_id)
+            .map(|(address, _bump)| address)
+    }
+
+    /// Linear-counting estimate of a day's active users from its bitmap, which unlike
+    /// `active_users` corrects for users sharing a bit
+    pub fn estimate_active_users(stats: &DailyStats) -> u64 {
+        let bits = analytics::ACTIVE_USER_BITS as f64;
+        let unset = stats.active_bitmap.iter().map(|word| word.count_zeros()).sum::<u32>() as f64;
+        if unset == 0.0 {
+            return stats.active_users;
+        }
+        (bits * (bits / unset).ln()).round() as u64
+    }
+
+    /// Mean transfer size, or zero before the first transfer
+    pub fn average_transfer_size(metrics: &Metrics) -> u64 {
+        metrics.total_volume.checked_div(metrics.transfer_count).unwrap_or(0)
+    }
+
+    /// Fees collected as basis points of total volume
+    pub fn effective_fee_basis_points(metrics: &Metrics) -> u64 {
+        (metrics.fees_collected as u128 * 10_000)
+            .checked_div(metrics.total_volume as u128)
+            .map_or(0, |bps| bps as u64)
+    }
+}
+
+/// Enumerating users through the registry pages `CreateUserAccount` appends to
+pub mod registry {
+    use super::*;
+    use my_solana_program::registry::{page_index, RegistryPage, PAGE_CAPACITY};
+    #[cfg(feature = "rpc")]
+    use solana_client::rpc_client::RpcClient;
+
+    /// Most accounts one `getMultipleAccounts` request may name
+    pub const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+    /// Address of the registry page at `index`
+    pub fn page_address(program_id: &Pubkey, index: u32) -> CommonResult<Pubkey> {
+        RegistryPage::find_address(index, program_id).map(|(address, _bump)| address)
+    }
+
+    /// Page to pass to `CreateUserAccount` while the metrics count `unique_users`
+    pub fn current_page_address(program_id: &Pubkey, unique_users: u64) -> CommonResult<Pubkey> {
+        page_address(program_id, page_index(unique_users)?)
+    }
+
+    /// Addresses of every page that may hold users, in order, given the metrics' `unique_users`
+    pub fn page_addresses(program_id: &Pubkey, unique_users: u64) -> CommonResult<Vec<Pubkey>> {
+        let pages = unique_users.div_ceil(PAGE_CAPACITY as u64);
+        (0..pages).map(|index| page_address(program_id, index as u32)).collect()
+    }
+
+    /// Decode raw registry page data
+    pub fn decode_page(data: &[u8]) -> CommonResult<RegistryPage> {
+        accounts::decode_account(data)
+    }
+
+    /// Users of `pages` in creation order; pages must be given in index order
+    pub fn users(pages: &[RegistryPage]) -> impl Iterator<Item = Pubkey> + '_ {
+        pages.iter().flat_map(|page| page.entries().iter().copied())
+    }
+
+    /// Every registered user in creation order, read page by page with `getMultipleAccounts`;
+    /// pages that were never allocated (users created before the registry) are skipped
+    #[cfg(feature = "rpc")]
+    pub fn fetch_users(client: &RpcClient, program_id: &Pubkey) -> CommonResult<Vec<Pubkey>> {
+        let rpc_error = |err| CommonError::Custom(format!("RPC error: {}", err));
+        let metrics = metrics::decode_metrics(
+            &client.get_account_data(&metrics::metrics_address(program_id)?).map_err(rpc_error)?,
+        )?;
+        
+        let mut pages = Vec::new();
+        for addresses in page_addresses(program_id, metrics.unique_users)?.chunks(MAX_MULTIPLE_ACCOUNTS) {
+            for account in client.get_multiple_accounts(addresses).map_err(rpc_error)?.into_iter().flatten() {
+                pages.push(decode_page(&account.data)?);
+            }
+        }
+        Ok(users(&pages).collect())
+    }
+}
+
+/// Client-side proof-of-work solving for `CreateUserAccount`
+pub mod pow {
+    use super::*;
+
+    /// Search for a nonce satisfying `difficulty`, starting at `start_nonce`
+    pub fn find_pow_nonce(user: &Pubkey, difficulty: u8, start_nonce: u64) -> Option<u64> {
+        (start_nonce..=u64::MAX)
+            .find(|&nonce| account_validation::validate_account_creation_pow(user, nonce, difficulty).is_ok())
+    }
+}
+
+/// Instruction builders
+pub mod instructions {
+    use super::*;
+
+    /// Build a `TransferWithFee` from `owner`'s user account to `recipient`'s, tagged with
+    /// read-only `references` for payment discovery
+    pub fn transfer_with_fee(
+        program_id: &Pubkey,
+        owner: &Pubkey,
+        recipient: &Pubkey,
+        amount: u64,
+        fee_basis_points: u16,
+        idempotency_key: Option<[u8; 16]>,
+        references: &[Pubkey],
+    ) -> CommonResult<Instruction> {
+        let (from, _) = account_creation::create_user_pda(owner, program_id)?;
+        let (to, _) = account_creation::create_user_pda(recipient, program_id)?;
+        let (program_state, _) = account_creation::create_program_state_pda(program_id)?;
+        let (metrics, _) = account_creation::create_metrics_pda(program_id)?;
+        let (program_config, _) = account_creation::create_program_config_pda(program_id)?;
+        let (treasury, _) = account_creation::create_treasury_pda(program_id)?;
+        
+        // The owner pays for the idempotency log on its first keyed payment
+        let owner_meta = if idempotency_key.is_some() {
+            AccountMeta::new(*owner, true)
+        } else {
+            AccountMeta::new_readonly(*owner, true)
+        };
+        let mut accounts = vec![
+            AccountMeta::new(from, false),
+            AccountMeta::new(to, false),
+            owner_meta,
+            AccountMeta::new_readonly(program_state, false),
+            AccountMeta::new(metrics, false),
+            AccountMeta::new_readonly(program_config, false),
+            AccountMeta::new(treasury, false),
+        ];
+        if idempotency_key.is_some() {
+            let (log, _) = account_creation::create_idempotency_log_pda(owner, program_id)?;
+            accounts.push(AccountMeta::new(log, false));
+            accounts.push(AccountMeta::new_readonly(system_program::id(), false));
+        }
+        accounts.extend(references.iter().map(|reference| AccountMeta::new_readonly(*reference, false)));
+        
+        let data = ProgramInstruction::TransferWithFee { amount, fee_basis_points, idempotency_key }
+            .pack()
+            .map_err(|_| CommonError::Custom("Failed to pack instruction".to_string()))?;
+        
+        Ok(Instruction { program_id: *program_id, accounts, data })
+    }
+}
+
+/// Solana Pay transfer request links (`solana:<recipient>?amount=...`)
+pub mod solana_pay {
+    use super::*;
+
+    /// URL scheme of a Solana Pay link
+    pub const SCHEME: &str = "solana:";
+
+    /// A decoded transfer request; `amount` is in base units of `DEFAULT_DECIMALS`
+    #[derive(Debug, Clone, PartialEq, Default)]
+    pub struct TransferRequest {
+        pub recipient: Pubkey,
+        pub amount: Option<u64>,
+        pub references: Vec<Pubkey>,
+        pub label: Option<String>,
+        pub message: Option<String>,
+        pub memo: Option<String>,
+    }
+
+    impl TransferRequest {
+        /// Build the program's `TransferWithFee` paying this request from `payer`
+        pub fn to_instruction(
+            &self,
+            program_id: &Pubkey,
+            payer: &Pubkey,
+            fee_basis_points: u16,
+        ) -> CommonResult<Instruction> {
+            let amount = self.amount
+                .ok_or_else(|| CommonError::Custom("Transfer request has no amount".to_string()))?;
+            instructions::transfer_with_fee(
+                program_id,
+                payer,
+                &self.recipient,
+                amount,
+                fee_basis_points,
+                None,
+                &self.references,
+            )
+        }
+
+        /// Encode as a `solana:` URL
+        pub fn to_url(&self) -> String {
+            let mut params = Vec::new();
+            if let Some(amount) = self.amount {
+                params.push(format!("amount={}", formatting::format_amount(amount)));
+            }
+            for reference in &self.references {
+                params.push(format!("reference={}", reference));
+            }
+            if let Some(label) = &self.label {
+                params.push(format!("label={}", percent_encode(label)));
+            }
+            if let Some(message) = &self.message {
+                params.push(format!("message={}", percent_encode(message)));
+            }
+            if let Some(memo) = &self.memo {
+                params.push(format!("memo={}", percent_encode(memo)));
+            }
+            
+            let mut url = format!("{}{}", SCHEME, self.recipient);
+            if !params.is_empty() {
+                url.push('?');
+                url.push_str(&params.join("&"));
+            }
+            url
+        }
+    }
+
+    /// Produce a transfer request link for `amount` base units
+    pub fn transfer_request_url(
+        recipient: &Pubkey,
+        amount: u64,
+        reference: Option<&Pubkey>,
+        label: Option<&str>,
+    ) -> String {
+        TransferRequest {
+            recipient: *recipient,
+            amount: Some(amount),
+            references: reference.into_iter().copied().collect(),
+            label: label.map(str::to_string),
+            ..TransferRequest::default()
+        }
+        .to_url()
+    }
+
+    /// Parse a `solana:` transfer request link
+    pub fn parse_transfer_request(url: &str) -> CommonResult<TransferRequest> {
+        let rest = url.strip_prefix(SCHEME)
+            .ok_or_else(|| CommonError::Custom("Not a solana: URL".to_string()))?;
+        let (recipient, query) = rest.split_once('?').unwrap_or((rest, ""));
+        
+        let mut request = TransferRequest {
+            recipient: Pubkey::from_str(recipient)
+                .map_err(|_| CommonError::Custom("Invalid recipient".to_string()))?,
+            ..TransferRequest::default()
+        };
+        
+        for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let value = percent_decode(value)?;
+            match key {
+                "amount" => request.amount = Some(formatting::parse_amount(&value)?),
+                "reference" => request.references.push(Pubkey::from_str(&value)
+                    .map_err(|_| CommonError::Custom("Invalid reference".to_string()))?),
+                "label" => request.label = Some(value),
+                "message" => request.message = Some(value),
+                "memo" => request.memo = Some(value),
+                // spl-token and future parameters are not handled by this program
+                _ => {}
+            }
+        }
+        
+        Ok(request)
+    }
+
+    fn percent_encode(value: &str) -> String {
+        value.bytes().map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        }).collect()
+    }
+
+    fn percent_decode(value: &str) -> CommonResult<String> {
+        let invalid = || CommonError::Custom("Invalid percent encoding".to_string());
+        let bytes = value.as_bytes();
+        let mut out = Vec::with_capacity(bytes.len());
+        let mut i = 0;
+        while i < bytes.len() {
+            match bytes[i] {
+                b'%' => {
+                    let hex = value.get(i + 1..i + 3).ok_or_else(invalid)?;
+                    out.push(u8::from_str_radix(hex, 16).map_err(|_| invalid())?);
+                    i += 3;
+                }
+                b'+' => {
+                    out.push(b' ');
+                    i += 1;
+                }
+                byte => {
+                    out.push(byte);
+                    i += 1;
+                }
+            }
+        }
+        String::from_utf8(out).map_err(|_| invalid())
+    }
+}
+
+/// Sign-In-With-Solana messages, signed in the off-chain message format
+pub mod siws {
+    use super::*;
+
+    const HEADER_SUFFIX: &str = " wants you to sign in with your Solana account:";
+
+    /// Shortest accepted nonce
+    pub const MIN_NONCE_LEN: usize = 8;
+
+    /// A sign-in request; times are unix seconds rendered as RFC 3339 UTC
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct SignInMessage {
+        pub domain: String,
+        pub address: Pubkey,
+        pub statement: Option<String>,
+        pub nonce: String,
+        pub issued_at: i64,
+        pub expiration_time: Option<i64>,
+    }
+
+    impl SignInMessage {
+        /// Render the human-readable message text
+        pub fn to_text(&self) -> String {
+            let mut text = format!("{}{}\n{}", self.domain, HEADER_SUFFIX, self.address);
+            if let Some(statement) = &self.statement {
+                text.push_str(&format!("\n\n{}", statement));
+            }
+            text.push_str(&format!("\n\nNonce: {}", self.nonce));
+            text.push_str(&format!("\nIssued At: {}", format_timestamp(self.issued_at)));
+            if let Some(expiration_time) = self.expiration_time {
+                text.push_str(&format!("\nExpiration Time: {}", format_timestamp(expiration_time)));
+            }
+            text
+        }
+
+        /// Parse message text produced by `to_text`
+        pub fn parse(text: &str) -> CommonResult<Self> {
+            let invalid = || CommonError::Custom("Invalid sign-in message".to_string());
+            let mut lines = text.split('\n');
+            
+            let domain = lines.next()
+                .and_then(|line| line.strip_suffix(HEADER_SUFFIX))
+                .ok_or_else(invalid)?
+                .to_string();
+            let address = lines.next()
+                .and_then(|line| Pubkey::from_str(line).ok())
+                .ok_or_else(invalid)?;
+            
+            let mut message = Self {
+                domain,
+                address,
+                statement: None,
+                nonce: String::new(),
+                issued_at: 0,
+                expiration_time: None,
+            };
+            
+            let mut issued_at = None;
+            for line in lines.filter(|line| !line.is_empty()) {
+                if let Some(nonce) = line.strip_prefix("Nonce: ") {
+                    message.nonce = nonce.to_string();
+                } else if let Some(time) = line.strip_prefix("Issued At: ") {
+                    issued_at = Some(parse_timestamp(time)?);
+                } else if let Some(time) = line.strip_prefix("Expiration Time: ") {
+                    message.expiration_time = Some(parse_timestamp(time)?);
+                } else if message.statement.is_none() && message.nonce.is_empty() {
+                    message.statement = Some(line.to_string());
+                } else {
+                    return Err(invalid());
+                }
+            }
+            message.issued_at = issued_at.ok_or_else(invalid)?;
+            
+            Ok(message)
+        }
+
+        /// Bytes the wallet signs
+        pub fn to_offchain_message(&self) -> CommonResult<OffchainMessage> {
+            OffchainMessage::new(self.to_text().as_bytes())
+        }
+
+        /// Verify `signature` by `address` and that the message is for `domain` and `nonce`,
+        /// issued at most `max_age_seconds` before `now` and not expired
+        pub fn verify(
+            &self,
+            signature: &[u8; 64],
+            domain: &str,
+            nonce: &str,
+            now: i64,
+            max_age_seconds: i64,
+        ) -> CommonResult<()> {
+            if self.domain != domain || self.nonce != nonce || self.nonce.len() < MIN_NONCE_LEN {
+                return Err(CommonError::InsufficientPermissions);
+            }
+            
+            let age = now.checked_sub(self.issued_at);
+            if self.issued_at > now || age.is_none_or(|age| age > max_age_seconds)
+                || self.expiration_time.is_some_and(|expiration_time| now >= expiration_time)
+            {
+                return Err(CommonError::Custom("Sign-in message expired".to_string()));
+            }
+            
+            let public_key = ed25519_dalek::PublicKey::from_bytes(self.address.as_ref())
+                .map_err(|_| CommonError::AccountValidationFailed)?;
+            let signature = ed25519_dalek::Signature::try_from(&signature[..])
+                .map_err(|_| CommonError::InsufficientPermissions)?;
+            public_key
+                .verify_strict(&self.to_offchain_message()?.serialize(), &signature)
+                .map_err(|_| CommonError::InsufficientPermissions)
+        }
+
+        /// Check that `user_account_data` at `user_account` is the signer's `UserAccount`
+        pub fn verify_user_account(
+            &self,
+            program_id: &Pubkey,
+            user_account: &Pubkey,
+            user_account_data: &[u8],
+        ) -> CommonResult<UserAccount> {
+            let (expected, _) = account_creation::create_user_pda(&self.address, program_id)?;
+            if expected != *user_account {
+                return Err(CommonError::AccountValidationFailed);
+            }
+            
+            let account = accounts::decode_user_account(user_account_data)?;
+            if account.owner != self.address {
+                return Err(CommonError::InsufficientPermissions);
+            }
+            Ok(account)
+        }
+    }
+
+    /// Format unix seconds as `YYYY-MM-DDTHH:MM:SSZ`
+    fn format_timestamp(timestamp: i64) -> String {
+        let days = timestamp.div_euclid(86_400);
+        let seconds = timestamp.rem_euclid(86_400);
+        
+        // Civil-from-days (Howard Hinnant)
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + i64::from(month <= 2);
+        
+        format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            year, month, day, seconds / 3_600, seconds % 3_600 / 60, seconds % 60
+        )
+    }
+
+    /// Parse `YYYY-MM-DDTHH:MM:SSZ` (optional fractional seconds) into unix seconds
+    fn parse_timestamp(value: &str) -> CommonResult<i64> {
+        let invalid = || CommonError::Custom(format!("Invalid timestamp {}", value));
+        let value = value.strip_suffix('Z').ok_or_else(invalid)?;
+        let value = value.split('.').next().ok_or_else(invalid)?;
+        if !value.is_ascii() || value.len() != 19 || value.as_bytes()[10] != b'T' {
+            return Err(invalid());
+        }
+        
+        let field = |range: std::ops::Range<usize>| -> CommonResult<i64> {
+            value[range].parse().map_err(|_| invalid())
+        };
+        let (year, month, day) = (field(0..4)?, field(5..7)?, field(8..10)?);
+        let (hour, minute, second) = (field(11..13)?, field(14..16)?, field(17..19)?);
+        if !(1..=12).contains(&month) || !(1..=31).contains(&day)
+            || hour > 23 || minute > 59 || second > 60
+        {
+            return Err(invalid());
+        }
+        
+        // Days-from-civil (Howard Hinnant)
+        let year = if month <= 2 { year - 1 } else { year };
+        let era = year.div_euclid(400);
+        let yoe = year.rem_euclid(400);
+        let mp = if month > 2 { month - 3 } else { month + 9 };
+        let doy = (153 * mp + 2) / 5 + day - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        let days = era * 146_097 + doe - 719_468;
+        
+        Ok(days * 86_400 + hour * 3_600 + minute * 60 + second)
+    }
+}
+
+/// Payment discovery over RPC using reference keys (the Solana Pay `findReference` pattern)
+#[cfg(feature = "rpc")]
+pub mod payments {
+    use super::*;
+    use solana_client::{
+        rpc_client::{GetConfirmedSignaturesForAddress2Config, RpcClient},
+        rpc_response::RpcConfirmedTransactionStatusWithSignature,
+    };
+
+    /// Maximum signatures returned by one `getSignaturesForAddress` call
+    pub const SIGNATURE_PAGE_LIMIT: usize = 1000;
+
+    /// Find the oldest transaction referencing `reference`, if any has landed
+    pub fn find_reference(
+        client: &RpcClient,
+        reference: &Pubkey,
+    ) -> CommonResult<Option<RpcConfirmedTransactionStatusWithSignature>> {
+        let config = GetConfirmedSignaturesForAddress2Config {
+            limit: Some(SIGNATURE_PAGE_LIMIT),
+            ..GetConfirmedSignaturesForAddress2Config::default()
+        };
+        
+        let mut signatures = client
+            .get_signatures_for_address_with_config(reference, config)
+            .map_err(|err| CommonError::Custom(format!("RPC error: {}", err)))?;
+        
+        // Signatures come newest first
+        Ok(signatures.pop())
+    }
+}
+
+/// Program events from `sol_log_data` logs or from noop instructions under `CPI_EVENTS`
+pub mod events {
+    use super::*;
+    use my_solana_program::{
+        compliance::ClawbackEvent, events::NOOP_PROGRAM_ID, BalanceCheckpointEvent, InitializeEvent,
+        InvoicePaidEvent, TransferEvent, TransferFilledEvent,
+    };
+
+    /// Prefix of the log lines written by `sol_log_data`
+    pub const PROGRAM_DATA_PREFIX: &str = "Program data: ";
+
+    /// An event emitted by the program
+    #[derive(Debug)]
+    pub enum ProgramEvent {
+        Initialize(InitializeEvent),
+        Transfer(TransferEvent),
+        TransferFilled(TransferFilledEvent),
+        InvoicePaid(InvoicePaidEvent),
+        Clawback(ClawbackEvent),
+        BalanceCheckpoint(BalanceCheckpointEvent),
+    }
+
+    /// Decode a tagged event payload; `None` for unknown tags or malformed data
+    pub fn decode_event(tag: &[u8], data: &[u8]) -> Option<ProgramEvent> {
+        match tag {
+            InitializeEvent::TAG => InitializeEvent::try_from_slice(data).ok().map(ProgramEvent::Initialize),
+            TransferEvent::TAG => TransferEvent::try_from_slice(data).ok().map(ProgramEvent::Transfer),
+            TransferFilledEvent::TAG => TransferFilledEvent::try_from_slice(data).ok().map(ProgramEvent::TransferFilled),
+            InvoicePaidEvent::TAG => InvoicePaidEvent::try_from_slice(data).ok().map(ProgramEvent::InvoicePaid),
+            ClawbackEvent::TAG => ClawbackEvent::try_from_slice(data).ok().map(ProgramEvent::Clawback),
+            BalanceCheckpointEvent::TAG => BalanceCheckpointEvent::try_from_slice(data).ok().map(ProgramEvent::BalanceCheckpoint),
+            _ => None,
+        }
+    }
+
+    /// Decode events from `Program data:` log lines, skipping other programs' data
+    pub fn decode_log_events(logs: &[String]) -> Vec<ProgramEvent> {
+        logs.iter()
+            .filter_map(|line| line.strip_prefix(PROGRAM_DATA_PREFIX))
+            .filter_map(|fields| {
+                let fields: Vec<Vec<u8>> = fields.split(' ').map(base64::decode).collect::<Result<_, _>>().ok()?;
+                match fields.as_slice() {
+                    [tag, data] => decode_event(tag, data),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+
+    /// Decoded fields of the `Program data:` lines logged by `program_id` itself, following
+    /// the invoke/success log lines so that data logged by other programs is excluded
+    pub fn program_data(logs: &[String], program_id: &Pubkey) -> Vec<Vec<Vec<u8>>> {
+        let program = program_id.to_string();
+        let mut invoked: Vec<&str> = Vec::new();
+        let mut data = Vec::new();
+        for line in logs {
+            let Some(rest) = line.strip_prefix("Program ") else {
+                continue;
+            };
+            if let Some(fields) = line.strip_prefix(PROGRAM_DATA_PREFIX) {
+                if invoked.last() == Some(&program.as_str()) {
+                    if let Ok(fields) = fields.split(' ').map(base64::decode).collect() {
+                        data.push(fields);
+                    }
+                }
+                continue;
+            }
+            
+            let mut words = rest.split(' ');
+            match (words.next(), words.next()) {
+                (Some(id), Some("invoke")) => invoked.push(id),
+                (Some(id), Some("success" | "failed:")) if invoked.last() == Some(&id) => {
+                    invoked.pop();
+                }
+                _ => {}
+            }
+        }
+        data
+    }
+
+    /// Decode the data of one noop instruction, laid out as `[tag_len, tag, borsh(event)]`
+    pub fn decode_cpi_event(instruction_data: &[u8]) -> Option<ProgramEvent> {
+        let (&tag_len, rest) = instruction_data.split_first()?;
+        if rest.len() < tag_len as usize {
+            return None;
+        }
+        let (tag, data) = rest.split_at(tag_len as usize);
+        decode_event(tag, data)
+    }
+
+    /// Decode events from a transaction's inner instructions, given as `(program id, data)`
+    /// pairs; instructions to other programs are skipped
+    pub fn decode_inner_instruction_events<'a>(
+        instructions: impl IntoIterator<Item = (&'a Pubkey, &'a [u8])>,
+    ) -> Vec<ProgramEvent> {
+        instructions
+            .into_iter()
+            .filter(|(program_id, _)| **program_id == NOOP_PROGRAM_ID)
+            .filter_map(|(_, data)| decode_cpi_event(data))
+            .collect()
+    }
+
+    /// All events of a transaction, whichever transport carried them
+    pub fn decode_transaction_events<'a>(
+        logs: &[String],
+        inner_instructions: impl IntoIterator<Item = (&'a Pubkey, &'a [u8])>,
+    ) -> Vec<ProgramEvent> {
+        let mut events = decode_log_events(logs);
+        events.extend(decode_inner_instruction_events(inner_instructions));
+        events
+    }
+}
+
+/// Historical balances rebuilt from the `BalanceCheckpointEvent` logged on every user
+/// account write
+#[cfg(feature = "rpc")]
+pub mod history {
+    use super::*;
+    use my_solana_program::BalanceCheckpointEvent;
+    use solana_client::rpc_client::{GetConfirmedSignaturesForAddress2Config, RpcClient};
+    use solana_client::rpc_config::RpcTransactionConfig;
+    use solana_sdk::signature::Signature;
+    use solana_transaction_status::UiTransactionEncoding;
+
+    /// Balance of a user account after one transaction
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct Checkpoint {
+        pub signature: Signature,
+        pub slot: u64,
+        pub block_time: Option<i64>,
+        pub balance: u64,
+        /// `user_flags` at the time; balances of `INTEREST_BEARING` accounts are scaled
+        pub flags: u32,
+    }
+
+    /// A balance change between two consecutive checkpoints
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct BalanceMovement {
+        pub signature: Signature,
+        pub slot: u64,
+        pub block_time: Option<i64>,
+        pub before: u64,
+        pub after: u64,
+    }
+
+    impl BalanceMovement {
+        /// Signed change, positive for incoming funds
+        pub fn delta(&self) -> i128 {
+            self.after as i128 - self.before as i128
+        }
+    }
+
+    fn rpc_error(err: impl std::fmt::Display) -> CommonError {
+        CommonError::Custom(format!("RPC error: {}", err))
+    }
+
+    /// The last checkpoint of `user_account` the program logged in one transaction
+    fn checkpoint_in(logs: &[String], program_id: &Pubkey, user_account: &Pubkey) -> Option<BalanceCheckpointEvent> {
+        events::program_data(logs, program_id)
+            .iter()
+            .rev()
+            .filter_map(|fields| match fields.as_slice() {
+                [tag, data] if tag.as_slice() == BalanceCheckpointEvent::TAG => {
+                    BalanceCheckpointEvent::try_from_slice(data).ok()
+                }
+                _ => None,
+            })
+            .find(|checkpoint| checkpoint.user_account == *user_account)
+    }
+
+    /// Visit the checkpoints of `user_account`, newest first, until `visit` returns false;
+    /// failed transactions and ones that did not write the account are skipped
+    pub fn walk_checkpoints(
+        client: &RpcClient,
+        program_id: &Pubkey,
+        user_account: &Pubkey,
+        mut visit: impl FnMut(&Checkpoint) -> bool,
+    ) -> CommonResult<()> {
+        let mut before = None;
+        loop {
+            let config = GetConfirmedSignaturesForAddress2Config {
+                before,
+                limit: Some(payments::SIGNATURE_PAGE_LIMIT),
+                ..GetConfirmedSignaturesForAddress2Config::default()
+            };
+            let page = client.get_signatures_for_address_with_config(user_account, config).map_err(rpc_error)?;
+            let Some(last) = page.last() else {
+                return Ok(());
+            };
+            before = Some(Signature::from_str(&last.signature).map_err(rpc_error)?);
+            
+            for status in page.iter().filter(|status| status.err.is_none()) {
+                let signature = Signature::from_str(&status.signature).map_err(rpc_error)?;
+                let config = RpcTransactionConfig {
+                    encoding: Some(UiTransactionEncoding::Json),
+                    max_supported_transaction_version: Some(0),
+                    ..RpcTransactionConfig::default()
+                };
+                let transaction = client.get_transaction_with_config(&signature, config).map_err(rpc_error)?;
+                let logs: Option<Vec<String>> =
+                    transaction.transaction.meta.and_then(|meta| meta.log_messages.into());
+                let Some(event) = checkpoint_in(&logs.unwrap_or_default(), program_id, user_account) else {
+                    continue;
+                };
+                
+                let checkpoint = Checkpoint {
+                    signature,
+                    slot: transaction.slot,
+                    block_time: transaction.block_time,
+                    balance: event.balance,
+                    flags: event.flags,
+                };
+                if !visit(&checkpoint) {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Balance of `user_account` at the end of `slot`; `None` if it had no checkpoint by then
+    pub fn balance_at(
+        client: &RpcClient,
+        program_id: &Pubkey,
+        user_account: &Pubkey,
+        slot: u64,
+    ) -> CommonResult<Option<u64>> {
+        let mut balance = None;
+        walk_checkpoints(client, program_id, user_account, |checkpoint| {
+            if checkpoint.slot <= slot {
+                balance = Some(checkpoint.balance);
+                return false;
+            }
+            true
+        })?;
+        Ok(balance)
+    }
+
+    /// Balance movements of `user_account` with block times in `[start, end)`, oldest
+    /// first; the first movement's `before` is 0 if the account was created in the range
+    pub fn transfers_between(
+        client: &RpcClient,
+        program_id: &Pubkey,
+        user_account: &Pubkey,
+        start: i64,
+        end: i64,
+    ) -> CommonResult<Vec<BalanceMovement>> {
+        let mut in_range = Vec::new();
+        let mut opening_balance = 0;
+        walk_checkpoints(client, program_id, user_account, |checkpoint| {
+            let time = checkpoint.block_time.unwrap_or(i64::MAX);
+            if time >= end {
+                return true;
+            }
+            if time >= start {
+                in_range.push(checkpoint.clone());
+                return true;
+            }
+            // The newest checkpoint before the range holds the opening balance
+            opening_balance = checkpoint.balance;
+            false
+        })?;
+        
+        let mut before = opening_balance;
+        let mut movements = Vec::new();
+        for checkpoint in in_range.into_iter().rev() {
+            if checkpoint.balance != before {
+                movements.push(BalanceMovement {
+                    signature: checkpoint.signature,
+                    slot: checkpoint.slot,
+                    block_time: checkpoint.block_time,
+                    before,
+                    after: checkpoint.balance,
+                });
+            }
+            before = checkpoint.balance;
+        }
+        Ok(movements)
+    }
+}
+
+/// Wallet confirmation previews built from `simulateTransaction`
+#[cfg(feature = "rpc")]
+pub mod preflight {
+    use super::*;
+    use events::ProgramEvent;
+    use solana_account_decoder::UiAccountEncoding;
+    use solana_client::{
+        rpc_client::RpcClient,
+        rpc_config::{RpcSimulateTransactionAccountsConfig, RpcSimulateTransactionConfig},
+    };
+    use solana_sdk::{account::Account, transaction::Transaction};
+
+    /// Change to a user account's token balance
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct BalanceChange {
+        pub user_account: Pubkey,
+        pub owner: Pubkey,
+        pub before: u64,
+        pub after: u64,
+    }
+
+    impl BalanceChange {
+        /// Signed change, positive when the account gains tokens
+        pub fn delta(&self) -> i128 {
+            self.after as i128 - self.before as i128
+        }
+    }
+
+    /// Change to an account's lamports
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct LamportChange {
+        pub address: Pubkey,
+        pub before: u64,
+        pub after: u64,
+    }
+
+    /// What a transaction would do if sent now
+    #[derive(Debug)]
+    pub struct SimulationSummary {
+        /// Simulation failure, `None` when the transaction would succeed
+        pub error: Option<String>,
+        /// Network fee in lamports
+        pub fee: u64,
+        pub units_consumed: Option<u64>,
+        /// Token balances of the program's user accounts among the writable accounts
+        pub balance_changes: Vec<BalanceChange>,
+        /// Lamport balances of the writable accounts, fee payer included
+        pub lamport_changes: Vec<LamportChange>,
+        /// Writable accounts that do not exist yet and would be created
+        pub new_accounts: Vec<Pubkey>,
+        /// Events logged with `sol_log_data`; simulation does not report the inner
+        /// instructions that carry `CPI_EVENTS` events
+        pub events: Vec<ProgramEvent>,
+        pub logs: Vec<String>,
+    }
+
+    impl SimulationSummary {
+        pub fn succeeded(&self) -> bool {
+            self.error.is_none()
+        }
+    }
+
+    fn rpc_error(err: impl std::fmt::Display) -> CommonError {
+        CommonError::Custom(format!("RPC error: {}", err))
+    }
+
+    /// Simulate `transaction` against current state and summarize its effects on the
+    /// writable accounts it touches; the signatures and blockhash are not checked
+    pub fn simulate_and_explain(
+        client: &RpcClient,
+        program_id: &Pubkey,
+        transaction: &Transaction,
+    ) -> CommonResult<SimulationSummary> {
+        let message = &transaction.message;
+        let writable: Vec<Pubkey> = message
+            .account_keys
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| message.is_writable(*index))
+            .map(|(_, key)| *key)
+            .collect();
+        
+        let before = client.get_multiple_accounts(&writable).map_err(rpc_error)?;
+        let fee = client.get_fee_for_message(message).map_err(rpc_error)?;
+        let config = RpcSimulateTransactionConfig {
+            sig_verify: false,
+            replace_recent_blockhash: true,
+            accounts: Some(RpcSimulateTransactionAccountsConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                addresses: writable.iter().map(Pubkey::to_string).collect(),
+            }),
+            ..RpcSimulateTransactionConfig::default()
+        };
+        let result = client.simulate_transaction_with_config(transaction, config).map_err(rpc_error)?.value;
+        
+        let after: Vec<Option<Account>> = match result.accounts {
+            Some(accounts) => accounts.iter().map(|account| account.as_ref().and_then(|account| account.decode())).collect(),
+            None => vec![None; writable.len()],
+        };
+        let logs = result.logs.unwrap_or_default();
+        
+        let mut summary = SimulationSummary {
+            error: result.err.map(|err| err.to_string()),
+            fee,
+            units_consumed: result.units_consumed,
+            balance_changes: Vec::new(),
+            lamport_changes: Vec::new(),
+            new_accounts: Vec::new(),
+            events: events::decode_log_events(&logs),
+            logs,
+        };
+        
+        // A failed simulation reports no post-state; the summary then carries only the error
+        if summary.succeeded() {
+            for ((address, before), after) in writable.iter().zip(&before).zip(&after) {
+                explain_account(program_id, address, before.as_ref(), after.as_ref(), &mut summary);
+            }
+        }
+        Ok(summary)
+    }
+
+    fn explain_account(
+        program_id: &Pubkey,
+        address: &Pubkey,
+        before: Option<&Account>,
+        after: Option<&Account>,
+        summary: &mut SimulationSummary,
+    ) {
+        let lamports = |account: Option<&Account>| account.map_or(0, |account| account.lamports);
+        if lamports(before) == 0 && lamports(after) > 0 {
+            summary.new_accounts.push(*address);
+        }
+        if lamports(before) != lamports(after) {
+            summary.lamport_changes.push(LamportChange { address: *address, before: lamports(before), after: lamports(after) });
+        }
+        
+        let user_account = |account: Option<&Account>| {
+            account
+                .filter(|account| account.owner == *program_id)
+                .and_then(|account| accounts::decode_user_account(&account.data).ok())
+                .filter(|user| {
+                    account_creation::create_user_pda(&user.owner, program_id).is_ok_and(|(pda, _bump)| pda == *address)
+                })
+        };
+        let (before, after) = (user_account(before), user_account(after));
+        let Some(owner) = after.as_ref().or(before.as_ref()).map(|user| user.owner) else {
+            return;
+        };
+        let balance = |user: Option<UserAccount>| user.map_or(0, |user| user.balance);
+        let (before, after) = (balance(before), balance(after));
+        if before != after {
+            summary.balance_changes.push(BalanceChange { user_account: *address, owner, before, after });
+        }
+    }
+}
+
+/// Transaction sending with blockhash refresh, backoff and a durable-nonce fallback
+#[cfg(feature = "rpc")]
+pub mod sender {
+    use super::*;
+    use solana_client::{
+        client_error::{ClientError, ClientErrorKind},
+        nonce_utils,
+        rpc_client::RpcClient,
+        rpc_config::RpcSendTransactionConfig,
+        rpc_request::{RpcError, RpcResponseErrorData},
+    };
+    use solana_sdk::{
+        commitment_config::CommitmentConfig, hash::Hash, signature::Signature, signers::Signers,
+        system_instruction, transaction::Transaction,
+    };
+    use std::{
+        thread,
+        time::{Duration, Instant},
+    };
+
+    /// Interval between signature status polls while awaiting confirmation
+    pub const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+    /// Retries of the blockhash path before falling back to the durable nonce
+    #[derive(Debug, Clone, Copy)]
+    pub struct RetryPolicy {
+        pub max_attempts: u32,
+        pub initial_backoff: Duration,
+        pub max_backoff: Duration,
+    }
+
+    impl Default for RetryPolicy {
+        fn default() -> Self {
+            Self {
+                max_attempts: 5,
+                initial_backoff: Duration::from_millis(500),
+                max_backoff: Duration::from_secs(8),
+            }
+        }
+    }
+
+    impl RetryPolicy {
+        /// Delay before retry `attempt` (0-based), doubling up to `max_backoff`
+        pub fn backoff(&self, attempt: u32) -> Duration {
+            self.initial_backoff
+                .checked_mul(1u32.checked_shl(attempt).unwrap_or(u32::MAX))
+                .map_or(self.max_backoff, |delay| delay.min(self.max_backoff))
+        }
+    }
+
+    /// A nonce account whose stored blockhash never expires, advanced by `authority`
+    #[derive(Debug, Clone, Copy)]
+    pub struct DurableNonce {
+        pub account: Pubkey,
+        pub authority: Pubkey,
+    }
+
+    /// Why one send attempt did not land
+    enum Attempt {
+        /// Network trouble or an expired blockhash; worth retrying
+        Transient(String),
+        /// The transaction itself fails; retrying cannot help
+        Rejected(String),
+    }
+
+    /// Sends and confirms transactions, retrying on congestion
+    pub struct TxSender<'a> {
+        client: &'a RpcClient,
+        commitment: CommitmentConfig,
+        retry: RetryPolicy,
+        durable_nonce: Option<DurableNonce>,
+        nonce_timeout: Duration,
+    }
+
+    impl<'a> TxSender<'a> {
+        /// Confirmed commitment, the default retry policy and no nonce fallback
+        pub fn new(client: &'a RpcClient) -> Self {
+            Self {
+                client,
+                commitment: CommitmentConfig::confirmed(),
+                retry: RetryPolicy::default(),
+                durable_nonce: None,
+                nonce_timeout: Duration::from_secs(90),
+            }
+        }
+
+        /// Commitment used for blockhashes, preflight and confirmation
+        pub fn commitment(mut self, commitment: CommitmentConfig) -> Self {
+            self.commitment = commitment;
+            self
+        }
+
+        pub fn retry(mut self, retry: RetryPolicy) -> Self {
+            self.retry = retry;
+            self
+        }
+
+        /// Fall back to `nonce` once the blockhash attempts are exhausted; the nonce
+        /// transaction cannot expire, so it is awaited for up to `timeout`
+        pub fn durable_nonce(mut self, nonce: DurableNonce, timeout: Duration) -> Self {
+            self.durable_nonce = Some(nonce);
+            self.nonce_timeout = timeout;
+            self
+        }
+
+        /// Sign `instructions` with `signers`, send and wait for the configured commitment
+        pub fn send<T: Signers>(
+            &self,
+            instructions: &[Instruction],
+            payer: &Pubkey,
+            signers: &T,
+        ) -> CommonResult<Signature> {
+            let mut last_error = String::new();
+            for attempt in 0..self.retry.max_attempts {
+                if attempt > 0 {
+                    thread::sleep(self.retry.backoff(attempt - 1));
+                }
+                
+                match self.send_with_blockhash(instructions, payer, signers) {
+                    Ok(signature) => return Ok(signature),
+                    Err(Attempt::Rejected(err)) => return Err(CommonError::Custom(err)),
+                    Err(Attempt::Transient(err)) => last_error = err,
+                }
+            }
+            
+            match self.durable_nonce {
+                Some(nonce) => self.send_with_nonce(&nonce, instructions, payer, signers).map_err(|err| match err {
+                    Attempt::Transient(err) | Attempt::Rejected(err) => CommonError::Custom(err),
+                }),
+                None => Err(CommonError::Custom(format!(
+                    "transaction not confirmed after {} attempts: {}",
+                    self.retry.max_attempts, last_error
+                ))),
+            }
+        }
+
+        fn send_with_blockhash<T: Signers>(
+            &self,
+            instructions: &[Instruction],
+            payer: &Pubkey,
+            signers: &T,
+        ) -> Result<Signature, Attempt> {
+            let (blockhash, _last_valid_height) =
+                self.client.get_latest_blockhash_with_commitment(self.commitment).map_err(classify)?;
+            let transaction = self.sign(instructions, payer, signers, blockhash)?;
+            let signature = self.submit(&transaction)?;
+            
+            loop {
+                if self.confirmed(&signature)? {
+                    return Ok(signature);
+                }
+                if !self.client.is_blockhash_valid(&blockhash, self.commitment).map_err(classify)? {
+                    // One last look: the transaction may have landed just before expiry
+                    if self.confirmed(&signature)? {
+                        return Ok(signature);
+                    }
+                    return Err(Attempt::Transient(format!("blockhash {} expired", blockhash)));
+                }
+                thread::sleep(POLL_INTERVAL);
+            }
+        }
+
+        fn send_with_nonce<T: Signers>(
+            &self,
+            nonce: &DurableNonce,
+            instructions: &[Instruction],
+            payer: &Pubkey,
+            signers: &T,
+        ) -> Result<Signature, Attempt> {
+            let account = nonce_utils::get_account_with_commitment(self.client, &nonce.account, self.commitment)
+                .map_err(|err| Attempt::Rejected(err.to_string()))?;
+            let blockhash = nonce_utils::data_from_account(&account)
+                .map_err(|err| Attempt::Rejected(err.to_string()))?
+                .blockhash();
+            
+            let mut with_advance = vec![system_instruction::advance_nonce_account(&nonce.account, &nonce.authority)];
+            with_advance.extend_from_slice(instructions);
+            let transaction = self.sign(&with_advance, payer, signers, blockhash)?;
+            let signature = self.submit(&transaction)?;
+            
+            let deadline = Instant::now() + self.nonce_timeout;
+            while Instant::now() < deadline {
+                if self.confirmed(&signature)? {
+                    return Ok(signature);
+                }
+                thread::sleep(POLL_INTERVAL);
+            }
+            Err(Attempt::Transient(format!("nonce transaction {} not confirmed in time", signature)))
+        }
+
+        fn sign<T: Signers>(
+            &self,
+            instructions: &[Instruction],
+            payer: &Pubkey,
+            signers: &T,
+            blockhash: Hash,
+        ) -> Result<Transaction, Attempt> {
+            let mut transaction = Transaction::new_with_payer(instructions, Some(payer));
+            transaction.try_sign(signers, blockhash).map_err(|err| Attempt::Rejected(err.to_string()))?;
+            Ok(transaction)
+        }
+
+        fn submit(&self, transaction: &Transaction) -> Result<Signature, Attempt> {
+            let config = RpcSendTransactionConfig {
+                preflight_commitment: Some(self.commitment.commitment),
+                // Retries are ours; the node should not rebroadcast past the blockhash window
+                max_retries: Some(0),
+                ..RpcSendTransactionConfig::default()
+            };
+            self.client.send_transaction_with_config(transaction, config).map_err(classify)
+        }
+
+        /// Whether `signature` reached the commitment; a landed failure is a rejection
+        fn confirmed(&self, signature: &Signature) -> Result<bool, Attempt> {
+            match self.client.get_signature_status_with_commitment(signature, self.commitment).map_err(classify)? {
+                Some(Ok(())) => Ok(true),
+                Some(Err(err)) => Err(Attempt::Rejected(format!("transaction {} failed: {}", signature, err))),
+                None => Ok(false),
+            }
+        }
+    }
+
+    /// Preflight and transaction errors are final; transport and node errors are retried
+    fn classify(err: ClientError) -> Attempt {
+        match err.kind() {
+            ClientErrorKind::TransactionError(_)
+            | ClientErrorKind::SigningError(_)
+            | ClientErrorKind::RpcError(RpcError::RpcResponseError {
+                data: RpcResponseErrorData::SendTransactionPreflightFailure(_),
+                ..
+            }) => Attempt::Rejected(err.to_string()),
+            _ => Attempt::Transient(err.to_string()),
+        }
+    }
+}
+
+/// Async streams over PDA-indexed program accounts, fetched in bounded batches
+#[cfg(feature = "rpc")]
+pub mod client {
+    use super::*;
+    use futures::{
+        future,
+        stream::{self, Stream, StreamExt, TryStreamExt},
+    };
+    use my_solana_program::{registry::RegistryPage, USER_ACCOUNT_LEN};
+    use solana_client::{
+        nonblocking::rpc_client::RpcClient,
+        rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+        rpc_filter::RpcFilterType,
+    };
+    use solana_account_decoder::UiAccountEncoding;
+    use solana_sdk::account::Account;
+
+    /// Accounts requested per `getMultipleAccounts` call unless configured otherwise
+    pub const DEFAULT_BATCH_SIZE: usize = 50;
+
+    /// A vault PDA of an owner that exists on chain
+    #[derive(Debug, Clone)]
+    pub struct Vault {
+        pub vault_id: u64,
+        pub address: Pubkey,
+        pub account: Account,
+    }
+
+    fn rpc_error(err: impl std::fmt::Display) -> CommonError {
+        CommonError::Custom(format!("RPC error: {}", err))
+    }
+
+    /// Reads program accounts page by page instead of fetching everything at once
+    pub struct ProgramClient {
+        rpc: RpcClient,
+        program_id: Pubkey,
+        batch_size: usize,
+    }
+
+    impl ProgramClient {
+        pub fn new(rpc: RpcClient, program_id: Pubkey) -> Self {
+            Self { rpc, program_id, batch_size: DEFAULT_BATCH_SIZE }
+        }
+
+        /// Accounts per request, clamped to `1..=registry::MAX_MULTIPLE_ACCOUNTS`
+        pub fn batch_size(mut self, batch_size: usize) -> Self {
+            self.batch_size = batch_size.clamp(1, registry::MAX_MULTIPLE_ACCOUNTS);
+            self
+        }
+
+        /// Existing accounts among `addresses`, in order, one request per batch
+        async fn fetch_existing(&self, addresses: &[Pubkey]) -> CommonResult<Vec<(Pubkey, Account)>> {
+            let mut found = Vec::new();
+            for batch in addresses.chunks(self.batch_size) {
+                let accounts = self.rpc.get_multiple_accounts(batch).await.map_err(rpc_error)?;
+                let existing = batch.iter().copied().zip(accounts).filter_map(|(address, account)| Some((address, account?)));
+                found.extend(existing);
+            }
+            Ok(found)
+        }
+
+        /// Wallets recorded in the user registry, or `None` when its first page does not exist
+        async fn registered_wallets(&self) -> CommonResult<Option<Vec<Pubkey>>> {
+            let metrics_data = self.rpc
+                .get_account_data(&metrics::metrics_address(&self.program_id)?)
+                .await
+                .map_err(rpc_error)?;
+            let unique_users = metrics::decode_metrics(&metrics_data)?.unique_users;
+            let addresses = registry::page_addresses(&self.program_id, unique_users)?;
+            
+            let pages = self.fetch_existing(&addresses).await?;
+            if unique_users > 0 && pages.first().map(|(address, _account)| address) != addresses.first() {
+                return Ok(None);
+            }
+            let pages = pages
+                .iter()
+                .map(|(_address, account)| registry::decode_page(&account.data))
+                .collect::<CommonResult<Vec<RegistryPage>>>()?;
+            Ok(Some(registry::users(&pages).collect()))
+        }
+
+        /// User accounts of every layout found by a `getProgramAccounts` scan filtered on size
+        async fn scan_user_accounts(&self) -> CommonResult<Vec<(Pubkey, UserAccount)>> {
+            let mut users = Vec::new();
+            for len in [USER_ACCOUNT_V1_LEN, USER_ACCOUNT_V2_LEN, USER_ACCOUNT_V3_LEN, USER_ACCOUNT_LEN] {
+                let config = RpcProgramAccountsConfig {
+                    filters: Some(vec![RpcFilterType::DataSize(len as u64)]),
+                    account_config: RpcAccountInfoConfig {
+                        encoding: Some(UiAccountEncoding::Base64),
+                        ..RpcAccountInfoConfig::default()
+                    },
+                    ..RpcProgramAccountsConfig::default()
+                };
+                let accounts = self.rpc
+                    .get_program_accounts_with_config(&self.program_id, config)
+                    .await
+                    .map_err(rpc_error)?;
+                users.extend(accounts.iter().filter_map(|(address, account)| self.user_account_at(address, account)));
+            }
+            Ok(users)
+        }
+
+        /// `account` decoded as a user account, if it is one living at its owner's user PDA
+        fn user_account_at(&self, address: &Pubkey, account: &Account) -> Option<(Pubkey, UserAccount)> {
+            let user = accounts::decode_user_account(&account.data).ok()?;
+            let pda = self.user_address(&user.owner).ok()?;
+            (pda == *address).then_some((pda, user))
+        }
+
+        fn user_address(&self, wallet: &Pubkey) -> CommonResult<Pubkey> {
+            account_creation::create_user_pda(wallet, &self.program_id).map(|(pda, _bump)| pda)
+        }
+
+        /// Every user account with its address: registered users in creation order, one
+        /// batch of user PDAs per request. Deployments without a registry fall back to a
+        /// filtered `getProgramAccounts` scan; users since closed or compressed are skipped
+        pub fn iter_user_accounts(&self) -> impl Stream<Item = CommonResult<(Pubkey, UserAccount)>> + '_ {
+            stream::once(self.registered_wallets())
+                .map_ok(move |wallets| match wallets {
+                    Some(wallets) => stream::iter(wallets)
+                        .chunks(self.batch_size)
+                        .then(move |wallets| async move {
+                            let addresses =
+                                wallets.iter().map(|wallet| self.user_address(wallet)).collect::<CommonResult<Vec<_>>>()?;
+                            let accounts = self.fetch_existing(&addresses).await?;
+                            Ok(accounts
+                                .iter()
+                                .filter_map(|(address, account)| self.user_account_at(address, account))
+                                .collect())
+                        })
+                        .left_stream(),
+                    None => stream::once(self.scan_user_accounts()).right_stream(),
+                })
+                .try_flatten()
+                .map_ok(|users: Vec<_>| stream::iter(users.into_iter().map(Ok)))
+                .try_flatten()
+        }
+
+        /// `owner`'s vaults by ascending id. Vault ids are expected to be assigned in order
+        /// from zero, so iteration stops after a batch of ids in which no vault exists
+        pub fn iter_vaults(&self, owner: &Pubkey) -> impl Stream<Item = CommonResult<Vault>> + '_ {
+            let owner = *owner;
+            stream::iter((0..).step_by(self.batch_size))
+                .then(move |first_id: u64| async move {
+                    let ids = first_id..first_id + self.batch_size as u64;
+                    let addresses = ids
+                        .clone()
+                        .map(|vault_id| Ok(account_creation::create_vault_pda(&owner, vault_id, &self.program_id)?.0))
+                        .collect::<CommonResult<Vec<_>>>()?;
+                    let accounts = self.rpc.get_multiple_accounts(&addresses).await.map_err(rpc_error)?;
+                    Ok(ids
+                        .zip(addresses)
+                        .zip(accounts)
+                        .filter_map(|((vault_id, address), account)| Some(Vault { vault_id, address, account: account? }))
+                        .collect::<Vec<_>>())
+                })
+                // An empty batch ends the stream; errors are passed on
+                .take_while(|vaults: &CommonResult<Vec<Vault>>| {
+                    future::ready(!matches!(vaults, Ok(vaults) if vaults.is_empty()))
+                })
+                .map_ok(|vaults| stream::iter(vaults.into_iter().map(Ok)))
+                .try_flatten()
+        }
+    }
+}
+
+/// Versioned account change records for indexers and downstream databases
+#[cfg(feature = "json")]
+pub mod indexer {
+    use super::*;
+    use borsh::BorshSerialize;
+    use serde::{Deserialize, Serialize};
+    use serde_json::Value;
+    use std::{
+        collections::{hash_map::DefaultHasher, HashMap},
+        hash::{Hash, Hasher},
+    };
+
+    /// Current `ChangeRecord::version`; bumped whenever the record layout changes
+    pub const CHANGE_RECORD_VERSION: u8 = 1;
+
+    /// `ChangeRecord::account_type` of an account that was closed or left the program
+    pub const CLOSED_ACCOUNT_TYPE: &str = "Closed";
+
+    /// One account write as delivered by any stream: a websocket `programSubscribe`
+    /// notification, a Geyser `update_account` callback or a snapshot scan
+    #[derive(Debug, Clone)]
+    pub struct AccountUpdate {
+        pub slot: u64,
+        pub pubkey: Pubkey,
+        pub owner: Pubkey,
+        pub lamports: u64,
+        pub data: Vec<u8>,
+    }
+
+    /// A decoded program account at a slot, encodable as Borsh or JSON
+    #[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Debug, Clone, PartialEq)]
+    pub struct ChangeRecord {
+        pub version: u8,
+        pub slot: u64,
+        #[serde(with = "common::serde_pubkey")]
+        pub pubkey: Pubkey,
+        /// Account struct name, or `CLOSED_ACCOUNT_TYPE`
+        pub account_type: String,
+        /// Decoded fields as a JSON object with `<field>_ui` token amounts; kept as text so the
+        /// Borsh encoding needs no JSON schema
+        pub fields: String,
+    }
+
+    impl ChangeRecord {
+        /// Decoded fields as a JSON value
+        pub fn fields(&self) -> CommonResult<Value> {
+            serde_json::from_str(&self.fields).map_err(|err| CommonError::Custom(err.to_string()))
+        }
+
+        /// JSON form with the fields inlined as an object rather than text
+        pub fn to_json(&self) -> CommonResult<Value> {
+            let mut record = serde_json::to_value(self).map_err(|err| CommonError::Custom(err.to_string()))?;
+            record["fields"] = self.fields()?;
+            Ok(record)
+        }
+
+        pub fn to_borsh(&self) -> CommonResult<Vec<u8>> {
+            self.try_to_vec().map_err(|err| CommonError::Custom(err.to_string()))
+        }
+
+        /// Decode a Borsh record, rejecting versions this SDK does not know
+        pub fn from_borsh(data: &[u8]) -> CommonResult<Self> {
+            let record = accounts::decode_account::<Self>(data)?;
+            if record.version != CHANGE_RECORD_VERSION {
+                return Err(CommonError::Custom(format!("Unsupported change record version {}", record.version)));
+            }
+            Ok(record)
+        }
+    }
+
+    /// Turns a stream of account updates into change records, dropping updates that are
+    /// stale (an older slot than already seen), unchanged or not decodable as program accounts
+    pub struct AccountIndexer {
+        program_id: Pubkey,
+        /// Last indexed slot and data fingerprint per account
+        seen: HashMap<Pubkey, (u64, u64)>,
+    }
+
+    impl AccountIndexer {
+        pub fn new(program_id: Pubkey) -> Self {
+            Self { program_id, seen: HashMap::new() }
+        }
+
+        /// Index one update, returning the record to ingest if it changes anything
+        pub fn process(&mut self, update: &AccountUpdate) -> Option<ChangeRecord> {
+            let closed = update.owner != self.program_id || update.lamports == 0;
+            let fingerprint = if closed { 0 } else { fingerprint(&update.data) };
+            match self.seen.get(&update.pubkey) {
+                Some(&(slot, _)) if slot > update.slot => return None,
+                Some(&(_, seen)) if seen == fingerprint => return None,
+                None if closed => return None,
+                _ => {}
+            }
+            
+            let (account_type, fields) = if closed {
+                (CLOSED_ACCOUNT_TYPE.to_string(), "{}".to_string())
+            } else {
+                let mut decoded = json::decode_account_json(&self.program_id, &update.pubkey, &update.data).ok()?;
+                let account_type = decoded["type"].as_str()?.to_string();
+                (account_type, decoded["data"].take().to_string())
+            };
+            
+            if closed {
+                self.seen.remove(&update.pubkey);
+            } else {
+                self.seen.insert(update.pubkey, (update.slot, fingerprint));
+            }
+            Some(ChangeRecord {
+                version: CHANGE_RECORD_VERSION,
+                slot: update.slot,
+                pubkey: update.pubkey,
+                account_type,
+                fields,
+            })
+        }
+
+        /// Index a whole stream, yielding only the records worth ingesting
+        pub fn records<'a, I>(&'a mut self, updates: I) -> impl Iterator<Item = ChangeRecord> + 'a
+        where
+            I: IntoIterator<Item = AccountUpdate> + 'a,
+        {
+            updates.into_iter().filter_map(move |update| self.process(&update))
+        }
+    }
+
+    fn fingerprint(data: &[u8]) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        data.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Websocket source: `programSubscribe` notifications as account updates
+    #[cfg(feature = "rpc")]
+    pub mod websocket {
+        use super::*;
+        use solana_account_decoder::UiAccountEncoding;
+        use solana_client::{
+            pubsub_client::{PubsubClient, PubsubProgramClientSubscription},
+            rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+            rpc_response::{Response, RpcKeyedAccount},
+        };
+        use solana_sdk::account::Account;
+
+        /// Convert one notification; `None` if it cannot be decoded
+        pub fn account_update(notification: &Response<RpcKeyedAccount>) -> Option<AccountUpdate> {
+            let account: Account = notification.value.account.decode()?;
+            Some(AccountUpdate {
+                slot: notification.context.slot,
+                pubkey: Pubkey::from_str(&notification.value.pubkey).ok()?,
+                owner: account.owner,
+                lamports: account.lamports,
+                data: account.data,
+            })
+        }
+
+        /// Subscribe to every account of `program_id`; the subscription must be kept alive
+        /// while the updates are consumed
+        pub fn subscribe(
+            url: &str,
+            program_id: &Pubkey,
+        ) -> CommonResult<(PubsubProgramClientSubscription, impl Iterator<Item = AccountUpdate>)> {
+            let config = RpcProgramAccountsConfig {
+                account_config: RpcAccountInfoConfig {
+                    encoding: Some(UiAccountEncoding::Base64),
+                    ..RpcAccountInfoConfig::default()
+                },
+                ..RpcProgramAccountsConfig::default()
+            };
+            let (subscription, receiver) = PubsubClient::program_subscribe(url, program_id, Some(config))
+                .map_err(|err| CommonError::Custom(format!("RPC error: {}", err)))?;
+            Ok((subscription, receiver.into_iter().filter_map(|notification| account_update(&notification))))
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use ed25519_dalek::{Keypair, PublicKey, SecretKey, Signer};
+use my_solana_program_sdk::siws::SignInMessage;
+use solana_program::pubkey::Pubkey;
+
+const ISSUED_AT: i64 = 1_700_000_000;
+
+fn keypair() -> Keypair {
+    let secret = SecretKey::from_bytes(&[7; 32]).unwrap();
+    let public = PublicKey::from(&secret);
+    Keypair { secret, public }
+}
+
+fn message(address: Pubkey) -> SignInMessage {
+    SignInMessage {
+        domain: "example.com".to_string(),
+        address,
+        statement: Some("Sign in to Example".to_string()),
+        nonce: "abcdef0123".to_string(),
+        issued_at: ISSUED_AT,
+        expiration_time: Some(ISSUED_AT + 600),
+    }
+}
+
+fn sign(keypair: &Keypair, message: &SignInMessage) -> [u8; 64] {
+    keypair.sign(&message.to_offchain_message().unwrap().serialize()).to_bytes()
+}
+
+#[test]
+fn text_round_trips_and_verifies() {
+    let keypair = keypair();
+    let message = message(Pubkey::new_from_array(keypair.public.to_bytes()));
+    
+    let text = message.to_text();
+    assert!(text.contains("Issued At: 2023-11-14T22:13:20Z"));
+    let parsed = SignInMessage::parse(&text).unwrap();
+    assert_eq!(parsed, message);
+    
+    parsed.verify(&sign(&keypair, &message), "example.com", "abcdef0123", ISSUED_AT + 60, 300).unwrap();
+}
+
+#[test]
+fn verify_rejects_another_domain_or_nonce() {
+    let keypair = keypair();
+    let message = message(Pubkey::new_from_array(keypair.public.to_bytes()));
+    let signature = sign(&keypair, &message);
+    
+    assert!(message.verify(&signature, "evil.com", "abcdef0123", ISSUED_AT, 300).is_err());
+    assert!(message.verify(&signature, "example.com", "0123abcdef", ISSUED_AT, 300).is_err());
+}
+
+#[test]
+fn verify_rejects_stale_expired_and_future_messages() {
+    let keypair = keypair();
+    let message = message(Pubkey::new_from_array(keypair.public.to_bytes()));
+    let signature = sign(&keypair, &message);
+    let verify = |now, max_age| message.verify(&signature, "example.com", "abcdef0123", now, max_age);
+    
+    assert!(verify(ISSUED_AT + 301, 300).is_err());
+    assert!(verify(ISSUED_AT + 600, 3_600).is_err());
+    assert!(verify(ISSUED_AT - 1, 300).is_err());
+    
+    // An age that overflows is too old, not a panic
+    let ancient = SignInMessage { issued_at: i64::MIN, expiration_time: None, ..message.clone() };
+    assert!(ancient.verify(&signature, "example.com", "abcdef0123", i64::MAX, i64::MAX).is_err());
+}
+
+#[test]
+fn verify_rejects_a_bad_signature() {
+    let keypair = keypair();
+    let message = message(Pubkey::new_from_array(keypair.public.to_bytes()));
+    
+    let mut signature = sign(&keypair, &message);
+    signature[0] ^= 1;
+    assert!(message.verify(&signature, "example.com", "abcdef0123", ISSUED_AT, 300).is_err());
+    
+    // A valid signature over a different statement
+    let other = SignInMessage { statement: None, ..message.clone() };
+    assert!(message.verify(&sign(&keypair, &other), "example.com", "abcdef0123", ISSUED_AT, 300).is_err());
+}
+
+#[test]
+fn parse_rejects_non_ascii_timestamps() {
+    let keypair = keypair();
+    let text = message(Pubkey::new_from_array(keypair.public.to_bytes()))
+        .to_text()
+        .replace("Issued At: 2023-11-14T22:13:20Z", "Issued At: 2023-11-14T2é:3:20Z");
+    
+    assert!(SignInMessage::parse(&text).is_err());
+}
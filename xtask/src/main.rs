@@ -0,0 +1,485 @@
+use account_utils::test_vectors;
+use borsh::{
+    schema::{BorshSchemaContainer, Definition, Fields},
+    BorshDeserialize, BorshSchema, BorshSerialize,
+};
+use my_solana_program::{
+    analytics::DailyStats,
+    errors,
+    bridge::PostedVaa,
+    compression::CompressedTree,
+    crank::{BountyPool, Cranker},
+    multisig::{Multisig, Proposal},
+    reclaim::ReclaimPolicy,
+    treasury::{FeeSchedule, Treasury},
+    registry::RegistryPage,
+    upload::Upload,
+    lockup::Lock,
+    vesting::VestingSchedule,
+    escrow::Escrow,
+    staking::StakeAccount,
+    withdrawal::PendingWithdrawal,
+    allowlist::AllowlistEntry,
+    compliance::{AccountLimits, Attestation, AuditLog, ComplianceConfig},
+    exchange_rate::RateState,
+    insurance::InsuranceFund,
+    lending::{LendingConfig, Position},
+    instruction::ProgramInstruction,
+    AuthorityHistory, IdempotencyLog, Invoice, Metrics, ProgramConfig, ProgramState,
+    ScheduledTransfer, SessionKey, Stream, Subscription, UserAccount, UserAccountV1,
+    UserAccountV2, UserAccountV3,
+};
+use serde_json::{json, Map, Value};
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+const USAGE: &str = "usage: cargo xtask check-layouts [--fixtures <dir>] [--allow-migrations]
+       cargo xtask export-schema [--out <file>]
+       cargo xtask export-test-vectors [--out <file>]
+       cargo xtask export-errors [--out <file.json|file.ts>]";
+
+/// Zero bytes appended when probing whether an old dump is a prefix of a grown layout
+const GROWTH_PROBE_LEN: usize = 10 * 1024;
+
+/// Outcome of decoding one previous-release account dump with the current struct
+#[derive(Debug, PartialEq)]
+enum Compatibility {
+    /// Decodes exactly and re-encodes to the same bytes
+    Compatible,
+    /// Current struct appends fields; live accounts need a realloc and migration
+    Grown { new_len: usize },
+    /// Current struct is shorter; the exact-length loader rejects live accounts
+    Shrunk { trailing: usize },
+    /// Bytes decode with the current struct but re-encode differently
+    Reencoded,
+    /// Bytes do not decode at all
+    Incompatible,
+}
+
+fn check<T: BorshDeserialize + BorshSerialize>(data: &[u8]) -> Compatibility {
+    if let Ok(value) = T::try_from_slice(data) {
+        return match value.try_to_vec() {
+            Ok(bytes) if bytes == data => Compatibility::Compatible,
+            _ => Compatibility::Reencoded,
+        };
+    }
+    
+    let mut remaining = data;
+    if T::deserialize(&mut remaining).is_ok() {
+        return Compatibility::Shrunk { trailing: remaining.len() };
+    }
+    
+    // Appended fields read as zero from the probe, as they would after a zero-filled realloc
+    let mut padded = data.to_vec();
+    padded.resize(data.len() + GROWTH_PROBE_LEN, 0);
+    let mut remaining = padded.as_slice();
+    match T::deserialize(&mut remaining) {
+        Ok(_) => Compatibility::Grown { new_len: padded.len() - remaining.len() },
+        Err(_) => Compatibility::Incompatible,
+    }
+}
+
+/// Account types checked against dumps named `<Type>.bin`
+fn check_account(name: &str, data: &[u8]) -> Option<Compatibility> {
+    let result = match name {
+        "ProgramState" => check::<ProgramState>(data),
+        "ProgramConfig" => check::<ProgramConfig>(data),
+        "UserAccount" => check::<UserAccount>(data),
+        "UserAccountV1" => check::<UserAccountV1>(data),
+        "UserAccountV2" => check::<UserAccountV2>(data),
+        "UserAccountV3" => check::<UserAccountV3>(data),
+        "SessionKey" => check::<SessionKey>(data),
+        "IdempotencyLog" => check::<IdempotencyLog>(data),
+        "AuthorityHistory" => check::<AuthorityHistory>(data),
+        "Metrics" => check::<Metrics>(data),
+        "ScheduledTransfer" => check::<ScheduledTransfer>(data),
+        "Cranker" => check::<Cranker>(data),
+        "BountyPool" => check::<BountyPool>(data),
+        "Subscription" => check::<Subscription>(data),
+        "Stream" => check::<Stream>(data),
+        "Invoice" => check::<Invoice>(data),
+        "PostedVaa" => check::<PostedVaa>(data),
+        "Multisig" => check::<Multisig>(data),
+        "Proposal" => check::<Proposal>(data),
+        "Treasury" => check::<Treasury>(data),
+        "Lock" => check::<Lock>(data),
+        "ComplianceConfig" => check::<ComplianceConfig>(data),
+        "AccountLimits" => check::<AccountLimits>(data),
+        "Attestation" => check::<Attestation>(data),
+        "AuditLog" => check::<AuditLog>(data),
+        "RateState" => check::<RateState>(data),
+        "LendingConfig" => check::<LendingConfig>(data),
+        "Position" => check::<Position>(data),
+        "InsuranceFund" => check::<InsuranceFund>(data),
+        "FeeSchedule" => check::<FeeSchedule>(data),
+        "ReclaimPolicy" => check::<ReclaimPolicy>(data),
+        "Upload" => check::<Upload>(data),
+        "RegistryPage" => check::<RegistryPage>(data),
+        "VestingSchedule" => check::<VestingSchedule>(data),
+        "Escrow" => check::<Escrow>(data),
+        "StakeAccount" => check::<StakeAccount>(data),
+        "PendingWithdrawal" => check::<PendingWithdrawal>(data),
+        "AllowlistEntry" => check::<AllowlistEntry>(data),
+        "CompressedTree" => check::<CompressedTree>(data),
+        "DailyStats" => check::<DailyStats>(data),
+        _ => return None,
+    };
+    Some(result)
+}
+
+/// Schema containers of the instruction enum and every account type
+fn schema_containers() -> Vec<BorshSchemaContainer> {
+    vec![
+        ProgramInstruction::schema_container(),
+        ProgramState::schema_container(),
+        ProgramConfig::schema_container(),
+        UserAccount::schema_container(),
+        UserAccountV1::schema_container(),
+        UserAccountV2::schema_container(),
+        UserAccountV3::schema_container(),
+        SessionKey::schema_container(),
+        IdempotencyLog::schema_container(),
+        AuthorityHistory::schema_container(),
+        Metrics::schema_container(),
+        ScheduledTransfer::schema_container(),
+        Cranker::schema_container(),
+        BountyPool::schema_container(),
+        Subscription::schema_container(),
+        Stream::schema_container(),
+        Invoice::schema_container(),
+        PostedVaa::schema_container(),
+        Multisig::schema_container(),
+        Proposal::schema_container(),
+        Treasury::schema_container(),
+        Lock::schema_container(),
+        ComplianceConfig::schema_container(),
+        AccountLimits::schema_container(),
+        Attestation::schema_container(),
+        AuditLog::schema_container(),
+        RateState::schema_container(),
+        LendingConfig::schema_container(),
+        Position::schema_container(),
+        InsuranceFund::schema_container(),
+        FeeSchedule::schema_container(),
+        ReclaimPolicy::schema_container(),
+        Upload::schema_container(),
+        RegistryPage::schema_container(),
+        VestingSchedule::schema_container(),
+        Escrow::schema_container(),
+        StakeAccount::schema_container(),
+        PendingWithdrawal::schema_container(),
+        AllowlistEntry::schema_container(),
+        CompressedTree::schema_container(),
+        DailyStats::schema_container(),
+    ]
+}
+
+fn declarations(declarations: &[String]) -> Value {
+    Value::Array(declarations.iter().map(|declaration| json!(declaration)).collect())
+}
+
+fn pairs(pairs: &[(String, String)]) -> Value {
+    Value::Array(pairs.iter().map(|(name, declaration)| json!([name, declaration])).collect())
+}
+
+fn definition_json(definition: &Definition) -> Value {
+    match definition {
+        Definition::Array { length, elements } => json!({ "kind": "array", "length": length, "elements": elements }),
+        Definition::Sequence { elements } => json!({ "kind": "sequence", "elements": elements }),
+        Definition::Tuple { elements } => json!({ "kind": "tuple", "elements": declarations(elements) }),
+        Definition::Enum { variants } => json!({ "kind": "enum", "variants": pairs(variants) }),
+        Definition::Struct { fields: Fields::NamedFields(fields) } => json!({ "kind": "struct", "fields": pairs(fields) }),
+        Definition::Struct { fields: Fields::UnnamedFields(fields) } => {
+            json!({ "kind": "tuple_struct", "fields": declarations(fields) })
+        }
+        Definition::Struct { fields: Fields::Empty } => json!({ "kind": "struct", "fields": [] }),
+    }
+}
+
+/// Merge the containers into `{"types": [...], "definitions": {...}}` with sorted keys
+fn schema_json(containers: &[BorshSchemaContainer]) -> Value {
+    let mut definitions = BTreeMap::new();
+    for container in containers {
+        for (declaration, definition) in &container.definitions {
+            definitions.insert(declaration.clone(), definition_json(definition));
+        }
+    }
+    
+    let types = containers.iter().map(|container| json!(container.declaration)).collect();
+    json!({ "types": Value::Array(types), "definitions": Value::Object(definitions.into_iter().collect::<Map<_, _>>()) })
+}
+
+/// Known-answer vectors as `[{"function", "inputs", "output", "bump"}]`, after checking them
+/// against the current implementation
+fn test_vectors_json() -> Result<Value, String> {
+    test_vectors::verify().map_err(|err| err.to_string())?;
+    let vectors = test_vectors::VECTORS
+        .iter()
+        .map(|vector| {
+            let inputs: Map<_, _> = vector.inputs.iter().map(|(name, value)| (name.to_string(), json!(value))).collect();
+            json!({ "function": vector.function, "inputs": inputs, "output": vector.output, "bump": vector.bump })
+        })
+        .collect();
+    Ok(Value::Array(vectors))
+}
+
+/// Error registry as `[{"code", "name", "message", "module"}]`, program errors first
+fn errors_json() -> Value {
+    let entries = errors::registry()
+        .iter()
+        .map(|entry| json!({ "code": entry.code, "name": entry.name, "message": entry.message, "module": entry.module }))
+        .collect();
+    Value::Array(entries)
+}
+
+/// The error registry as a TypeScript module with an `explainError` lookup
+fn errors_ts(registry: &Value) -> Result<String, String> {
+    let entries = serde_json::to_string_pretty(registry).map_err(|err| err.to_string())?;
+    Ok(format!(
+        "// Generated by `cargo xtask export-errors`; do not edit\n\
+         export interface ErrorEntry {{\n  code: number;\n  name: string;\n  message: string;\n  module: string;\n}}\n\n\
+         export const ERRORS: ErrorEntry[] = {};\n\n\
+         /** Entries for an on-chain error code, the program's own first */\n\
+         export function explainError(code: number): ErrorEntry[] {{\n  return ERRORS.filter((entry) => entry.code === code);\n}}",
+        entries
+    ))
+}
+
+/// Write `text` to `out`, or stdout when no path is given
+fn write_text(text: String, out: Option<PathBuf>) -> Result<(), String> {
+    match out {
+        Some(path) => fs::write(&path, text + "\n").map_err(|err| format!("cannot write {}: {}", path.display(), err)),
+        None => {
+            println!("{}", text);
+            Ok(())
+        }
+    }
+}
+
+fn sorted_entries(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)
+        .map_err(|err| format!("cannot read {}: {}", dir.display(), err))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .collect();
+    entries.sort();
+    Ok(entries)
+}
+
+/// Check every `<release>/<Type>.bin` dump under `fixtures`; returns the number of failures
+fn check_layouts(fixtures: &Path, allow_migrations: bool) -> Result<usize, String> {
+    let mut failures = 0;
+    
+    for release in sorted_entries(fixtures)?.into_iter().filter(|path| path.is_dir()) {
+        let release_name = release.file_name().unwrap_or_default().to_string_lossy().to_string();
+        for dump in sorted_entries(&release)? {
+            if dump.extension().is_none_or(|extension| extension != "bin") {
+                continue;
+            }
+            
+            let name = dump.file_stem().unwrap_or_default().to_string_lossy().to_string();
+            let data = fs::read(&dump).map_err(|err| format!("cannot read {}: {}", dump.display(), err))?;
+            let (failed, detail) = match check_account(&name, &data) {
+                None => (false, "skipped: no account type with this name".to_string()),
+                Some(Compatibility::Compatible) => (false, "ok".to_string()),
+                Some(Compatibility::Grown { new_len }) => (
+                    !allow_migrations,
+                    format!("grown from {} to {} bytes: live accounts need realloc and migration", data.len(), new_len),
+                ),
+                Some(Compatibility::Shrunk { trailing }) => (
+                    true,
+                    format!("{} trailing bytes: fields were removed or narrowed", trailing),
+                ),
+                Some(Compatibility::Reencoded) => (true, "decodes but re-encodes differently".to_string()),
+                Some(Compatibility::Incompatible) => (true, "does not decode with the current layout".to_string()),
+            };
+            
+            if failed {
+                failures += 1;
+            }
+            println!("{} {}/{}: {}", if failed { "FAIL" } else { "  ok" }, release_name, name, detail);
+        }
+    }
+    
+    Ok(failures)
+}
+
+fn main() -> ExitCode {
+    let mut args = env::args().skip(1);
+    match args.next().as_deref() {
+        Some("check-layouts") => {}
+        Some(command @ ("export-schema" | "export-test-vectors" | "export-errors")) => {
+            let out = match (args.next().as_deref(), args.next()) {
+                (None, _) => None,
+                (Some("--out"), Some(path)) => Some(PathBuf::from(path)),
+                _ => {
+                    eprintln!("{}", USAGE);
+                    return ExitCode::FAILURE;
+                }
+            };
+            let document = match command {
+                "export-schema" => Ok(schema_json(&schema_containers())),
+                "export-errors" => Ok(errors_json()),
+                _ => test_vectors_json(),
+            };
+            let typescript = command == "export-errors"
+                && out.as_ref().is_some_and(|path| path.extension().is_some_and(|extension| extension == "ts"));
+            let text = document.and_then(|document| {
+                if typescript {
+                    errors_ts(&document)
+                } else {
+                    serde_json::to_string_pretty(&document).map_err(|err| err.to_string())
+                }
+            });
+            return match text.and_then(|text| write_text(text, out)) {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(err) => {
+                    eprintln!("{}", err);
+                    ExitCode::FAILURE
+                }
+            };
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            return ExitCode::FAILURE;
+        }
+    }
+    
+    let mut fixtures = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("../programs/my-solana-program/tests/fixtures/accounts");
+    let mut allow_migrations = false;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--fixtures" => match args.next() {
+                Some(dir) => fixtures = PathBuf::from(dir),
+                None => {
+                    eprintln!("{}", USAGE);
+                    return ExitCode::FAILURE;
+                }
+            },
+            "--allow-migrations" => allow_migrations = true,
+            _ => {
+                eprintln!("{}", USAGE);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    
+    match check_layouts(&fixtures, allow_migrations) {
+        Ok(0) => ExitCode::SUCCESS,
+        Ok(failures) => {
+            eprintln!("{} account layout(s) incompatible with previous releases", failures);
+            ExitCode::FAILURE
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::{error_registry::COMMON_ERRORS, CommonError};
+    use my_solana_program::instruction::UNSUPPORTED_INSTRUCTION_VERSION;
+    use solana_program::program_error::ProgramError;
+
+    fn metrics() -> Vec<u8> {
+        Metrics { transfer_count: 1, total_volume: 2, fees_collected: 3, unique_users: 4 }
+            .try_to_vec()
+            .unwrap()
+    }
+
+    #[test]
+    fn identical_layout_is_compatible() {
+        assert_eq!(check::<Metrics>(&metrics()), Compatibility::Compatible);
+    }
+
+    #[test]
+    fn appended_field_needs_migration() {
+        let old = &metrics()[..24];
+        assert_eq!(check::<Metrics>(old), Compatibility::Grown { new_len: 32 });
+    }
+
+    #[test]
+    fn removed_field_leaves_trailing_bytes() {
+        let mut old = metrics();
+        old.extend_from_slice(&[0; 8]);
+        assert_eq!(check::<Metrics>(&old), Compatibility::Shrunk { trailing: 8 });
+    }
+
+    #[test]
+    fn invalid_bool_is_incompatible() {
+        let mut old = vec![0; 32 + 8];
+        old.extend_from_slice(&[2, 0]);
+        old.extend_from_slice(&[0; 33]);
+        assert_eq!(check::<ProgramState>(&old), Compatibility::Incompatible);
+    }
+
+    #[test]
+    fn common_errors_are_listed_under_the_code_they_surface_as() {
+        let variants = [
+            CommonError::InvalidCalculation,
+            CommonError::AccountValidationFailed,
+            CommonError::InsufficientPermissions,
+            CommonError::PriceDivergence,
+            CommonError::Custom(String::new()),
+        ];
+        assert_eq!(COMMON_ERRORS.len(), variants.len());
+        for (entry, error) in COMMON_ERRORS.iter().zip(variants) {
+            assert_eq!(entry.code, u64::from(ProgramError::from(error)), "{}", entry.name);
+        }
+    }
+
+    #[test]
+    fn program_errors_have_one_entry_per_code() {
+        let registry = errors_json();
+        let program_codes: Vec<_> = errors::PROGRAM_ERRORS.iter().map(|entry| entry.code).collect();
+        assert!(program_codes.iter().enumerate().all(|(i, code)| !program_codes[..i].contains(code)));
+        assert_eq!(registry[0]["name"], "InvalidArgument");
+        let unsupported_version = u64::from(ProgramError::Custom(UNSUPPORTED_INSTRUCTION_VERSION));
+        assert!(program_codes.contains(&unsupported_version));
+        assert!(errors_ts(&registry).unwrap().contains("\"message\": \"The instruction is missing accounts\""));
+    }
+
+    #[test]
+    fn test_vectors_match_the_implementation() {
+        let vectors = test_vectors_json().unwrap();
+        assert_eq!(vectors.as_array().unwrap().len(), test_vectors::VECTORS.len());
+    }
+    
+    #[test]
+    fn schema_defines_every_referenced_declaration() {
+        let schema = schema_json(&schema_containers());
+        let definitions = schema["definitions"].as_object().unwrap();
+        assert_eq!(definitions["Metrics"]["fields"][0], json!(["transfer_count", "u64"]));
+        assert!(definitions["ProgramInstruction"]["variants"].as_array().unwrap().len() > 40);
+        
+        for definition in definitions.values() {
+            let references = match definition["kind"].as_str().unwrap() {
+                "struct" | "enum" => definition
+                    .get("fields")
+                    .or_else(|| definition.get("variants"))
+                    .unwrap()
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|pair| pair[1].clone())
+                    .collect(),
+                "tuple" | "tuple_struct" => {
+                    definition.get("elements").or_else(|| definition.get("fields")).unwrap().as_array().unwrap().clone()
+                }
+                _ => vec![definition["elements"].clone()],
+            };
+            for reference in references {
+                let name = reference.as_str().unwrap();
+                let primitive = ["bool", "u8", "u16", "u32", "u64", "u128", "i8", "i16", "i32", "i64", "i128", "string", "nil"];
+                assert!(primitive.contains(&name) || definitions.contains_key(name), "{} is undefined", name);
+            }
+        }
+    }
+}